@@ -0,0 +1,72 @@
+// Raw instructions per second of the interpreter core, with nothing attached to it: no
+// emulator, pacing, or devices, just `Chip8::step` in a loop
+//
+//   cargo bench --bench core
+//
+//   Criterion reports each as a throughput in instructions (elements) per second.
+//
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use chippity::chip8::{Chip8, Platform};
+
+// Instructions run per iteration
+const INSTRUCTIONS: u64 = 10_000;
+// Instructions between timer ticks, as at the default clock rate
+const TICK_INTERVAL: u64 = 12;
+
+// A game's mix of instructions: mostly arithmetic, skips, and jumps, and some draws
+const BRIX: &[u8] = include_bytes!("../roms/retro/BRIX");
+
+// Nothing but draws: an 8-pixel-wide sprite of 15 rows, moved right a pixel each time so
+// it straddles bytes of the display most of the time and wraps around its right edge
+//
+//   0x200  A000  LD I, 0x000
+//   0x202  D01F  DRW V0, V1, 0xF
+//   0x204  7001  ADD V0, 0x01
+//   0x206  1202  JP 0x202
+//
+const DRAW_LOOP: &[u8] = &[0xA0, 0x00, 0xD0, 0x1F, 0x70, 0x01, 0x12, 0x02];
+// The same in SUPER-CHIP's 128x64 mode, switched to first
+//
+//   0x200  00FF  HIGH
+//   0x202  the loop above, two bytes further on
+//
+const HIRES_DRAW_LOOP: &[u8] = &[0x00, 0xFF, 0xA0, 0x00, 0xD0, 0x1F, 0x70, 0x01, 0x12, 0x04];
+
+fn machine(platform: &Platform, rom: &[u8]) -> Chip8 {
+    let mut system = Chip8::new();
+    system.set_platform(platform);
+    system.set_rng_seed(0);
+    system.load_rom(rom).unwrap();
+    system
+}
+
+fn run(system: &mut Chip8) {
+    for n in 0..INSTRUCTIONS {
+        black_box(system.step().unwrap());
+        if n % TICK_INTERVAL == 0 {
+            system.tick_timers();
+        }
+    }
+}
+
+fn bench(c: &mut Criterion, name: &str, platform: Platform, rom: &[u8]) {
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(INSTRUCTIONS));
+    // Each iteration carries on from where the last left off, as a session would
+    let mut system = machine(&platform, rom);
+    group.bench_function("fetch+exec", |b| b.iter(|| run(&mut system)));
+    group.finish();
+}
+
+fn game(c: &mut Criterion) {
+    bench(c, "brix", Platform::CHIP8, BRIX);
+}
+
+fn draws(c: &mut Criterion) {
+    bench(c, "draw", Platform::CHIP8, DRAW_LOOP);
+    bench(c, "draw-hires", Platform::SCHIP, HIRES_DRAW_LOOP);
+}
+
+criterion_group!(benches, game, draws);
+criterion_main!(benches);
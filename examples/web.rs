@@ -0,0 +1,49 @@
+// chippity in the browser: the page (web.html) hands a ROM to `run`, which plays it on a
+// canvas through `driver::web`, one slice of emulation per animation frame
+//
+//   Build it for the web and generate its JavaScript bindings, then serve this directory
+//   (browsers won't load WebAssembly from file:// URLs):
+//
+//     cargo build --release --example web --features web --target wasm32-unknown-unknown
+//     wasm-bindgen --target web --out-dir examples/pkg \
+//         target/wasm32-unknown-unknown/release/examples/web.wasm
+//     python3 -m http.server --directory examples
+//
+//   and open http://localhost:8000/web.html
+//
+use std::{cell::RefCell, rc::Rc};
+
+use chippity::{driver::web::Web, emulator::Emulator};
+use wasm_bindgen::prelude::*;
+
+// Start playing `rom` (named `name`, e.g. its file name) on the canvas with id "screen"
+#[wasm_bindgen]
+pub fn run(name: &str, rom: Vec<u8>) -> Result<(), JsValue> {
+    // The emulator outlives this call, living on in the animation frame callbacks, so it and
+    // the device it borrows are kept for the rest of the page's life
+    let device: &'static RefCell<Web> =
+        Box::leak(Box::new(RefCell::new(Web::new("screen", name)?)));
+    let emulator = Box::leak(Box::new(Emulator::with_peripherals(device, device, device)));
+    emulator
+        .load_program_data(name, rom)
+        .map_err(|e| e.to_string())?;
+
+    // Each frame's callback schedules the next, until the program exits
+    let callback = Rc::new(RefCell::new(None::<Closure<dyn FnMut()>>));
+    let next = Rc::clone(&callback);
+    *callback.borrow_mut() = Some(Closure::new(move || match emulator.run_slice() {
+        Ok(true) => request_animation_frame(next.borrow().as_ref().unwrap()),
+        Ok(false) => {}
+        Err(e) => web_sys::console::error_1(&e.to_string().into()),
+    }));
+    request_animation_frame(callback.borrow().as_ref().unwrap());
+
+    Ok(())
+}
+
+fn request_animation_frame(callback: &Closure<dyn FnMut()>) {
+    web_sys::window()
+        .unwrap()
+        .request_animation_frame(callback.as_ref().unchecked_ref())
+        .expect("requestAnimationFrame failed");
+}
@@ -0,0 +1,59 @@
+// Arbitrary ROMs, on every platform, must run without panicking: whatever they do, `step`
+// either carries on or returns an error
+//
+//   The first byte of the input picks the platform and the second toggles its quirks; the
+//   rest is loaded as the ROM. Keys are pressed and timers ticked as it runs, so paths
+//   depending on them (e.g. EX9E with any Vx) are reached too
+//
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use chippity::chip8::{Chip8, InputMsg, Platform, NUM_KEYS};
+
+// Instructions run per input, unless the ROM stops sooner
+const STEPS: usize = 10_000;
+// Instructions between timer ticks, as at the default clock rate
+const TICK_INTERVAL: usize = 12;
+
+const PLATFORMS: [Platform; 5] = [
+    Platform::CHIP8,
+    Platform::VIP,
+    Platform::CHIP48,
+    Platform::SCHIP,
+    Platform::XOCHIP,
+];
+
+fuzz_target!(|data: &[u8]| {
+    let [platform, quirks, rom @ ..] = data else {
+        return;
+    };
+
+    let mut platform = PLATFORMS[*platform as usize % PLATFORMS.len()];
+    let flip = |bit: u8| quirks & (1 << bit) != 0;
+    platform.quirks.shift_vy ^= flip(0);
+    platform.quirks.memory_increment ^= flip(1);
+    platform.quirks.jump_vx ^= flip(2);
+    platform.quirks.vf_reset ^= flip(3);
+    platform.quirks.wrap ^= flip(4);
+
+    let mut system = Chip8::new();
+    system.set_platform(&platform);
+    system.set_rng_seed(0);
+    if system.load_rom(rom).is_err() {
+        return;
+    }
+
+    for n in 0..STEPS {
+        if system.step().is_err() {
+            break;
+        }
+        if n % TICK_INTERVAL == 0 {
+            system.tick_timers();
+            // Press each key in turn, so key waits and skips see some input
+            let mut keys = InputMsg::ZERO;
+            keys.set((n / TICK_INTERVAL) % NUM_KEYS, true);
+            system.receive_input(Some(keys));
+        }
+    }
+});
@@ -0,0 +1,68 @@
+use core::ops::{Range, RangeInclusive};
+
+use super::{Chip8, Rect, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+// CHIP-8X colors its 64x32 display through the VP-590 color board, in zones 8 pixels wide
+// and (at finest) 1 pixel tall; lit pixels take their zone's foreground color, unlit ones
+// the background color shared by the whole display
+pub const ZONE_WIDTH: usize = 8;
+pub const ZONE_COLUMNS: usize = DISPLAY_WIDTH / ZONE_WIDTH;
+// Rows of a zone colored by BXY0, which works in coarser 8x4 zones than BXYN
+const BLOCK_HEIGHT: usize = 4;
+// Foreground colors: black, red, blue, violet, green, yellow, aqua, and white
+pub const NUM_FOREGROUNDS: u8 = 8;
+// Background colors, stepped through in turn by 02A0: dark blue, black, dark green, and
+// dark red
+pub const NUM_BACKGROUNDS: u8 = 4;
+// Zones start out red (on dark blue)
+pub(super) const DEFAULT_FOREGROUND: u8 = 1;
+
+impl Chip8 {
+    // Foreground color (0-7) of the pixel at (x, y); only CHIP-8X colors them
+    pub fn foreground(&self, x: usize, y: usize) -> u8 {
+        self.zone_colors[y % DISPLAY_HEIGHT][x / ZONE_WIDTH % ZONE_COLUMNS]
+    }
+
+    // Background color (0-3) of the display
+    pub fn background(&self) -> u8 {
+        self.background
+    }
+
+    // 02A0 - step to the next background color
+    pub(super) fn step_background(&mut self) {
+        self.background = (self.background + 1) % NUM_BACKGROUNDS;
+        self.add_damage(Rect::full((DISPLAY_WIDTH, DISPLAY_HEIGHT)));
+    }
+
+    // BXY0 - color whole blocks of zones: `h` gives the first column of them (low nibble)
+    // and how many more follow it (high nibble), `v` the same of blocks 4 rows tall; both
+    // wrap around the display
+    pub(super) fn color_blocks(&mut self, h: u8, v: u8, color: u8) {
+        let columns = (h & 0xF) as usize..=(h & 0xF) as usize + (h >> 4) as usize;
+        let first = (v & 0xF) as usize * BLOCK_HEIGHT;
+        let rows = first..first + ((v >> 4) as usize + 1) * BLOCK_HEIGHT;
+        self.color_zones(columns, rows, color);
+    }
+
+    // BXYN - color the zone containing pixel column `x` in `n` rows from row `y`
+    pub(super) fn color_rows(&mut self, x: u8, y: u8, n: u8, color: u8) {
+        let column = x as usize / ZONE_WIDTH;
+        self.color_zones(column..=column, y as usize..y as usize + n as usize, color);
+    }
+
+    fn color_zones(&mut self, columns: RangeInclusive<usize>, rows: Range<usize>, color: u8) {
+        for row in rows {
+            for column in columns.clone() {
+                self.zone_colors[row % DISPLAY_HEIGHT][column % ZONE_COLUMNS] =
+                    color % NUM_FOREGROUNDS;
+            }
+        }
+        self.add_damage(Rect::full((DISPLAY_WIDTH, DISPLAY_HEIGHT)));
+    }
+}
+
+// 5XY1 - add each of the two 3-bit fields of Vx and Vy (bits 0-2 and 4-6) apart, the
+// VP-590's colors being packed that way; carries out of either are dropped
+pub(super) fn add_packed(vx: u8, vy: u8) -> u8 {
+    ((vx & 0x77) + (vy & 0x77)) & 0x77
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+
+use smallvec::SmallVec;
+
+use super::{opcode, Mode};
+
+// A decoded instruction, or data the decoder couldn't make sense of, at its load address
+//
+//   Displayed as the address, the raw bytes, and the mnemonic:
+//
+//     0x200  00E0       CLRS
+//     0x202  A22A       LD I, 0x22A
+//     0x204  F000 0300  LD I, long 0x0300
+//     0x208  FFFF       DW 0xFFFF           ; unknown
+//
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Line {
+    pub addr: u16,
+    // 2 bytes, 4 for XO-CHIP's F000 NNNN, or 1 for a trailing odd byte
+    pub bytes: SmallVec<[u8; 4]>,
+    pub mnemonic: String,
+    // Whether the bytes didn't decode to an instruction in the mode disassembled for
+    pub unknown: bool,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self
+            .bytes
+            .chunks(2)
+            .map(|word| word.iter().map(|b| format!("{b:02X}")).collect::<String>())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let line = format!("{:#05X}  {bytes:<9}  {}", self.addr, self.mnemonic);
+        match self.unknown {
+            true => write!(f, "{line:<36}; unknown"),
+            false => write!(f, "{line}"),
+        }
+    }
+}
+
+// Decode `rom`, as loaded for `mode` (at 0x200, or 0x300 for CHIP-8X), into one line per
+// instruction
+//
+//   ROMs interleave code with sprites and other data, which is decoded all the same;
+//   there's no telling them apart without running the program
+//
+pub fn disassemble(rom: &[u8], mode: Mode) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+
+    while offset < rom.len() {
+        let addr = mode.rom_start() + offset as u16;
+        let line = match rom[offset..] {
+            // XO-CHIP's F000 NNNN carries its operand in the following word
+            [0xF0, 0x00, hi, lo, ..] if mode == Mode::XoChip => Line {
+                addr,
+                bytes: SmallVec::from_slice(&[0xF0, 0x00, hi, lo]),
+                mnemonic: format!("LD I, long {:#06X}", u16::from_be_bytes([hi, lo])),
+                unknown: false,
+            },
+            [hi, lo, ..] => {
+                let opcode = u16::from_be_bytes([hi, lo]);
+                let mnemonic = decode(opcode, mode);
+                Line {
+                    addr,
+                    bytes: SmallVec::from_slice(&[hi, lo]),
+                    unknown: mnemonic.is_none(),
+                    mnemonic: mnemonic.unwrap_or_else(|| data_word(opcode)),
+                }
+            }
+            [byte, ..] => Line {
+                addr,
+                bytes: SmallVec::from_slice(&[byte]),
+                mnemonic: format!("DB {byte:#04X}"),
+                unknown: true,
+            },
+            [] => unreachable!(),
+        };
+
+        offset += line.bytes.len();
+        lines.push(line);
+    }
+
+    lines
+}
+
+// Mnemonic of `opcode` in `mode`, or the data word it'd be disassembled as if it isn't an
+// instruction
+pub fn mnemonic(opcode: u16, mode: Mode) -> String {
+    decode(opcode, mode).unwrap_or_else(|| data_word(opcode))
+}
+
+fn data_word(opcode: u16) -> String {
+    format!("DW {opcode:#06X}")
+}
+
+// Mnemonic of a single instruction in `mode`, or None if it isn't one
+pub fn decode(opcode: u16, mode: Mode) -> Option<String> {
+    opcode::decode(opcode, mode).ok().map(|op| op.to_string())
+}
@@ -0,0 +1,84 @@
+use core::{error, fmt};
+
+use super::NUM_DATA_REGS;
+
+// Ways a ROM can bring the CHIP-8 VM to a halt
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ChipError {
+    // ROM doesn't fit in the program area of memory
+    RomTooLarge {
+        size: usize,
+        capacity: usize,
+    },
+    // Program Counter left the program area of memory
+    PcOutOfBounds {
+        pc: u16,
+    },
+    // 00EE with no subroutine to return from
+    StackUnderflow {
+        pc: u16,
+    },
+    // 2NNN with subroutines already nested as deep as the call stack goes
+    StackOverflow {
+        pc: u16,
+    },
+    // Instruction at `pc` accessed `len` bytes of memory starting past the end of RAM
+    MemoryOutOfBounds {
+        pc: u16,
+        addr: usize,
+        len: usize,
+    },
+    // Opcode not recognized in the current mode
+    UnknownInstruction {
+        pc: u16,
+        opcode: u16,
+    },
+    // Save state that couldn't be loaded
+    #[cfg(feature = "std")]
+    InvalidState(String),
+}
+
+impl fmt::Display for ChipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChipError::RomTooLarge { size, capacity } => {
+                write!(
+                    f,
+                    "ROM is too large ({size} bytes, at most {capacity} fit in memory)"
+                )
+            }
+            ChipError::PcOutOfBounds { pc } => {
+                write!(f, "program counter out of bounds at {pc:#05X}")
+            }
+            ChipError::StackUnderflow { pc } => {
+                write!(f, "return with an empty call stack at {pc:#05X}")
+            }
+            ChipError::StackOverflow { pc } => {
+                write!(f, "call stack overflow at {pc:#05X}")
+            }
+            ChipError::MemoryOutOfBounds { pc, addr, len } => write!(
+                f,
+                "out of bounds access of {len} byte(s) at {addr:#05X} by instruction at {pc:#05X}"
+            ),
+            ChipError::UnknownInstruction { pc, opcode } => {
+                write!(f, "unrecognized instruction {opcode:04X} at {pc:#05X}")
+            }
+            #[cfg(feature = "std")]
+            ChipError::InvalidState(reason) => write!(f, "invalid save state: {reason}"),
+        }
+    }
+}
+
+impl error::Error for ChipError {}
+
+// What a ROM brought the machine to a halt with, and the machine state at that point, for
+// the host to show (see `emulator::Signal::Fault`)
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FaultInfo {
+    pub err: ChipError,
+    pub pc: u16,
+    // Instruction at `pc`, as far as it's in memory
+    pub opcode: u16,
+    pub i_reg: u16,
+    pub v_reg: [u8; NUM_DATA_REGS],
+}
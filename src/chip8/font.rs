@@ -0,0 +1,188 @@
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+pub(super) const FONT_PX_HEIGHT: usize = 5;
+pub(super) const BIG_FONT_PX_HEIGHT: usize = 10;
+
+// Set of hex digit sprites loaded into the interpreter area (see `Chip8::load_fonts`): 4x5
+// ones for FX29, and 8x10 ones for SCHIP's FX30
+//
+//   Programs only find them through FX29 and FX30, so any set runs them, but some draw
+//   scores and text in the style of the interpreter they were written on
+//
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Font {
+    // The set most interpreters since CHIP-48 share, with Octo's large digits and letters
+    #[default]
+    Modern,
+    // The COSMAC VIP's own, with its squarer 1, 4, 7, B, and D
+    Vip,
+    // The DREAM 6800's, 3 pixels wide
+    Dream6800,
+    // The ETI-660's, 3 pixels wide too, with lowercase b and d
+    Eti660,
+    // SUPER-CHIP 1.1's own rounded large digits (with Octo's large letters, which it lacked)
+    Schip,
+}
+
+impl Font {
+    pub fn sprites(self) -> &'static [[u8; FONT_PX_HEIGHT]; 16] {
+        match self {
+            Font::Modern | Font::Schip => &FONT_SPRITES,
+            Font::Vip => &VIP_FONT_SPRITES,
+            Font::Dream6800 => &DREAM6800_FONT_SPRITES,
+            Font::Eti660 => &ETI660_FONT_SPRITES,
+        }
+    }
+
+    pub fn big_sprites(self) -> &'static [[u8; BIG_FONT_PX_HEIGHT]; 16] {
+        match self {
+            Font::Schip => &SCHIP_BIG_FONT_SPRITES,
+            _ => &BIG_FONT_SPRITES,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for Font {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            // Octo's default is the modern set
+            "modern" | "octo" => Ok(Font::Modern),
+            "vip" => Ok(Font::Vip),
+            "dream6800" => Ok(Font::Dream6800),
+            "eti660" => Ok(Font::Eti660),
+            "schip" => Ok(Font::Schip),
+            _ => Err(format!(
+                "unrecognized font '{s}' (expected modern, octo, vip, dream6800, eti660, or schip)"
+            )),
+        }
+    }
+}
+
+// Font sprite for hex digit `digit`; lets frontends draw text in the same style as the guest
+pub fn font_sprite(digit: u8) -> &'static [u8; FONT_PX_HEIGHT] {
+    &FONT_SPRITES[(digit & 0xF) as usize]
+}
+
+// Pre-defined "static" font data that will occupy memory reserved for the interpreter (<0x200)
+const FONT_SPRITES: [[u8; FONT_PX_HEIGHT]; 16] = [
+    [0xF0, 0x90, 0x90, 0x90, 0xF0], // 0
+    [0x20, 0x60, 0x20, 0x20, 0x70], // 1
+    [0xF0, 0x10, 0xF0, 0x80, 0xF0], // 2
+    [0xF0, 0x10, 0xF0, 0x10, 0xF0], // 3
+    [0x90, 0x90, 0xF0, 0x10, 0x10], // 4
+    [0xF0, 0x80, 0xF0, 0x10, 0xF0], // 5
+    [0xF0, 0x80, 0xF0, 0x90, 0xF0], // 6
+    [0xF0, 0x10, 0x20, 0x40, 0x40], // 7
+    [0xF0, 0x90, 0xF0, 0x90, 0xF0], // 8
+    [0xF0, 0x90, 0xF0, 0x10, 0xF0], // 9
+    [0xF0, 0x90, 0xF0, 0x90, 0x90], // A
+    [0xE0, 0x90, 0xE0, 0x90, 0xE0], // B
+    [0xF0, 0x80, 0x80, 0x80, 0xF0], // C
+    [0xE0, 0x90, 0x90, 0x90, 0xE0], // D
+    [0xF0, 0x80, 0xF0, 0x80, 0xF0], // E
+    [0xF0, 0x80, 0xF0, 0x80, 0x80], // F
+];
+
+const VIP_FONT_SPRITES: [[u8; FONT_PX_HEIGHT]; 16] = [
+    [0xF0, 0x90, 0x90, 0x90, 0xF0], // 0
+    [0x60, 0x20, 0x20, 0x20, 0x70], // 1
+    [0xF0, 0x10, 0xF0, 0x80, 0xF0], // 2
+    [0xF0, 0x10, 0x70, 0x10, 0xF0], // 3
+    [0xA0, 0xA0, 0xF0, 0x20, 0x20], // 4
+    [0xF0, 0x80, 0xF0, 0x10, 0xF0], // 5
+    [0xF0, 0x80, 0xF0, 0x90, 0xF0], // 6
+    [0xF0, 0x10, 0x10, 0x10, 0x10], // 7
+    [0xF0, 0x90, 0xF0, 0x90, 0xF0], // 8
+    [0xF0, 0x90, 0xF0, 0x10, 0xF0], // 9
+    [0xF0, 0x90, 0xF0, 0x90, 0x90], // A
+    [0xF0, 0x50, 0x70, 0x50, 0xF0], // B
+    [0xF0, 0x80, 0x80, 0x80, 0xF0], // C
+    [0xF0, 0x50, 0x50, 0x50, 0xF0], // D
+    [0xF0, 0x80, 0xF0, 0x80, 0xF0], // E
+    [0xF0, 0x80, 0xF0, 0x80, 0x80], // F
+];
+
+const DREAM6800_FONT_SPRITES: [[u8; FONT_PX_HEIGHT]; 16] = [
+    [0xE0, 0xA0, 0xA0, 0xA0, 0xE0], // 0
+    [0x40, 0x40, 0x40, 0x40, 0x40], // 1
+    [0xE0, 0x20, 0xE0, 0x80, 0xE0], // 2
+    [0xE0, 0x20, 0xE0, 0x20, 0xE0], // 3
+    [0x80, 0xA0, 0xA0, 0xE0, 0x20], // 4
+    [0xE0, 0x80, 0xE0, 0x20, 0xE0], // 5
+    [0xE0, 0x80, 0xE0, 0xA0, 0xE0], // 6
+    [0xE0, 0x20, 0x20, 0x20, 0x20], // 7
+    [0xE0, 0xA0, 0xE0, 0xA0, 0xE0], // 8
+    [0xE0, 0xA0, 0xE0, 0x20, 0xE0], // 9
+    [0xE0, 0xA0, 0xE0, 0xA0, 0xA0], // A
+    [0xC0, 0xA0, 0xE0, 0xA0, 0xC0], // B
+    [0xE0, 0x80, 0x80, 0x80, 0xE0], // C
+    [0xC0, 0xA0, 0xA0, 0xA0, 0xC0], // D
+    [0xE0, 0x80, 0xE0, 0x80, 0xE0], // E
+    [0xE0, 0x80, 0xC0, 0x80, 0x80], // F
+];
+
+const ETI660_FONT_SPRITES: [[u8; FONT_PX_HEIGHT]; 16] = [
+    [0xE0, 0xA0, 0xA0, 0xA0, 0xE0], // 0
+    [0x20, 0x20, 0x20, 0x20, 0x20], // 1
+    [0xE0, 0x20, 0xE0, 0x80, 0xE0], // 2
+    [0xE0, 0x20, 0xE0, 0x20, 0xE0], // 3
+    [0xA0, 0xA0, 0xE0, 0x20, 0x20], // 4
+    [0xE0, 0x80, 0xE0, 0x20, 0xE0], // 5
+    [0xE0, 0x80, 0xE0, 0xA0, 0xE0], // 6
+    [0xE0, 0x20, 0x20, 0x20, 0x20], // 7
+    [0xE0, 0xA0, 0xE0, 0xA0, 0xE0], // 8
+    [0xE0, 0xA0, 0xE0, 0x20, 0xE0], // 9
+    [0xE0, 0xA0, 0xE0, 0xA0, 0xA0], // A
+    [0x80, 0x80, 0xE0, 0xA0, 0xE0], // B
+    [0xE0, 0x80, 0x80, 0x80, 0xE0], // C
+    [0x20, 0x20, 0xE0, 0xA0, 0xE0], // D
+    [0xE0, 0x80, 0xE0, 0x80, 0xE0], // E
+    [0xE0, 0x80, 0xE0, 0x80, 0x80], // F
+];
+
+// SCHIP 8x10 large font data for FX30, as Octo draws it; the original SCHIP 1.1 only
+// defined '0' - '9', the letters follow the common Octo extension of the set
+const BIG_FONT_SPRITES: [[u8; BIG_FONT_PX_HEIGHT]; 16] = [
+    [0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF], // 0
+    [0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF], // 1
+    [0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF], // 2
+    [0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF], // 3
+    [0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03], // 4
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF], // 5
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF], // 6
+    [0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18], // 7
+    [0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF], // 8
+    [0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF], // 9
+    [0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3], // A
+    [0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC], // B
+    [0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C], // C
+    [0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC], // D
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF], // E
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0], // F
+];
+
+// SUPER-CHIP 1.1's large digits as it shipped them, '0' - '9'
+const SCHIP_BIG_FONT_SPRITES: [[u8; BIG_FONT_PX_HEIGHT]; 16] = [
+    [0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C], // 0
+    [0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C], // 1
+    [0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF], // 2
+    [0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C], // 3
+    [0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06], // 4
+    [0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C], // 5
+    [0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C], // 6
+    [0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60], // 7
+    [0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C], // 8
+    [0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C], // 9
+    BIG_FONT_SPRITES[0xA],
+    BIG_FONT_SPRITES[0xB],
+    BIG_FONT_SPRITES[0xC],
+    BIG_FONT_SPRITES[0xD],
+    BIG_FONT_SPRITES[0xE],
+    BIG_FONT_SPRITES[0xF],
+];
@@ -0,0 +1,79 @@
+use bitvec::field::BitField;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    fnv1a, Chip8, Mode, Quirks, HIRES_DISPLAY_WIDTH, MAX_STACK_DEPTH, NUM_DATA_REGS, NUM_KEYS,
+    XO_RAM_SIZE,
+};
+
+// Everything about the machine but its display, for tooling outside the emulator (e.g.
+// snapshot tests, or a debugger in another process) to read field by field
+//
+//   Unlike a save state (see `save_state`), it's a plain struct, to serialize in whatever
+//   format suits; it can't be loaded back into a machine
+//
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MachineState {
+    pub mode: Mode,
+    pub quirks: Quirks,
+    pub pc: u16,
+    pub i_reg: u16,
+    pub v_reg: [u8; NUM_DATA_REGS],
+    // Return addresses of the subroutines being executed, innermost last
+    pub stack: heapless::Vec<u16, MAX_STACK_DEPTH>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    // Whether the display is in SCHIP 128x64 high resolution mode
+    pub hires: bool,
+    // XO-CHIP bitmask of planes affected by drawing, clearing, and scrolling
+    pub planes: u8,
+    // Keys held down, bit n for key n
+    pub keys: u16,
+    // All of RAM: 4KiB, or 64KiB in XO-CHIP mode, unless set otherwise
+    pub memory: heapless::Vec<u8, XO_RAM_SIZE>,
+}
+
+impl Chip8 {
+    pub fn dump_state(&self) -> MachineState {
+        MachineState {
+            mode: self.mode,
+            quirks: self.quirks,
+            pc: self.pc,
+            i_reg: self.i_reg,
+            v_reg: self.v_reg,
+            stack: self.stack.clone(),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            hires: self.hires,
+            planes: self.planes,
+            keys: self.input_bus[..NUM_KEYS].load_le(),
+            memory: self.memory.clone(),
+        }
+    }
+
+    // The display at its current resolution, a row at a time from the top, as a byte per
+    // pixel: its color, with bit n set if the pixel is lit in plane n
+    pub fn framebuffer_rows(
+        &self,
+    ) -> impl Iterator<Item = heapless::Vec<u8, HIRES_DISPLAY_WIDTH>> + '_ {
+        let (width, height) = self.display_dims();
+        let planes = &self.display_bus[..self.num_planes()];
+        (0..height).map(move |y| {
+            (0..width)
+                .map(|x| {
+                    planes.iter().enumerate().fold(0, |color, (n, plane)| {
+                        color | (plane[y * width + x] as u8) << n
+                    })
+                })
+                .collect()
+        })
+    }
+
+    // Hash of the display's resolution and `framebuffer_rows`; the same on every build and
+    // host for the same frame, so runs can be compared by it
+    pub fn display_hash(&self) -> u64 {
+        let (width, height) = self.display_dims();
+        let size = [width as u8, height as u8];
+        fnv1a(size.into_iter().chain(self.framebuffer_rows().flatten()))
+    }
+}
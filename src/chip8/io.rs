@@ -0,0 +1,141 @@
+use core::ops::{BitOr, BitOrAssign};
+
+use bitvec::BitArr;
+
+use super::{NUM_KEYS, PATTERN_LEN};
+
+// Messages passed between the CHIP-8 core and whatever hosts it; they only carry fixed-size
+// data, so the core stays usable without an allocator (see the `std` feature). The emulator
+// and drivers re-export them from where they've always lived
+
+// A 16-bit CHIP-8 input message representing the incoming, updated key states
+// where the nth bit corresponds to the (n as hex) key status
+//
+//   Example: 0b1000_0001_0000_1101
+//         => keys 0, 1, 3, 8, and F are in the down state
+//            and all other keys in the up state
+//
+pub type InputMsg = BitArr!(for NUM_KEYS);
+
+pub const KEY_UP: bool = false;
+pub const KEY_DOWN: bool = true;
+
+// Keys that went down and keys that came back up since the last message; a key tapped in
+// between is in both
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct KeyEvents {
+    pub pressed: InputMsg,
+    pub released: InputMsg,
+}
+
+impl KeyEvents {
+    // Transitions taking the key states from `prev` to `next`
+    pub fn between(prev: InputMsg, next: InputMsg) -> Self {
+        KeyEvents {
+            pressed: next & !prev,
+            released: prev & !next,
+        }
+    }
+}
+
+// Sound the CHIP-8 is making, sent to audio devices on each timer tick the sound timer runs
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AudioMsg {
+    // The standard beep, on or off
+    Beep(bool),
+    // XO-CHIP sampled sound: 128 1-bit samples (most significant bit first) looped at
+    // `driver::waveform::pattern_rate(pitch)`; devices that can't play samples beep instead
+    Pattern { bits: [u8; PATTERN_LEN], pitch: u8 },
+}
+
+impl AudioMsg {
+    // Whether any sound is playing
+    pub fn is_on(self) -> bool {
+        self != AudioMsg::Beep(false)
+    }
+}
+
+// Rectangular region of a frame (x, y from the top-left, w x h pixels)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+impl Rect {
+    // The whole of a frame of `dims` (w, h)
+    pub fn full(dims: (usize, usize)) -> Self {
+        Rect {
+            x: 0,
+            y: 0,
+            w: dims.0,
+            h: dims.1,
+        }
+    }
+
+    // Smallest region covering both `self` and `other`
+    pub fn union(self, other: Rect) -> Self {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        Rect {
+            x,
+            y,
+            w: (self.x + self.w).max(other.x + other.w) - x,
+            h: (self.y + self.h).max(other.y + other.h) - y,
+        }
+    }
+}
+
+// Events raised by the core while executing an instruction or ticking its timers, as a set
+// of flags so that any number of them coming up at once are all delivered
+//
+//   let events = chip8.exec_instruction(instr)?;
+//   if events.contains(Events::REFRESH_DISPLAY) { /* present the frame */ }
+//
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Events(u8);
+
+impl Events {
+    pub const NONE: Events = Events(0);
+    // The display changed
+    pub const REFRESH_DISPLAY: Events = Events(1 << 0);
+    // The sound timer ticked down, i.e. sound is playing
+    pub const SOUND_AUDIO: Events = Events(1 << 1);
+    // The sound timer was set, starting a sound
+    pub const SOUND_STARTED: Events = Events(1 << 2);
+    // The program asked to exit (SCHIP 00FD)
+    pub const PROGRAM_EXIT: Events = Events(1 << 3);
+    // A machine code routine call (0NNN), or CHIP-8X I/O port instruction, was skipped over
+    pub const SKIPPED_INSTRUCTION: Events = Events(1 << 4);
+    // A sprite was drawn with the display wait quirk on; nothing more runs until the next
+    // timer tick
+    pub const DISPLAY_WAIT: Events = Events(1 << 5);
+    // The instruction hit a debugger watchpoint (see `Chip8::watch_hit`)
+    pub const WATCHPOINT: Events = Events(1 << 6);
+    // The flag registers were stored to (SCHIP FX75), for the host to keep them
+    pub const FLAGS_SAVED: Events = Events(1 << 7);
+
+    // Whether every event in `other` is in the set
+    pub const fn contains(self, other: Events) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for Events {
+    type Output = Events;
+
+    fn bitor(self, other: Events) -> Events {
+        Events(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Events {
+    fn bitor_assign(&mut self, other: Events) {
+        self.0 |= other.0;
+    }
+}
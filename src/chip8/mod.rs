@@ -1,11 +1,41 @@
-mod instruction;
+mod color;
+#[cfg(feature = "std")]
+pub mod disasm;
+mod error;
+mod font;
+mod inspect;
+mod io;
+pub mod opcode;
+mod platform;
+mod quirks;
+mod register;
+#[cfg(feature = "std")]
+mod state;
+mod timing;
+#[cfg(feature = "std")]
+mod trainer;
+mod watch;
 
-use bitvec::{bitarr, order::Msb0, slice::BitSlice, view::BitView, BitArr};
-use smallvec::SmallVec;
+use core::ops::Range;
 
-use crate::driver::InputMsg;
-use crate::emulator;
-use instruction::Instruction;
+use bitvec::{bitarr, field::BitField, slice::BitSlice, BitArr};
+use serde::{Deserialize, Serialize};
+
+use font::{BIG_FONT_PX_HEIGHT, FONT_PX_HEIGHT};
+
+pub use color::{NUM_BACKGROUNDS, NUM_FOREGROUNDS, ZONE_COLUMNS, ZONE_WIDTH};
+pub use error::{ChipError, FaultInfo};
+pub use font::{font_sprite, Font};
+pub use inspect::MachineState;
+pub use io::{AudioMsg, Events, InputMsg, KeyEvents, Rect, KEY_DOWN, KEY_UP};
+pub use opcode::{Opcode, UnknownOpcode};
+pub use platform::Platform;
+pub use quirks::Quirks;
+pub use register::Register;
+pub use timing::{Timing, VIP_CYCLE_RATE};
+#[cfg(feature = "std")]
+pub use trainer::{Action, Trainer};
+pub use watch::{Watchpoint, MAX_WATCHPOINTS};
 
 //    CHIP-8 Virtual Machine memory layout:
 //    +-----------------------------------+= 0xFFF (4095) End of CHIP-8 RAM
@@ -25,6 +55,10 @@ use instruction::Instruction;
 //    |           0x000 to 0x1FF          |
 //    |        Reserved for CHIP-8        |
 //    |            interpreter            |
+//    + - - - - - - - - - - - - - - - - - += 0xF0 (240)* End of SCHIP large font set
+//    |            0x50 to 0xF0           |
+//    |      SCHIP Large Font Data        |
+//    |             '0' - 'F'             |
 //    + - - - - - - - - - - - - - - - - - += 0x50 (80)* End of conventional CHIP-8 font set
 //    |            0x00 to 0x50           |
 //    |          CHIP-8 Font Data         |
@@ -38,54 +72,105 @@ use instruction::Instruction;
 //    Instead, it is common to store font data representing the hexadecimal digits there.
 
 const RAM_SIZE: usize = 4096;
+const XO_RAM_SIZE: usize = 65536; // XO-CHIP extends the address space to 64KiB
 const FONT_START: u16 = 0x000; // Starting addr of fonts (== RAM_START)
+const BIG_FONT_START: u16 = 0x050; // Starting addr of SCHIP large fonts (right after the small font)
 const ROM_START: u16 = 0x200; // Starting addr of CHIP-8 programs
-const ROM_END: u16 = 0xFFF; // Upper bounds addr of CHIP-8 programs (== RAM_SIZE)
-const STACK_SIZE: usize = 12;
-const NUM_DATA_REGS: usize = 16;
+const CHIP8X_ROM_START: u16 = 0x300; // CHIP-8X's larger interpreter pushes programs back
+const DEFAULT_STACK_DEPTH: usize = 16; // Nesting depth of SCHIP and XO-CHIP subroutines
+pub const MAX_STACK_DEPTH: usize = 256; // Deepest `set_stack_depth` allows
+pub const NUM_DATA_REGS: usize = 16;
+pub const NUM_FLAGS: usize = 16; // HP48 "RPL user flags" saved by FX75, as extended by XO-CHIP
+const SCHIP_FLAGS: usize = 8; // Flag registers of the original SCHIP, V0-V7
 const PC_STEP: u16 = 2; // mem::size_of::<Instruction>() / chip8_addressable_unit = 2
 
-// Pre-defined "static" font data that will occupy memory reserved for the interpreter (<0x200)
-const FONT_SPRITES: [[u8; FONT_PX_HEIGHT]; 16] = [
-    [0xF0, 0x90, 0x90, 0x90, 0xF0], // 0
-    [0x20, 0x60, 0x20, 0x20, 0x70], // 1
-    [0xF0, 0x10, 0xF0, 0x80, 0xF0], // 2
-    [0xF0, 0x10, 0xF0, 0x10, 0xF0], // 3
-    [0x90, 0x90, 0xF0, 0x10, 0x10], // 4
-    [0xF0, 0x80, 0xF0, 0x10, 0xF0], // 5
-    [0xF0, 0x80, 0xF0, 0x90, 0xF0], // 6
-    [0xF0, 0x10, 0x20, 0x40, 0x40], // 7
-    [0xF0, 0x90, 0xF0, 0x90, 0xF0], // 8
-    [0xF0, 0x90, 0xF0, 0x10, 0xF0], // 9
-    [0xF0, 0x90, 0xF0, 0x90, 0x90], // A
-    [0xE0, 0x90, 0xE0, 0x90, 0xE0], // B
-    [0xF0, 0x80, 0x80, 0x80, 0xF0], // C
-    [0xE0, 0x90, 0x90, 0x90, 0xE0], // D
-    [0xF0, 0x80, 0xF0, 0x80, 0xF0], // E
-    [0xF0, 0x80, 0xF0, 0x80, 0x80], // F
-];
-const FONT_PX_HEIGHT: usize = 5;
-
 pub const DISPLAY_WIDTH: usize = 64;
 pub const DISPLAY_HEIGHT: usize = 32;
+pub const HIRES_DISPLAY_WIDTH: usize = 128; // SCHIP high resolution mode
+pub const HIRES_DISPLAY_HEIGHT: usize = 64;
+pub const NUM_PLANES: usize = 2; // XO-CHIP bitplanes; other modes only draw to the first
 pub const NUM_KEYS: usize = 16;
+pub const PATTERN_LEN: usize = 16; // XO-CHIP audio pattern buffer: 128 1-bit samples
+const DEFAULT_PITCH: u8 = 64; // XO-CHIP playback rate of 4000 samples/s
 pub const TIMER_FREQ: f32 = 60.0;
 
+// FNV-1a, which unlike `DefaultHasher` is guaranteed to hash the same on every build
+pub(crate) fn fnv1a(data: impl IntoIterator<Item = u8>) -> u64 {
+    data.into_iter().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3)
+    })
+}
+
+// Instruction set extensions the VM can be configured to recognize
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Mode {
+    // The original CHIP-8 instruction set
+    #[default]
+    Chip8,
+    // SUPER-CHIP 1.1: 128x64 high resolution mode, scrolling, 16x16 sprites, and large fonts
+    SuperChip,
+    // XO-CHIP: SUPER-CHIP plus 64KiB of memory, two display bitplanes (4 colors), and
+    // register range loads/stores
+    XoChip,
+    // CHIP-8X: the VIP's CHIP-8 with its color board (foreground colors in 8x1-pixel zones,
+    // and a background color), a second keypad, and I/O port instructions in place of BNNN
+    Chip8X,
+}
+
+impl Mode {
+    // Whether the SUPER-CHIP instructions are available
+    pub fn has_schip(self) -> bool {
+        matches!(self, Mode::SuperChip | Mode::XoChip)
+    }
+
+    // Bytes of memory, interpreter area included, unless set otherwise (see
+    // `Chip8::set_memory_size`)
+    pub fn memory_size(self) -> usize {
+        match self {
+            Mode::XoChip => XO_RAM_SIZE,
+            _ => RAM_SIZE,
+        }
+    }
+
+    // Address programs are loaded, and start running, at
+    pub fn rom_start(self) -> u16 {
+        match self {
+            Mode::Chip8X => CHIP8X_ROM_START,
+            _ => ROM_START,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Chip8 {
-    // RAM of the CHIP-8 VM
-    memory: [u8; RAM_SIZE],
+    // Instruction set extension in use
+    mode: Mode,
+    // Interpretation of ambiguous opcodes
+    quirks: Quirks,
+    // Digit sprites loaded for FX29; they're saved with the rest of memory
+    #[serde(skip)]
+    font: Font,
+    // RAM of the CHIP-8 VM; 4KiB, or 64KiB in XO-CHIP mode, unless set otherwise. Room for
+    // the most that can be addressed is always reserved inline, keeping the core free of
+    // allocations
+    memory: heapless::Vec<u8, XO_RAM_SIZE>,
     // Program Counter
     pc: u16,
     // CHIP-8 call stack; its only purpose is to push/pop any callers' return address
     //   "The original RCA 1802 version allowed up to 12 levels of
     //   nesting; _modern implementations may wish to allocate more_"
-    stack: SmallVec<[u16; STACK_SIZE]>,
+    // Room for `MAX_STACK_DEPTH` return addresses is reserved, of which `stack_depth` can be
+    // used before calls overflow; the limit is a setting, not part of the machine state
+    stack: heapless::Vec<u16, MAX_STACK_DEPTH>,
+    #[serde(skip, default = "default_stack_depth")]
+    stack_depth: usize,
     // I - the address register
     i_reg: u16,
     // V - general purpose data registers
     v_reg: [u8; NUM_DATA_REGS],
 
-    //  Output device: 64x32-pixel monochrome display
+    //  Output device: 64x32-pixel (128x64 in SCHIP hi-res mode) display made up of
+    //  NUM_PLANES monochrome bitplanes, each laid out as
     //    +--------------------+
     //    |(0, 0)       (63, 0)|
     //    |                    |
@@ -96,8 +181,22 @@ pub struct Chip8 {
     //                     w, w+1,  ... , 2w-1
     //                     ...      ... , nw-1
     //                     w(h-1),  ... , wh-1
-    //          and stored as a 2048-bit array
-    display_bus: BitArr!(for DISPLAY_WIDTH * DISPLAY_HEIGHT),
+    //          and stored as an 8192-bit array, of which the first w*h bits are in use
+    //  Pixel color is the combination of the corresponding bits across planes (plane n => bit n)
+    display_bus: [BitArr!(for HIRES_DISPLAY_WIDTH * HIRES_DISPLAY_HEIGHT); NUM_PLANES],
+    // XO-CHIP bitmask of planes affected by drawing, clearing, and scrolling
+    planes: u8,
+    // Whether the display is in SCHIP 128x64 high resolution mode
+    hires: bool,
+    // Region of the display changed since the last call to `take_damage`
+    #[serde(skip)]
+    damage: Option<Rect>,
+    // CHIP-8X foreground color of each 8x1-pixel zone of the display, by row, and the
+    // background color; saved separately from the rest of the state (see `save_state`)
+    #[serde(skip, default = "default_zone_colors")]
+    zone_colors: [[u8; ZONE_COLUMNS]; DISPLAY_HEIGHT],
+    #[serde(skip)]
+    background: u8,
 
     //  Input device: 16-key keypad (0x0-0xF)
     //    +------------+
@@ -109,350 +208,1020 @@ pub struct Chip8 {
     //  Stored as a 16-bit array with the (n as hex)th bit
     //  corresponding to the key state; KEY_UP = 0, KEY_DOWN = 1
     input_bus: BitArr!(for NUM_KEYS),
+    // FX0A: keys that went down since the machine started waiting on one, if it is, and
+    // the key whose release ended the wait; as on the COSMAC VIP, it's a key coming back
+    // up that completes FX0A, so a key held down from before doesn't retrigger it
+    #[serde(skip)]
+    key_wait: Option<InputMsg>,
+    #[serde(skip)]
+    key_released: Option<u8>,
     // General timer used for game events
     delay_timer: u8,
     // Timer for sound effects; a beep is made when the value is nonzero
     sound_timer: u8,
+    // SCHIP flag registers (FX75/FX85); kept on the calculator apart from any program,
+    // so they outlive the machine state, and aren't saved or loaded with it
+    #[serde(skip)]
+    flags: [u8; NUM_FLAGS],
+    // XO-CHIP sound: samples played in place of the beep once F002 loads some, and the
+    // pitch (FX3A) they're played back at; saved separately from the rest of the state
+    // (see `save_state`)
+    #[serde(skip)]
+    audio_pattern: Option<[u8; PATTERN_LEN]>,
+    #[serde(skip)]
+    pitch: u8,
+    // Random number source for CXNN (and FX0A's pick between released keys), owned by the
+    // instance so a seeded run plays out the same every time; saved separately from the
+    // rest of the state (see `save_state`)
+    #[serde(skip, default = "new_rng")]
+    rng: fastrand::Rng,
+    // Patches applied to the running ROM; not part of the machine state
+    #[cfg(feature = "std")]
+    #[serde(skip)]
+    trainer: Trainer,
+    // Debugger watchpoints, and the one hit by the last instruction; not part of the
+    // machine state either
+    #[serde(skip)]
+    watchpoints: heapless::Vec<Watchpoint, MAX_WATCHPOINTS>,
+    #[serde(skip)]
+    watch_hit: Option<Watchpoint>,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chip8 {
     pub fn new() -> Self {
         let mut sys = Chip8 {
-            memory: [0; RAM_SIZE],
+            mode: Mode::default(),
+            quirks: Quirks::default(),
+            font: Font::default(),
+            memory: heapless::Vec::new(),
             pc: ROM_START,
-            stack: SmallVec::new(),
+            stack: heapless::Vec::new(),
+            stack_depth: DEFAULT_STACK_DEPTH,
             i_reg: 0,
             v_reg: [0; NUM_DATA_REGS],
-            display_bus: bitarr![0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            display_bus: [bitarr![0; HIRES_DISPLAY_WIDTH * HIRES_DISPLAY_HEIGHT]; NUM_PLANES],
+            planes: 0b01,
+            hires: false,
+            damage: None,
+            zone_colors: default_zone_colors(),
+            background: 0,
             input_bus: bitarr![0; NUM_KEYS],
+            key_wait: None,
+            key_released: None,
             delay_timer: 0,
             sound_timer: 0,
+            flags: [0; NUM_FLAGS],
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            rng: new_rng(),
+            #[cfg(feature = "std")]
+            trainer: Trainer::default(),
+            watchpoints: heapless::Vec::new(),
+            watch_hit: None,
         };
 
+        sys.set_mode(Mode::default());
         sys.load_fonts();
         sys
     }
 
+    // Should be set before loading a ROM since XO-CHIP changes the size of memory
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.pc = mode.rom_start();
+        // Never fails, `memory` having room for the largest of them
+        let _ = self.memory.resize(mode.memory_size(), 0);
+    }
+
+    // Bytes of memory, interpreter area included, in place of the mode's (e.g. 64KiB for a
+    // CHIP-8 program written for a larger machine); after `set_mode`, which sets it back, and
+    // before loading a ROM. Returns false if `size` leaves no room for a program or is more
+    // than can be addressed (64KiB)
+    pub fn set_memory_size(&mut self, size: usize) -> bool {
+        if !self.memory_size_fits(size) {
+            return false;
+        }
+        // Never fails, having just been checked
+        let _ = self.memory.resize(size, 0);
+        true
+    }
+
+    // Whether `size` bytes of memory hold the interpreter area and a program, and can all be
+    // addressed
+    fn memory_size_fits(&self, size: usize) -> bool {
+        size > self.mode.rom_start() as usize && size <= XO_RAM_SIZE
+    }
+
+    // Subroutines that can be nested before a call overflows the stack (16 by default);
+    // returns false if `depth` is 0 or more than `MAX_STACK_DEPTH`
+    pub fn set_stack_depth(&mut self, depth: usize) -> bool {
+        if !(1..=MAX_STACK_DEPTH).contains(&depth) {
+            return false;
+        }
+        self.stack_depth = depth;
+        true
+    }
+
+    // Set up the machine as `platform` was (its clock rate is up to the caller); like
+    // `set_mode`, before loading a ROM
+    pub fn set_platform(&mut self, platform: &Platform) {
+        self.set_mode(platform.mode);
+        self.set_quirks(platform.quirks);
+        self.set_font(platform.font);
+    }
+
+    pub fn set_font(&mut self, font: Font) {
+        self.font = font;
+        self.load_fonts();
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    // Make random numbers follow the same sequence every run; otherwise they're seeded
+    // from the system's entropy
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.rng.seed(seed);
+    }
+
+    #[cfg(feature = "std")]
+    pub fn set_trainer(&mut self, trainer: Trainer) {
+        self.trainer = trainer;
+    }
+
     fn load_fonts(&mut self) {
-        for (i, font) in FONT_SPRITES.iter().flatten().enumerate() {
-            self.memory[(FONT_START as usize) + i] = *font;
+        // (where it goes, sprite data) for each set the font has
+        let sets: [(u16, &[u8]); 2] = [
+            (FONT_START, self.font.sprites().as_flattened()),
+            (BIG_FONT_START, self.font.big_sprites().as_flattened()),
+        ];
+        for (start, data) in sets {
+            let start = start as usize;
+            self.memory[start..start + data.len()].copy_from_slice(data);
+        }
+    }
+
+    // Current display resolution (w, h)
+    pub fn display_dims(&self) -> (usize, usize) {
+        match self.hires {
+            true => (HIRES_DISPLAY_WIDTH, HIRES_DISPLAY_HEIGHT),
+            false => (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+        }
+    }
+
+    // Number of display planes in use
+    fn num_planes(&self) -> usize {
+        match self.mode {
+            Mode::XoChip => NUM_PLANES,
+            _ => 1,
         }
     }
 
-    pub fn load_rom(&mut self, data: &[u8]) {
+    // Planes currently selected for drawing, as indices into `display_bus`
+    fn selected_planes(&self) -> impl Iterator<Item = usize> {
+        let planes = self.planes;
+        (0..NUM_PLANES).filter(move |p| planes & (1 << p) != 0)
+    }
+
+    fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        for plane in self.display_bus.iter_mut() {
+            plane.fill(false);
+        }
+        self.add_damage(Rect::full(self.display_dims()));
+    }
+
+    fn add_damage(&mut self, rect: Rect) {
+        self.damage = Some(self.damage.map_or(rect, |damage| damage.union(rect)));
+    }
+
+    // Bounding box of the pixels changed since the last call, if any
+    pub fn take_damage(&mut self) -> Option<Rect> {
+        self.damage.take()
+    }
+
+    // Shift the selected planes' contents down by `n` rows
+    fn scroll_down(&mut self, n: usize) {
+        let (w, h) = self.display_dims();
+        let n = n.min(h);
+        self.add_damage(Rect::full((w, h)));
+
+        for p in self.selected_planes() {
+            let display = &mut self.display_bus[p][..w * h];
+            display.copy_within(..(h - n) * w, n * w);
+            display[..n * w].fill(false);
+        }
+    }
+
+    // Shift the selected planes' contents horizontally by `n` columns (positive = right)
+    fn scroll_horizontal(&mut self, n: isize) {
+        let (w, h) = self.display_dims();
+        let shift = n.unsigned_abs().min(w);
+        self.add_damage(Rect::full((w, h)));
+
+        for p in self.selected_planes() {
+            for row in self.display_bus[p][..w * h].chunks_mut(w) {
+                if n > 0 {
+                    row.copy_within(..w - shift, shift);
+                    row[..shift].fill(false);
+                } else {
+                    row.copy_within(shift.., 0);
+                    row[w - shift..].fill(false);
+                }
+            }
+        }
+    }
+
+    // Step over the next instruction; XO-CHIP's F000 NNNN is twice as long as the rest
+    fn skip_instruction(&mut self) {
+        let next = self.pc.wrapping_add(PC_STEP) as usize;
+        let long =
+            self.mode == Mode::XoChip && self.memory.get(next..next + 2) == Some(&[0xF0, 0x00][..]);
+
+        self.pc = self
+            .pc
+            .wrapping_add(if long { 2 * PC_STEP } else { PC_STEP });
+    }
+
+    // Move on from the current instruction without executing it, e.g. to get past an
+    // unrecognized one
+    pub fn advance_pc(&mut self) {
+        self.pc = self.pc.wrapping_add(PC_STEP);
+    }
+
+    pub fn load_rom(&mut self, data: &[u8]) -> Result<(), ChipError> {
         let rom_size = data.len();
-        if rom_size > (ROM_END - ROM_START) as usize {
-            panic!("Insufficient memory: invalid ROM");
+        let start = self.mode.rom_start() as usize;
+        let capacity = self.memory.len() - start;
+        if rom_size > capacity {
+            return Err(ChipError::RomTooLarge {
+                size: rom_size,
+                capacity,
+            });
+        }
+
+        self.memory[start..start + rom_size].copy_from_slice(data);
+        Ok(())
+    }
+
+    // Power the machine back on: memory, registers, timers, the stack, and the display are
+    // cleared and the fonts reloaded, leaving the program to be loaded again. What it's set
+    // up as (mode, quirks, and font), the flag registers, and the random number source are
+    // kept
+    pub fn reset(&mut self) {
+        self.memory.fill(0);
+        self.load_fonts();
+        self.pc = self.mode.rom_start();
+        self.stack.clear();
+        self.i_reg = 0;
+        self.v_reg = [0; NUM_DATA_REGS];
+        for plane in self.display_bus.iter_mut() {
+            plane.fill(false);
         }
+        self.planes = 0b01;
+        self.hires = false;
+        self.zone_colors = default_zone_colors();
+        self.background = 0;
+        self.key_wait = None;
+        self.key_released = None;
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.audio_pattern = None;
+        self.pitch = DEFAULT_PITCH;
+        self.watch_hit = None;
+        self.add_damage(Rect::full(self.display_dims()));
+    }
+
+    // Bounds-checked range of `len` bytes of memory starting at `addr`
+    fn mem_range(&self, addr: usize, len: usize) -> Result<Range<usize>, ChipError> {
+        match addr + len <= self.memory.len() {
+            true => Ok(addr..addr + len),
+            false => Err(ChipError::MemoryOutOfBounds {
+                pc: self.pc,
+                addr,
+                len,
+            }),
+        }
+    }
 
-        let start = ROM_START as usize;
-        let end = (ROM_START as usize) + rom_size;
-        self.memory[start..end].copy_from_slice(data);
+    // `mem_range` for an instruction to read from, checked against the watchpoints
+    fn mem_read(&mut self, addr: usize, len: usize) -> Result<Range<usize>, ChipError> {
+        let range = self.mem_range(addr, len)?;
+        if !self.watchpoints.is_empty() {
+            self.watch_memory(&range, false);
+        }
+        Ok(range)
     }
 
-    pub fn tick_timers(&mut self) -> emulator::Signal {
+    // `mem_range` for an instruction to write to, checked against the watchpoints
+    fn mem_write(&mut self, addr: usize, len: usize) -> Result<Range<usize>, ChipError> {
+        let range = self.mem_range(addr, len)?;
+        if !self.watchpoints.is_empty() {
+            self.watch_memory(&range, true);
+        }
+        Ok(range)
+    }
+
+    pub fn tick_timers(&mut self) -> Events {
         if self.delay_timer > 0 {
             self.delay_timer -= 1;
         }
         if self.sound_timer > 0 {
             self.sound_timer -= 1;
-            emulator::Signal::SoundAudio
+            Events::SOUND_AUDIO
         } else {
-            emulator::Signal::None
+            Events::NONE
         }
     }
 
-    pub fn fetch_instruction(&self) -> Instruction {
+    // Execute the instruction at PC: one cycle of the machine, timers aside
+    //
+    //   Whatever the ROM, this returns an error rather than panicking, e.g. for a sprite
+    //   read past the end of memory or PC running off the end of the address space
+    //
+    pub fn step(&mut self) -> Result<Events, ChipError> {
+        let op = self.fetch_instruction()?;
+        self.exec_instruction(op)
+    }
+
+    // Fetch and decode the instruction at PC
+    //
+    //   Inlined into the run loop, decoding and all, so the `Opcode` is handed on to
+    //   `exec_instruction` in registers instead of through memory
+    //
+    #[inline(always)]
+    pub fn fetch_instruction(&self) -> Result<Opcode, ChipError> {
         // Program Counter is monotonically non-decreasing starting at 0x200;
         // it is up to the ROM to ensure that the PC remains within valid bounds
-        if self.pc < ROM_START || self.pc >= ROM_END {
-            panic!("Segfault: invalid ROM");
+        let pc = self.pc as usize;
+        match self.memory.get(pc..pc + 2) {
+            // CHIP-8 instructions are stored big-endian
+            Some(&[hb, lb]) if self.pc >= ROM_START => self.decode(u16::from_be_bytes([hb, lb])),
+            _ => Err(ChipError::PcOutOfBounds { pc: self.pc }),
         }
+    }
 
-        // CHIP-8 instructions are stored big-endian
-        let hb = self.memory[self.pc as usize];
-        let lb = self.memory[(self.pc + 1) as usize];
-        // Instruction (`modular_bitfield::bitfield`) is constructed lsb -> msb
-        Instruction::from_bytes([lb, hb])
+    // Decode `opcode`, at PC, in the current mode
+    #[inline(always)]
+    fn decode(&self, opcode: u16) -> Result<Opcode, ChipError> {
+        opcode::decode(opcode, self.mode).map_err(|UnknownOpcode(opcode)| {
+            ChipError::UnknownInstruction {
+                pc: self.pc,
+                opcode,
+            }
+        })
     }
 
-    pub fn exec_instruction(&mut self, instr: Instruction) -> emulator::Signal {
+    pub fn exec_instruction(&mut self, op: Opcode) -> Result<Events, ChipError> {
         // Whether to step the PC at the end of cycle - true; false if any jumps are issued
         let mut incr_pc = true;
-        // I/O ret code
-        let mut status = emulator::Signal::None;
-
-        /*
-        println!(
-            "Instruction: {:X}{:X} {:X}{:X} --- PC: {:X}",
-            instr.get_o(), instr.get_x(), instr.get_y(), instr.get_n(), self.pc,
-        );
-        */
-
-        // Decode and excute instruction
-        match (instr.get_o(), instr.get_x(), instr.get_y(), instr.get_n()) {
+        // I/O events raised
+        let mut events = Events::NONE;
+        // Registers as they were, to tell which ones the instruction changes
+        let v_before = match self.watchpoints.is_empty() {
+            true => None,
+            false => {
+                self.watch_hit = None;
+                Some(self.v_reg)
+            }
+        };
+
+        // Trainer rules take precedence over the ROM
+        #[cfg(feature = "std")]
+        let op = match self.trainer.hook(self.pc, op) {
+            Some(Action::Skip) => {
+                self.pc = self.pc.wrapping_add(match op {
+                    Opcode::LoadILong => 2 * PC_STEP,
+                    _ => PC_STEP,
+                });
+                return Ok(events);
+            }
+            Some(Action::Replace(opcode)) => self.decode(opcode)?,
+            None => op,
+        };
+
+        // Execute the decoded instruction
+        match op {
             // 00E0 - CLRS
-            (0x0, 0x0, 0xE, 0x0) => {
-                self.display_bus.fill(false);
+            Opcode::Cls => {
+                for p in self.selected_planes() {
+                    self.display_bus[p].fill(false);
+                }
+                self.add_damage(Rect::full(self.display_dims()));
 
-                status = emulator::Signal::RefreshDisplay;
+                events |= Events::REFRESH_DISPLAY;
             }
             // 00EE - RET
-            (0x0, 0x0, 0xE, 0xE) => {
-                let ret_addr = self.stack.pop().expect("Segfault: invalid ROM");
+            Opcode::Ret => {
+                let ret_addr = self
+                    .stack
+                    .pop()
+                    .ok_or(ChipError::StackUnderflow { pc: self.pc })?;
                 self.pc = ret_addr;
             }
+            // 00CN - SCD nibble (SCHIP)
+            Opcode::ScrollDown(n) => {
+                self.scroll_down(n as usize);
+
+                events |= Events::REFRESH_DISPLAY;
+            }
+            // 00FB - SCR (SCHIP)
+            Opcode::ScrollRight => {
+                self.scroll_horizontal(4);
+
+                events |= Events::REFRESH_DISPLAY;
+            }
+            // 00FC - SCL (SCHIP)
+            Opcode::ScrollLeft => {
+                self.scroll_horizontal(-4);
+
+                events |= Events::REFRESH_DISPLAY;
+            }
+            // 00FD - EXIT (SCHIP)
+            Opcode::Exit => {
+                incr_pc = false;
+                events |= Events::PROGRAM_EXIT;
+            }
+            // 00FE - LOW (SCHIP)
+            Opcode::LowRes => {
+                self.set_hires(false);
+
+                events |= Events::REFRESH_DISPLAY;
+            }
+            // 00FF - HIGH (SCHIP)
+            Opcode::HighRes => {
+                self.set_hires(true);
+
+                events |= Events::REFRESH_DISPLAY;
+            }
+            // 02A0 - STEPBG (CHIP-8X)
+            Opcode::StepBackground => {
+                self.step_background();
+
+                events |= Events::REFRESH_DISPLAY;
+            }
             // 0NNN - SYSC addr (Ignored by modern interpreters)
-            (0x0, _n1, _n2, _n3) => {
-                eprintln!(
-                    "Encountered unsupported instruction - {:#04X}",
-                    u16::from(instr)
-                );
+            Opcode::Sys(_) => {
+                events |= Events::SKIPPED_INSTRUCTION;
             }
             // 1NNN - JMP addr
-            (0x1, _n1, _n2, _n3) => {
-                let addr = instr.get_nnn();
+            Opcode::Jump(addr) => {
                 self.pc = addr;
                 incr_pc = false;
             }
             // 2NNN - CALL addr
-            (0x2, _n1, _n2, _n3) => {
-                let addr = instr.get_nnn();
-                self.stack.push(self.pc);
+            Opcode::Call(addr) => {
+                if self.stack.len() >= self.stack_depth {
+                    return Err(ChipError::StackOverflow { pc: self.pc });
+                }
+                // Never fails, `stack_depth` being at most its capacity
+                let _ = self.stack.push(self.pc);
                 self.pc = addr;
                 incr_pc = false;
             }
             // 3XNN - SKE Vx, byte
-            (0x3, x, _n2, _n3) => {
-                if self.v_reg[x as usize] == instr.get_nn() {
-                    self.pc += PC_STEP;
+            Opcode::SkipEqVx { x, nn } => {
+                if self.v_reg[x as usize] == nn {
+                    self.skip_instruction();
                 }
             }
             // 4XNN - SKNE Vx, byte
-            (0x4, x, _n2, _n3) => {
-                if self.v_reg[x as usize] != instr.get_nn() {
-                    self.pc += PC_STEP;
+            Opcode::SkipNeVx { x, nn } => {
+                if self.v_reg[x as usize] != nn {
+                    self.skip_instruction();
                 }
             }
             // 5XY0 - SKE Vx, Vy
-            (0x5, x, y, 0x0) => {
+            Opcode::SkipEqVxVy { x, y } => {
                 if self.v_reg[x as usize] == self.v_reg[y as usize] {
-                    self.pc += PC_STEP;
+                    self.skip_instruction();
+                }
+            }
+            // 5XY1 - PADD Vx, Vy (CHIP-8X)
+            Opcode::AddPacked { x, y } => {
+                self.v_reg[x as usize] =
+                    color::add_packed(self.v_reg[x as usize], self.v_reg[y as usize]);
+            }
+            // 5XY2 - LD [I], Vx..Vy (XO-CHIP)
+            //   Store registers Vx through Vy (in either order) from addr I onwards; I is unchanged
+            Opcode::StoreRange { x, y } => {
+                let regs = reg_range(x, y);
+                let range = self.mem_write(self.i_reg as usize, regs.len())?;
+                for (addr, reg) in range.zip(regs) {
+                    self.memory[addr] = self.v_reg[reg];
+                }
+            }
+            // 5XY3 - LD Vx..Vy, [I] (XO-CHIP)
+            //   Load registers Vx through Vy (in either order) from addr I onwards; I is unchanged
+            Opcode::LoadRange { x, y } => {
+                let regs = reg_range(x, y);
+                let range = self.mem_read(self.i_reg as usize, regs.len())?;
+                for (addr, reg) in range.zip(regs) {
+                    self.v_reg[reg] = self.memory[addr];
                 }
             }
             // 6XNN - LD Vx, byte
-            (0x6, x, _n2, _n3) => {
-                self.v_reg[x as usize] = instr.get_nn();
+            Opcode::LoadVx { x, nn } => {
+                self.v_reg[x as usize] = nn;
             }
             // 7XNN - ADD Vx, byte
-            (0x7, x, _n2, _n3) => {
-                self.v_reg[x as usize] = self.v_reg[x as usize].wrapping_add(instr.get_nn());
+            Opcode::AddVx { x, nn } => {
+                self.v_reg[x as usize] = self.v_reg[x as usize].wrapping_add(nn);
             }
             // 8XY0 - LD Vx, Vy
-            (0x8, x, y, 0x0) => {
+            Opcode::LoadVxVy { x, y } => {
                 self.v_reg[x as usize] = self.v_reg[y as usize];
             }
             // 8XY1 - OR Vx, Vy
-            (0x8, x, y, 0x1) => {
+            Opcode::Or { x, y } => {
                 self.v_reg[x as usize] |= self.v_reg[y as usize];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             }
             // 8XY2 - AND Vx, Vy
-            (0x8, x, y, 0x2) => {
+            Opcode::And { x, y } => {
                 self.v_reg[x as usize] &= self.v_reg[y as usize];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             }
             // 8XY3 - XOR Vx, Vy
-            (0x8, x, y, 0x3) => {
+            Opcode::Xor { x, y } => {
                 self.v_reg[x as usize] ^= self.v_reg[y as usize];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             }
             // 8XY4 - ADD Vx, Vy; set VF
-            (0x8, x, y, 0x4) => {
+            Opcode::AddVxVy { x, y } => {
                 let (vx, carry) = self.v_reg[x as usize].overflowing_add(self.v_reg[y as usize]);
                 self.v_reg[x as usize] = vx;
                 self.v_reg[0xF] = carry as u8;
             }
             // 8XY5 - SUB Vx, Vy; set VF
-            (0x8, x, y, 0x5) => {
+            Opcode::Sub { x, y } => {
                 let (vx, borrow) = self.v_reg[x as usize].overflowing_sub(self.v_reg[y as usize]);
                 self.v_reg[x as usize] = vx;
                 self.v_reg[0xF] = !borrow as u8;
             }
             // 8XY6 - SHR Vx {, Vy}; set VF
-            //   WARN: There is conflicting info on whether Vx = { Vx >> 1 or Vy >> 1 }
-            (0x8, x, _y, 0x6) => {
+            //   Quirk: Vx = { Vx >> 1 (default) or Vy >> 1 (shift_vy) }
+            Opcode::Shr { x, y } => {
+                if self.quirks.shift_vy {
+                    self.v_reg[x as usize] = self.v_reg[y as usize];
+                }
                 let lsb = self.v_reg[x as usize] & 0x1;
                 self.v_reg[x as usize] >>= 1;
                 self.v_reg[0xF] = lsb;
             }
             // 8XY7 - SUBN Vx, Vy; set VF
-            (0x8, x, y, 0x7) => {
+            Opcode::SubN { x, y } => {
                 let (vx, borrow) = self.v_reg[y as usize].overflowing_sub(self.v_reg[x as usize]);
                 self.v_reg[x as usize] = vx;
                 self.v_reg[0xF] = !borrow as u8;
             }
             // 8XYE - SHL Vx {, Vy}; set VF
-            //   WARN: There is conflicting info on whether Vx = { Vx << 1 or Vy << 1 }
-            (0x8, x, _y, 0xE) => {
+            //   Quirk: Vx = { Vx << 1 (default) or Vy << 1 (shift_vy) }
+            Opcode::Shl { x, y } => {
+                if self.quirks.shift_vy {
+                    self.v_reg[x as usize] = self.v_reg[y as usize];
+                }
                 let msb = (self.v_reg[x as usize] >> (u8::BITS - 1)) & 0x1;
                 self.v_reg[x as usize] <<= 1;
                 self.v_reg[0xF] = msb;
             }
             // 9XY0 - SKNE Vx, Vy
-            (0x9, x, y, 0x0) => {
+            Opcode::SkipNeVxVy { x, y } => {
                 if self.v_reg[x as usize] != self.v_reg[y as usize] {
-                    self.pc += PC_STEP;
+                    self.skip_instruction();
                 }
             }
             // ANNN - LD I, addr
-            (0xA, _n1, _n2, _n3) => {
-                let addr = instr.get_nnn();
+            Opcode::LoadI(addr) => {
                 self.i_reg = addr;
             }
             // BNNN - JMP V0, addr
-            (0xB, _n1, _n2, _n3) => {
-                let addr = instr.get_nnn();
-                self.pc = addr + (self.v_reg[0x0] as u16);
+            //   Quirk: jump to { NNN + V0 (default) or XNN + Vx (jump_vx) }
+            Opcode::JumpOffset { x, nnn: addr } => {
+                let offset = match self.quirks.jump_vx {
+                    true => self.v_reg[x as usize],
+                    false => self.v_reg[0x0],
+                };
+                self.pc = addr + (offset as u16);
                 incr_pc = false;
             }
+            // BXYN - COL Vx, Vy, nibble (CHIP-8X)
+            //   Set zones to foreground color Vy: BXY0 colors blocks of 8x4-pixel zones,
+            //   positioned by Vx horizontally and V(x+1) vertically; BXYN colors N rows of
+            //   the 8x1 zone at pixel (Vx, V(x+1))
+            Opcode::Color { x, y, n } => {
+                let h = self.v_reg[x as usize];
+                let v = self.v_reg[((x + 1) & 0xF) as usize];
+                let color = self.v_reg[y as usize];
+                match n {
+                    0 => self.color_blocks(h, v, color),
+                    n => self.color_rows(h, v, n, color),
+                }
+
+                events |= Events::REFRESH_DISPLAY;
+            }
             // CXNN - RAND Vx, byte
-            (0xC, x, _n2, _n3) => {
-                self.v_reg[x as usize] = fastrand::u8(..) & instr.get_nn();
+            Opcode::Rand { x, nn } => {
+                self.v_reg[x as usize] = self.rng.u8(..) & nn;
             }
             // DXYN - DRAW Vx, Vy, nibble; set VF
             //   Read an n-byte sprite from memory starting at addr I and display onto coordinates (Vx, Vy)
-            //   Detect collision and set VF accordingly; pixels positioned offscreen are wrapped around the display
-            (0xD, x, y, n) => {
-                let sprite = &self.memory[self.i_reg as usize..(self.i_reg + n as u16) as usize];
-                let coord = (self.v_reg[x as usize], self.v_reg[y as usize]);
+            //   Detect collision and set VF accordingly; pixels positioned offscreen are wrapped around the
+            //   display, or clipped without the wrap quirk (the starting coordinates always wrap)
+            //   SCHIP: DXY0 draws a 16x16 sprite stored as 32 bytes (2 bytes per row)
+            //   XO-CHIP: one sprite is drawn to each selected plane, read consecutively from I
+            Opcode::Draw { x, y, n } => {
+                let (width, height) = self.display_dims();
+                let (rows, row_bytes) = match n {
+                    0 if self.mode.has_schip() => (16, 2),
+                    n => (n as usize, 1),
+                };
+                let sprite_len = rows * row_bytes;
+                let coord = (
+                    self.v_reg[x as usize] as usize % width,
+                    self.v_reg[y as usize] as usize % height,
+                );
+                let wrap = self.quirks.wrap;
                 self.v_reg[0xF] = 0;
 
-                for (dy, byte) in sprite.iter().enumerate() {
-                    let coord_y = (coord.1 as usize + dy) % DISPLAY_HEIGHT;
-                    for (dx, bit) in byte.view_bits::<Msb0>().iter().enumerate() {
-                        let coord_x = (coord.0 as usize + dx) % DISPLAY_WIDTH;
-                        let idx = coord_y * DISPLAY_WIDTH + coord_x;
-                        let display_bit = self.display_bus[idx];
+                let planes = self
+                    .selected_planes()
+                    .collect::<heapless::Vec<usize, NUM_PLANES>>();
+                for (n, p) in planes.into_iter().enumerate() {
+                    let start = self.i_reg as usize + n * sprite_len;
+                    let range = self.mem_read(start, sprite_len)?;
+                    let sprite = &self.memory[range];
+                    let display = &mut self.display_bus[p];
 
-                        // Collided if any corresponding sprite and display bits are HIGH (bitwise AND)
-                        self.v_reg[0xF] |= (display_bit & *bit) as u8;
-                        self.display_bus.set(idx, display_bit ^ *bit);
+                    // Drawn a row at a time, XORing the sprite row into the display as a
+                    // whole: first the part that fits before the right edge, then any
+                    // part wrapped around to the left edge
+                    let row_width = row_bytes * 8;
+                    let fits = (width - coord.0).min(row_width);
+                    for (dy, row) in sprite.chunks(row_bytes).enumerate() {
+                        if !wrap && coord.1 + dy >= height {
+                            break;
+                        }
+                        let row_start = (coord.1 + dy) % height * width;
+                        // Leftmost pixel in the lowest bit, as the display stores them
+                        let bits = row.iter().fold(0u32, |bits, &byte| bits << 8 | byte as u32)
+                            << (32 - row_width);
+                        let bits = bits.reverse_bits();
+                        let parts = [
+                            (coord.0, fits, bits & ((1 << fits) - 1)),
+                            (0, row_width - fits, bits >> fits),
+                        ];
+                        for (x0, len, bits) in parts.into_iter().take(1 + wrap as usize) {
+                            if len == 0 {
+                                continue;
+                            }
+                            let pixels = &mut display[row_start + x0..row_start + x0 + len];
+                            let old = pixels.load_le::<u32>();
+                            // Collided if any corresponding sprite and display bits are HIGH (bitwise AND)
+                            self.v_reg[0xF] |= (old & bits != 0) as u8;
+                            pixels.store_le(old ^ bits);
+                        }
                     }
                 }
 
-                status = emulator::Signal::RefreshDisplay;
+                // Span of a sprite along one axis, which covers the whole axis if it wraps around
+                let span = |start: usize, len: usize, max: usize| match start + len <= max {
+                    true => (start, len),
+                    false if wrap => (0, max),
+                    false => (start, max - start),
+                };
+                let (x0, w) = span(coord.0, row_bytes * 8, width);
+                let (y0, h) = span(coord.1, rows, height);
+                if h > 0 {
+                    self.add_damage(Rect { x: x0, y: y0, w, h });
+                }
+
+                events |= Events::REFRESH_DISPLAY;
+                if self.quirks.display_wait {
+                    events |= Events::DISPLAY_WAIT;
+                }
             }
             // EX9E - SKP Vx
-            (0xE, x, 0x9, 0xE) => {
-                let key_down = self.input_bus[self.v_reg[x as usize] as usize];
+            //   Only the low nibble of Vx names a key, as on the VIP
+            Opcode::SkipKey { x } => {
+                let key_down = self.input_bus[(self.v_reg[x as usize] & 0xF) as usize];
                 if key_down {
-                    self.pc += PC_STEP;
+                    self.skip_instruction();
                 }
             }
             // EXA1 - SKNP Vx
-            (0xE, x, 0xA, 0x1) => {
-                let key_down = self.input_bus[self.v_reg[x as usize] as usize];
+            Opcode::SkipNoKey { x } => {
+                let key_down = self.input_bus[(self.v_reg[x as usize] & 0xF) as usize];
                 if !key_down {
-                    self.pc += PC_STEP;
+                    self.skip_instruction();
                 }
             }
+            // EXF2 - SKP2 Vx (CHIP-8X)
+            //   The second keypad isn't emulated, so none of its keys are ever down
+            Opcode::SkipKey2 { .. } => {}
+            // EXF5 - SKNP2 Vx (CHIP-8X)
+            Opcode::SkipNoKey2 { .. } => {
+                self.skip_instruction();
+            }
             // FX07 - LD Vx, DT
-            (0xF, x, 0x0, 0x7) => {
+            Opcode::LoadVxDt { x } => {
                 self.v_reg[x as usize] = self.delay_timer;
             }
             // FX0A - LD Vx, K
-            (0xF, x, 0x0, 0xA) => {
-                // Randomly select a pressed key instead of one with the lowest index; avoids having
-                // a key always taking precedence over another when both are simulatneously pressed
-                let rand = fastrand::usize(0..NUM_KEYS);
-                if let Some(k_idx) = self
-                    .input_bus
-                    .iter()
-                    .skip(rand)
-                    .position(|key_down| *key_down)
-                {
-                    self.v_reg[x as usize] = (rand + k_idx) as u8;
-                } else {
-                    // Block execution (no-op and repeat instr next cycle) until input detected
+            Opcode::WaitKey { x } => match self.key_released.take() {
+                Some(key) => {
+                    self.v_reg[x as usize] = key;
+                    self.key_wait = None;
+                }
+                None => {
+                    // Block execution (no-op and repeat instr next cycle) until a key pressed
+                    // from here on is released (see `receive_key_events`)
+                    self.key_wait.get_or_insert(bitarr![0; NUM_KEYS]);
                     incr_pc = false;
                 }
+            },
+            // F000 NNNN - LD I, long addr (XO-CHIP)
+            //   Load the 16-bit word following this instruction into I, then skip over it
+            Opcode::LoadILong => {
+                let range = self.mem_range(self.pc as usize + PC_STEP as usize, 2)?;
+                let word = &self.memory[range];
+                self.i_reg = u16::from_be_bytes([word[0], word[1]]);
+                self.pc = self.pc.wrapping_add(PC_STEP);
+            }
+            // F002 - AUDIO (XO-CHIP)
+            //   Load the 16 bytes at [I] into the audio pattern buffer
+            Opcode::Audio => {
+                let range = self.mem_read(self.i_reg as usize, PATTERN_LEN)?;
+                let mut pattern = [0; PATTERN_LEN];
+                pattern.copy_from_slice(&self.memory[range]);
+                self.audio_pattern = Some(pattern);
+            }
+            // FN01 - PLANE n (XO-CHIP)
+            //   Select the bitplanes (as a bitmask) affected by drawing, clearing, and scrolling
+            Opcode::Plane(n) => {
+                self.planes = n & 0b11;
             }
             // FX15 - LD DT, Vx
-            (0xF, x, 0x1, 0x5) => {
+            Opcode::LoadDtVx { x } => {
                 self.delay_timer = self.v_reg[x as usize];
             }
             // FX18 - LD ST, Vx
-            (0xF, x, 0x1, 0x8) => {
+            Opcode::LoadStVx { x } => {
                 self.sound_timer = self.v_reg[x as usize];
+                if self.sound_timer > 0 {
+                    events |= Events::SOUND_STARTED;
+                }
             }
             // FX1E - ADD I, Vx
-            (0xF, x, 0x1, 0xE) => {
+            Opcode::AddIVx { x } => {
                 self.i_reg = self.i_reg.wrapping_add(self.v_reg[x as usize] as u16);
             }
             // FX29 - LEA I, F(Vx)
-            (0xF, x, 0x2, 0x9) => {
+            Opcode::Font { x } => {
                 // Address for font sprite representing hex digit '{Vx}'
                 //             = FONT_START + Vx * bytes_per_font_sprite
-                self.i_reg = FONT_START + (self.v_reg[x as usize] as u16) * (FONT_PX_HEIGHT as u16);
+                //   Only the low nibble picks the digit, so I stays within the font
+                self.i_reg =
+                    FONT_START + (self.v_reg[x as usize] as u16 & 0xF) * (FONT_PX_HEIGHT as u16);
+            }
+            // FX30 - LEA I, HF(Vx) (SCHIP)
+            Opcode::BigFont { x } => {
+                // Address for large font sprite representing hex digit '{Vx}'
+                self.i_reg = BIG_FONT_START
+                    + (self.v_reg[x as usize] as u16 & 0xF) * (BIG_FONT_PX_HEIGHT as u16);
+            }
+            // FX3A - PITCH Vx (XO-CHIP)
+            //   Set the audio pattern's playback rate to 4000 * 2^((Vx - 64) / 48) samples/s
+            Opcode::Pitch { x } => {
+                self.pitch = self.v_reg[x as usize];
             }
             // FX33 - LD [I], D2(Vx)
             //           [I + 1], D1(Vx)
             //           [I + 2], D0(Vx)
-            (0xF, x, 0x3, 0x3) => {
+            Opcode::Bcd { x } => {
                 let vx = self.v_reg[x as usize];
                 // Extracts the n-th decimal digit (inline? https://godbolt.org/z/scffbPj7s)
                 let d = |val, n| val / u8::pow(10, n) % 10;
-                self.memory[self.i_reg as usize] = d(vx, 2);
-                self.memory[self.i_reg as usize + 1] = d(vx, 1);
-                self.memory[self.i_reg as usize + 2] = d(vx, 0);
+                let range = self.mem_write(self.i_reg as usize, 3)?;
+                self.memory[range].copy_from_slice(&[d(vx, 2), d(vx, 1), d(vx, 0)]);
             }
             // FX55 - LD [I], V0
             //           [I + 1], V1
             //             ...
             //           [I + x], Vx
-            //   WARN: There is conflicting info on whether I = {I or I + x + 1}
-            (0xF, x, 0x5, 0x5) => {
-                for offset in 0..=(x as usize) {
-                    self.memory[self.i_reg as usize + offset] = self.v_reg[offset];
+            //   Quirk: I = { I (default) or I + x + 1 (memory_increment) }
+            Opcode::StoreRegs { x } => {
+                let range = self.mem_write(self.i_reg as usize, x as usize + 1)?;
+                self.memory[range].copy_from_slice(&self.v_reg[..=(x as usize)]);
+                if self.quirks.memory_increment {
+                    self.i_reg = self.i_reg.wrapping_add(x as u16 + 1);
                 }
             }
             // FX65 - LD Vx, [I]
             //           V1, [I + 1]
             //             ...
             //           Vx, [I + x]
-            //   WARN: There is conflicting info on whether I = {I or I + x + 1}
-            (0xF, x, 0x6, 0x5) => {
-                for offset in 0..=(x as usize) {
-                    self.v_reg[offset] = self.memory[self.i_reg as usize + offset];
+            //   Quirk: I = { I (default) or I + x + 1 (memory_increment) }
+            Opcode::LoadRegs { x } => {
+                let range = self.mem_read(self.i_reg as usize, x as usize + 1)?;
+                self.v_reg[..=(x as usize)].copy_from_slice(&self.memory[range]);
+                if self.quirks.memory_increment {
+                    self.i_reg = self.i_reg.wrapping_add(x as u16 + 1);
                 }
             }
-            (_, _, _, _) => {
-                panic!(
-                    "Encountered unrecognized instruction - {:#04X}: invalid ROM",
-                    u16::from(instr)
-                );
+            // FX75 - LD R, V0..Vx (SCHIP)
+            //   Store registers V0 through Vx in the flag registers
+            Opcode::StoreFlags { x } => {
+                self.flags[..=(x as usize)].copy_from_slice(&self.v_reg[..=(x as usize)]);
+                events |= Events::FLAGS_SAVED;
+            }
+            // FX85 - LD V0..Vx, R (SCHIP)
+            //   Load registers V0 through Vx from the flag registers
+            Opcode::LoadFlags { x } => {
+                self.v_reg[..=(x as usize)].copy_from_slice(&self.flags[..=(x as usize)]);
+            }
+            // FXF8 - OUT Vx, FXFB - IN Vx (CHIP-8X)
+            //   Nothing is attached to the I/O port, so these are skipped like 0NNN
+            Opcode::Output { .. } | Opcode::Input { .. } => {
+                events |= Events::SKIPPED_INSTRUCTION;
             }
         }
 
+        // Off the end of the address space, PC wraps around to 0, where the next fetch stops
         if incr_pc {
-            self.pc += PC_STEP;
+            self.pc = self.pc.wrapping_add(PC_STEP);
+        }
+        if let Some(v_before) = &v_before {
+            events |= self.watch_registers(v_before);
         }
 
-        status
+        Ok(events)
     }
 
     // Rx 16-bit input key state
     pub fn receive_input(&mut self, msg: Option<InputMsg>) {
         if let Some(input) = msg {
+            let events = KeyEvents::between(self.input_bus, input);
             self.input_bus = input;
+            self.receive_key_events(events);
+        }
+    }
+
+    // Rx key transitions; `receive_input` works these out from the key states it's sent,
+    // but a host that sees them directly can pass on ones (e.g. a tap) too quick to show
+    // up between two of those
+    pub fn receive_key_events(&mut self, events: KeyEvents) {
+        let Some(wait) = &mut self.key_wait else {
+            return;
+        };
+        *wait |= events.pressed;
+        if self.key_released.is_some() {
+            return;
+        }
+
+        // Randomly select a released key instead of one with the lowest index; avoids having
+        // a key always taking precedence over another when both are simultaneously released
+        let released = *wait & events.released;
+        let count = released.count_ones();
+        if count > 0 {
+            let pick = self.rng.usize(0..count);
+            self.key_released = released.iter_ones().nth(pick).map(|key| key as u8);
+        }
+    }
+
+    // Keys the machine currently sees held down
+    pub fn keys(&self) -> InputMsg {
+        self.input_bus
+    }
+
+    // Keys FX0A is waiting on a release of, if it's waiting: those that went down since it
+    // started, or none yet, in which case the next key pressed and released will do
+    pub fn key_wait(&self) -> Option<InputMsg> {
+        self.key_wait
+    }
+
+    // Sound timer value; the beeper sounds for this many more timer ticks
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    // SCHIP flag registers, as FX75 last stored them
+    pub fn flags(&self) -> &[u8; NUM_FLAGS] {
+        &self.flags
+    }
+
+    // Restore the flag registers, e.g. as a previous run of the program left them
+    pub fn set_flags(&mut self, flags: [u8; NUM_FLAGS]) {
+        self.flags = flags;
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    // Instruction set extension in use
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    // Address of the next instruction to execute
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    // The machine state as `err` brought it to a halt
+    pub fn fault(&self, err: ChipError) -> FaultInfo {
+        FaultInfo {
+            err,
+            pc: self.pc,
+            opcode: u16::from_be_bytes([
+                self.read_mem(self.pc),
+                self.read_mem(self.pc.wrapping_add(1)),
+            ]),
+            i_reg: self.i_reg,
+            v_reg: self.v_reg,
+        }
+    }
+
+    pub fn i_reg(&self) -> u16 {
+        self.i_reg
+    }
+
+    pub fn v_reg(&self) -> &[u8; NUM_DATA_REGS] {
+        &self.v_reg
+    }
+
+    // Return addresses of the subroutines being executed, innermost last
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    // Tx sound channel: the beep, or the XO-CHIP audio pattern if one's loaded
+    pub fn transmit_audio(&self) -> AudioMsg {
+        match (self.sound_timer > 0, self.audio_pattern) {
+            (true, Some(bits)) => AudioMsg::Pattern {
+                bits,
+                pitch: self.pitch,
+            },
+            (on, _) => AudioMsg::Beep(on),
         }
     }
 
-    // Tx 1-bit sound channel
-    pub fn transmit_audio(&self) -> bool {
-        self.sound_timer > 0
+    // Read a byte of RAM; addresses past the end of memory read as 0
+    pub fn read_mem(&self, addr: u16) -> u8 {
+        self.memory.get(addr as usize).copied().unwrap_or(0)
     }
 
-    // Tx 2048 (64x32) bit display out
-    pub fn transmit_frame(&self) -> &BitSlice<usize> {
-        self.display_bus.as_bitslice()
+    // Tx 2048 (64x32) or 8192 (128x64) bit display out per plane, depending on the resolution
+    // mode; only XO-CHIP transmits more than one plane
+    pub fn transmit_frame(&self) -> heapless::Vec<&BitSlice<usize>, NUM_PLANES> {
+        let (w, h) = self.display_dims();
+        self.display_bus[..self.num_planes()]
+            .iter()
+            .map(|plane| &plane[..w * h])
+            .collect()
     }
 }
+
+// Register indices from x through y, counting down if y < x
+fn reg_range(x: u8, y: u8) -> heapless::Vec<usize, NUM_DATA_REGS> {
+    let (x, y) = (x as usize, y as usize);
+    if x <= y {
+        (x..=y).collect()
+    } else {
+        (y..=x).rev().collect()
+    }
+}
+
+fn default_zone_colors() -> [[u8; ZONE_COLUMNS]; DISPLAY_HEIGHT] {
+    [[color::DEFAULT_FOREGROUND; ZONE_COLUMNS]; DISPLAY_HEIGHT]
+}
+
+fn default_stack_depth() -> usize {
+    DEFAULT_STACK_DEPTH
+}
+
+#[cfg(feature = "std")]
+fn new_rng() -> fastrand::Rng {
+    fastrand::Rng::new()
+}
+
+// Without std there's no entropy to seed from, so hosts should call `set_rng_seed` with
+// some of their own (e.g. from a hardware RNG or a floating ADC pin)
+#[cfg(not(feature = "std"))]
+fn new_rng() -> fastrand::Rng {
+    fastrand::Rng::with_seed(0)
+}
@@ -0,0 +1,469 @@
+use core::fmt;
+
+use super::{Mode, SCHIP_FLAGS};
+
+//    CHIP-8 Instruction Set format:
+//
+//   <-- msb                                                     lsb -->
+//                    |---    x    ---|---    y    ---|
+//    +---------------+---------------+---------------+---------------+
+//    |      n0       |      n1       |      n2       |      n3       |
+//    |  bits 12-15   |   bits 8-11   |   bits 4-7    |   bits 0-3    |
+//    +---------------+---------------+---------------+---------------+
+//    |---    o    ---|---                   nnn                   ---|
+//                                    |---           nn            ---|
+//                                                    |---    n    ---|
+//
+
+// A decoded instruction, with its operands pulled out of the opcode; see
+// `Chip8::exec_instruction` for what each does
+//
+//   Decoding depends on the instruction set extension: SUPER-CHIP's 00FB is a machine code
+//   routine call (0NNN) to plain CHIP-8, and XO-CHIP's 5XY2 isn't an instruction at all;
+//   CHIP-8X goes its own way, with BNNN replaced by its color instructions
+//
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Opcode {
+    // 00E0 - CLRS
+    Cls,
+    // 00EE - RET
+    Ret,
+    // 00CN - SCD nibble (SCHIP)
+    ScrollDown(u8),
+    // 00FB - SCR (SCHIP)
+    ScrollRight,
+    // 00FC - SCL (SCHIP)
+    ScrollLeft,
+    // 00FD - EXIT (SCHIP)
+    Exit,
+    // 00FE - LOW (SCHIP)
+    LowRes,
+    // 00FF - HIGH (SCHIP)
+    HighRes,
+    // 02A0 - STEPBG (CHIP-8X)
+    StepBackground,
+    // 0NNN - SYSC addr
+    Sys(u16),
+    // 1NNN - JMP addr
+    Jump(u16),
+    // 2NNN - CALL addr
+    Call(u16),
+    // 3XNN - SKE Vx, byte
+    SkipEqVx { x: u8, nn: u8 },
+    // 4XNN - SKNE Vx, byte
+    SkipNeVx { x: u8, nn: u8 },
+    // 5XY0 - SKE Vx, Vy
+    SkipEqVxVy { x: u8, y: u8 },
+    // 5XY1 - PADD Vx, Vy (CHIP-8X)
+    AddPacked { x: u8, y: u8 },
+    // 5XY2 - LD [I], Vx..Vy (XO-CHIP)
+    StoreRange { x: u8, y: u8 },
+    // 5XY3 - LD Vx..Vy, [I] (XO-CHIP)
+    LoadRange { x: u8, y: u8 },
+    // 6XNN - LD Vx, byte
+    LoadVx { x: u8, nn: u8 },
+    // 7XNN - ADD Vx, byte
+    AddVx { x: u8, nn: u8 },
+    // 8XY0 - LD Vx, Vy
+    LoadVxVy { x: u8, y: u8 },
+    // 8XY1 - OR Vx, Vy
+    Or { x: u8, y: u8 },
+    // 8XY2 - AND Vx, Vy
+    And { x: u8, y: u8 },
+    // 8XY3 - XOR Vx, Vy
+    Xor { x: u8, y: u8 },
+    // 8XY4 - ADD Vx, Vy
+    AddVxVy { x: u8, y: u8 },
+    // 8XY5 - SUB Vx, Vy
+    Sub { x: u8, y: u8 },
+    // 8XY6 - SHR Vx {, Vy}
+    Shr { x: u8, y: u8 },
+    // 8XY7 - SUBN Vx, Vy
+    SubN { x: u8, y: u8 },
+    // 8XYE - SHL Vx {, Vy}
+    Shl { x: u8, y: u8 },
+    // 9XY0 - SKNE Vx, Vy
+    SkipNeVxVy { x: u8, y: u8 },
+    // ANNN - LD I, addr
+    LoadI(u16),
+    // BNNN - JMP V0, addr (or BXNN - JMP Vx, addr with the jump quirk)
+    JumpOffset { x: u8, nnn: u16 },
+    // BXYN - COL Vx, Vy, nibble (CHIP-8X)
+    Color { x: u8, y: u8, n: u8 },
+    // CXNN - RAND Vx, byte
+    Rand { x: u8, nn: u8 },
+    // DXYN - DRAW Vx, Vy, nibble
+    Draw { x: u8, y: u8, n: u8 },
+    // EX9E - SKP Vx
+    SkipKey { x: u8 },
+    // EXA1 - SKNP Vx
+    SkipNoKey { x: u8 },
+    // EXF2 - SKP2 Vx (CHIP-8X)
+    SkipKey2 { x: u8 },
+    // EXF5 - SKNP2 Vx (CHIP-8X)
+    SkipNoKey2 { x: u8 },
+    // FX07 - LD Vx, DT
+    LoadVxDt { x: u8 },
+    // FX0A - LD Vx, K
+    WaitKey { x: u8 },
+    // F000 NNNN - LD I, long addr (XO-CHIP); the address is the word after it
+    LoadILong,
+    // F002 - AUDIO (XO-CHIP)
+    Audio,
+    // FN01 - PLANE n (XO-CHIP)
+    Plane(u8),
+    // FX15 - LD DT, Vx
+    LoadDtVx { x: u8 },
+    // FX18 - LD ST, Vx
+    LoadStVx { x: u8 },
+    // FX1E - ADD I, Vx
+    AddIVx { x: u8 },
+    // FX29 - LEA I, F(Vx)
+    Font { x: u8 },
+    // FX30 - LEA I, HF(Vx) (SCHIP)
+    BigFont { x: u8 },
+    // FX3A - PITCH Vx (XO-CHIP)
+    Pitch { x: u8 },
+    // FX33 - LD [I], D(Vx)
+    Bcd { x: u8 },
+    // FX55 - LD [I], V0..Vx
+    StoreRegs { x: u8 },
+    // FX65 - LD V0..Vx, [I]
+    LoadRegs { x: u8 },
+    // FX75 - LD R, V0..Vx (SCHIP)
+    StoreFlags { x: u8 },
+    // FX85 - LD V0..Vx, R (SCHIP)
+    LoadFlags { x: u8 },
+    // FXF8 - OUT Vx (CHIP-8X)
+    Output { x: u8 },
+    // FXFB - IN Vx (CHIP-8X)
+    Input { x: u8 },
+}
+
+// An opcode that isn't an instruction in the mode it was decoded for
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct UnknownOpcode(pub u16);
+
+// Decode `opcode` as an instruction of `mode`
+#[inline(always)]
+pub fn decode(opcode: u16, mode: Mode) -> Result<Opcode, UnknownOpcode> {
+    let o = (opcode >> 12) as u8;
+    let x = (opcode >> 8 & 0xF) as u8;
+    let y = (opcode >> 4 & 0xF) as u8;
+    let n = (opcode & 0xF) as u8;
+    let nn = opcode as u8;
+    let nnn = opcode & 0xFFF;
+    let schip = mode.has_schip();
+    let xochip = mode == Mode::XoChip;
+    let chip8x = mode == Mode::Chip8X;
+
+    // Dispatched on the first digit, then whichever digits tell that family apart
+    let op = match o {
+        0x0 => match opcode {
+            0x00E0 => Opcode::Cls,
+            0x00EE => Opcode::Ret,
+            0x00C0..=0x00CF if schip => Opcode::ScrollDown(n),
+            0x00FB if schip => Opcode::ScrollRight,
+            0x00FC if schip => Opcode::ScrollLeft,
+            0x00FD if schip => Opcode::Exit,
+            0x00FE if schip => Opcode::LowRes,
+            0x00FF if schip => Opcode::HighRes,
+            0x02A0 if chip8x => Opcode::StepBackground,
+            _ => Opcode::Sys(nnn),
+        },
+        0x1 => Opcode::Jump(nnn),
+        0x2 => Opcode::Call(nnn),
+        0x3 => Opcode::SkipEqVx { x, nn },
+        0x4 => Opcode::SkipNeVx { x, nn },
+        0x5 => match n {
+            0x0 => Opcode::SkipEqVxVy { x, y },
+            0x1 if chip8x => Opcode::AddPacked { x, y },
+            0x2 if xochip => Opcode::StoreRange { x, y },
+            0x3 if xochip => Opcode::LoadRange { x, y },
+            _ => return Err(UnknownOpcode(opcode)),
+        },
+        0x6 => Opcode::LoadVx { x, nn },
+        0x7 => Opcode::AddVx { x, nn },
+        0x8 => match n {
+            0x0 => Opcode::LoadVxVy { x, y },
+            0x1 => Opcode::Or { x, y },
+            0x2 => Opcode::And { x, y },
+            0x3 => Opcode::Xor { x, y },
+            0x4 => Opcode::AddVxVy { x, y },
+            0x5 => Opcode::Sub { x, y },
+            0x6 => Opcode::Shr { x, y },
+            0x7 => Opcode::SubN { x, y },
+            0xE => Opcode::Shl { x, y },
+            _ => return Err(UnknownOpcode(opcode)),
+        },
+        0x9 if n == 0x0 => Opcode::SkipNeVxVy { x, y },
+        0xA => Opcode::LoadI(nnn),
+        0xB if chip8x => Opcode::Color { x, y, n },
+        0xB => Opcode::JumpOffset { x, nnn },
+        0xC => Opcode::Rand { x, nn },
+        0xD => Opcode::Draw { x, y, n },
+        0xE => match nn {
+            0x9E => Opcode::SkipKey { x },
+            0xA1 => Opcode::SkipNoKey { x },
+            0xF2 if chip8x => Opcode::SkipKey2 { x },
+            0xF5 if chip8x => Opcode::SkipNoKey2 { x },
+            _ => return Err(UnknownOpcode(opcode)),
+        },
+        0xF => match nn {
+            0x07 => Opcode::LoadVxDt { x },
+            0x0A => Opcode::WaitKey { x },
+            0x00 if xochip && x == 0x0 => Opcode::LoadILong,
+            0x02 if xochip && x == 0x0 => Opcode::Audio,
+            0x01 if xochip => Opcode::Plane(x),
+            0x15 => Opcode::LoadDtVx { x },
+            0x18 => Opcode::LoadStVx { x },
+            0x1E => Opcode::AddIVx { x },
+            0x29 => Opcode::Font { x },
+            0x30 if schip => Opcode::BigFont { x },
+            0x3A if xochip => Opcode::Pitch { x },
+            0x33 => Opcode::Bcd { x },
+            0x55 => Opcode::StoreRegs { x },
+            0x65 => Opcode::LoadRegs { x },
+            // The HP48 has 8 flag registers; XO-CHIP extends them to all 16 V registers
+            0x75 if schip && (xochip || (x as usize) < SCHIP_FLAGS) => Opcode::StoreFlags { x },
+            0x85 if schip && (xochip || (x as usize) < SCHIP_FLAGS) => Opcode::LoadFlags { x },
+            0xF8 if chip8x => Opcode::Output { x },
+            0xFB if chip8x => Opcode::Input { x },
+            _ => return Err(UnknownOpcode(opcode)),
+        },
+        _ => return Err(UnknownOpcode(opcode)),
+    };
+
+    Ok(op)
+}
+
+impl Opcode {
+    // The opcode this was decoded from
+    pub fn encode(self) -> u16 {
+        let xy = |o: u16, x: u8, y: u8, n: u16| o << 12 | (x as u16) << 8 | (y as u16) << 4 | n;
+        let xnn = |o: u16, x: u8, nn: u8| o << 12 | (x as u16) << 8 | nn as u16;
+        let fx = |x: u8, nn: u16| 0xF000 | (x as u16) << 8 | nn;
+
+        match self {
+            Opcode::Cls => 0x00E0,
+            Opcode::Ret => 0x00EE,
+            Opcode::ScrollDown(n) => 0x00C0 | n as u16,
+            Opcode::ScrollRight => 0x00FB,
+            Opcode::ScrollLeft => 0x00FC,
+            Opcode::Exit => 0x00FD,
+            Opcode::LowRes => 0x00FE,
+            Opcode::HighRes => 0x00FF,
+            Opcode::StepBackground => 0x02A0,
+            Opcode::Sys(nnn) => nnn,
+            Opcode::Jump(nnn) => 0x1000 | nnn,
+            Opcode::Call(nnn) => 0x2000 | nnn,
+            Opcode::SkipEqVx { x, nn } => xnn(0x3, x, nn),
+            Opcode::SkipNeVx { x, nn } => xnn(0x4, x, nn),
+            Opcode::SkipEqVxVy { x, y } => xy(0x5, x, y, 0x0),
+            Opcode::AddPacked { x, y } => xy(0x5, x, y, 0x1),
+            Opcode::StoreRange { x, y } => xy(0x5, x, y, 0x2),
+            Opcode::LoadRange { x, y } => xy(0x5, x, y, 0x3),
+            Opcode::LoadVx { x, nn } => xnn(0x6, x, nn),
+            Opcode::AddVx { x, nn } => xnn(0x7, x, nn),
+            Opcode::LoadVxVy { x, y } => xy(0x8, x, y, 0x0),
+            Opcode::Or { x, y } => xy(0x8, x, y, 0x1),
+            Opcode::And { x, y } => xy(0x8, x, y, 0x2),
+            Opcode::Xor { x, y } => xy(0x8, x, y, 0x3),
+            Opcode::AddVxVy { x, y } => xy(0x8, x, y, 0x4),
+            Opcode::Sub { x, y } => xy(0x8, x, y, 0x5),
+            Opcode::Shr { x, y } => xy(0x8, x, y, 0x6),
+            Opcode::SubN { x, y } => xy(0x8, x, y, 0x7),
+            Opcode::Shl { x, y } => xy(0x8, x, y, 0xE),
+            Opcode::SkipNeVxVy { x, y } => xy(0x9, x, y, 0x0),
+            Opcode::LoadI(nnn) => 0xA000 | nnn,
+            Opcode::JumpOffset { nnn, .. } => 0xB000 | nnn,
+            Opcode::Color { x, y, n } => xy(0xB, x, y, n as u16),
+            Opcode::Rand { x, nn } => xnn(0xC, x, nn),
+            Opcode::Draw { x, y, n } => xy(0xD, x, y, n as u16),
+            Opcode::SkipKey { x } => xnn(0xE, x, 0x9E),
+            Opcode::SkipNoKey { x } => xnn(0xE, x, 0xA1),
+            Opcode::SkipKey2 { x } => xnn(0xE, x, 0xF2),
+            Opcode::SkipNoKey2 { x } => xnn(0xE, x, 0xF5),
+            Opcode::LoadVxDt { x } => fx(x, 0x07),
+            Opcode::WaitKey { x } => fx(x, 0x0A),
+            Opcode::LoadILong => 0xF000,
+            Opcode::Audio => 0xF002,
+            Opcode::Plane(n) => fx(n, 0x01),
+            Opcode::LoadDtVx { x } => fx(x, 0x15),
+            Opcode::LoadStVx { x } => fx(x, 0x18),
+            Opcode::AddIVx { x } => fx(x, 0x1E),
+            Opcode::Font { x } => fx(x, 0x29),
+            Opcode::BigFont { x } => fx(x, 0x30),
+            Opcode::Pitch { x } => fx(x, 0x3A),
+            Opcode::Bcd { x } => fx(x, 0x33),
+            Opcode::StoreRegs { x } => fx(x, 0x55),
+            Opcode::LoadRegs { x } => fx(x, 0x65),
+            Opcode::StoreFlags { x } => fx(x, 0x75),
+            Opcode::LoadFlags { x } => fx(x, 0x85),
+            Opcode::Output { x } => fx(x, 0xF8),
+            Opcode::Input { x } => fx(x, 0xFB),
+        }
+    }
+}
+
+// Mnemonic, with the same names as the comments above
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Opcode::Cls => write!(f, "CLRS"),
+            Opcode::Ret => write!(f, "RET"),
+            Opcode::ScrollDown(n) => write!(f, "SCD {n:#X}"),
+            Opcode::ScrollRight => write!(f, "SCR"),
+            Opcode::ScrollLeft => write!(f, "SCL"),
+            Opcode::Exit => write!(f, "EXIT"),
+            Opcode::LowRes => write!(f, "LOW"),
+            Opcode::HighRes => write!(f, "HIGH"),
+            Opcode::StepBackground => write!(f, "STEPBG"),
+            Opcode::Sys(nnn) => write!(f, "SYSC {nnn:#05X}"),
+            Opcode::Jump(nnn) => write!(f, "JMP {nnn:#05X}"),
+            Opcode::Call(nnn) => write!(f, "CALL {nnn:#05X}"),
+            Opcode::SkipEqVx { x, nn } => write!(f, "SKE V{x:X}, {nn:#04X}"),
+            Opcode::SkipNeVx { x, nn } => write!(f, "SKNE V{x:X}, {nn:#04X}"),
+            Opcode::SkipEqVxVy { x, y } => write!(f, "SKE V{x:X}, V{y:X}"),
+            Opcode::AddPacked { x, y } => write!(f, "PADD V{x:X}, V{y:X}"),
+            Opcode::StoreRange { x, y } => write!(f, "LD [I], V{x:X}..V{y:X}"),
+            Opcode::LoadRange { x, y } => write!(f, "LD V{x:X}..V{y:X}, [I]"),
+            Opcode::LoadVx { x, nn } => write!(f, "LD V{x:X}, {nn:#04X}"),
+            Opcode::AddVx { x, nn } => write!(f, "ADD V{x:X}, {nn:#04X}"),
+            Opcode::LoadVxVy { x, y } => write!(f, "LD V{x:X}, V{y:X}"),
+            Opcode::Or { x, y } => write!(f, "OR V{x:X}, V{y:X}"),
+            Opcode::And { x, y } => write!(f, "AND V{x:X}, V{y:X}"),
+            Opcode::Xor { x, y } => write!(f, "XOR V{x:X}, V{y:X}"),
+            Opcode::AddVxVy { x, y } => write!(f, "ADD V{x:X}, V{y:X}"),
+            Opcode::Sub { x, y } => write!(f, "SUB V{x:X}, V{y:X}"),
+            Opcode::Shr { x, y } => write!(f, "SHR V{x:X}, V{y:X}"),
+            Opcode::SubN { x, y } => write!(f, "SUBN V{x:X}, V{y:X}"),
+            Opcode::Shl { x, y } => write!(f, "SHL V{x:X}, V{y:X}"),
+            Opcode::SkipNeVxVy { x, y } => write!(f, "SKNE V{x:X}, V{y:X}"),
+            Opcode::LoadI(nnn) => write!(f, "LD I, {nnn:#05X}"),
+            Opcode::JumpOffset { nnn, .. } => write!(f, "JMP V0, {nnn:#05X}"),
+            Opcode::Color { x, y, n } => write!(f, "COL V{x:X}, V{y:X}, {n:#X}"),
+            Opcode::Rand { x, nn } => write!(f, "RAND V{x:X}, {nn:#04X}"),
+            Opcode::Draw { x, y, n } => write!(f, "DRAW V{x:X}, V{y:X}, {n:#X}"),
+            Opcode::SkipKey { x } => write!(f, "SKP V{x:X}"),
+            Opcode::SkipNoKey { x } => write!(f, "SKNP V{x:X}"),
+            Opcode::SkipKey2 { x } => write!(f, "SKP2 V{x:X}"),
+            Opcode::SkipNoKey2 { x } => write!(f, "SKNP2 V{x:X}"),
+            Opcode::LoadVxDt { x } => write!(f, "LD V{x:X}, DT"),
+            Opcode::WaitKey { x } => write!(f, "LD V{x:X}, K"),
+            Opcode::LoadILong => write!(f, "LD I, long"),
+            Opcode::Audio => write!(f, "AUDIO"),
+            Opcode::Plane(n) => write!(f, "PLANE {n:#X}"),
+            Opcode::LoadDtVx { x } => write!(f, "LD DT, V{x:X}"),
+            Opcode::LoadStVx { x } => write!(f, "LD ST, V{x:X}"),
+            Opcode::AddIVx { x } => write!(f, "ADD I, V{x:X}"),
+            Opcode::Font { x } => write!(f, "LEA I, F(V{x:X})"),
+            Opcode::BigFont { x } => write!(f, "LEA I, HF(V{x:X})"),
+            Opcode::Pitch { x } => write!(f, "PITCH V{x:X}"),
+            Opcode::Bcd { x } => write!(f, "LD [I], D(V{x:X})"),
+            Opcode::StoreRegs { x } => write!(f, "LD [I], V0..V{x:X}"),
+            Opcode::LoadRegs { x } => write!(f, "LD V0..V{x:X}, [I]"),
+            Opcode::StoreFlags { x } => write!(f, "LD R, V0..V{x:X}"),
+            Opcode::LoadFlags { x } => write!(f, "LD V0..V{x:X}, R"),
+            Opcode::Output { x } => write!(f, "OUT V{x:X}"),
+            Opcode::Input { x } => write!(f, "IN V{x:X}"),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::chip8::disasm;
+
+    const MODES: [Mode; 4] = [Mode::Chip8, Mode::SuperChip, Mode::XoChip, Mode::Chip8X];
+
+    // Instructions only an extension of CHIP-8 has
+    fn is_extension(op: Opcode) -> bool {
+        matches!(
+            op,
+            Opcode::ScrollDown(_)
+                | Opcode::ScrollRight
+                | Opcode::ScrollLeft
+                | Opcode::Exit
+                | Opcode::LowRes
+                | Opcode::HighRes
+                | Opcode::StepBackground
+                | Opcode::AddPacked { .. }
+                | Opcode::StoreRange { .. }
+                | Opcode::LoadRange { .. }
+                | Opcode::Color { .. }
+                | Opcode::SkipKey2 { .. }
+                | Opcode::SkipNoKey2 { .. }
+                | Opcode::LoadILong
+                | Opcode::Audio
+                | Opcode::Plane(_)
+                | Opcode::BigFont { .. }
+                | Opcode::Pitch { .. }
+                | Opcode::StoreFlags { .. }
+                | Opcode::LoadFlags { .. }
+                | Opcode::Output { .. }
+                | Opcode::Input { .. }
+        )
+    }
+
+    #[test]
+    fn round_trips_through_disassembler() {
+        for mode in MODES {
+            for opcode in 0..=u16::MAX {
+                let decoded = decode(opcode, mode);
+                let mnemonic = disasm::decode(opcode, mode);
+                match decoded {
+                    Ok(op) => {
+                        assert_eq!(op.encode(), opcode, "{op:?} in {mode:?}");
+                        assert_eq!(mnemonic, Some(op.to_string()), "{opcode:#06X} in {mode:?}");
+                    }
+                    Err(UnknownOpcode(unknown)) => {
+                        assert_eq!(unknown, opcode);
+                        assert_eq!(mnemonic, None, "{opcode:#06X} in {mode:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn extensions_rejected_in_chip8() {
+        for opcode in 0..=u16::MAX {
+            if let Ok(op) = decode(opcode, Mode::Chip8) {
+                assert!(!is_extension(op), "{opcode:#06X} decoded as {op:?}");
+            }
+        }
+        // Machine code routines to plain CHIP-8, or no instruction at all
+        assert_eq!(decode(0x00FF, Mode::Chip8), Ok(Opcode::Sys(0x0FF)));
+        assert_eq!(decode(0x5122, Mode::Chip8), Err(UnknownOpcode(0x5122)));
+        assert_eq!(decode(0xF130, Mode::Chip8), Err(UnknownOpcode(0xF130)));
+        assert_eq!(
+            decode(0x5122, Mode::XoChip),
+            Ok(Opcode::StoreRange { x: 1, y: 2 })
+        );
+        assert_eq!(
+            decode(0xB123, Mode::Chip8X),
+            Ok(Opcode::Color { x: 1, y: 2, n: 3 })
+        );
+    }
+
+    #[test]
+    fn every_extension_instruction_decodes_in_its_mode() {
+        let extensions = |mode| {
+            (0..=u16::MAX)
+                .filter_map(|opcode| decode(opcode, mode).ok())
+                .filter(|&op| is_extension(op))
+                .count()
+        };
+        // 00CN, 00FB-00FF, FX30, and FX75/FX85 for the HP48's 8 flag registers
+        assert_eq!(extensions(Mode::SuperChip), 16 + 5 + 16 + 2 * 8);
+        // Those with all 16 flag registers, 5XY2, 5XY3, F000, F002, FN01, and FX3A
+        assert_eq!(
+            extensions(Mode::XoChip),
+            16 + 5 + 16 + 2 * 16 + 2 * 256 + 2 + 16 + 16
+        );
+        // 02A0, 5XY1, BXYN, EXF2, EXF5, FXF8, and FXFB
+        assert_eq!(extensions(Mode::Chip8X), 1 + 256 + 4096 + 4 * 16);
+    }
+}
@@ -0,0 +1,104 @@
+use super::{
+    Font, Mode, Quirks, DISPLAY_HEIGHT, DISPLAY_WIDTH, HIRES_DISPLAY_HEIGHT, HIRES_DISPLAY_WIDTH,
+};
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+// Machine a ROM was written for, bundling everything that sets one apart: the instruction
+// set (and with it memory and display size), quirks, font, and clock rate
+//
+//   ROMs written for one rarely run right on another, e.g. CHIP-48 reinterpreted BNNN and
+//   the shift instructions that COSMAC VIP programs rely on
+//
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Platform {
+    pub mode: Mode,
+    pub quirks: Quirks,
+    pub font: Font,
+    // Instructions executed per second
+    pub clock_hz: f32,
+}
+
+impl Default for Platform {
+    fn default() -> Self {
+        Platform::CHIP8
+    }
+}
+
+impl Platform {
+    // What this core does unless told otherwise, which is what most modern ROMs expect
+    pub const CHIP8: Platform = Platform {
+        mode: Mode::Chip8,
+        quirks: Quirks::DEFAULT,
+        font: Font::Modern,
+        clock_hz: 720.0,
+    };
+    // The COSMAC VIP's original interpreter (1977)
+    pub const VIP: Platform = Platform {
+        mode: Mode::Chip8,
+        quirks: Quirks::VIP,
+        font: Font::Vip,
+        clock_hz: 540.0,
+    };
+    // CHIP-48 on the HP48 calculators (1990)
+    pub const CHIP48: Platform = Platform {
+        mode: Mode::Chip8,
+        quirks: Quirks::CHIP48,
+        font: Font::Modern,
+        clock_hz: 900.0,
+    };
+    // SUPER-CHIP 1.1 on the HP48 calculators (1991)
+    pub const SCHIP: Platform = Platform {
+        mode: Mode::SuperChip,
+        quirks: Quirks::SCHIP,
+        font: Font::Modern,
+        clock_hz: 1800.0,
+    };
+    // CHIP-8X on the COSMAC VIP with its color board (1980)
+    pub const CHIP8X: Platform = Platform {
+        mode: Mode::Chip8X,
+        quirks: Quirks::VIP,
+        font: Font::Vip,
+        clock_hz: 540.0,
+    };
+    // Octo's XO-CHIP (2014), at the 1000 instructions per frame its ROMs commonly ask for
+    pub const XOCHIP: Platform = Platform {
+        mode: Mode::XoChip,
+        quirks: Quirks::XOCHIP,
+        font: Font::Modern,
+        clock_hz: 60_000.0,
+    };
+
+    // Bytes of memory, interpreter area included
+    pub fn memory_size(&self) -> usize {
+        self.mode.memory_size()
+    }
+
+    // Largest display resolution, as (width, height)
+    pub fn display_size(&self) -> (usize, usize) {
+        match self.mode.has_schip() {
+            true => (HIRES_DISPLAY_WIDTH, HIRES_DISPLAY_HEIGHT),
+            false => (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+        }
+    }
+}
+
+// Parses a platform name: `chip8`, `vip`, `chip8x`, `chip48`, `schip`, or `xochip`
+#[cfg(feature = "std")]
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chip8" => Ok(Platform::CHIP8),
+            "vip" => Ok(Platform::VIP),
+            "chip8x" => Ok(Platform::CHIP8X),
+            "chip48" => Ok(Platform::CHIP48),
+            "schip" => Ok(Platform::SCHIP),
+            "xochip" => Ok(Platform::XOCHIP),
+            _ => Err(format!(
+                "unrecognized platform '{s}' (expected chip8, vip, chip8x, chip48, schip, or xochip)"
+            )),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+// Behaviors of ambiguous opcodes that differ between CHIP-8 interpreters; ROMs written
+// for one interpreter may rely on its particular interpretation
+//
+//   The default matches this core's original behavior, which is what most modern ROMs expect
+//
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Quirks {
+    // 8XY6/8XYE: shift Vy into Vx (COSMAC VIP) rather than shifting Vx in place
+    pub shift_vy: bool,
+    // FX55/FX65: leave I pointing past the last register stored/loaded (COSMAC VIP)
+    pub memory_increment: bool,
+    // BNNN: jump to XNN + Vx (SUPER-CHIP) rather than NNN + V0
+    pub jump_vx: bool,
+    // 8XY1/8XY2/8XY3: reset VF to 0 (COSMAC VIP)
+    pub vf_reset: bool,
+    // DXYN: wrap sprite pixels positioned offscreen around the display rather than clipping them
+    pub wrap: bool,
+    // DXYN: wait for the display's vertical blank, at the next timer tick, before carrying
+    // on (COSMAC VIP), so at most 60 sprites are drawn a second. Not saved in save states,
+    // which predate it; it's a setting of the machine rather than part of its state
+    #[serde(skip)]
+    pub display_wait: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::DEFAULT
+    }
+}
+
+impl Quirks {
+    pub const DEFAULT: Quirks = Quirks {
+        shift_vy: false,
+        memory_increment: false,
+        jump_vx: false,
+        vf_reset: false,
+        wrap: true,
+        display_wait: false,
+    };
+    // Quirks of the COSMAC VIP's original interpreter
+    pub const VIP: Quirks = Quirks {
+        shift_vy: true,
+        memory_increment: true,
+        jump_vx: false,
+        vf_reset: true,
+        wrap: false,
+        display_wait: true,
+    };
+    // Quirks of CHIP-48 on the HP48, which left I pointing at the last register (FX55/FX65)
+    // rather than past it; incrementing it comes closest
+    pub const CHIP48: Quirks = Quirks {
+        shift_vy: false,
+        memory_increment: true,
+        jump_vx: true,
+        vf_reset: false,
+        wrap: false,
+        display_wait: false,
+    };
+    // Quirks of SUPER-CHIP 1.1 on the HP48
+    pub const SCHIP: Quirks = Quirks {
+        shift_vy: false,
+        memory_increment: false,
+        jump_vx: true,
+        vf_reset: false,
+        wrap: false,
+        display_wait: false,
+    };
+    // Quirks of Octo's XO-CHIP
+    pub const XOCHIP: Quirks = Quirks {
+        shift_vy: true,
+        memory_increment: true,
+        jump_vx: false,
+        vf_reset: false,
+        wrap: true,
+        display_wait: false,
+    };
+
+    // Override a single quirk by name, e.g. `set("shift", "y")`
+    //
+    //   shift=x|y, memory=keep|increment, jump=v0|vx, vfreset=on|off, wrap=on|off,
+    //   vblank=on|off
+    //
+    #[cfg(feature = "std")]
+    pub fn set(&mut self, quirk: &str, value: &str) -> Result<(), String> {
+        let (field, enabled) = match (quirk, value) {
+            ("shift", "x" | "y") => (&mut self.shift_vy, value == "y"),
+            ("memory", "keep" | "increment") => (&mut self.memory_increment, value == "increment"),
+            ("jump", "v0" | "vx") => (&mut self.jump_vx, value == "vx"),
+            ("vfreset", "on" | "off") => (&mut self.vf_reset, value == "on"),
+            ("wrap", "on" | "off") => (&mut self.wrap, value == "on"),
+            ("vblank", "on" | "off") => (&mut self.display_wait, value == "on"),
+            ("shift" | "memory" | "jump" | "vfreset" | "wrap" | "vblank", _) => {
+                return Err(format!("invalid value '{value}' for quirk '{quirk}'"))
+            }
+            _ => return Err(format!("unrecognized quirk '{quirk}'")),
+        };
+        *field = enabled;
+
+        Ok(())
+    }
+}
+
+// Parses a preset name: `default`, `vip`, `chip48`, `schip`, or `xochip`
+#[cfg(feature = "std")]
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Quirks::default()),
+            "vip" => Ok(Quirks::VIP),
+            "chip48" => Ok(Quirks::CHIP48),
+            "schip" => Ok(Quirks::SCHIP),
+            "xochip" => Ok(Quirks::XOCHIP),
+            _ => Err(format!(
+                "unrecognized quirks preset '{s}' (expected default, vip, chip48, schip, or xochip)"
+            )),
+        }
+    }
+}
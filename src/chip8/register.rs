@@ -0,0 +1,81 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+use super::{Chip8, ChipError};
+
+// Registers of the CHIP-8 VM that can be inspected and written from outside, e.g. by the
+// debugger's console
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+    Delay,
+    Sound,
+}
+
+impl Chip8 {
+    pub fn register(&self, reg: Register) -> u16 {
+        match reg {
+            Register::V(x) => self.v_reg[x as usize] as u16,
+            Register::I => self.i_reg,
+            Register::Pc => self.pc,
+            Register::Delay => self.delay_timer as u16,
+            Register::Sound => self.sound_timer as u16,
+        }
+    }
+
+    // Write `value` to `reg`, truncated to the width of the register
+    pub fn set_register(&mut self, reg: Register, value: u16) {
+        match reg {
+            Register::V(x) => self.v_reg[x as usize] = value as u8,
+            Register::I => self.i_reg = value,
+            Register::Pc => self.pc = value,
+            Register::Delay => self.delay_timer = value as u8,
+            Register::Sound => self.sound_timer = value as u8,
+        }
+    }
+
+    // Write a byte of RAM
+    pub fn write_mem(&mut self, addr: u16, value: u8) -> Result<(), ChipError> {
+        let range = self.mem_range(addr as usize, 1)?;
+        self.memory[range][0] = value;
+        Ok(())
+    }
+}
+
+// Parses `V0`-`VF`, `I`, `PC`, `DT`, or `ST` (case insensitive)
+#[cfg(feature = "std")]
+impl FromStr for Register {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "I" => Ok(Register::I),
+            "PC" => Ok(Register::Pc),
+            "DT" => Ok(Register::Delay),
+            "ST" => Ok(Register::Sound),
+            reg => reg
+                .strip_prefix('V')
+                .filter(|x| x.len() == 1)
+                .and_then(|x| u8::from_str_radix(x, 16).ok())
+                .map(Register::V)
+                .ok_or(format!(
+                    "unrecognized register '{s}' (expected V0-VF, I, PC, DT, or ST)"
+                )),
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{x:X}"),
+            Register::I => write!(f, "I"),
+            Register::Pc => write!(f, "PC"),
+            Register::Delay => write!(f, "DT"),
+            Register::Sound => write!(f, "ST"),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use super::{
+    default_zone_colors, Chip8, ChipError, DEFAULT_PITCH, DISPLAY_HEIGHT, NUM_BACKGROUNDS,
+    NUM_FOREGROUNDS, PATTERN_LEN, ZONE_COLUMNS,
+};
+
+// Save state file layout: MAGIC, a version byte, the bincode-encoded `Chip8` (mode,
+// quirks, memory, registers, stack, timers, and display contents), the state of its RNG
+// (u64 LE), then its XO-CHIP audio: whether a pattern is loaded (0 or 1), the pattern
+// (zeroes if not), and the pitch; then its CHIP-8X colors: the zones' foreground colors,
+// row by row, and the background color
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u8 = 4;
+// Oldest version still loaded; version 1 predates saving the RNG, versions before 3 the
+// audio pattern, and versions before 4 the colors
+const MIN_VERSION: u8 = 1;
+
+impl Chip8 {
+    // Serialize the entire machine state
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = MAGIC.to_vec();
+        state.push(VERSION);
+        bincode::serialize_into(&mut state, self).expect("machine state is always serializable");
+        state.extend_from_slice(&self.rng.get_seed().to_le_bytes());
+        state.push(self.audio_pattern.is_some() as u8);
+        state.extend_from_slice(&self.audio_pattern.unwrap_or_default());
+        state.push(self.pitch);
+        state.extend(self.zone_colors.iter().flatten());
+        state.push(self.background);
+        state
+    }
+
+    // Replace the machine state with one produced by `save_state`; the current state is
+    // left untouched if `state` is invalid
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), ChipError> {
+        let data = state
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or(ChipError::InvalidState("not a save state".to_owned()))?;
+        match data.split_first() {
+            Some((&version, mut data)) if (MIN_VERSION..=VERSION).contains(&version) => {
+                let mut system: Chip8 = bincode::deserialize_from(&mut data)
+                    .map_err(|e| ChipError::InvalidState(e.to_string()))?;
+                // Held to the same bounds as `set_memory_size`, or the next reset would have
+                // no room for the fonts
+                if !system.memory_size_fits(system.memory.len()) {
+                    return Err(ChipError::InvalidState(format!(
+                        "{} bytes of memory is too little for the mode",
+                        system.memory.len()
+                    )));
+                }
+                let truncated = || ChipError::InvalidState("truncated save state".to_owned());
+
+                system.rng = match version {
+                    // Nothing saved, so carry on with the current sequence
+                    1 => self.rng.clone(),
+                    _ => {
+                        let (seed, rest) = data.split_first_chunk().ok_or_else(truncated)?;
+                        data = rest;
+                        fastrand::Rng::with_seed(u64::from_le_bytes(*seed))
+                    }
+                };
+                (system.audio_pattern, system.pitch) = match version {
+                    1 | 2 => (None, DEFAULT_PITCH),
+                    _ => {
+                        let (&[loaded, ref pattern @ .., pitch], rest) = data
+                            .split_first_chunk::<{ PATTERN_LEN + 2 }>()
+                            .ok_or_else(truncated)?;
+                        data = rest;
+                        ((loaded != 0).then_some(*pattern), pitch)
+                    }
+                };
+                (system.zone_colors, system.background) = match version {
+                    1..=3 => (default_zone_colors(), 0),
+                    _ => {
+                        let (colors, rest) = data
+                            .split_first_chunk::<{ ZONE_COLUMNS * DISPLAY_HEIGHT }>()
+                            .ok_or_else(truncated)?;
+                        let (&background, rest) = rest.split_first().ok_or_else(truncated)?;
+                        data = rest;
+                        // Kept in range, whatever the file says
+                        let mut zone_colors = default_zone_colors();
+                        for (zone, &color) in zone_colors.iter_mut().flatten().zip(colors) {
+                            *zone = color % NUM_FOREGROUNDS;
+                        }
+                        (zone_colors, background % NUM_BACKGROUNDS)
+                    }
+                };
+                if !data.is_empty() {
+                    return Err(ChipError::InvalidState(
+                        "unexpected data at the end of the save state".to_owned(),
+                    ));
+                }
+                system.trainer = std::mem::take(&mut self.trainer);
+                system.watchpoints = std::mem::take(&mut self.watchpoints);
+                system.flags = self.flags;
+                system.stack_depth = self.stack_depth;
+                system.font = self.font;
+                system.quirks.display_wait = self.quirks.display_wait;
+                *self = system;
+                Ok(())
+            }
+            Some((version, _)) => Err(ChipError::InvalidState(format!(
+                "unsupported save state version {version}"
+            ))),
+            None => Err(ChipError::InvalidState("truncated save state".to_owned())),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use super::Opcode;
+
+// What to do in place of an instruction a trainer rule covers
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    // Pass over the instruction without executing it
+    Skip,
+    // Execute this opcode instead
+    Replace(u16),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Rule {
+    // Opcode the rule is limited to, if any
+    expect: Option<u16>,
+    action: Action,
+}
+
+// Patches to a ROM's behavior applied as it runs, e.g. for infinite lives, consulted
+// before each instruction is executed
+//
+//   Written one rule per line, with `#` starting a comment:
+//
+//     ADDR[:OPCODE] ACTION
+//
+//   where ACTION is `skip` to pass over the instruction at ADDR, or an opcode to execute
+//   in its place (all in hex). Given OPCODE, the rule only applies while that's the
+//   instruction at ADDR, so it can't misfire on another version of the ROM or on code
+//   the ROM has since overwritten. For example, `2F4:75FF skip` keeps V5 from being
+//   decremented at 0x2F4, and `31A 6509` sets it to 9 instead of whatever 0x31A does.
+//
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Trainer {
+    rules: BTreeMap<u16, Rule>,
+}
+
+impl Trainer {
+    // Action to take in place of `op` at `pc`, if a rule covers it
+    pub fn hook(&self, pc: u16, op: Opcode) -> Option<Action> {
+        self.rules
+            .get(&pc)
+            .filter(|rule| rule.expect.is_none_or(|expect| expect == op.encode()))
+            .map(|rule| rule.action)
+    }
+}
+
+impl FromStr for Trainer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rules = BTreeMap::new();
+        let hex = |num: &str| u16::from_str_radix(num.trim_start_matches("0x"), 16);
+
+        for (n, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let err = |what: &str| format!("line {}: {what} in trainer rule '{line}'", n + 1);
+
+            let (target, action) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| err("missing action"))?;
+            let (addr, expect) = match target.split_once(':') {
+                Some((addr, opcode)) => {
+                    (addr, Some(hex(opcode).map_err(|_| err("invalid opcode"))?))
+                }
+                None => (target, None),
+            };
+            let addr = hex(addr).map_err(|_| err("invalid address"))?;
+            let action = match action.trim() {
+                "skip" => Action::Skip,
+                opcode => Action::Replace(hex(opcode).map_err(|_| err("invalid action"))?),
+            };
+
+            if rules.insert(addr, Rule { expect, action }).is_some() {
+                return Err(err("duplicate address"));
+            }
+        }
+
+        Ok(Trainer { rules })
+    }
+}
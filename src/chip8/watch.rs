@@ -0,0 +1,116 @@
+use core::{fmt, ops::Range};
+#[cfg(feature = "std")]
+use std::str::FromStr;
+
+use super::{Chip8, Events, NUM_DATA_REGS};
+
+// Most watchpoints set at once
+pub const MAX_WATCHPOINTS: usize = 16;
+
+// Access to memory or a register that stops the machine, for the debugger
+//
+//   Instructions are checked against them as they execute: memory is watched through the
+//   reads and writes an instruction makes of it (not through fetching instructions), and
+//   registers through their value changing. An instruction that hits one raises
+//   `Events::WATCHPOINT`, with `watch_hit` saying which
+//
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Watchpoint {
+    // The byte at the address is read
+    Read(u16),
+    // The byte at the address is written
+    Write(u16),
+    // Vx changes value
+    Register(u8),
+}
+
+impl Chip8 {
+    // Start watching for `watchpoint`; returns false if there's no room for any more
+    // (see `MAX_WATCHPOINTS`)
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> bool {
+        self.watchpoints.contains(&watchpoint) || self.watchpoints.push(watchpoint).is_ok()
+    }
+
+    // Stop watching for `watchpoint`; returns whether it was set
+    pub fn remove_watchpoint(&mut self, watchpoint: Watchpoint) -> bool {
+        match self.watchpoints.iter().position(|&w| w == watchpoint) {
+            Some(n) => {
+                self.watchpoints.remove(n);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    // Watchpoint the last instruction executed hit, if any
+    pub fn watch_hit(&self) -> Option<Watchpoint> {
+        self.watch_hit
+    }
+
+    // Check an instruction's access of `range` of memory against the watchpoints
+    pub(super) fn watch_memory(&mut self, range: &Range<usize>, write: bool) {
+        let hit = self.watchpoints.iter().find(|w| match **w {
+            Watchpoint::Read(addr) => !write && range.contains(&(addr as usize)),
+            Watchpoint::Write(addr) => write && range.contains(&(addr as usize)),
+            Watchpoint::Register(_) => false,
+        });
+        if let Some(&hit) = hit {
+            self.watch_hit = Some(hit);
+        }
+    }
+
+    // Check the registers an instruction left behind against the ones it started with,
+    // `before`; returns the events to raise for any watchpoint hit by the instruction
+    pub(super) fn watch_registers(&mut self, before: &[u8; NUM_DATA_REGS]) -> Events {
+        let hit = self.watchpoints.iter().find(|w| match **w {
+            Watchpoint::Register(x) => before[x as usize] != self.v_reg[x as usize],
+            _ => false,
+        });
+        if let Some(&hit) = hit {
+            self.watch_hit = Some(hit);
+        }
+        match self.watch_hit {
+            Some(_) => Events::WATCHPOINT,
+            None => Events::NONE,
+        }
+    }
+}
+
+// Shown as parsed, e.g. `0x0350:w` or `V3`
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Watchpoint::Read(addr) => write!(f, "{addr:#06X}:r"),
+            Watchpoint::Write(addr) => write!(f, "{addr:#06X}:w"),
+            Watchpoint::Register(x) => write!(f, "V{x:X}"),
+        }
+    }
+}
+
+// Parses `V0`-`VF` to watch a register, or a hex address to watch for writes to it,
+// optionally followed by `:w`, or by `:r` to watch for reads instead
+#[cfg(feature = "std")]
+impl FromStr for Watchpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid watchpoint '{s}' (expected V0-VF or ADDR[:r|:w])");
+
+        if let Some(x) = s.strip_prefix(['V', 'v']).filter(|x| x.len() == 1) {
+            return u8::from_str_radix(x, 16)
+                .map(Watchpoint::Register)
+                .map_err(|_| err());
+        }
+        let (addr, access) = s.split_once(':').unwrap_or((s, "w"));
+        let addr = u16::from_str_radix(addr.trim_start_matches("0x"), 16).map_err(|_| err())?;
+        match access {
+            "r" => Ok(Watchpoint::Read(addr)),
+            "w" => Ok(Watchpoint::Write(addr)),
+            _ => Err(err()),
+        }
+    }
+}
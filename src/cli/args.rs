@@ -0,0 +1,1645 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config;
+use crate::driver::{
+    cells::CellMode,
+    keymap::{self, Keymap},
+    orientation::{Orientation, Rotation},
+    palette::{self, Palette},
+    upscale::Filter,
+    waveform::Waveform,
+};
+#[cfg(feature = "recording")]
+use crate::emulator::RecordFormat;
+use crate::emulator::{rom, romdb, snapshot::ImageFormat, LoopTrigger, Pacing, Patch};
+use crate::{
+    chip8::{self, Platform},
+    emulator,
+};
+
+// Command line arguments
+pub struct Args {
+    pub(super) command: Command,
+    // ROM(s) to run; only `check` accepts more than one, and interactive sessions none,
+    // showing the ROM menu instead
+    pub(super) roms: Vec<String>,
+    // Directory the ROM menu lists ROMs from
+    pub(super) rom_dir: PathBuf,
+    pub(super) gui: bool,
+    // Backend for GUI mode, if this build has one
+    pub(super) gui_backend: Option<GuiBackend>,
+    // Draw the display through CRT effects (`--gui-backend=pixels` only)
+    #[cfg_attr(not(feature = "pixels"), allow(dead_code))]
+    pub(super) crt: bool,
+    // Pixel-art upscaler for the display (`--gui-backend=minifb` only)
+    #[cfg_attr(not(feature = "minifb"), allow(dead_code))]
+    pub(super) filter: Option<Filter>,
+    // Present frames in step with the monitor's refresh (sdl2 and pixels backends only)
+    #[cfg_attr(not(any(feature = "sdl2", feature = "pixels")), allow(dead_code))]
+    pub(super) vsync: bool,
+    // Only read when there's more than one to pick from
+    #[cfg_attr(not(feature = "rich-tui"), allow(dead_code))]
+    pub(super) tui_style: TuiStyle,
+    // Backend for TUI mode, if this build has one
+    pub(super) tui_backend: Option<TuiBackend>,
+    pub(super) audio: AudioBackend,
+    // Only read by the backends playing tones themselves
+    #[cfg_attr(
+        not(any(feature = "rodio", feature = "cpal", feature = "sdl2")),
+        allow(dead_code)
+    )]
+    pub(super) wave: Waveform,
+    // In [0, 1]
+    pub(super) volume: f32,
+    // Over the platform's clock rate
+    pub(super) emu_clock_hz: Option<f32>,
+    pub(super) timer_hz: f32,
+    pub(super) pacing: Pacing,
+    pub(super) loop_trigger: LoopTrigger,
+    pub(super) orientation: Orientation,
+    pub(super) ghosting: bool,
+    pub(super) keypad: bool,
+    pub(super) render: CellMode,
+    // Colors for every display, over the devices' own defaults
+    pub(super) palette: Option<Palette>,
+    // Serial port of a hardware keypad
+    pub(super) serial_keypad: Option<String>,
+    #[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+    pub(super) gamepad: bool,
+    pub(super) summary: bool,
+    pub(super) lenient: bool,
+    pub(super) poll_skip: bool,
+    pub(super) stats: bool,
+    pub(super) watch_rom: bool,
+    pub(super) debug: bool,
+    // Start each ROM paused before its first instruction, with the debugger panel shown
+    pub(super) start_paused: bool,
+    pub(super) breakpoints: Vec<u16>,
+    pub(super) break_ops: Vec<emulator::OpcodePattern>,
+    pub(super) watchpoints: Vec<chip8::Watchpoint>,
+    pub(super) sound_log: Option<String>,
+    // Where instructions are traced to, if anywhere: a file, or stderr if None
+    pub(super) trace: Option<Option<PathBuf>>,
+    // Animated GIF/APNG the display is recorded to from the start
+    #[cfg_attr(not(feature = "recording"), allow(dead_code))]
+    pub(super) record: Option<String>,
+    pub(super) state: Option<String>,
+    // Where the machine state is dumped if the ROM crashes
+    pub(super) crash_dump: Option<String>,
+    // Where flag registers are kept, if anywhere
+    pub(super) flags_dir: Option<PathBuf>,
+    pub(super) rewind_secs: usize,
+    // Machine to emulate; left to the ROM database (see `romdb`) for the ROM run when not
+    // given, like the settings of it below
+    pub(super) platform: Option<Platform>,
+    pub(super) mode: Option<chip8::Mode>,
+    pub(super) quirks: Option<chip8::Quirks>,
+    // `--quirk-NAME` overrides, applied on top of the quirks however they're settled
+    pub(super) quirk_overrides: Vec<(String, String)>,
+    // Quirks `compare` runs the ROM under besides its own
+    pub(super) against: Option<QuirkSpec>,
+    pub(super) font: Option<chip8::Font>,
+    pub(super) timing: chip8::Timing,
+    pub(super) remap: bool,
+    // Keymap bindings or file, or a layout preset, used instead of the saved keymap
+    pub(super) keymap: Option<String>,
+    pub(super) layout: Option<Keymap>,
+    pub(super) profile: Option<String>,
+    pub(super) save_profile: Option<String>,
+    pub(super) install_desktop: bool,
+    // Print the built-in ROMs and exit
+    pub(super) list_builtin: bool,
+    pub(super) cycle_budget: u64,
+    pub(super) jobs: usize,
+    // For `render`: frames to run, the image of the last one, and the directory to write
+    // every one to, if any
+    pub(super) render_frames: u64,
+    pub(super) render_out: Option<PathBuf>,
+    pub(super) render_dir: Option<PathBuf>,
+    pub(super) headless: bool,
+    pub(super) max_cycles: Option<u64>,
+    pub(super) run_time: Option<Duration>,
+    pub(super) display_hash: bool,
+    pub(super) profiler: bool,
+    // Tolerance (fraction of the clock rate) to check pacing against, if asked to
+    pub(super) check_pacing: Option<f64>,
+    pub(super) seed: Option<u64>,
+    // Over the mode's memory size and the default stack depth
+    pub(super) memory_size: Option<usize>,
+    pub(super) stack_depth: Option<usize>,
+    pub(super) trainer: Option<chip8::Trainer>,
+    // Bytes of the ROM changed as it's loaded, from --patch and --poke
+    pub(super) patch: Patch,
+    // Rhai script run every frame (see `emulator::Script`)
+    #[cfg_attr(not(feature = "scripting"), allow(dead_code))]
+    pub(super) script: Option<String>,
+    // Netplay session to host or join, and the input delay (frames) to play with
+    pub(super) netplay: Option<NetplayRole>,
+    pub(super) input_delay: usize,
+    // Address to serve the session at for remote play, or to play one served at
+    pub(super) serve: Option<String>,
+    pub(super) connect: Option<String>,
+}
+
+pub(super) enum NetplayRole {
+    Host(String),
+    Join(String),
+}
+
+// What to do with the ROM, given as the first argument (see COMMANDS in the help)
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub(super) enum Command {
+    // Run it, or the ROM menu without one
+    #[default]
+    Run,
+    // Print its instructions
+    Disasm,
+    // Run each ROM given headless and report how the runs ended
+    Check,
+    // Print what's known about it
+    Info,
+    // Run it headless for a number of frames and write the display out as an image
+    Render,
+    // Run it under two sets of quirks in lockstep and report where they part ways
+    Compare,
+}
+
+impl Command {
+    fn name(self) -> &'static str {
+        match self {
+            Command::Run => "run",
+            Command::Disasm => "disasm",
+            Command::Check => "check",
+            Command::Info => "info",
+            Command::Render => "render",
+            Command::Compare => "compare",
+        }
+    }
+
+    // Whether `option` (its long name) has any bearing on the command; `--help` goes for all
+    fn takes(self, option: &str) -> bool {
+        // Settle the machine the ROM runs on (see `Args::rom_settings`)
+        let machine = option.starts_with("quirk-")
+            || [
+                "platform", "schip", "xochip", "quirks", "font", "freq", "timing",
+            ]
+            .contains(&option);
+        let takes = |options: &[&str]| option == "builtin" || options.contains(&option);
+        match self {
+            Command::Run => {
+                !["cycles", "jobs", "frames", "out", "out-dir", "against"].contains(&option)
+            }
+            Command::Disasm => takes(&["platform", "schip", "xochip"]),
+            Command::Check => machine || takes(&["cycles", "jobs", "seed"]),
+            Command::Info => machine || takes(&[]),
+            Command::Render => {
+                machine
+                    || takes(&[
+                        "frames",
+                        "out",
+                        "out-dir",
+                        "seed",
+                        "ram",
+                        "stack-depth",
+                        "lenient",
+                        "trainer",
+                        "patch",
+                        "poke",
+                        "palette",
+                        "fg",
+                        "bg",
+                    ])
+            }
+            Command::Compare => machine || takes(&["against", "cycles", "seed"]),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "run" => Ok(Command::Run),
+            "disasm" => Ok(Command::Disasm),
+            "check" => Ok(Command::Check),
+            "info" => Ok(Command::Info),
+            "render" => Ok(Command::Render),
+            "compare" => Ok(Command::Compare),
+            _ => Err(format!(
+                "unrecognized command '{s}' (expected run, disasm, check, info, render, or \
+                 compare)"
+            )),
+        }
+    }
+}
+
+// Quirks preset to start from, if not the ROM's own quirks, and `--quirk-NAME` style
+// overrides on top of it
+pub(super) struct QuirkSpec {
+    pub(super) preset: Option<chip8::Quirks>,
+    pub(super) overrides: Vec<(String, String)>,
+}
+
+// What TUI mode shows
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum TuiStyle {
+    // Just the display, and the debugger panel while paused
+    Minimal,
+    // The display framed by live machine state panels, drawn with ratatui
+    #[cfg(feature = "rich-tui")]
+    Rich,
+}
+
+impl FromStr for TuiStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minimal" => Ok(TuiStyle::Minimal),
+            #[cfg(feature = "rich-tui")]
+            "rich" => Ok(TuiStyle::Rich),
+            #[cfg(not(feature = "rich-tui"))]
+            "rich" => Err(
+                "the rich TUI isn't part of this build (build with `--features rich-tui`)"
+                    .to_owned(),
+            ),
+            _ => Err(format!(
+                "invalid TUI style '{s}' (expected minimal or rich)"
+            )),
+        }
+    }
+}
+
+// Terminal library driving TUI mode
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum TuiBackend {
+    #[cfg(all(unix, feature = "termion"))]
+    Termion,
+    #[cfg(feature = "crossterm")]
+    Crossterm,
+}
+
+impl TuiBackend {
+    // Termion wherever it's available
+    #[cfg(all(unix, feature = "termion"))]
+    const DEFAULT: Option<Self> = Some(TuiBackend::Termion);
+    #[cfg(all(not(all(unix, feature = "termion")), feature = "crossterm"))]
+    const DEFAULT: Option<Self> = Some(TuiBackend::Crossterm);
+    #[cfg(all(not(all(unix, feature = "termion")), not(feature = "crossterm")))]
+    const DEFAULT: Option<Self> = None;
+}
+
+impl FromStr for TuiBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            #[cfg(all(unix, feature = "termion"))]
+            "termion" => Ok(TuiBackend::Termion),
+            #[cfg(all(unix, not(feature = "termion")))]
+            "termion" => Err(
+                "the 'termion' TUI backend isn't part of this build (build with \
+                 `--features termion`)"
+                    .to_owned(),
+            ),
+            #[cfg(not(unix))]
+            "termion" => Err("the 'termion' TUI backend is only available on Unix".to_owned()),
+            #[cfg(feature = "crossterm")]
+            "crossterm" => Ok(TuiBackend::Crossterm),
+            #[cfg(not(feature = "crossterm"))]
+            "crossterm" => Err(
+                "the 'crossterm' TUI backend isn't part of this build (build with \
+                 `--features crossterm`)"
+                    .to_owned(),
+            ),
+            _ => Err(format!(
+                "invalid TUI backend '{s}' (expected termion or crossterm)"
+            )),
+        }
+    }
+}
+
+// Windowing library driving GUI mode
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum GuiBackend {
+    #[cfg(feature = "minifb")]
+    Minifb,
+    #[cfg(feature = "sdl2")]
+    Sdl2,
+    #[cfg(feature = "pixels")]
+    Pixels,
+}
+
+impl GuiBackend {
+    // minifb wherever it's built in, being the lightest
+    #[cfg(feature = "minifb")]
+    const DEFAULT: Option<Self> = Some(GuiBackend::Minifb);
+    #[cfg(all(not(feature = "minifb"), feature = "sdl2"))]
+    const DEFAULT: Option<Self> = Some(GuiBackend::Sdl2);
+    #[cfg(all(not(feature = "minifb"), not(feature = "sdl2"), feature = "pixels"))]
+    const DEFAULT: Option<Self> = Some(GuiBackend::Pixels);
+    #[cfg(not(any(feature = "minifb", feature = "sdl2", feature = "pixels")))]
+    const DEFAULT: Option<Self> = None;
+}
+
+impl FromStr for GuiBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            #[cfg(feature = "minifb")]
+            "minifb" => Ok(GuiBackend::Minifb),
+            #[cfg(not(feature = "minifb"))]
+            "minifb" => Err(
+                "the 'minifb' GUI backend isn't part of this build (build with \
+                 `--features minifb`)"
+                    .to_owned(),
+            ),
+            #[cfg(feature = "sdl2")]
+            "sdl2" => Ok(GuiBackend::Sdl2),
+            #[cfg(not(feature = "sdl2"))]
+            "sdl2" => Err(
+                "the 'sdl2' GUI backend isn't part of this build (build with \
+                 `--features sdl2`)"
+                    .to_owned(),
+            ),
+            #[cfg(feature = "pixels")]
+            "pixels" => Ok(GuiBackend::Pixels),
+            #[cfg(not(feature = "pixels"))]
+            "pixels" => Err(
+                "the 'pixels' GUI backend isn't part of this build (build with \
+                 `--features pixels`)"
+                    .to_owned(),
+            ),
+            _ => Err(format!(
+                "invalid GUI backend '{s}' (expected minifb, sdl2, or pixels)"
+            )),
+        }
+    }
+}
+
+// Where the sound timer's tone is played
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum AudioBackend {
+    // Terminal bell (BEL control code), TUI mode only
+    Bell,
+    #[cfg(feature = "rodio")]
+    Rodio,
+    #[cfg(feature = "cpal")]
+    Cpal,
+    // The SDL2 window's own audio, SDL2 GUI mode only
+    #[cfg(feature = "sdl2")]
+    Sdl2,
+    // No sound at all, in GUI mode when this build has no native audio
+    #[cfg(not(any(feature = "rodio", feature = "cpal")))]
+    Silent,
+}
+
+impl AudioBackend {
+    // Native audio in GUI mode: cpal, or rodio if that's all this build has
+    #[cfg(feature = "cpal")]
+    const NATIVE: Self = AudioBackend::Cpal;
+    #[cfg(all(not(feature = "cpal"), feature = "rodio"))]
+    const NATIVE: Self = AudioBackend::Rodio;
+    #[cfg(not(any(feature = "rodio", feature = "cpal")))]
+    const NATIVE: Self = AudioBackend::Silent;
+}
+
+impl FromStr for AudioBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bell" => Ok(AudioBackend::Bell),
+            #[cfg(feature = "rodio")]
+            "rodio" => Ok(AudioBackend::Rodio),
+            #[cfg(not(feature = "rodio"))]
+            "rodio" => Err(
+                "the 'rodio' audio backend isn't part of this build (build with \
+                 `--features rodio`)"
+                    .to_owned(),
+            ),
+            #[cfg(feature = "cpal")]
+            "cpal" => Ok(AudioBackend::Cpal),
+            #[cfg(not(feature = "cpal"))]
+            "cpal" => Err(
+                "the 'cpal' audio backend isn't part of this build (build with \
+                 `--features cpal`)"
+                    .to_owned(),
+            ),
+            #[cfg(feature = "sdl2")]
+            "sdl2" => Ok(AudioBackend::Sdl2),
+            #[cfg(not(feature = "sdl2"))]
+            "sdl2" => Err(
+                "the 'sdl2' audio backend isn't part of this build (build with \
+                 `--features sdl2`)"
+                    .to_owned(),
+            ),
+            _ => Err(format!(
+                "invalid audio backend '{s}' (expected bell, rodio, cpal, or sdl2)"
+            )),
+        }
+    }
+}
+
+// Settings for running a particular ROM: those given on the command line, and the rest as
+// the ROM database recommends for it, if it's known there
+pub(super) struct RomSettings {
+    pub(super) platform: Platform,
+    pub(super) palette: Option<Palette>,
+}
+
+impl Args {
+    pub(super) fn rom_settings(&self, program: &[u8]) -> RomSettings {
+        let info = romdb::lookup(program);
+        // `--platform`, then what the ROM was written for, with the options tweaking one on top
+        let mut platform = self
+            .platform
+            .or(info.map(|info| info.platform))
+            .unwrap_or_default();
+        platform.mode = self.mode.unwrap_or(platform.mode);
+        platform.quirks = self.quirks.unwrap_or(platform.quirks);
+        platform.font = self.font.unwrap_or(platform.font);
+        for (name, value) in &self.quirk_overrides {
+            // Checked while parsing the arguments
+            let _ = platform.quirks.set(name, value);
+        }
+        platform.clock_hz = match self.timing {
+            chip8::Timing::Vip => chip8::VIP_CYCLE_RATE,
+            chip8::Timing::Flat => self.emu_clock_hz.unwrap_or(platform.clock_hz),
+        };
+        RomSettings {
+            platform,
+            palette: self.palette.or(info.and_then(|info| info.palette)),
+        }
+    }
+}
+
+// Arguments the binary was run with
+pub fn parse_args() -> Result<Args, lexopt::Error> {
+    parse_args_from(std::env::args_os().skip(1))
+}
+
+// `args` being those after the program name
+pub fn parse_args_from(args: impl IntoIterator<Item = OsString>) -> Result<Args, lexopt::Error> {
+    use lexopt::prelude::*;
+
+    let help_msg = "\
+USAGE:
+    cargo run -- [run] [OPTIONS] [ROM]
+    cargo run -- disasm [--schip | --xochip] <ROM>
+    cargo run -- check [--cycles=NUM] [--jobs=NUM] <ROM>...
+    cargo run -- info [OPTIONS] <ROM>
+    cargo run -- render [--frames=NUM] [--out=FILE] [--out-dir=DIR] <ROM>
+    cargo run -- compare --against=QUIRKS [OPTIONS] <ROM>
+    cargo run -- --install-desktop
+    cargo run -- --list-builtin
+    cargo run -- --headless [--max-cycles=NUM] [--run-seconds=SECS] <ROM>
+    cargo run -- (--host=ADDR | --join=ADDR) [OPTIONS] <ROM>
+
+COMMANDS:
+    run       Run the ROM, or pick one from a menu without it. The default,
+              so `chippity ROM` is the same as `chippity run ROM`.
+    disasm    Print the ROM's instructions (address, raw bytes, and
+              mnemonic) instead of running it. Data mixed in with the code
+              is decoded too, or marked as unknown.
+    check     Run every given ROM headless for a budget of cycles and
+              report how each run ended.
+    info      Print what's known about the ROM: its size and hash, its
+              title if it's in the ROM database, and the platform, quirks,
+              and font it runs with (given the options).
+    render    Run the ROM headless for a number of frames and write the
+              display as it ends up to an image, e.g. a golden image for a
+              regression test, or a screenshot. Random numbers are seeded
+              (see --seed), so the image is the same every time.
+    compare   Run the ROM twice in lockstep, with the quirks it would run
+              with and with those of --against, and report the first
+              instruction after which their PC, registers, stack, or
+              display differ, to find out which quirks it expects.
+
+    Options a command has no use for are rejected, e.g. --gui with
+    `info`. --disassemble and --batch still work as `disasm` and `check`.
+    A ROM file named like a command (e.g. `run`) is run if it exists,
+    rather than taken as the command; `./run` always names the file.
+
+ARGS:
+    <ROM>    Filepath to the CHIP-8 ROM to be read by the emulator. A list of 
+             ROMs released to the public domain can be found at:
+                 - https://zophar.net/pdroms/chip8/chip-8-games-pack.html
+                 - https://johnearnest.github.io/chip8Archive/?sort=platform
+             Besides raw binaries, hex dumps, web pages embedding the
+             program (e.g. Octo HTML exports), ZIP archives holding one
+             ROM, and Octo cartridges (.gif) saved with the program as
+             bytes rather than source are accepted. `-` reads the ROM from stdin (e.g.
+             `curl -s URL | chippity -`), and http:// or https:// URLs
+             are downloaded (built with the `http` feature). Without one,
+             a menu lists the .ch8 and .c8 ROMs in --rom-dir to pick from
+             with keypad keys 5/8 (W/S on QWERTY) and 6 (E); exiting a ROM
+             picked there returns to it.
+             ROMs known to chippity run on the --platform, and with the
+             colors, they were written for, where those aren't given on the
+             command line.
+
+OPTIONS:
+    -h, --help          Print this help message.
+    -g, --gui           GUI mode — run this program in a native window.
+    --gui-backend=NAME  Drive GUI mode with `minifb` (built with the default
+                          `minifb` feature); `sdl2`, which scales the display
+                          on the GPU, holds keys down until they are released,
+                          and plays sound itself (built with the `sdl2`
+                          feature; needs the SDL2 library); or `pixels`, which
+                          draws through wgpu and holds keys down until they
+                          are released (built with the `pixels` feature).
+                          (default: minifb)
+    --crt               Draw the display like a CRT: curved, with scanlines
+                          and glowing phosphors (--gui-backend=pixels only).
+    --filter=NAME       Smooth the display's diagonal edges with a pixel-art
+                          upscaler, `scale2x`, `scale3x`, or `scale4x`, before
+                          scaling it up to the window (--gui-backend=minifb
+                          only).
+    --vsync             Present frames in step with the monitor's refresh, so
+                          they don't tear (--gui-backend=sdl2 or pixels only).
+                          The emulated clock rate is kept either way.
+    -t, --tui[=STYLE]   TUI mode — run this program in the terminal. STYLE is
+                          `minimal`, just the display, or `rich`, the display
+                          beside live panels with the frame and instruction
+                          rates, registers, timers, and a clickable keypad
+                          (built with the `rich-tui` feature; always drawn
+                          with crossterm). (default: minimal)
+    --tui-backend=NAME  Drive TUI mode with `termion` (Unix only; built with
+                          the default `termion` feature) or `crossterm`, which
+                          also runs on Windows and holds keys down until they
+                          are released in terminals that report releases
+                          (built with the `crossterm` feature).
+                          (default: termion)
+    -a, --audio=BACKEND Play sound through BACKEND: `bell` (the terminal's BEL
+                          control code), the native audio host API via
+                          `rodio` or `cpal` (built with the default features
+                          of the same names), or `sdl2` (the SDL2 window's own
+                          audio). You may want native audio if your terminal
+                          emulator does not support BEL. (default: bell, cpal
+                          with --gui, or sdl2 with --gui-backend=sdl2)
+    --wave=WAVE         Shape of the tone played by native audio: `sine`,
+                          `square`, `triangle`, or `sawtooth`. The square wave
+                          sounds closest to the original hardware's buzzer.
+                          (default: sine)
+    --volume=PCT        Play sound at PCT percent of full volume, 0-100; the
+                          terminal bell can only be silenced, at 0. See
+                          HOTKEYS to change it while playing. (default: 100)
+    -f, --freq=NUM      Set the clock rate of the emulator (Hz) to uint NUM
+                          in the range 1–1000000, or `unlimited` to run as
+                          fast as possible (timers still follow the default
+                          clock rate). The display is refreshed once per frame,
+                          a tick of the timers, whatever the clock rate.
+                          (default: 720)
+    --timer-hz=NUM      Tick the delay and sound timers NUM times a second of
+                          emulated time, in the range 1–1000, e.g. 50 as on PAL
+                          machines; frames (and so display refreshes) go by at
+                          the same rate. (default: 60)
+    --practice=TRIGGER  Reload the marked state (see HOTKEYS) whenever
+                          TRIGGER fires: `key`, `timer:SECS`, or
+                          `watch:ADDR=VAL` once RAM[ADDR] becomes VAL.
+                          (default: key)
+    -r, --rotate=DEG    Rotate the display clockwise by DEG degrees: 0, 90,
+                          180, or 270. (default: 0)
+    -m, --mirror        Mirror the display horizontally (after rotation).
+    --ghosting          Keep pixels lit for a couple of frames after they go
+                          dark, like a CRT's phosphor, to stop sprites erased
+                          and redrawn every frame from flickering.
+    -k, --keypad        Show a clickable keypad next to the display in TUI
+                          mode, with the keys the machine sees held (and
+                          those FX0A waits on) under the display, or in a
+                          window of its own in GUI mode (--gui-backend=minifb
+                          only).
+    --render=MODE       Draw pixels in TUI mode as `block` (one full block
+                          per pixel, stretched 2:1), `halfblock` (two pixels
+                          per character), `braille` (2x4 pixels per
+                          character, in one color), or `image` (a bitmap with
+                          square pixels, in terminals supporting sixel or the
+                          kitty graphics protocol with --tui-backend=termion;
+                          halfblock elsewhere). (default: block)
+    --palette=PALETTE   Color the display with a preset palette (`green`,
+                          `amber`, `gameboy`, or `high-contrast`), or with up
+                          to four comma-separated RRGGBB colors: off, on, then
+                          XO-CHIP's two extra colors.
+    --fg=RRGGBB         Color lit pixels RRGGBB (over --palette).
+    --bg=RRGGBB         Color unlit pixels RRGGBB (over --palette).
+    --gamepad           Also take key presses from game controllers: the D-pad
+                          presses 2/4/6/8, the face buttons 5 (south), 0
+                          (east), 7 (west), and 9 (north), the shoulder
+                          buttons 1 and 3, and Start pauses. (built with the
+                          default `gamepad` feature)
+    --serial-keypad=PORT
+                        Also take key presses from a hardware hex keypad on
+                          serial port PORT (e.g. /dev/ttyACM0), which sends a
+                          line per key change: the key's hex digit, then 1 for
+                          down or 0 for up (e.g. `A 1`). Set the port's baud
+                          rate beforehand, e.g. `stty -F PORT 115200 raw`.
+    -s, --summary       Print a summary of the session's performance on exit.
+    --lenient           Skip over unrecognized instructions instead of
+                          stopping; they are listed in the summary.
+    --no-poll-skip      Run loops polling the delay timer instruction by
+                          instruction, rather than skipping ahead to the next
+                          timer tick once one is entered (as jumps to self
+                          and FX0A always are). Implied by --headless.
+    --stats             Show the instructions executed per second against the
+                          clock rate, and the frames presented and timer ticks
+                          per second, in a corner of the display (TUI mode).
+    --watch-rom         Reload the ROM file and start it over whenever it
+                          changes (e.g. when assembled again), keeping the
+                          window or terminal open. Not during netplay.
+    --debug             Show the machine state (registers, stack, and memory)
+                          beside the display while paused.
+    --start-paused      Start the ROM paused before its first instruction,
+                          with the machine state shown as with --debug, so
+                          breakpoints and watchpoints can be set before any
+                          of it runs. Not headless, when serving, or during
+                          netplay.
+    --break=ADDR        Pause before executing the instruction at hex address
+                          ADDR, showing the machine state. May be repeated.
+                          Not during netplay.
+    --break-op=PATTERN  Pause before executing any opcode matching PATTERN,
+                          e.g. DXYN or F?1E (hex digits must match, other
+                          characters are wildcards). May be repeated.
+                          Not during netplay.
+    --watch=WATCHPOINT  Pause after any instruction that writes the byte at
+                          hex address ADDR (`ADDR` or `ADDR:w`), reads it
+                          (`ADDR:r`), or changes register Vx (`VX`, e.g. V3),
+                          showing the instruction responsible. May be
+                          repeated. Not during netplay.
+    --rom-dir=DIR       Directory the ROM menu lists ROMs from. (default: the
+                          path saved in the `rom-dir` file of the config
+                          directory, or the current directory)
+    --state=FILE        Save state file used by the F5/F7 hotkeys; the game
+                          resumes from it if it exists. (default: the ROM
+                          path with .state appended, not resumed)
+    --crash-dump=FILE   File the machine state (registers, stack, the last
+                          instructions executed, and all of memory) is written
+                          to if the ROM crashes, e.g. on an unrecognized
+                          instruction or a return with an empty stack. The
+                          frontend then shows the error and registers until
+                          F2 resets the machine or Esc quits. (default: the
+                          ROM path with .dump appended)
+    --flags-dir=DIR     Directory the flag registers of SCHIP programs (FX75,
+                          often used for high scores) are kept in between
+                          runs, a file per ROM. (default: the flags directory
+                          of the data directory, $XDG_DATA_HOME/chippity, i.e.
+                          ~/.local/share/chippity; none headless)
+    --rewind=SECS       Keep the last SECS seconds of play to rewind through
+                          (see HOTKEYS); 0 disables rewinding. (default: 10)
+    --trace[=FILE]      Log every instruction executed (address, opcode,
+                          mnemonic, and the registers it changed), along with
+                          any warnings, to FILE, or to stderr if it is
+                          redirected (or with --headless).
+    --sound-log=FILE    Write every sound timer activation, timestamped in
+                          emulated time, to FILE as CSV on exit.
+    --record=FILE       Record the display from the start to FILE, an animated
+                          GIF (.gif) or APNG (.png or .apng) in the --palette
+                          colors, written out as the run ends. F9 starts and
+                          stops recording too (built with the default
+                          `recording` feature). (default: the ROM path with
+                          .gif appended, for F9)
+    --platform=NAME     Emulate the machine a ROM was written for, with its
+                          instruction set (and memory and display size),
+                          quirks, font, and clock rate: `chip8`, the defaults
+                          of the options below; `vip`, the COSMAC VIP;
+                          `chip8x`, the VIP with the CHIP-8X color extension;
+                          `chip48` or `schip`, CHIP-48 or SUPER-CHIP 1.1 on
+                          the HP48; or `xochip`. --schip, --xochip, --quirks,
+                          --quirk-NAME, --font, and --freq override its
+                          settings.
+    --schip             Enable the SUPER-CHIP 1.1 instruction set extension
+                          (128x64 hi-res mode, scrolling, 16x16 sprites).
+    --xochip            Enable the XO-CHIP extension on top of SUPER-CHIP
+                          (64KiB of memory, 4-color bitplane graphics).
+    --ram=BYTES         Give the machine BYTES of memory, up to 65536, in
+                          place of its own (4KiB, or 64KiB with XO-CHIP).
+    --stack-depth=NUM   Let subroutines nest NUM deep, up to 256, before a
+                          call overflows the stack. (default: 16)
+    --font=NAME         Hex digit sprites programs draw with: `modern` (or
+                          `octo`), `vip`, the COSMAC VIP's, `dream6800`,
+                          `eti660`, or `schip`, SUPER-CHIP 1.1's own large
+                          digits. (default: the platform's)
+    --timing=MODEL      How long instructions take: `flat`, one clock cycle
+                          each, or `vip`, as many machine cycles as on the
+                          COSMAC VIP (approximately), run at its clock rate,
+                          for ROMs timed by their instructions' speed rather
+                          than the delay timer. Can't be used with --freq.
+                          (default: flat)
+    --seed=NUM          Seed random numbers (CXNN) with uint NUM so every run
+                          of the ROM plays out the same given the same inputs.
+                          (default for `render`: 0)
+    --trainer=FILE      Patch the ROM as it runs with the rules in FILE, e.g.
+                          for infinite lives. Each line is `ADDR[:OPCODE]
+                          ACTION` in hex: ACTION is `skip` to pass over the
+                          instruction at ADDR, or an opcode to run instead;
+                          with OPCODE, only while that's the instruction at
+                          ADDR. e.g. `2F4:75FF skip`. `#` starts a comment.
+    --script=FILE       Run the Rhai script in FILE alongside the ROM, e.g. a
+                          bot playing it: its `fn on_frame(m)` is called every
+                          frame with the registers, memory, and display to
+                          read (`m.v(N)`, `m.peek(ADDR)`, `m.pixel(X, Y)`,
+                          ...), and can hold keys down with `press(KEY)`,
+                          `release(KEY)`, and `tap(KEY)`. (built with the
+                          `scripting` feature)
+    --patch=FILE        Change bytes of the ROM as it's loaded, e.g. for a fan
+                          fix or a cheat, with the IPS patch in FILE, or a
+                          text one with an `ADDR:BYTE` line (in hex) for each
+                          byte. Bytes outside the ROM are an error.
+    --poke=ADDR=BYTE    Change the ROM's byte at ADDR to BYTE as it's loaded,
+                          in hex (e.g. `0x3A2=0xFF`), after any --patch. Can
+                          be given more than once.
+    --quirks=PRESET     Emulate the ambiguous opcode behaviors of another
+                          interpreter: `default`, `vip`, `chip48`, `schip`,
+                          or `xochip`.
+    --quirk-NAME=VAL    Override a single quirk (after any --quirks preset):
+                          shift=x|y, memory=keep|increment, jump=v0|vx,
+                          vfreset=on|off, wrap=on|off, vblank=on|off (DXYN
+                          waits for the next 60Hz frame, as on the VIP)
+    --against=QUIRKS    Quirks for `compare` to run the ROM under besides its
+                          own: a --quirks preset and/or NAME=VAL overrides,
+                          comma-separated (e.g. `schip` or `shift=y,jump=vx`).
+    --keymap=MAP        Bind host keys to CHIP-8 keys with MAP, given as
+                          `host=KEY` pairs (e.g. `1=1,2=2,3=3,4=C,...,v=F`,
+                          or just the keys to change, e.g. `a=4,z=5,q=7,w=A`
+                          for AZERTY) or the path of a file holding them, one
+                          pair per line. Used instead of the saved keymap.
+    --layout=NAME       Bind the keys in the same places on the keyboard as
+                          the default does on QWERTY, for another layout:
+                          `qwerty`, `azerty`, or `colemak`; or `lefty`, under
+                          the right hand (7890/UIOP/JKL;/NM./). Used instead
+                          of the saved keymap.
+    --remap             Interactively choose the key for each CHIP-8 key
+                          before starting. The keymap is saved to
+                          $XDG_CONFIG_HOME/chippity/keymap and reused.
+    --profile=NAME      Start with the settings (keymap, palette, scale,
+                          keypad) of a profile saved in
+                          $XDG_CONFIG_HOME/chippity/profiles/NAME.
+    --save-profile=NAME Save the settings in effect as profile NAME.
+    --cycles=NUM        Cycle budget per ROM for `check` and `compare`.
+                          (default: 100000)
+    --jobs=NUM          Worker threads used by `check`. (default: number of
+                          available CPUs)
+    --frames=NUM        Frames (at 60 per second) to run for `render`.
+                          (default: 600)
+    --out=FILE          Image `render` writes, as PBM (.pbm) or PNG (.png) in
+                          the ROM's colors (PNG built with the default
+                          `recording` feature). (default: the ROM's path, with
+                          .png appended, or .pbm without PNG)
+    --out-dir=DIR       Also have `render` write every frame to DIR, numbered
+                          from 00001, in the format of --out.
+    --install-desktop   Add chippity to the desktop's applications menu (under
+                          $XDG_DATA_HOME, i.e. ~/.local/share), with an icon,
+                          and associate .ch8 files with it so file managers
+                          can open them in GUI mode.
+    --builtin=NAME      Run the ROM named NAME built into chippity instead of
+                          one from a file (built with the `bundled-roms`
+                          feature).
+    --list-builtin      List the ROMs built into chippity, to run with
+                          --builtin.
+    --headless          Run with no input, display, or audio, e.g. for ROM
+                          regression tests in CI. Runs as fast as possible
+                          unless --freq is given.
+    --max-cycles=NUM    End the run after NUM instructions.
+    --run-seconds=SECS  End the run after SECS seconds of emulated time.
+    --display-hash      Print a hash of the display contents at the end of
+                          the run, e.g. to compare against a known good run.
+    --profiler          Count the instructions executed by kind and by address,
+                          and the sprites drawn per second, and print the
+                          busiest of each on exit along with how late the
+                          emulator woke from its sleeps on average.
+    --check-pacing      Measure the instructions executed in each second of
+                          wall-clock time, and how far each sleep overshoots,
+                          and report on exit; fails if any second is off the
+                          clock rate by more than the tolerance.
+    --tolerance=PCT     Tolerance of --check-pacing, in percent. (default: 2)
+    --host=ADDR         Host a two-player netplay session at ADDR (e.g.
+                          0.0.0.0:7777), waiting for the other player to join
+                          with the same ROM and options. Only key presses are
+                          exchanged; both players' keys count as pressed.
+    --join=ADDR         Join the netplay session hosted at ADDR.
+    --input-delay=NUM   Frames that local key presses take to register during
+                          netplay, hiding up to that much latency. (default: 2)
+    --serve=ADDR        Serve the session at ADDR (e.g. 0.0.0.0:7778) for
+                          remote play instead of opening a frontend, waiting
+                          for a client to connect with --connect. The display
+                          and sound are streamed to the client, whose keys
+                          play the game.
+    --connect=ADDR      Play the session served at ADDR on this machine's
+                          frontend, with no ROM of its own.
+
+KEYMAP:
+    +---+---+---+---+
+    | 1 | 2 | 3 | 4 |
+    +---+---+---+---+
+    | Q | W | E | R |
+    +---+---+---+---+
+    | A | S | D | F |
+    +---+---+---+---+
+    | Z | X | C | V |
+    +---+---+---+---+
+
+HOTKEYS:
+    M                   Mark the current machine state for practice.
+    N                   Return to the marked machine state.
+    O                   Rotate the display 90 degrees clockwise.
+    H                   Toggle horizontal mirroring of the display.
+    P                   Switch to the next saved profile.
+    F2                  Start the program over (reset the machine and load
+                          the ROM again).
+    F5                  Save the machine state to the save state file.
+    F7                  Load the machine state from the save state file.
+    F8                  Switch between the terminal and a window (see
+                          --tui-backend and --gui-backend), keeping the
+                          machine running.
+    F9                  Start or stop recording the display (see --record).
+    Backspace           Hold to rewind.
+    = / -               Double or halve the clock speed.
+    0 / 9               Raise or lower the volume by 10%.
+    8                   Mute or unmute the sound.
+    Tab                 Hold to fast-forward at 8x speed (toggles in terminals
+                          that don't report key releases).
+    Space               Pause or resume.
+    .                   Execute a single instruction while paused.
+    ,                   Run to the end of the current 60Hz frame while paused
+                          (as many instructions as the clock rate fits in).
+    B                   Set or clear a breakpoint at the PC while paused.
+    [ ]                 Scroll the memory view while paused (see --debug).
+    :                   Type a debugger console command while paused (read
+                          from stdin lines with --gui), e.g. `peek 0x3A0 4`,
+                          `poke 0x3A0 0xFF`, `set I 0x200`, `get V3`, or
+                          `undo` to revert the last poke or set. Monitor-style
+                          `m ADDR [LEN]` (hex dump), `w ADDR VAL...` (write),
+                          `r` (registers), and `g` (resume) work too, as do
+                          `watch WATCHPOINT` and `unwatch WATCHPOINT` (see
+                          --watch).";
+
+    let mut roms = Vec::new();
+    let mut rom_dir = None;
+    let mut gui = false;
+    let mut tui_style = TuiStyle::Minimal;
+    let mut tui_backend = None;
+    let mut gui_backend = None;
+    let mut crt = false;
+    let mut filter = None;
+    let mut vsync = false;
+    let mut audio = None;
+    let mut wave = Waveform::default();
+    let mut volume = 100;
+    let mut emu_clock_hz = emulator::DEFAULT_CLOCK_FREQ;
+    let mut timer_hz = chip8::TIMER_FREQ;
+    let mut pacing = Pacing::Realtime;
+    let mut loop_trigger = LoopTrigger::Key;
+    let mut orientation = Orientation::default();
+    let mut ghosting = false;
+    let mut keypad = false;
+    let mut render = CellMode::default();
+    let mut palette = None;
+    let mut fg = None;
+    let mut bg = None;
+    let mut serial_keypad = None;
+    let mut gamepad = false;
+    let mut install_desktop = false;
+    let mut list_builtin = false;
+    let mut summary = false;
+    let mut lenient = false;
+    let mut poll_skip = true;
+    let mut stats = false;
+    let mut watch_rom = false;
+    let mut debug = false;
+    let mut start_paused = false;
+    let mut breakpoints = Vec::new();
+    let mut break_ops = Vec::new();
+    let mut watchpoints = Vec::new();
+    let mut sound_log = None;
+    let mut trace = None;
+    #[cfg_attr(not(feature = "recording"), allow(unused_mut))]
+    let mut record = None;
+    let mut state = None;
+    let mut memory_size = None;
+    let mut stack_depth = None;
+    let mut crash_dump = None;
+    let mut flags_dir = None;
+    let mut rewind_secs = emulator::DEFAULT_REWIND_SECS;
+    let mut platform = None;
+    let mut mode = None;
+    let mut quirks: Option<chip8::Quirks> = None;
+    let mut quirk_overrides = Vec::new();
+    let mut against = None;
+    let mut font = None;
+    let mut timing = chip8::Timing::default();
+    let mut remap = false;
+    let mut keymap = None;
+    let mut layout = None;
+    let mut profile = None;
+    let mut save_profile = None;
+    let mut cycle_budget = 100_000;
+    let mut render_frames = 600;
+    let mut render_out = None;
+    let mut render_dir = None;
+    let mut jobs = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut headless = false;
+    let mut max_cycles = None;
+    let mut run_time = None;
+    let mut display_hash = false;
+    let mut check_pacing = false;
+    let mut profiler = false;
+    let mut tolerance = 2.0;
+    let mut seed = None;
+    let mut trainer = None;
+    let mut patch = None;
+    let mut pokes = Vec::new();
+    let mut script = None;
+    let mut netplay = None;
+    let mut input_delay = emulator::DEFAULT_INPUT_DELAY;
+    let mut serve = None;
+    let mut connect = None;
+    let mut freq_given = false;
+
+    // Anything but a command up front is taken as a ROM to run, i.e. `run` is optional; so is
+    // a file that happens to be named like one
+    let mut args = args.into_iter().peekable();
+    let given_command = args
+        .peek()
+        .filter(|arg| !Path::new(arg).exists())
+        .and_then(|arg| arg.to_str()?.parse::<Command>().ok());
+    if given_command.is_some() {
+        args.next();
+    }
+    let mut command = given_command.unwrap_or_default();
+    let mut parser = lexopt::Parser::from_args(args);
+    // Long names of the options given, checked against the command once it's settled
+    let mut options = Vec::new();
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Short(c) => options.push(
+                match c {
+                    'g' => "gui",
+                    't' => "tui",
+                    'a' => "audio",
+                    'f' => "freq",
+                    'r' => "rotate",
+                    'm' => "mirror",
+                    'k' => "keypad",
+                    's' => "summary",
+                    _ => "help",
+                }
+                .to_owned(),
+            ),
+            Long(name) if !matches!(name, "disassemble" | "batch") => options.push(name.to_owned()),
+            _ => (),
+        }
+        match arg {
+            Short('g') | Long("gui") => {
+                gui = true;
+            }
+            Short('t') | Long("tui") => {
+                gui = false;
+                if let Some(style) = parser.optional_value() {
+                    tui_style = style.parse()?;
+                }
+            }
+            Long("gui-backend") => {
+                gui_backend = Some(parser.value()?.parse()?);
+            }
+            Long("crt") => {
+                crt = true;
+            }
+            Long("filter") => {
+                filter = Some(parser.value()?.parse()?);
+            }
+            Long("vsync") => {
+                vsync = true;
+            }
+            Long("tui-backend") => {
+                tui_backend = Some(parser.value()?.parse()?);
+            }
+            Short('a') | Long("audio") => {
+                audio = Some(parser.value()?.parse()?);
+            }
+            Long("wave") => {
+                wave = parser.value()?.parse()?;
+            }
+            Long("volume") => {
+                volume = parser.value()?.parse::<u8>()?;
+                if volume > 100 {
+                    return Err("out of bounds value for option '--volume'".into());
+                }
+            }
+            Short('f') | Long("freq") => {
+                let value = parser.value()?;
+                (emu_clock_hz, pacing) = match value.to_str() {
+                    Some("unlimited") => (emulator::DEFAULT_CLOCK_FREQ, Pacing::Unthrottled),
+                    _ => (value.parse::<u32>()? as f32, Pacing::Realtime),
+                };
+                if !(1.0..=emulator::MAX_CLOCK_FREQ).contains(&emu_clock_hz) {
+                    return Err("out of bounds value for option '--freq'".into());
+                }
+                freq_given = true;
+            }
+            Long("timer-hz") => {
+                timer_hz = parser.value()?.parse::<f32>()?;
+                if !(1.0..=emulator::MAX_TIMER_FREQ).contains(&timer_hz) {
+                    return Err("out of bounds value for option '--timer-hz'".into());
+                }
+            }
+            Long("practice") => {
+                loop_trigger = parser.value()?.parse()?;
+            }
+            Short('r') | Long("rotate") => {
+                orientation.rotation = parser.value()?.parse::<Rotation>()?;
+            }
+            Short('m') | Long("mirror") => {
+                orientation.mirror = true;
+            }
+            Long("ghosting") => {
+                ghosting = true;
+            }
+            Short('k') | Long("keypad") => {
+                keypad = true;
+            }
+            Long("render") => {
+                render = parser.value()?.parse()?;
+            }
+            Long("palette") => {
+                palette = Some(parser.value()?.parse::<Palette>()?);
+            }
+            Long("fg") => {
+                fg = Some(palette::parse_color(&parser.value()?.string()?)?);
+            }
+            Long("bg") => {
+                bg = Some(palette::parse_color(&parser.value()?.string()?)?);
+            }
+            Long("gamepad") => {
+                if !cfg!(feature = "gamepad") {
+                    return Err(
+                        "'--gamepad' needs chippity built with the `gamepad` feature".into(),
+                    );
+                }
+                gamepad = true;
+            }
+            // Modes of running from before there were commands
+            Long(name @ ("disassemble" | "batch")) => {
+                let alias = match name {
+                    "batch" => Command::Check,
+                    _ => Command::Disasm,
+                };
+                if given_command.is_some_and(|given| given != alias) {
+                    return Err(
+                        format!("'--{name}' can't be used with `{}`", command.name()).into(),
+                    );
+                }
+                command = alias;
+            }
+            Long("install-desktop") => {
+                install_desktop = true;
+            }
+            Long("list-builtin") => {
+                list_builtin = true;
+            }
+            Long("builtin") => {
+                let name = parser.value()?.string()?;
+                if !rom::BUILTIN.iter().any(|(builtin, ..)| *builtin == name) {
+                    return Err(if cfg!(feature = "bundled-roms") {
+                        format!("no built-in ROM named '{name}' (see --list-builtin)").into()
+                    } else {
+                        "'--builtin' needs chippity built with the `bundled-roms` feature".into()
+                    });
+                }
+                roms.push(format!("{}{name}", rom::BUILTIN_PREFIX));
+            }
+            Long("serial-keypad") => {
+                serial_keypad = Some(parser.value()?.string()?);
+            }
+            Short('s') | Long("summary") => {
+                summary = true;
+            }
+            Long("lenient") => {
+                lenient = true;
+            }
+            Long("no-poll-skip") => {
+                poll_skip = false;
+            }
+            Long("stats") => {
+                stats = true;
+            }
+            Long("watch-rom") => {
+                watch_rom = true;
+            }
+            Long("debug") => {
+                debug = true;
+            }
+            Long("start-paused") => {
+                start_paused = true;
+            }
+            Long("break") => {
+                breakpoints.push(parser.value()?.parse_with(|addr| {
+                    let addr = addr.strip_prefix("0x").unwrap_or(addr);
+                    u16::from_str_radix(addr, 16)
+                })?);
+            }
+            Long("break-op") => {
+                break_ops.push(parser.value()?.parse()?);
+            }
+            Long("watch") => {
+                if watchpoints.len() == chip8::MAX_WATCHPOINTS {
+                    return Err(format!(
+                        "at most {} watchpoints can be set",
+                        chip8::MAX_WATCHPOINTS
+                    )
+                    .into());
+                }
+                watchpoints.push(parser.value()?.parse()?);
+            }
+            Long("rom-dir") => {
+                rom_dir = Some(PathBuf::from(parser.value()?));
+            }
+            Long("state") => {
+                state = Some(parser.value()?.string()?);
+            }
+            Long("crash-dump") => {
+                crash_dump = Some(parser.value()?.string()?);
+            }
+            Long("flags-dir") => {
+                flags_dir = Some(PathBuf::from(parser.value()?));
+            }
+            Long("rewind") => {
+                rewind_secs = parser.value()?.parse()?;
+            }
+            Long("sound-log") => {
+                sound_log = Some(parser.value()?.string()?);
+            }
+            #[cfg(feature = "recording")]
+            Long("record") => {
+                let path = parser.value()?.string()?;
+                if RecordFormat::from_path(Path::new(&path)).is_none() {
+                    return Err(format!(
+                        "invalid recording file '{path}' (expected a .gif, .png, or .apng file)"
+                    )
+                    .into());
+                }
+                record = Some(path);
+            }
+            #[cfg(not(feature = "recording"))]
+            Long("record") => {
+                return Err("'--record' needs chippity built with the `recording` feature".into());
+            }
+            Long("platform") => {
+                platform = Some(parser.value()?.parse()?);
+            }
+            Long("font") => {
+                font = Some(parser.value()?.parse()?);
+            }
+            Long("schip") => {
+                mode = Some(chip8::Mode::SuperChip);
+            }
+            Long("xochip") => {
+                mode = Some(chip8::Mode::XoChip);
+            }
+            Long("seed") => {
+                seed = Some(parser.value()?.parse()?);
+            }
+            Long("ram") => {
+                let size = parser.value()?.parse::<usize>()?;
+                // Room for a program at 0x300 (CHIP-8X's start), up to all of 64KiB
+                if !(0x301..=0x10000).contains(&size) {
+                    return Err("out of bounds value for option '--ram'".into());
+                }
+                memory_size = Some(size);
+            }
+            Long("stack-depth") => {
+                let depth = parser.value()?.parse::<usize>()?;
+                if !(1..=chip8::MAX_STACK_DEPTH).contains(&depth) {
+                    return Err("out of bounds value for option '--stack-depth'".into());
+                }
+                stack_depth = Some(depth);
+            }
+            Long("trainer") => {
+                let path = parser.value()?.string()?;
+                let rules = fs::read_to_string(&path)
+                    .map_err(|e| format!("failed to read trainer file '{path}': {e}"))?;
+                trainer = Some(rules.parse()?);
+            }
+            Long("patch") => {
+                let path = parser.value()?.string()?;
+                let data = fs::read(&path)
+                    .map_err(|e| format!("failed to read patch file '{path}': {e}"))?;
+                patch = Some(Patch::parse(&data).map_err(|e| format!("'{path}': {e}"))?);
+            }
+            Long("poke") => {
+                let poke = parser.value()?.string()?;
+                pokes.push(
+                    Patch::parse_poke(&poke)
+                        .map_err(|e| format!("{e} in '--poke={poke}' (expected ADDR=BYTE)"))?,
+                );
+            }
+            Long("script") => {
+                let path = parser.value()?.string()?;
+                if !cfg!(feature = "scripting") {
+                    return Err(
+                        "'--script' needs chippity built with the `scripting` feature".into(),
+                    );
+                }
+                script = Some(path);
+            }
+            Long("host") => {
+                netplay = Some(NetplayRole::Host(parser.value()?.string()?));
+            }
+            Long("join") => {
+                netplay = Some(NetplayRole::Join(parser.value()?.string()?));
+            }
+            Long("input-delay") => {
+                input_delay = parser.value()?.parse()?;
+            }
+            Long("serve") => {
+                serve = Some(parser.value()?.string()?);
+            }
+            Long("connect") => {
+                connect = Some(parser.value()?.string()?);
+            }
+            Long("quirks") => {
+                quirks = Some(parser.value()?.parse()?);
+            }
+            Long(name) if name.starts_with("quirk-") => {
+                let name = name.trim_start_matches("quirk-").to_owned();
+                quirk_overrides.push((name, parser.value()?.string()?));
+            }
+            Long("against") => {
+                let mut preset = None;
+                let mut overrides = Vec::new();
+                for item in parser.value()?.string()?.split(',') {
+                    match item.split_once('=') {
+                        Some((name, value)) => {
+                            chip8::Quirks::default().set(name, value)?;
+                            overrides.push((name.to_owned(), value.to_owned()));
+                        }
+                        None => preset = Some(item.parse()?),
+                    }
+                }
+                against = Some(QuirkSpec { preset, overrides });
+            }
+            Long("timing") => {
+                timing = parser.value()?.parse()?;
+            }
+            Long("trace") => {
+                trace = Some(parser.optional_value().map(PathBuf::from));
+            }
+            Long("remap") => {
+                remap = true;
+            }
+            Long("keymap") => {
+                keymap = Some(parser.value()?.string()?);
+            }
+            Long("layout") => {
+                let name = parser.value()?.string()?;
+                layout = Some(Keymap::layout(&name).ok_or_else(|| {
+                    let layouts = keymap::LAYOUTS.map(|(name, _)| name).join(", ");
+                    format!("unrecognized layout '{name}' (expected one of {layouts})")
+                })?);
+            }
+            Long("profile") => {
+                profile = Some(parser.value()?.string()?);
+            }
+            Long("save-profile") => {
+                save_profile = Some(parser.value()?.string()?);
+            }
+            Long("cycles") => {
+                cycle_budget = parser.value()?.parse()?;
+            }
+            Long("jobs") => {
+                jobs = parser.value()?.parse()?;
+            }
+            Long("frames") => {
+                render_frames = parser.value()?.parse()?;
+            }
+            Long("out") => {
+                let path = PathBuf::from(parser.value()?);
+                if ImageFormat::from_path(&path).is_none() {
+                    return Err(if cfg!(feature = "recording") {
+                        "'--out' has to be a .pbm or .png file".into()
+                    } else {
+                        "'--out' has to be a .pbm file (.png needs chippity built with the \
+                         `recording` feature)"
+                            .into()
+                    });
+                }
+                render_out = Some(path);
+            }
+            Long("out-dir") => {
+                render_dir = Some(PathBuf::from(parser.value()?));
+            }
+            Long("headless") => {
+                headless = true;
+            }
+            Long("max-cycles") => {
+                max_cycles = Some(parser.value()?.parse()?);
+            }
+            Long("run-seconds") => {
+                let secs = parser.value()?.parse::<f64>()?;
+                run_time = Some(
+                    Duration::try_from_secs_f64(secs)
+                        .map_err(|_| "out of bounds value for option '--run-seconds'")?,
+                );
+            }
+            Long("display-hash") => {
+                display_hash = true;
+            }
+            Long("profiler") => {
+                profiler = true;
+            }
+            Long("check-pacing") => {
+                check_pacing = true;
+            }
+            Long("tolerance") => {
+                tolerance = parser.value()?.parse::<f64>()?;
+                if !(0.0..=100.0).contains(&tolerance) {
+                    return Err("out of bounds value for option '--tolerance'".into());
+                }
+            }
+            Value(path) => {
+                roms.push(path.string()?);
+            }
+
+            Short('h') | Long("help") => {
+                println!("{}", help_msg);
+                std::process::exit(0);
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    if let Some(option) = options.iter().find(|option| !command.takes(option)) {
+        return Err(format!("'--{option}' can't be used with `{}`", command.name()).into());
+    }
+
+    // Catch bad overrides now; they're applied once the ROM run settles the quirks
+    let mut checked = quirks.unwrap_or_default();
+    for (name, value) in &quirk_overrides {
+        checked.set(name, value)?;
+    }
+
+    // Only interactive sessions have a menu to pick one from
+    if roms.is_empty()
+        && (command != Command::Run || headless || netplay.is_some() || serve.is_some())
+    {
+        return Err("missing argument <ROM>\n
+  Refer to --help for more information"
+            .into());
+    }
+    // Sessions picked from the menu, or played on a server, run without one
+    if script.is_some() && roms.is_empty() {
+        return Err("'--script' needs a ROM to run it with".into());
+    }
+    if connect.is_some() && !roms.is_empty() {
+        return Err("'--connect' plays the server's ROM, and takes none of its own".into());
+    }
+    if command == Command::Compare && against.is_none() {
+        return Err("'compare' needs the quirks to compare against (see --against)".into());
+    }
+    if command != Command::Check && roms.len() > 1 {
+        return Err(format!("unexpected argument '{}'", roms[1]).into());
+    }
+    let gui_backend = gui_backend.or(GuiBackend::DEFAULT);
+    #[cfg(feature = "pixels")]
+    let crt_drawn = gui && gui_backend == Some(GuiBackend::Pixels);
+    #[cfg(not(feature = "pixels"))]
+    let crt_drawn = false;
+    if crt && !crt_drawn {
+        return Err("'--crt' needs '--gui --gui-backend=pixels'".into());
+    }
+    #[cfg(feature = "minifb")]
+    let minifb_drawn = gui && gui_backend == Some(GuiBackend::Minifb);
+    #[cfg(not(feature = "minifb"))]
+    let minifb_drawn = false;
+    if filter.is_some() && !minifb_drawn {
+        return Err("'--filter' needs '--gui' with '--gui-backend=minifb'".into());
+    }
+    // minifb has no way of waiting for the monitor's refresh
+    if vsync && !(gui && gui_backend.is_some() && !minifb_drawn) {
+        return Err("'--vsync' needs '--gui' with '--gui-backend=sdl2' or 'pixels'".into());
+    }
+    let audio = match (audio, gui) {
+        (Some(AudioBackend::Bell), true) => {
+            return Err("the 'bell' audio backend is only available in TUI mode".into());
+        }
+        #[cfg(feature = "sdl2")]
+        (Some(AudioBackend::Sdl2), _) if !gui || gui_backend != Some(GuiBackend::Sdl2) => {
+            return Err(
+                "the 'sdl2' audio backend is only available with '--gui-backend=sdl2'".into(),
+            );
+        }
+        (Some(audio), _) => audio,
+        #[cfg(feature = "sdl2")]
+        (None, true) if gui_backend == Some(GuiBackend::Sdl2) => AudioBackend::Sdl2,
+        (None, true) => AudioBackend::NATIVE,
+        (None, false) => AudioBackend::Bell,
+    };
+    // Nobody could resume it headless or when serving, and netplay would leave the peer
+    // behind
+    if start_paused && (headless || serve.is_some() || netplay.is_some()) {
+        return Err(
+            "'--start-paused' can't be used headless, when serving, or with netplay".into(),
+        );
+    }
+    // Rules aren't part of the state checked against the peer's, so they'd desync it
+    if trainer.is_some() && netplay.is_some() {
+        return Err("'--trainer' can't be used with netplay".into());
+    }
+    // Hitting one would pause the local machine, with no way to resume it during netplay
+    if netplay.is_some()
+        && !(breakpoints.is_empty() && break_ops.is_empty() && watchpoints.is_empty())
+    {
+        return Err("'--break', '--break-op', and '--watch' can't be used with netplay".into());
+    }
+    // Applied after the patch file, whichever came first
+    let mut patch = patch.unwrap_or_default();
+    for (addr, byte) in pokes {
+        patch.poke(addr, byte);
+    }
+    // Patches are made for a particular ROM, not whatever's picked from the menu
+    if !patch.is_empty() && roms.is_empty() {
+        return Err("'--patch' and '--poke' need a ROM to patch".into());
+    }
+    if keymap.is_some() && layout.is_some() {
+        return Err("'--layout' can't be used with '--keymap'".into());
+    }
+    // The clock rate is the VIP's, which instruction costs are counted in
+    if timing != chip8::Timing::Flat && freq_given {
+        return Err("'--freq' can't be used with '--timing=vip'".into());
+    }
+    // Traces would be written over the TUI (see `diagnostics` for the same reasoning)
+    if trace == Some(None) && !headless && io::stderr().is_terminal() {
+        return Err("'--trace' needs stderr redirected, or '--trace=FILE'".into());
+    }
+    if check_pacing && pacing == Pacing::Unthrottled {
+        return Err("'--check-pacing' needs a clock rate to check against".into());
+    }
+    // Nobody is watching, so there's no reason to hold back
+    if headless && !freq_given && !check_pacing {
+        pacing = Pacing::Unthrottled;
+    }
+
+    if fg.is_some() || bg.is_some() {
+        let colors = &mut palette.get_or_insert_with(Palette::default).colors;
+        colors[0] = bg.unwrap_or(colors[0]);
+        colors[1] = fg.unwrap_or(colors[1]);
+    }
+
+    Ok(Args {
+        command,
+        roms,
+        rom_dir: rom_dir
+            .or_else(config::rom_dir)
+            .unwrap_or_else(|| PathBuf::from(".")),
+        gui,
+        gui_backend,
+        crt,
+        filter,
+        vsync,
+        tui_style,
+        tui_backend: tui_backend.or(TuiBackend::DEFAULT),
+        audio,
+        wave,
+        volume: volume as f32 / 100.0,
+        emu_clock_hz: freq_given.then_some(emu_clock_hz),
+        timer_hz,
+        pacing,
+        loop_trigger,
+        orientation,
+        ghosting,
+        keypad,
+        render,
+        palette,
+        serial_keypad,
+        gamepad,
+        summary,
+        lenient,
+        // Headless, a budget of cycles has to cover the same frames of a ROM whether or not
+        // it polls the timer
+        poll_skip: poll_skip && !headless,
+        stats,
+        watch_rom,
+        debug,
+        start_paused,
+        breakpoints,
+        break_ops,
+        watchpoints,
+        sound_log,
+        trace,
+        record,
+        state,
+        crash_dump,
+        // Headless runs (e.g. for `--display-hash`) have to come out the same on any machine,
+        // which flags left by earlier runs wouldn't, so they only keep them when told where
+        flags_dir: match (flags_dir, headless) {
+            (Some(dir), _) => Some(dir),
+            (None, true) => None,
+            (None, false) => config::data_dir().map(|dir| dir.join("flags")),
+        },
+        rewind_secs,
+        platform,
+        mode,
+        quirks,
+        quirk_overrides,
+        against,
+        font,
+        timing,
+        remap,
+        keymap,
+        layout,
+        profile,
+        save_profile,
+        install_desktop,
+        list_builtin,
+        cycle_budget,
+        jobs,
+        render_frames,
+        render_out,
+        render_dir,
+        headless,
+        max_cycles,
+        run_time,
+        display_hash,
+        profiler,
+        check_pacing: check_pacing.then_some(tolerance / 100.0),
+        seed,
+        memory_size,
+        stack_depth,
+        trainer,
+        patch,
+        script,
+        netplay,
+        input_delay,
+        serve,
+        connect,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, String> {
+        parse_args_from(args.split_whitespace().map(OsString::from)).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn command_comes_first() {
+        let args = parse("disasm --schip game.ch8").unwrap();
+        assert_eq!(args.command, Command::Disasm);
+        assert_eq!(args.roms, ["game.ch8"]);
+        // `run` is the default, and anything but a command up front is a ROM
+        assert_eq!(parse("game.ch8").unwrap().command, Command::Run);
+        assert_eq!(parse("run game.ch8").unwrap().command, Command::Run);
+        let args = parse("./check").unwrap();
+        assert_eq!(
+            (args.command, args.roms),
+            (Command::Run, vec!["./check".to_owned()])
+        );
+    }
+
+    #[test]
+    fn old_modes_still_work() {
+        assert_eq!(
+            parse("--disassemble game.ch8").unwrap().command,
+            Command::Disasm
+        );
+        assert_eq!(
+            parse("--batch a.ch8 b.ch8").unwrap().command,
+            Command::Check
+        );
+        assert_eq!(
+            parse("check --batch a.ch8").unwrap().command,
+            Command::Check
+        );
+        assert_eq!(
+            parse("info --batch a.ch8").err().as_deref(),
+            Some("'--batch' can't be used with `info`")
+        );
+    }
+
+    #[test]
+    fn options_must_apply_to_the_command() {
+        assert_eq!(
+            parse("info --gui game.ch8").err().as_deref(),
+            Some("'--gui' can't be used with `info`")
+        );
+        assert_eq!(
+            parse("disasm -s game.ch8").err().as_deref(),
+            Some("'--summary' can't be used with `disasm`")
+        );
+        assert_eq!(
+            parse("run --cycles=100 game.ch8").err().as_deref(),
+            Some("'--cycles' can't be used with `run`")
+        );
+        assert!(parse("check --cycles=100 --schip game.ch8").is_ok());
+        assert!(parse("render --frames=10 --fg=ffffff game.ch8").is_ok());
+    }
+}
@@ -0,0 +1,12 @@
+// The `chippity` command line, which the binary is a thin wrapper around: the arguments
+// (see `args`), and the session built and run from them (see `session`)
+//
+//   `parse_args` reads the options, and the command they go with, checking them against
+//   each other; `run` then carries out the command, or opens the frontend asked for
+//   and plays the ROM (or the ROM menu) on it
+//
+mod args;
+mod session;
+
+pub use args::{parse_args, parse_args_from, Args};
+pub use session::run;
@@ -0,0 +1,974 @@
+use std::cell::{Cell, RefCell};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::{panic, thread};
+
+use super::args::{Args, Command, NetplayRole};
+// Only named by the frontends and audio backends this build has
+#[cfg(any(
+    all(unix, feature = "termion"),
+    feature = "crossterm",
+    feature = "sdl2",
+    feature = "rodio",
+    feature = "cpal"
+))]
+use super::args::AudioBackend;
+#[cfg(any(feature = "minifb", feature = "sdl2", feature = "pixels"))]
+use super::args::GuiBackend;
+#[cfg(any(all(unix, feature = "termion"), feature = "crossterm"))]
+use super::args::TuiBackend;
+#[cfg(feature = "rich-tui")]
+use super::args::TuiStyle;
+use crate::config::{
+    desktop,
+    profile::{Profile, Profiles},
+};
+#[cfg(feature = "cpal")]
+use crate::driver::cpal::Cpal;
+#[cfg(feature = "crossterm")]
+use crate::driver::crossterm::Crossterm;
+#[cfg(feature = "gamepad")]
+use crate::driver::gamepad::Gamepad;
+#[cfg(feature = "minifb")]
+use crate::driver::minifb::Minifb;
+#[cfg(feature = "pixels")]
+use crate::driver::pixels::Pixels;
+#[cfg(feature = "rich-tui")]
+use crate::driver::ratatui::Ratatui;
+#[cfg(feature = "rodio")]
+use crate::driver::rodio::Rodio;
+#[cfg(feature = "sdl2")]
+use crate::driver::sdl2::Sdl2;
+#[cfg(all(unix, feature = "termion"))]
+use crate::driver::termion::Termion;
+use crate::driver::{
+    interrupt,
+    keymap::Keymap,
+    merged::MergedInput,
+    net::{self, NetServer},
+    serial::SerialKeypad,
+    threaded::{self, Link},
+    AudioDevice, AudioMsg, DisplayDevice, InputDevice, NullDevice,
+};
+#[cfg(feature = "scripting")]
+use crate::emulator::Script;
+use crate::emulator::{
+    batch::{self, BatchJob},
+    lockstep::{self, Outcome},
+    rom, romdb,
+    snapshot::{ImageFormat, Snapshot},
+    trace, Debugger, EmuError, Emulator, ExecutionHook, FairnessReport, Netplay, ProfileReport,
+    RomPicker, RunSummary,
+};
+use crate::{
+    chip8::{self, disasm},
+    diagnostics,
+};
+
+// What's left of a session once the frontend is torn down
+struct SessionResult {
+    summary: RunSummary,
+    // Hash of the final display contents
+    display_hash: u64,
+    pacing: Option<FairnessReport>,
+    profile: Option<ProfileReport>,
+}
+
+// Opens a frontend and presents the session on it (see `attach`)
+type OpenFrontend<'f> = &'f dyn Fn(&mut Option<Link>) -> Result<(), String>;
+
+// Hooks a session is run with (see `emulator::ExecutionHook`), which may have to go over to
+// the thread it runs on
+type Hooks = Vec<Box<dyn ExecutionHook + Send>>;
+
+// Present the session on a frontend's devices, with the keys of `extra_inputs` pressed along
+// with its own, until it ends; if the user switches frontends instead, the link is left
+// for the next one
+#[cfg_attr(
+    not(any(
+        all(unix, feature = "termion"),
+        feature = "crossterm",
+        feature = "minifb",
+        feature = "sdl2",
+        feature = "pixels"
+    )),
+    allow(dead_code)
+)]
+fn attach<I, D, A>(
+    link: &mut Option<Link>,
+    (input, display, audio): (&RefCell<I>, &RefCell<D>, &RefCell<A>),
+    extra_inputs: &RefCell<Vec<Box<dyn InputDevice>>>,
+) where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    let Some(free) = link.take() else {
+        return;
+    };
+    let extras = extra_inputs.take();
+    if extras.is_empty() {
+        *link = free.attach(input, display, audio).run();
+    } else {
+        let merged = RefCell::new(MergedInput::new(input, extras));
+        *link = free.attach(&merged, display, audio).run();
+        extra_inputs.replace(merged.into_inner().into_extras());
+    }
+}
+
+// Run a session on a thread of its own, while this one drives the frontend's devices (see
+// `driver::threaded`), starting with `frontends[first]` and switching between the two on F8
+fn run_threaded(
+    args: &Args,
+    program: Option<&[u8]>,
+    netplay: Option<Netplay>,
+    hooks: Hooks,
+    frontends: [OpenFrontend; 2],
+    first: usize,
+) -> Result<Option<SessionResult>, String> {
+    // Frontends are only torn down properly if the session ends by itself, and panics are
+    // only reported once they're gone
+    let _panics = diagnostics::hold_panics();
+    if let Err(e) = interrupt::catch_signals() {
+        diagnostics::warn(
+            "signals",
+            format!("couldn't catch termination signals: {e}"),
+        );
+    }
+    let (link, (mut input, display, audio)) = threaded::link();
+    thread::scope(|scope| {
+        let session = thread::Builder::new()
+            .name("core".to_owned())
+            .spawn_scoped(scope, move || {
+                input.wait_attached();
+                let (input, display, audio) = (
+                    RefCell::new(input),
+                    RefCell::new(display),
+                    RefCell::new(audio),
+                );
+                if let Some(addr) = &args.connect {
+                    audio.borrow_mut().set_volume(args.volume);
+                    return net::run_client(addr, (&input, &display, &audio))
+                        .map(|()| None)
+                        .map_err(EmuError::Remote);
+                }
+                let Some(program) = program else {
+                    return run_menu((&input, &display, &audio), args);
+                };
+                run_session(
+                    Emulator::with_peripherals(&input, &display, &audio),
+                    &args.roms[0],
+                    program,
+                    args,
+                    netplay,
+                    hooks,
+                )
+                .map(Some)
+            })
+            .expect("failed to spawn the emulator thread");
+
+        let mut link = Some(link);
+        let mut current = first;
+        let mut opened = Ok(());
+        // Whether `current` is being switched to from the other frontend, which can be
+        // gone back to if it fails to open
+        let mut switching = false;
+        while link.is_some() {
+            match frontends[current](&mut link) {
+                Ok(()) => switching = true,
+                Err(e) if switching => {
+                    diagnostics::warn("frontend", format!("couldn't switch frontends: {e}"));
+                    if let Some(link) = &mut link {
+                        link.notify("Couldn't switch frontends");
+                    }
+                    switching = false;
+                }
+                Err(e) => {
+                    opened = Err(e);
+                    break;
+                }
+            }
+            current = 1 - current;
+        }
+        // The session ends once its frontend is gone
+        drop(link);
+
+        let result = session
+            .join()
+            .unwrap_or_else(|panic| panic::resume_unwind(panic));
+        opened.and_then(|()| result.map_err(|e| e.to_string()))
+    })
+}
+
+// Show the ROM menu on the session's devices, running each ROM picked from it until it
+// exits; the result is that of the last ROM run, if any
+fn run_menu<I, D, A>(
+    (input, display, audio): (&RefCell<I>, &RefCell<D>, &RefCell<A>),
+    args: &Args,
+) -> Result<Option<SessionResult>, EmuError>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    let mut picker = RomPicker::new(&args.rom_dir);
+    let mut last = None;
+    while let Some(rom) = picker.pick(input, display) {
+        let rom = rom.to_string_lossy();
+        let emu = Emulator::with_peripherals(input, display, audio);
+        let result = rom::read_rom(&rom)
+            .and_then(|program| run_session(emu, &rom, &program, args, None, Vec::new()));
+        match result {
+            Ok(result) => last = Some(result),
+            // Back to the menu to pick another
+            Err(e) => display.borrow_mut().receive_notification(&e.to_string()),
+        }
+        // Don't carry a beep cut off mid-way over to the menu
+        audio
+            .borrow_mut()
+            .receive_signal(AudioMsg::Beep(false))
+            .play_audio();
+    }
+    Ok(last)
+}
+
+// Configure the emulator from the command line and run the ROM (`program`, read from `rom`)
+// to completion
+fn run_session<I, D, A>(
+    mut emu: Emulator<I, D, A>,
+    rom: &str,
+    program: &[u8],
+    args: &Args,
+    netplay: Option<Netplay>,
+    hooks: Hooks,
+) -> Result<SessionResult, EmuError>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    let settings = args.rom_settings(program);
+    emu.set_platform(&settings.platform);
+    // Both checked while parsing the arguments
+    if let Some(size) = args.memory_size {
+        let _ = emu.set_memory_size(size);
+    }
+    if let Some(depth) = args.stack_depth {
+        let _ = emu.set_stack_depth(depth);
+    }
+    emu.set_timing(args.timing);
+    emu.set_timer_freq(args.timer_hz);
+    emu.set_pacing(args.pacing);
+    // Nobody is at the keys headless, but a script's presses have to land on the same frames
+    // every run
+    emu.set_frame_locked_input(args.headless);
+    emu.set_loop_trigger(args.loop_trigger);
+    emu.set_rewind_length(args.rewind_secs);
+    emu.set_lenient(args.lenient);
+    emu.set_skip_timer_polls(args.poll_skip);
+    // Nobody is at the frontend to reset from it headless or when serving, and a reset
+    // during netplay would leave the peer behind
+    emu.set_fault_screen(!args.headless && args.serve.is_none() && netplay.is_none());
+    emu.set_show_stats(args.stats);
+    emu.set_sound_log(args.sound_log.is_some());
+    emu.set_volume(args.volume);
+    emu.set_run_limits(args.max_cycles, args.run_time);
+    if let Some(tolerance) = args.check_pacing {
+        emu.check_fairness(tolerance);
+    }
+    if args.profiler {
+        emu.enable_profiler();
+    }
+
+    let mut debugger = Debugger::new();
+    if args.debug || args.start_paused {
+        debugger.enable();
+    }
+    for &addr in &args.breakpoints {
+        debugger.add_breakpoint(addr);
+    }
+    for &pattern in &args.break_ops {
+        debugger.add_opcode_break(pattern);
+    }
+    emu.set_debugger(debugger);
+    for &watchpoint in &args.watchpoints {
+        // Never fails, there being no more of them than `MAX_WATCHPOINTS` (see `parse_args`)
+        let _ = emu.add_watchpoint(watchpoint);
+    }
+    emu.set_orientation(args.orientation);
+    emu.set_ghosting(args.ghosting);
+    if let Some(seed) = args.seed {
+        emu.set_rng_seed(seed);
+    }
+    if let Some(trainer) = &args.trainer {
+        emu.set_trainer(trainer.clone());
+    }
+    for hook in hooks {
+        emu.add_hook(hook);
+    }
+    emu.set_patch(args.patch.clone());
+    emu.load_rom(program)?;
+    emu.set_paused(args.start_paused);
+
+    // Resume from an explicitly given save state
+    match &args.state {
+        Some(path) => {
+            emu.set_state_path(path);
+            emu.load_state()?;
+        }
+        None => emu.set_state_path(format!("{}.state", rom::local_path(rom))),
+    }
+    match &args.crash_dump {
+        Some(path) => emu.set_crash_path(path),
+        None => emu.set_crash_path(format!("{}.dump", rom::local_path(rom))),
+    }
+    #[cfg(feature = "recording")]
+    {
+        let palette = settings.palette.unwrap_or_default();
+        match &args.record {
+            Some(path) => {
+                emu.set_record_path(path, palette);
+                emu.start_recording()?;
+            }
+            None => emu.set_record_path(format!("{}.gif", rom::local_path(rom)), palette),
+        }
+    }
+    // Netplay peers have to start out the same, which flags left by earlier runs on either
+    // side wouldn't
+    if let (Some(dir), None) = (&args.flags_dir, &netplay) {
+        emu.set_flags_path(rom::flags_path(dir, program))?;
+    }
+    // Reloading would leave the peer behind
+    if args.watch_rom && netplay.is_none() {
+        emu.watch_rom(rom);
+    }
+    if let Some(netplay) = netplay {
+        emu.start_netplay(netplay)?;
+    }
+
+    emu.run()?;
+    Ok(SessionResult {
+        summary: emu.summary().clone(),
+        display_hash: emu.display_hash(),
+        pacing: emu.fairness_report(),
+        profile: emu.profile_report(),
+    })
+}
+
+// Run each ROM headless and print one line per result
+fn run_batch(args: &Args) -> Result<(), lexopt::Error> {
+    let jobs = args
+        .roms
+        .iter()
+        .map(|rom| {
+            let program = rom::read_rom(rom).map_err(|e| e.to_string())?;
+            let settings = args.rom_settings(&program);
+            Ok(BatchJob {
+                name: rom.clone(),
+                rom: program,
+                platform: settings.platform,
+                cycle_budget: args.cycle_budget,
+                seed: args.seed,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    for result in batch::run_batch(jobs, args.jobs) {
+        println!(
+            "{}: {} cycles, {}, display {:016x}",
+            result.name, result.cycles, result.outcome, result.display_hash
+        );
+    }
+    print_diagnostics();
+
+    Ok(())
+}
+
+// Run the ROM headless for `--frames` frames, and write the last one presented to `--out`
+// (and every one to `--out-dir`, numbered from 00001), for golden images or screenshots.
+// Random numbers are seeded (with 0 if not given), so the same ROM renders the same
+fn render(args: &Args) -> Result<(), lexopt::Error> {
+    let rom = &args.roms[0];
+    let program = rom::read_rom(rom).map_err(|e| e.to_string())?;
+    let settings = args.rom_settings(&program);
+    let palette = settings.palette.unwrap_or_default();
+    let out = match &args.render_out {
+        Some(path) => path.clone(),
+        None => PathBuf::from(format!(
+            "{}.{}",
+            rom::local_path(rom),
+            ImageFormat::DEFAULT.extension()
+        )),
+    };
+    // Checked while parsing the arguments
+    let format = ImageFormat::from_path(&out).unwrap_or(ImageFormat::DEFAULT);
+    if let Some(dir) = &args.render_dir {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create '{}': {e}", dir.display()))?;
+    }
+
+    let snapshot = RefCell::new(Snapshot::default());
+    let input = RefCell::new(NullDevice::Input);
+    let display = RefCell::new(NullDevice::Display);
+    let audio = RefCell::new(NullDevice::Audio);
+    let mut emu = Emulator::with_peripherals(&input, &display, &audio);
+    emu.set_platform(&settings.platform);
+    // Both checked while parsing the arguments
+    if let Some(size) = args.memory_size {
+        let _ = emu.set_memory_size(size);
+    }
+    if let Some(depth) = args.stack_depth {
+        let _ = emu.set_stack_depth(depth);
+    }
+    emu.set_timing(args.timing);
+    emu.set_lenient(args.lenient);
+    emu.set_rng_seed(args.seed.unwrap_or(0));
+    if let Some(trainer) = &args.trainer {
+        emu.set_trainer(trainer.clone());
+    }
+    emu.set_patch(args.patch.clone());
+    emu.add_hook(&snapshot);
+    emu.load_rom(&program).map_err(|e| e.to_string())?;
+
+    let write = |path: &Path| {
+        snapshot
+            .borrow()
+            .write(path, format, &palette)
+            .map_err(|e| format!("failed to write '{}': {e}", path.display()))
+    };
+    // Ends early if the ROM exits
+    for (n, frame) in emu.frames().take(args.render_frames as usize).enumerate() {
+        frame.map_err(|e| e.to_string())?;
+        if let Some(dir) = &args.render_dir {
+            write(&dir.join(format!("{:05}.{}", n + 1, format.extension())))?;
+        }
+    }
+    if snapshot.borrow().is_empty() {
+        return Err("the ROM didn't present a frame to render".into());
+    }
+    write(&out)?;
+    print_diagnostics();
+
+    Ok(())
+}
+
+// Print what's known about the ROM, and the settings it would run with
+//
+//   Size       246 bytes
+//   Hash       0b4a3ad1d3bf2a6d
+//   Title      Pong (1 player)
+//   Platform   Chip8 at 720 Hz
+//   Quirks     shift=x memory=keep jump=v0 vfreset=off wrap=on vblank=off
+//   Font       Modern
+//
+fn print_info(args: &Args) -> Result<(), lexopt::Error> {
+    let program = rom::read_rom(&args.roms[0]).map_err(|e| e.to_string())?;
+    let settings = args.rom_settings(&program);
+    let platform = settings.platform;
+
+    println!("Size       {} bytes", program.len());
+    println!("Hash       {:016x}", romdb::hash(&program));
+    match romdb::lookup(&program) {
+        Some(info) => println!("Title      {}", info.title),
+        None => println!("Title      (not in the ROM database)"),
+    }
+    println!("Platform   {:?} at {} Hz", platform.mode, platform.clock_hz);
+    println!("Quirks     {}", quirk_values(&platform.quirks));
+    println!("Font       {:?}", platform.font);
+
+    Ok(())
+}
+
+// Quirks as `--quirk-NAME` takes them, e.g.
+// `shift=x memory=keep jump=v0 vfreset=off wrap=on vblank=off`
+fn quirk_values(quirks: &chip8::Quirks) -> String {
+    let value = |on, (off_value, on_value): (&'static str, &'static str)| match on {
+        true => on_value,
+        false => off_value,
+    };
+    format!(
+        "shift={} memory={} jump={} vfreset={} wrap={} vblank={}",
+        value(quirks.shift_vy, ("x", "y")),
+        value(quirks.memory_increment, ("keep", "increment")),
+        value(quirks.jump_vx, ("v0", "vx")),
+        value(quirks.vf_reset, ("off", "on")),
+        value(quirks.wrap, ("off", "on")),
+        value(quirks.display_wait, ("off", "on")),
+    )
+}
+
+// Run the ROM with the quirks it'd run with (A) and with `--against`'s (B) in lockstep, and
+// print where they first part ways, if they do
+//
+//   A  shift=x memory=keep jump=v0 vfreset=off wrap=on vblank=off
+//   B  shift=y memory=keep jump=v0 vfreset=off wrap=on vblank=off
+//   diverged after 1843 instructions, at 0x2A4  8346  SHR V3, V4
+//       V3  05 vs 0A
+//
+fn run_compare(args: &Args) -> Result<(), lexopt::Error> {
+    let program = rom::read_rom(&args.roms[0]).map_err(|e| e.to_string())?;
+    let platform = args.rom_settings(&program).platform;
+    let mut against = platform;
+    if let Some(spec) = &args.against {
+        against.quirks = spec.preset.unwrap_or(against.quirks);
+        for (name, value) in &spec.overrides {
+            // Checked while parsing the arguments
+            let _ = against.quirks.set(name, value);
+        }
+    }
+
+    println!("A  {}", quirk_values(&platform.quirks));
+    println!("B  {}", quirk_values(&against.quirks));
+    let budget = args.cycle_budget;
+    let outcome = lockstep::run_lockstep(
+        &program,
+        [&platform, &against],
+        args.seed.unwrap_or(0),
+        budget,
+    )
+    .map_err(|e| format!("invalid ROM: {e}"))?;
+    match outcome {
+        Outcome::Diverged(divergence) => println!("{divergence}"),
+        Outcome::BudgetExhausted => println!("no divergence in {budget} instructions"),
+        Outcome::Exited(steps) => {
+            println!("both exited after {steps} instructions without diverging")
+        }
+        Outcome::Crashed(steps, err) => {
+            println!("both halted after {steps} instructions without diverging: {err}")
+        }
+    }
+
+    Ok(())
+}
+
+// Warnings collected over the run (see `diagnostics`), now that the terminal is free
+fn print_diagnostics() {
+    // Frontends may leave restoring the terminal buffered up
+    let _ = io::stdout().flush();
+    let (diagnostics, dropped) = diagnostics::take();
+    for diagnostic in diagnostics {
+        eprintln!("{diagnostic}");
+    }
+    if dropped > 0 {
+        eprintln!("warning: {dropped} more diagnostic(s) not shown");
+    }
+}
+
+// Run what `args` asks for: a command, or a session of the ROM (or menu) on the frontend
+// asked for, with whatever it ends with reported on exit
+pub fn run(args: Args) -> Result<(), lexopt::Error> {
+    if args.install_desktop {
+        let exe = std::env::current_exe()
+            .map_err(|e| format!("couldn't find the chippity executable: {e}"))?;
+        for path in desktop::install(&exe)? {
+            println!("Wrote {}", path.display());
+        }
+        return Ok(());
+    }
+    if args.list_builtin {
+        if rom::BUILTIN.is_empty() {
+            return Err(
+                "'--list-builtin' needs chippity built with the `bundled-roms` feature".into(),
+            );
+        }
+        for (name, description, _) in rom::BUILTIN {
+            println!("{name:<16}{description}");
+        }
+        return Ok(());
+    }
+    match args.command {
+        Command::Run => (),
+        Command::Disasm => {
+            let rom = rom::read_rom(&args.roms[0]).map_err(|e| e.to_string())?;
+            for line in disasm::disassemble(&rom, args.rom_settings(&rom).platform.mode) {
+                println!("{line}");
+            }
+            return Ok(());
+        }
+        Command::Check => return run_batch(&args),
+        Command::Info => return print_info(&args),
+        Command::Render => return render(&args),
+        Command::Compare => return run_compare(&args),
+    }
+
+    if let Some(path) = &args.trace {
+        trace::init(path.as_deref()).map_err(|e| format!("failed to start tracing: {e}"))?;
+    }
+
+    // Read up front, so the frontends can take on any colors the ROM database has for it
+    let program = args
+        .roms
+        .first()
+        .map(|rom| rom::read_rom(rom))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let palette = match &program {
+        Some(program) => args.rom_settings(program).palette,
+        None => args.palette,
+    };
+
+    // Connect before any frontend takes over the terminal
+    let netplay = match &args.netplay {
+        Some(NetplayRole::Host(addr)) => {
+            eprintln!("Waiting for the other player to join at {addr}...");
+            let seed = args.seed.unwrap_or_else(|| fastrand::u64(..));
+            Some(Netplay::host(addr, seed, args.input_delay))
+        }
+        Some(NetplayRole::Join(addr)) => Some(Netplay::join(addr, args.input_delay)),
+        None => None,
+    }
+    .transpose()
+    .map_err(|e| format!("netplay: {e}"))?;
+
+    // Input devices pressing keys alongside the frontend's
+    let mut extra_inputs: Vec<Box<dyn InputDevice>> = Vec::new();
+    if let Some(path) = &args.serial_keypad {
+        let port =
+            File::open(path).map_err(|e| format!("failed to open keypad port '{path}': {e}"))?;
+        extra_inputs.push(Box::new(SerialKeypad::new(port)));
+    }
+    #[cfg(feature = "gamepad")]
+    if args.gamepad {
+        extra_inputs.push(Box::new(Gamepad::new()?));
+    }
+    // Hooks observing the session, handed to the thread it runs on
+    #[cfg_attr(not(feature = "scripting"), allow(unused_mut))]
+    let mut hooks: Hooks = Vec::new();
+    #[cfg(feature = "scripting")]
+    if let Some(path) = &args.script {
+        let (script, keys) = Script::load(path)?;
+        hooks.push(Box::new(script));
+        extra_inputs.push(Box::new(keys));
+    }
+
+    // Only windows are titled with it
+    #[cfg_attr(
+        not(any(feature = "minifb", feature = "sdl2", feature = "pixels")),
+        allow(unused_variables)
+    )]
+    let program_name = match args.roms.first() {
+        Some(rom) => Path::new(rom::local_path(rom))
+            .file_stem()
+            .unwrap_or_default(),
+        None => OsStr::new("chippity"),
+    };
+
+    // The default profile comes from the command line; saved profiles are layered on top of it
+    let base = Profile {
+        keymap: match (&args.keymap, args.layout) {
+            (Some(keymap), _) => Keymap::from_arg(keymap)?,
+            (None, Some(layout)) => layout,
+            (None, None) => Keymap::from_config()?,
+        },
+        keypad: args.keypad,
+        render: args.render,
+        palette,
+        ..Profile::new(Profile::DEFAULT_NAME)
+    };
+    let mut profiles = vec![base.clone()];
+    for name in Profile::saved_names() {
+        profiles.push(Profile::load(&name, &base)?);
+    }
+    let active = match &args.profile {
+        Some(name) => profiles
+            .iter()
+            .position(|profile| profile.name == *name)
+            .ok_or(format!("no saved profile named '{name}'"))?,
+        None => 0,
+    };
+    let profiles = Profiles::new(profiles, active);
+
+    // The remapping flow, if requested, runs on the first frontend opened, and the profiles
+    // it leaves are saved for future sessions and carried over to frontends switched to
+    let profiles = RefCell::new(profiles);
+    let first_open = Cell::new(true);
+    // Builds without a frontend (e.g. `--no-default-features --features std`) only play
+    // headless or served, opening none
+    #[cfg_attr(
+        not(any(
+            all(unix, feature = "termion"),
+            feature = "crossterm",
+            feature = "minifb",
+            feature = "sdl2",
+            feature = "pixels"
+        )),
+        allow(unused_variables)
+    )]
+    let setup = |remap_keys: &mut dyn FnMut() -> Option<Keymap>| -> Result<Profiles, String> {
+        let mut profiles = profiles.borrow_mut();
+        if first_open.replace(false) {
+            if let Some(remapped) = args.remap.then(remap_keys).flatten() {
+                remapped.save_config()?;
+                profiles.current_mut().keymap = remapped;
+            }
+            if let Some(name) = &args.save_profile {
+                Profile {
+                    name: name.clone(),
+                    ..profiles.current().clone()
+                }
+                .save()?;
+            }
+        }
+        Ok(profiles.clone())
+    };
+
+    // Lazily evaluate our emulator frontend
+    #[cfg(all(unix, feature = "termion"))]
+    let termion = || -> Result<_, String> {
+        let mut tui = Termion::new();
+        let profiles = setup(&mut || tui.remap_keys())?;
+        tui.set_profiles(profiles);
+        Ok(RefCell::new(tui))
+    };
+    #[cfg(feature = "crossterm")]
+    let crossterm = || -> Result<_, String> {
+        let mut tui = Crossterm::new();
+        let profiles = setup(&mut || tui.remap_keys())?;
+        tui.set_profiles(profiles);
+        Ok(RefCell::new(tui))
+    };
+    #[cfg(feature = "rich-tui")]
+    let ratatui = || -> Result<_, String> {
+        let mut tui = Ratatui::new();
+        let profiles = setup(&mut || tui.remap_keys())?;
+        tui.set_profiles(profiles);
+        Ok(RefCell::new(tui))
+    };
+    #[cfg(feature = "minifb")]
+    let minifb = || -> Result<_, String> {
+        let title = program_name.to_str().unwrap();
+        let mut gui = Minifb::new(title, profiles.borrow().current().scale)?;
+        gui.set_filter(args.filter);
+        let profiles = setup(&mut || gui.remap_keys(title))?;
+        gui.set_profiles(profiles);
+        Ok(RefCell::new(gui))
+    };
+    #[cfg(feature = "sdl2")]
+    let sdl2 = || -> Result<_, String> {
+        let title = program_name.to_str().unwrap();
+        let scale = profiles.borrow().current().scale;
+        let mut gui = Sdl2::new(title, scale, args.wave, args.vsync)?;
+        let profiles = setup(&mut || gui.remap_keys(title))?;
+        gui.set_profiles(profiles);
+        Ok(RefCell::new(gui))
+    };
+    #[cfg(feature = "pixels")]
+    let pixels = || -> Result<_, String> {
+        let title = program_name.to_str().unwrap();
+        let scale = profiles.borrow().current().scale;
+        let mut gui = Pixels::new(title, scale, args.crt, args.vsync)?;
+        let profiles = setup(&mut || gui.remap_keys(title))?;
+        gui.set_profiles(profiles);
+        Ok(RefCell::new(gui))
+    };
+    #[cfg_attr(
+        not(any(
+            all(unix, feature = "termion"),
+            feature = "crossterm",
+            feature = "minifb",
+            feature = "sdl2",
+            feature = "pixels"
+        )),
+        allow(unused_variables)
+    )]
+    let native_audio = || -> RefCell<Box<dyn AudioDevice>> {
+        RefCell::new(match args.audio {
+            // A machine with no audio device runs the ROM all the same, silently
+            #[cfg(feature = "rodio")]
+            AudioBackend::Rodio => match Rodio::new(args.wave) {
+                Ok(rodio) => Box::new(rodio),
+                Err(e) => {
+                    diagnostics::warn("rodio", e);
+                    Box::new(NullDevice::Audio)
+                }
+            },
+            #[cfg(feature = "cpal")]
+            AudioBackend::Cpal => match Cpal::new(args.wave) {
+                Ok(cpal) => Box::new(cpal),
+                Err(e) => {
+                    diagnostics::warn("cpal", e);
+                    Box::new(NullDevice::Audio)
+                }
+            },
+            // Silent, the bell and SDL2's audio only ever being played by their frontends
+            _ => Box::new(NullDevice::Audio),
+        })
+    };
+
+    // The two frontends the session can be switched between: the terminal, and a window.
+    // Frontends are torn down at the end of each arm, so the next one (and anything printed
+    // afterwards) lands on the restored terminal
+    let extra_inputs = RefCell::new(extra_inputs);
+    // Builds without a TUI backend (or a GUI one, below) only ever report having none
+    #[cfg_attr(
+        not(any(all(unix, feature = "termion"), feature = "crossterm")),
+        allow(unreachable_code, unused_variables)
+    )]
+    let tui = |link: &mut Option<Link>| -> Result<(), String> {
+        match (args.tui_backend, args.audio) {
+            #[cfg(feature = "rich-tui")]
+            (_, AudioBackend::Bell) if args.tui_style == TuiStyle::Rich => {
+                let tui = ratatui()?;
+                attach(link, (&tui, &tui, &tui), &extra_inputs);
+            }
+            #[cfg(feature = "rich-tui")]
+            _ if args.tui_style == TuiStyle::Rich => {
+                let tui = ratatui()?;
+                let audio = native_audio();
+                attach(link, (&tui, &tui, &audio), &extra_inputs);
+            }
+            #[cfg(all(unix, feature = "termion"))]
+            (Some(TuiBackend::Termion), AudioBackend::Bell) => {
+                let tui = termion()?;
+                attach(link, (&tui, &tui, &tui), &extra_inputs);
+            }
+            #[cfg(all(unix, feature = "termion"))]
+            (Some(TuiBackend::Termion), _) => {
+                let tui = termion()?;
+                let audio = native_audio();
+                attach(link, (&tui, &tui, &audio), &extra_inputs);
+            }
+            #[cfg(feature = "crossterm")]
+            (Some(TuiBackend::Crossterm), AudioBackend::Bell) => {
+                let tui = crossterm()?;
+                attach(link, (&tui, &tui, &tui), &extra_inputs);
+            }
+            #[cfg(feature = "crossterm")]
+            (Some(TuiBackend::Crossterm), _) => {
+                let tui = crossterm()?;
+                let audio = native_audio();
+                attach(link, (&tui, &tui, &audio), &extra_inputs);
+            }
+            (None, _) => {
+                return Err(
+                    "this build has no TUI backend; run with --gui, or build with \
+                     `--features termion` or `--features crossterm`"
+                        .into(),
+                );
+            }
+        }
+        Ok(())
+    };
+    #[cfg_attr(
+        not(any(feature = "minifb", feature = "sdl2", feature = "pixels")),
+        allow(unreachable_code, unused_variables)
+    )]
+    let gui = |link: &mut Option<Link>| -> Result<(), String> {
+        match (args.gui_backend, args.audio) {
+            #[cfg(feature = "sdl2")]
+            (_, AudioBackend::Sdl2) => {
+                let gui = sdl2()?;
+                attach(link, (&gui, &gui, &gui), &extra_inputs);
+            }
+            #[cfg(feature = "sdl2")]
+            (Some(GuiBackend::Sdl2), _) => {
+                let gui = sdl2()?;
+                let audio = native_audio();
+                attach(link, (&gui, &gui, &audio), &extra_inputs);
+            }
+            #[cfg(feature = "pixels")]
+            (Some(GuiBackend::Pixels), _) => {
+                let gui = pixels()?;
+                let audio = native_audio();
+                attach(link, (&gui, &gui, &audio), &extra_inputs);
+            }
+            #[cfg(feature = "minifb")]
+            (Some(GuiBackend::Minifb), _) => {
+                let gui = minifb()?;
+                let audio = native_audio();
+                attach(link, (&gui, &gui, &audio), &extra_inputs);
+            }
+            (None, _) => {
+                return Err(
+                    "this build has no GUI backend; run with --tui, or build with \
+                     `--features minifb`"
+                        .into(),
+                );
+            }
+        }
+        Ok(())
+    };
+
+    let summary = match (program.as_deref(), &args.serve) {
+        (Some(program), Some(addr)) => {
+            eprintln!("Waiting for a client to connect at {addr}...");
+            let server =
+                RefCell::new(NetServer::listen(addr).map_err(|e| format!("remote play: {e}"))?);
+            // The extra devices' keys are pressed along with the client's
+            let input = RefCell::new(MergedInput::new(&server, extra_inputs.take()));
+            run_session(
+                Emulator::with_peripherals(&input, &server, &server),
+                &args.roms[0],
+                program,
+                &args,
+                netplay,
+                hooks,
+            )
+            .map(Some)
+            .map_err(|e| e.to_string())
+        }
+        (Some(program), None) if args.headless => {
+            // Nobody is at the keys, but a script may be
+            let keys = RefCell::new(NullDevice::Input);
+            let input = RefCell::new(MergedInput::new(&keys, extra_inputs.take()));
+            let display = RefCell::new(NullDevice::Display);
+            let audio = RefCell::new(NullDevice::Audio);
+            run_session(
+                Emulator::with_peripherals(&input, &display, &audio),
+                &args.roms[0],
+                program,
+                &args,
+                netplay,
+                hooks,
+            )
+            .map(Some)
+            .map_err(|e| e.to_string())
+        }
+        (program, _) => run_threaded(
+            &args,
+            program,
+            netplay,
+            hooks,
+            [&tui, &gui],
+            args.gui as usize,
+        ),
+    };
+
+    log::logger().flush();
+    print_diagnostics();
+
+    // Nothing was run if the ROM menu was left without picking one
+    let Some(SessionResult {
+        summary,
+        display_hash,
+        pacing,
+        profile,
+    }) = summary?
+    else {
+        return Ok(());
+    };
+
+    if args.summary {
+        println!("{summary}");
+    }
+    if args.display_hash {
+        println!("display {display_hash:016x}");
+    }
+    if let Some(path) = &args.sound_log {
+        fs::write(path, summary.sound_log_csv())
+            .map_err(|e| format!("failed to write sound log to '{path}': {e}"))?;
+    }
+    if let Some(report) = profile {
+        println!("{report}");
+    }
+    if let Some(report) = pacing {
+        println!("{report}");
+        if !report.passed() {
+            return Err("pacing check failed".into());
+        }
+    }
+
+    Ok(())
+}
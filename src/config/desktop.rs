@@ -0,0 +1,139 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use super::data_home;
+use crate::driver::palette::Palette;
+
+// Application icon: a little screen showing "C8", one character per pixel ('#' lit)
+pub const ICON: [&str; 16] = [
+    "................",
+    ".##############.",
+    ".#............#.",
+    ".#.###...###..#.",
+    ".#.#.....#.#..#.",
+    ".#.#.....###..#.",
+    ".#.#.....#.#..#.",
+    ".#.###...###..#.",
+    ".#............#.",
+    ".##############.",
+    "................",
+    "......####......",
+    "....########....",
+    "................",
+    "................",
+    "................",
+];
+pub const ICON_SIZE: usize = 16;
+
+// MIME type registered for CHIP-8 programs; only plain CHIP-8 extensions are associated,
+// since SUPER-CHIP and XO-CHIP programs need their mode given on the command line
+const MIME_TYPE: &str = "application/x-chip8";
+const MIME_GLOBS: [&str; 2] = ["*.ch8", "*.c8"];
+
+// Whether the icon's pixel at (x, y) is lit
+pub fn icon_pixel(x: usize, y: usize) -> bool {
+    ICON[y].as_bytes()[x] == b'#'
+}
+
+// Install a desktop entry launching `exe` in GUI mode, along with its icon and a MIME
+// type for CHIP-8 programs, so file managers offer to open .ch8 files with it; returns
+// the files written
+//
+//   The desktop and MIME databases are refreshed afterwards if their tools are around;
+//   otherwise desktop environments pick the changes up on their next scan
+//
+pub fn install(exe: &Path) -> Result<Vec<PathBuf>, String> {
+    // Where desktop entries, icons, and MIME types go
+    let data = data_home().ok_or("couldn't find the user data directory (is $HOME set?)")?;
+    let files = [
+        (
+            data.join("applications/chippity.desktop"),
+            desktop_entry(exe),
+        ),
+        (data.join("mime/packages/chippity.xml"), mime_package()),
+        (
+            data.join("icons/hicolor/scalable/apps/chippity.svg"),
+            icon_svg(),
+        ),
+    ];
+
+    for (path, contents) in &files {
+        fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| fs::write(path, contents))
+            .map_err(|e| format!("failed to write '{}': {e}", path.display()))?;
+    }
+
+    // Best effort; a missing tool isn't worth failing over
+    let _ = Command::new("update-mime-database")
+        .arg(data.join("mime"))
+        .status();
+    let _ = Command::new("update-desktop-database")
+        .arg(data.join("applications"))
+        .status();
+
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
+
+fn desktop_entry(exe: &Path) -> String {
+    // Quoted per the desktop entry spec, which reserves a few characters inside quotes
+    let mut exec = String::new();
+    for c in exe.display().to_string().chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            exec.push('\\');
+        }
+        exec.push(c);
+    }
+
+    format!(
+        "[Desktop Entry]
+Type=Application
+Name=chippity
+GenericName=CHIP-8 Emulator
+Comment=Run CHIP-8 programs
+Exec=\"{exec}\" --gui %f
+Icon=chippity
+Terminal=false
+Categories=Game;Emulator;
+MimeType={MIME_TYPE};
+"
+    )
+}
+
+fn mime_package() -> String {
+    let globs = MIME_GLOBS
+        .iter()
+        .map(|glob| format!("    <glob pattern=\"{glob}\"/>\n"))
+        .collect::<String>();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">
+  <mime-type type=\"{MIME_TYPE}\">
+    <comment>CHIP-8 program</comment>
+{globs}  </mime-type>
+</mime-info>
+"
+    )
+}
+
+// `ICON` as an SVG in the default palette
+fn icon_svg() -> String {
+    let [off, on] = [0, 1].map(|n| format!("#{:06x}", Palette::default().colors[n]));
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {ICON_SIZE} {ICON_SIZE}\" \
+         shape-rendering=\"crispEdges\">
+  <rect width=\"{ICON_SIZE}\" height=\"{ICON_SIZE}\" rx=\"2\" fill=\"{off}\"/>
+"
+    );
+    for y in 0..ICON_SIZE {
+        for x in (0..ICON_SIZE).filter(|&x| icon_pixel(x, y)) {
+            svg +=
+                &format!("  <rect x=\"{x}\" y=\"{y}\" width=\"1\" height=\"1\" fill=\"{on}\"/>\n");
+        }
+    }
+    svg + "</svg>\n"
+}
@@ -0,0 +1,40 @@
+pub mod desktop;
+pub mod profile;
+
+use std::{env, fs, path::PathBuf};
+
+// Directory holding user configuration files (keymap, profiles, etc.):
+//   - $XDG_CONFIG_HOME/chippity, falling back to ~/.config/chippity
+//   - %APPDATA%\chippity on Windows
+pub fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+
+    Some(base.join("chippity"))
+}
+
+// Directory holding data kept between runs (e.g. SCHIP flag registers): the `chippity`
+// directory of `data_home`
+pub fn data_dir() -> Option<PathBuf> {
+    Some(data_home()?.join("chippity"))
+}
+
+// Directory holding user application data, that of every application:
+//   - $XDG_DATA_HOME, falling back to ~/.local/share
+//   - %APPDATA% on Windows
+pub fn data_home() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+}
+
+// Directory the ROM picker lists ROMs from when no `--rom-dir` is given, as saved in the
+// `rom-dir` file of the configuration directory
+pub fn rom_dir() -> Option<PathBuf> {
+    let contents = fs::read_to_string(config_dir()?.join("rom-dir")).ok()?;
+    let dir = contents.trim();
+    (!dir.is_empty()).then(|| PathBuf::from(dir))
+}
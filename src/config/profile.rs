@@ -0,0 +1,201 @@
+use std::{fs, path::PathBuf};
+
+use crate::{
+    config,
+    driver::{cells::CellMode, keymap::Keymap, palette::Palette},
+};
+
+// Window scale factors supported by the GUI
+pub const SCALES: [usize; 6] = [1, 2, 4, 8, 16, 32];
+
+// Named bundle of frontend settings (e.g. "left-handed", "laptop", "stream-overlay"),
+// stored as one file per profile in the `profiles` configuration directory
+//
+//   Serialized as `setting = value` lines; `#` starts a comment:
+//
+//     keymap = 1=1,2=2,3=3,4=C,q=4,...
+//     palette = 1e1c2d,e0def4,eb6f92,f6c177
+//     scale = 16
+//     keypad = on
+//     render = halfblock
+//
+//   Settings left unspecified keep the value they have in the default profile
+//
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Profile {
+    pub name: String,
+    pub keymap: Keymap,
+    // None leaves each display device with its own default colors
+    pub palette: Option<Palette>,
+    // GUI window scale factor (one of `SCALES`)
+    pub scale: usize,
+    // Whether to show the clickable keypad (TUI overlay, or minifb window)
+    pub keypad: bool,
+    // How the TUI packs pixels into character cells
+    pub render: CellMode,
+}
+
+impl Profile {
+    // Name of the profile built from command line options rather than loaded from a file
+    pub const DEFAULT_NAME: &'static str = "default";
+
+    pub fn new(name: &str) -> Self {
+        Profile {
+            name: name.to_owned(),
+            keymap: Keymap::default(),
+            palette: None,
+            scale: 16,
+            keypad: false,
+            render: CellMode::default(),
+        }
+    }
+
+    // Directory holding the user's saved profiles
+    pub fn config_dir() -> Option<PathBuf> {
+        config::config_dir().map(|dir| dir.join("profiles"))
+    }
+
+    // Names of all saved profiles, in alphabetical order
+    pub fn saved_names() -> Vec<String> {
+        let mut names = Profile::config_dir()
+            .and_then(|dir| fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name != Profile::DEFAULT_NAME)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    // Load the saved profile `name`, filling in unspecified settings from `base`
+    pub fn load(name: &str, base: &Profile) -> Result<Profile, String> {
+        let path = Profile::config_dir()
+            .map(|dir| dir.join(name))
+            .ok_or("no configuration directory available")?;
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read profile '{name}' ({}): {e}", path.display()))?;
+
+        let mut profile = Profile {
+            name: name.to_owned(),
+            ..base.clone()
+        };
+        for line in contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        {
+            let err = |what: &str| format!("{what} in profile '{name}': '{line}'");
+            let (setting, value) = line
+                .split_once('=')
+                .map(|(setting, value)| (setting.trim(), value.trim()))
+                .ok_or_else(|| err("expected `setting = value`"))?;
+
+            match setting {
+                "keymap" => profile.keymap = value.parse().map_err(|e: String| err(&e))?,
+                "palette" => profile.palette = Some(value.parse().map_err(|e: String| err(&e))?),
+                "scale" => {
+                    profile.scale = value
+                        .parse()
+                        .ok()
+                        .filter(|scale| SCALES.contains(scale))
+                        .ok_or_else(|| err("invalid scale (expected 1, 2, 4, 8, 16, or 32)"))?
+                }
+                "keypad" => {
+                    profile.keypad = match value {
+                        "on" => true,
+                        "off" => false,
+                        _ => return Err(err("invalid keypad setting (expected on or off)")),
+                    }
+                }
+                "render" => profile.render = value.parse().map_err(|e: String| err(&e))?,
+                _ => return Err(err("unrecognized setting")),
+            }
+        }
+
+        Ok(profile)
+    }
+
+    // Save this profile under its name, overwriting any profile saved there before
+    pub fn save(&self) -> Result<(), String> {
+        let dir = Profile::config_dir().ok_or("no configuration directory available")?;
+        let path = dir.join(&self.name);
+
+        let mut contents = format!("keymap = {}\n", self.keymap);
+        if let Some(palette) = self.palette {
+            contents += &format!("palette = {palette}\n");
+        }
+        contents += &format!("scale = {}\n", self.scale);
+        contents += &format!("keypad = {}\n", if self.keypad { "on" } else { "off" });
+        contents += &format!("render = {}\n", self.render);
+
+        fs::create_dir_all(&dir)
+            .and_then(|_| fs::write(&path, contents))
+            .map_err(|e| format!("failed to save profile to {}: {e}", path.display()))
+    }
+}
+
+// Profiles the user can cycle through while running (see the P hotkey)
+#[derive(Clone)]
+pub struct Profiles {
+    list: Vec<Profile>,
+    active: usize,
+}
+
+impl Profiles {
+    // `list` must be non-empty; `active` falls back to the first profile if out of range
+    pub fn new(list: Vec<Profile>, active: usize) -> Self {
+        assert!(!list.is_empty(), "at least one profile is required");
+        let active = if active < list.len() { active } else { 0 };
+        Profiles { list, active }
+    }
+
+    pub fn current(&self) -> &Profile {
+        &self.list[self.active]
+    }
+
+    pub fn current_mut(&mut self) -> &mut Profile {
+        &mut self.list[self.active]
+    }
+
+    // Switch to the next profile, wrapping around after the last one
+    pub fn cycle(&mut self) -> &Profile {
+        self.active = (self.active + 1) % self.list.len();
+        self.current()
+    }
+}
+
+// The profiles a frontend switches between with the P hotkey, if it was given any. Hands
+// back the profile whose keymap, palette, etc. the frontend should apply
+#[derive(Clone, Default)]
+pub struct ProfileCycle(Option<Profiles>);
+
+impl ProfileCycle {
+    // Keep `profiles` around for cycling, returning the current one
+    pub fn set(&mut self, profiles: Profiles) -> Profile {
+        self.0.insert(profiles).current().clone()
+    }
+
+    // Switch to the next profile, returning it; None if there are no profiles to switch to
+    pub fn cycle(&mut self) -> Option<Profile> {
+        self.0.as_mut().map(|profiles| profiles.cycle().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_cycle_wraps_around() {
+        let mut cycle = ProfileCycle::default();
+        assert_eq!(cycle.cycle(), None);
+
+        let names = ["default", "laptop", "stream"];
+        let list = names.iter().map(|name| Profile::new(name)).collect();
+        assert_eq!(cycle.set(Profiles::new(list, 1)).name, "laptop");
+        assert_eq!(cycle.cycle().unwrap().name, "stream");
+        assert_eq!(cycle.cycle().unwrap().name, "default");
+        assert_eq!(cycle.cycle().unwrap().name, "laptop");
+    }
+}
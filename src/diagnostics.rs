@@ -0,0 +1,134 @@
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    fmt,
+    io::{self, Write},
+    panic::{self, PanicHookInfo},
+    sync::Mutex,
+    thread,
+};
+
+// Distinct messages kept; any more are only counted
+const MAX_DIAGNOSTICS: usize = 256;
+
+// Warnings from the core and drivers about things that went wrong without ending the
+// session (e.g. an unsupported instruction, or a dropped audio buffer)
+//
+//   Printing them as they happen would scribble over the TUI, and a ROM can trip the
+//   same one hundreds of times a second, so they're collected here instead, each
+//   distinct message once along with how many times it came up, for the frontend to
+//   show once it's done with the terminal (see `take`)
+//
+static LOG: Mutex<Log> = Mutex::new(Log {
+    diagnostics: Vec::new(),
+    dropped: 0,
+});
+
+// Reports of panics held back while a frontend has the terminal (see `hold_panics`)
+static HELD_PANICS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Sync + Send + 'static>;
+
+struct Log {
+    diagnostics: Vec<Diagnostic>,
+    // Messages that didn't fit in `MAX_DIAGNOSTICS`
+    dropped: u64,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    // Part of the program it came from, e.g. "chip8" or "minifb"
+    pub source: &'static str,
+    pub message: String,
+    // Times it came up
+    pub count: u64,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "warning ({}): {}", self.source, self.message)?;
+        if self.count > 1 {
+            write!(f, " (x{})", self.count)?;
+        }
+        Ok(())
+    }
+}
+
+// Report `message` from `source`
+pub fn warn(source: &'static str, message: impl Into<String>) {
+    let message = message.into();
+    // Also into the trace (see `emulator::trace`), in order with what led up to it
+    log::warn!(target: source, "{message}");
+    let mut log = LOG.lock().unwrap_or_else(|e| e.into_inner());
+
+    let seen = log
+        .diagnostics
+        .iter()
+        .position(|diag| diag.source == source && diag.message == message);
+    match seen {
+        Some(n) => log.diagnostics[n].count += 1,
+        None if log.diagnostics.len() < MAX_DIAGNOSTICS => log.diagnostics.push(Diagnostic {
+            source,
+            message,
+            count: 1,
+        }),
+        None => log.dropped += 1,
+    }
+}
+
+// Show `message` from `source` right away rather than collecting it, for frontends that
+// leave the terminal alone (e.g. the GUI ones); it goes into the trace as well
+pub fn print(source: &'static str, message: &str) {
+    log::info!(target: source, "{message}");
+    eprintln!("{message}\n");
+}
+
+// Diagnostics reported so far, oldest first, along with how many more there were than
+// could be kept; the log starts over empty
+pub fn take() -> (Vec<Diagnostic>, u64) {
+    let mut log = LOG.lock().unwrap_or_else(|e| e.into_inner());
+    let dropped = std::mem::take(&mut log.dropped);
+    (std::mem::take(&mut log.diagnostics), dropped)
+}
+
+// Hold back reports of panics on any thread until the returned guard is dropped, and print
+// them then; printed as they happen, they'd land on the TUI's alternate screen (and be gone
+// with it) or come out garbled in raw mode, before the frontend got to restore the
+// terminal. The guard should outlive the frontends
+pub fn hold_panics() -> HeldPanics {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|info| {
+        let thread = thread::current();
+        let mut report = format!("thread '{}' {info}", thread.name().unwrap_or("<unnamed>"));
+        let backtrace = Backtrace::capture();
+        match backtrace.status() {
+            BacktraceStatus::Captured => report += &format!("\nstack backtrace:\n{backtrace}"),
+            _ => report += "\nnote: run with `RUST_BACKTRACE=1` to display a backtrace",
+        }
+        HELD_PANICS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(report);
+    }));
+    HeldPanics { hook: Some(hook) }
+}
+
+// Prints the panics held back by `hold_panics` when dropped
+pub struct HeldPanics {
+    // Hook in place before, put back once done
+    hook: Option<PanicHook>,
+}
+
+impl Drop for HeldPanics {
+    fn drop(&mut self) {
+        // Hooks can't be swapped out while unwinding from a panic, which is over soon anyway
+        if let (Some(hook), false) = (self.hook.take(), thread::panicking()) {
+            panic::set_hook(hook);
+        }
+        // Frontends may leave restoring the terminal buffered up
+        let _ = io::stdout().flush();
+        let held = std::mem::take(&mut *HELD_PANICS.lock().unwrap_or_else(|e| e.into_inner()));
+        for report in held {
+            eprintln!("\n{report}");
+        }
+    }
+}
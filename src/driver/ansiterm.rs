@@ -1,10 +1,13 @@
-use crate::driver::{AudioDevice, AudioInfo};
+use crate::driver::{AudioDevice, AudioInfo, AudioMsg, Bell};
 
-pub struct AnsiTerm;
+#[derive(Default)]
+pub struct AnsiTerm {
+    bell: Bell,
+}
 
 impl AudioDevice for AnsiTerm {
-    fn receive_signal(&mut self, data: bool) -> &mut dyn AudioDevice {
-        if data {
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice {
+        if self.bell.ring(data) {
             println!("\x07");
         }
 
@@ -13,6 +16,10 @@ impl AudioDevice for AnsiTerm {
 
     fn play_audio(&mut self) {}
 
+    fn set_volume(&mut self, volume: f32) {
+        self.bell.set_volume(volume);
+    }
+
     fn device_info(&self) -> AudioInfo {
         AudioInfo::AnsiTerm
     }
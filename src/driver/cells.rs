@@ -0,0 +1,166 @@
+use std::{fmt, str::FromStr};
+
+use bitvec::slice::BitSlice;
+
+use crate::driver::{palette, pixel_color, Rect};
+
+// How frame pixels are packed into terminal character cells
+//
+//   Cells are about twice as tall as they are wide, so a full block per pixel stretches
+//   the display out; the denser modes keep its aspect and fit in smaller terminals:
+//
+//     block        █  one pixel per cell
+//     halfblock    ▀  two pixels (1x2) per cell: the top one in the foreground color and
+//                     the bottom one in the background color
+//     braille      ⣿  eight pixels (2x4) per cell as Braille dots, all lit dots in one
+//                     color; the highest color index among them wins (and among the
+//                     unlit ones, for CHIP-8X's background)
+//     image           a bitmap drawn through the terminal's graphics protocol (see
+//                     `graphics`) by drivers that speak one, with square pixels; drawn
+//                     as halfblock otherwise, and by everything here
+//
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CellMode {
+    #[default]
+    Block,
+    HalfBlock,
+    Braille,
+    Image,
+}
+
+// What to draw in a cell: a character, and the color indices (see `pixel_color`) of its
+// foreground and background
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: usize,
+    pub bg: usize,
+}
+
+// Braille dot bits by pixel position within the cell, [row][column]
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+const BRAILLE_BLANK: u32 = 0x2800;
+// Largest scale `fit_scale` goes up to
+const MAX_SCALE: usize = 32;
+
+impl CellMode {
+    // Pixels (w, h) packed into each cell
+    pub fn cell_size(self) -> (usize, usize) {
+        match self {
+            CellMode::Block => (1, 1),
+            CellMode::HalfBlock | CellMode::Image => (1, 2),
+            CellMode::Braille => (2, 4),
+        }
+    }
+
+    // Cells (w, h) taken up by a frame of `dims` (w, h) with its pixels scaled up `scale`
+    // times
+    pub fn cells(self, dims: (usize, usize), scale: usize) -> (usize, usize) {
+        let (w, h) = self.cell_size();
+        ((dims.0 * scale).div_ceil(w), (dims.1 * scale).div_ceil(h))
+    }
+
+    // Cells covering the pixels in `damage` of a frame scaled up `scale` times
+    pub fn cell_rect(self, damage: Rect, scale: usize) -> Rect {
+        let (w, h) = self.cell_size();
+        let (x, y) = (damage.x * scale / w, damage.y * scale / h);
+        Rect {
+            x,
+            y,
+            w: ((damage.x + damage.w) * scale).div_ceil(w) - x,
+            h: ((damage.y + damage.h) * scale).div_ceil(h) - y,
+        }
+    }
+
+    // Largest integer scale a frame of `dims` fits in `room` (w, h) cells at, or None if it
+    // doesn't fit even unscaled
+    pub fn fit_scale(self, dims: (usize, usize), room: (usize, usize)) -> Option<usize> {
+        let fits = |scale| {
+            let (w, h) = self.cells(dims, scale);
+            w <= room.0 && h <= room.1
+        };
+        (1..=MAX_SCALE).take_while(|&scale| fits(scale)).last()
+    }
+
+    // The cell at column `x`, row `y` of a frame of `dims` scaled up `scale` times; pixels
+    // past the edge of the frame are off
+    pub fn cell(
+        self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+        scale: usize,
+        x: usize,
+        y: usize,
+    ) -> Cell {
+        let (cell_w, cell_h) = self.cell_size();
+        let (px, py) = (x * cell_w, y * cell_h);
+        let pixel = |dx: usize, dy: usize| {
+            let (sx, sy) = ((px + dx) / scale, (py + dy) / scale);
+            match (sx < dims.0, sy < dims.1) {
+                (true, true) => pixel_color(planes, sy * dims.0 + sx),
+                _ => 0,
+            }
+        };
+
+        match self {
+            CellMode::Block => Cell {
+                ch: '█',
+                fg: pixel(0, 0),
+                bg: pixel(0, 0),
+            },
+            CellMode::HalfBlock | CellMode::Image => Cell {
+                ch: '▀',
+                fg: pixel(0, 0),
+                bg: pixel(0, 1),
+            },
+            CellMode::Braille => {
+                let (mut dots, mut fg, mut bg) = (0, 0, 0);
+                for (dy, row) in BRAILLE_DOTS.iter().enumerate() {
+                    for (dx, &dot) in row.iter().enumerate() {
+                        let color = pixel(dx, dy);
+                        if palette::is_lit(color) {
+                            dots |= dot;
+                            fg = fg.max(color);
+                        } else {
+                            bg = bg.max(color);
+                        }
+                    }
+                }
+                Cell {
+                    // Always a valid Braille pattern (U+2800 to U+28FF)
+                    ch: char::from_u32(BRAILLE_BLANK + dots).unwrap(),
+                    fg,
+                    bg,
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for CellMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(CellMode::Block),
+            "halfblock" => Ok(CellMode::HalfBlock),
+            "braille" => Ok(CellMode::Braille),
+            "image" => Ok(CellMode::Image),
+            _ => Err(format!(
+                "invalid render mode '{s}' (expected block, halfblock, braille, or image)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CellMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CellMode::Block => "block",
+            CellMode::HalfBlock => "halfblock",
+            CellMode::Braille => "braille",
+            CellMode::Image => "image",
+        };
+        write!(f, "{name}")
+    }
+}
@@ -0,0 +1,200 @@
+use bitvec::{bitarr, slice::BitSlice, vec::BitVec};
+use tokio::sync::{mpsc, watch};
+
+use crate::{
+    chip8::NUM_KEYS,
+    driver::{AudioDevice, AudioInfo, DisplayDevice, DisplayInfo, InputDevice, InputInfo},
+    driver::{AudioMsg, InputMsg, KEY_UP},
+    emulator::Signal,
+};
+
+// Devices that pass the emulator's I/O over tokio channels, so frontends running as tasks
+// (e.g. network backends) can drive an emulator running with `Emulator::run_async` on the
+// same runtime. Each constructor returns the frontend's end of the channel along with
+// the device.
+
+// Input sent to a `ChannelInput` by the frontend
+#[derive(Debug)]
+pub enum RemoteInput {
+    // Updated state of every key
+    Keys(InputMsg),
+    // Emulator hotkey, e.g. `Signal::MarkState` or `Signal::ProgramExit`
+    Hotkey(Signal),
+    // Debugger console command (see `Debugger::execute`)
+    Command(String),
+}
+
+pub struct ChannelInput {
+    rx: mpsc::UnboundedReceiver<RemoteInput>,
+    // Key states last received
+    keybuf: InputMsg,
+    // Console command last received, until the emulator takes it
+    command: Option<String>,
+}
+
+impl ChannelInput {
+    pub fn new() -> (mpsc::UnboundedSender<RemoteInput>, Self) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let device = ChannelInput {
+            rx,
+            keybuf: bitarr![0; NUM_KEYS],
+            command: None,
+        };
+        (tx, device)
+    }
+}
+
+impl InputDevice for ChannelInput {
+    // Handles one message per call; the program exits once every sender is dropped
+    fn handle_inputs(&mut self) -> Signal {
+        match self.rx.try_recv() {
+            Ok(RemoteInput::Keys(keys)) => {
+                let changed = keys != self.keybuf;
+                self.keybuf = keys;
+                match changed {
+                    true => Signal::NewInputs,
+                    false => Signal::None,
+                }
+            }
+            Ok(RemoteInput::Hotkey(
+                hotkey @ (Signal::ProgramExit
+                | Signal::MarkState
+                | Signal::RestoreMark
+                | Signal::RotateDisplay
+                | Signal::MirrorDisplay
+                | Signal::SaveState
+                | Signal::LoadState
+                | Signal::Reset
+                | Signal::Rewind
+                | Signal::Pause
+                | Signal::Resume
+                | Signal::Step
+                | Signal::FrameAdvance
+                | Signal::SpeedUp
+                | Signal::SpeedDown
+                | Signal::TurboOn
+                | Signal::TurboOff
+                | Signal::VolumeUp
+                | Signal::VolumeDown
+                | Signal::ToggleMute
+                | Signal::ToggleBreakpoint
+                | Signal::ScrollMemoryUp
+                | Signal::ScrollMemoryDown),
+            )) => hotkey,
+            Ok(RemoteInput::Command(command)) => {
+                self.command = Some(command);
+                Signal::DebugCommand
+            }
+            // Not something a user can ask for
+            Ok(RemoteInput::Hotkey(_)) => Signal::None,
+            Err(mpsc::error::TryRecvError::Empty) => Signal::None,
+            Err(mpsc::error::TryRecvError::Disconnected) => {
+                self.keybuf.fill(KEY_UP);
+                Signal::ProgramExit
+            }
+        }
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        Some(self.keybuf)
+    }
+
+    fn take_command(&mut self) -> Option<String> {
+        self.command.take()
+    }
+
+    fn device_info(&self) -> InputInfo {
+        InputInfo::Channel
+    }
+}
+
+// Frame sent out by a `ChannelDisplay`
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct RemoteFrame {
+    // Bitplanes of the row-major frame (see `DisplayDevice::receive_frame`)
+    pub planes: Vec<BitVec<usize>>,
+    // Dimensions (w, h) of the frame
+    pub dims: (usize, usize),
+}
+
+pub struct ChannelDisplay {
+    tx: watch::Sender<RemoteFrame>,
+    // Frame being received, sent on `drive_display`
+    frame: RemoteFrame,
+}
+
+impl ChannelDisplay {
+    // Only the latest frame is kept, so frontends slower than the emulator skip frames
+    // rather than fall behind
+    pub fn new() -> (watch::Receiver<RemoteFrame>, Self) {
+        let (tx, rx) = watch::channel(RemoteFrame::default());
+        let device = ChannelDisplay {
+            tx,
+            frame: RemoteFrame::default(),
+        };
+        (rx, device)
+    }
+}
+
+impl DisplayDevice for ChannelDisplay {
+    fn receive_frame(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice {
+        self.frame.planes.resize_with(planes.len(), BitVec::new);
+        for (out, plane) in self.frame.planes.iter_mut().zip(planes) {
+            out.clear();
+            out.extend_from_bitslice(plane);
+        }
+        self.frame.dims = dims;
+        self
+    }
+
+    fn drive_display(&mut self) {
+        self.tx.send_modify(|frame| frame.clone_from(&self.frame));
+    }
+
+    fn device_info(&self) -> DisplayInfo {
+        DisplayInfo::Channel
+    }
+}
+
+pub struct ChannelAudio {
+    tx: watch::Sender<AudioMsg>,
+    // Sound that should be playing
+    tone: AudioMsg,
+}
+
+impl ChannelAudio {
+    // The frontend sees the sound currently playing
+    pub fn new() -> (watch::Receiver<AudioMsg>, Self) {
+        let (tx, rx) = watch::channel(AudioMsg::Beep(false));
+        (
+            rx,
+            ChannelAudio {
+                tx,
+                tone: AudioMsg::Beep(false),
+            },
+        )
+    }
+}
+
+impl AudioDevice for ChannelAudio {
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice {
+        self.tone = data;
+        self
+    }
+
+    fn play_audio(&mut self) {
+        self.tx.send_if_modified(|tone| {
+            let changed = *tone != self.tone;
+            *tone = self.tone;
+            changed
+        });
+    }
+
+    fn device_info(&self) -> AudioInfo {
+        AudioInfo::Channel
+    }
+}
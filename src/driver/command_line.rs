@@ -0,0 +1,106 @@
+// Debugger console command typed after `:`, edited the same way by all the TUI backends;
+// each translates its own key events into `Edit`s
+
+// What a key does to the command being typed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Edit {
+    Insert(char),
+    Backspace,
+    // Enter the command, closing the prompt
+    Submit,
+    // Close the prompt, dropping the command
+    Cancel,
+}
+
+#[derive(Default)]
+pub struct CommandLine {
+    // Command being typed, while the prompt is open
+    typing: Option<String>,
+    // Command last entered, until it's taken
+    entered: Option<String>,
+}
+
+impl CommandLine {
+    // Open the prompt on an empty command
+    pub fn open(&mut self) {
+        self.typing = Some(String::new());
+    }
+
+    // Command being typed, if the prompt is open
+    pub fn typing(&self) -> Option<&str> {
+        self.typing.as_deref()
+    }
+
+    // Apply `edit` to the command being typed; returns whether it was entered
+    pub fn edit(&mut self, edit: Edit) -> bool {
+        let Some(command) = &mut self.typing else {
+            return false;
+        };
+
+        match edit {
+            Edit::Insert(c) => command.push(c),
+            Edit::Backspace => _ = command.pop(),
+            Edit::Submit => {
+                self.entered = self.typing.take();
+                return true;
+            }
+            Edit::Cancel => self.typing = None,
+        }
+        false
+    }
+
+    // Command last entered, if it hasn't been taken already
+    pub fn take(&mut self) -> Option<String> {
+        self.entered.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_in(line: &mut CommandLine, text: &str) {
+        for c in text.chars() {
+            assert!(!line.edit(Edit::Insert(c)));
+        }
+    }
+
+    #[test]
+    fn submit_enters_the_command() {
+        let mut line = CommandLine::default();
+        line.open();
+        type_in(&mut line, "peekx");
+        assert!(!line.edit(Edit::Backspace));
+        type_in(&mut line, " 200");
+        assert_eq!(line.typing(), Some("peek 200"));
+
+        assert!(line.edit(Edit::Submit));
+        assert_eq!(line.typing(), None);
+        assert_eq!(line.take().as_deref(), Some("peek 200"));
+        assert_eq!(line.take(), None);
+    }
+
+    #[test]
+    fn cancel_drops_the_command() {
+        let mut line = CommandLine::default();
+        line.open();
+        type_in(&mut line, "r");
+        assert!(!line.edit(Edit::Cancel));
+        assert_eq!(line.typing(), None);
+        assert_eq!(line.take(), None);
+    }
+
+    #[test]
+    fn edits_are_ignored_while_closed() {
+        let mut line = CommandLine::default();
+        assert!(!line.edit(Edit::Insert('x')));
+        assert!(!line.edit(Edit::Submit));
+        assert_eq!(line.typing(), None);
+        assert_eq!(line.take(), None);
+
+        // Backspace on an empty command leaves the prompt open
+        line.open();
+        assert!(!line.edit(Edit::Backspace));
+        assert_eq!(line.typing(), Some(""));
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::{Arc, Mutex};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+use crate::{
+    diagnostics,
+    driver::{
+        waveform::{Playback, Synth, Waveform},
+        AudioDevice, AudioInfo, AudioMsg,
+    },
+};
+
+// Native audio straight through the host's audio API, synthesizing the sound itself
+pub struct Cpal {
+    // Output stream; playback stops once it's dropped
+    _stream: cpal::Stream,
+    // Sound that should be playing, and its volume, read by the stream's callback
+    playback: Arc<Mutex<Playback>>,
+}
+
+impl Cpal {
+    // Open the default output device, or explain why there's none to play sound on
+    pub fn new(waveform: Waveform) -> Result<Self, String> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no audio output device available")?;
+        let config = device
+            .default_output_config()
+            .map_err(|e| format!("audio output device has no usable configuration: {e}"))?;
+        let playback = Arc::new(Mutex::new(Playback::default()));
+
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::I16 => {
+                build_stream::<i16>(&device, &config.into(), waveform, &playback)
+            }
+            cpal::SampleFormat::U16 => {
+                build_stream::<u16>(&device, &config.into(), waveform, &playback)
+            }
+            _ => build_stream::<f32>(&device, &config.into(), waveform, &playback),
+        }
+        .map_err(|e| format!("audio output stream creation failed: {e}"))?;
+        stream
+            .play()
+            .map_err(|e| format!("audio output stream failed to start: {e}"))?;
+
+        Ok(Cpal {
+            _stream: stream,
+            playback,
+        })
+    }
+}
+
+// Stream `playback` (see `Synth`) to every channel
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    waveform: Waveform,
+    playback: &Arc<Mutex<Playback>>,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let shared = Arc::clone(playback);
+    let mut synth = Synth::new(waveform, config.sample_rate.0);
+    let mut playing = Playback::default();
+
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            // Rather than wait on the emulator, keep playing what was playing for a buffer
+            if let Ok(playback) = shared.try_lock() {
+                playing = *playback;
+            }
+            for frame in data.chunks_mut(channels) {
+                frame.fill(T::from_sample(synth.play(playing)));
+            }
+        },
+        // An occasional dropout isn't worth interrupting the session over
+        |e| diagnostics::warn("cpal", e.to_string()),
+        None,
+    )
+}
+
+impl AudioDevice for Cpal {
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice {
+        self.playback
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .sound = data;
+        self
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.playback
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .volume = volume;
+    }
+
+    fn play_audio(&mut self) {}
+
+    fn device_info(&self) -> AudioInfo {
+        AudioInfo::Cpal
+    }
+}
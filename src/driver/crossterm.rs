@@ -0,0 +1,821 @@
+use std::{
+    io::{stdout, Stdout, Write},
+    time::{Duration, Instant},
+};
+
+use bitvec::slice::BitSlice;
+use crossterm::{
+    cursor::{self, MoveTo},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers, KeyboardEnhancementFlags, MouseButton, MouseEvent, MouseEventKind,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use crate::{
+    chip8::{DISPLAY_HEIGHT, DISPLAY_WIDTH},
+    config::profile::{Profile, ProfileCycle, Profiles},
+    driver::{
+        cells::CellMode,
+        command_line::{CommandLine, Edit},
+        keymap::{Keymap, Remap, RemapStep, KEYPAD_ORDER},
+        palette::{Palette, NUM_COLORS},
+        tui_layout::{self, Debounced, KeyMark, Layout},
+        AudioDevice, AudioInfo, AudioMsg, Bell, DisplayDevice, DisplayInfo, InputDevice, InputInfo,
+        InputMsg, StatusMsg, KEY_DOWN, NOTIFICATION_DURATION,
+    },
+    emulator::Signal,
+};
+
+// Default pixel colors: the standard (not bright) ANSI black and white, with bright red
+// and standard yellow for XO-CHIP's extra colors, then the nearest ANSI colors to
+// CHIP-8X's
+const DEFAULT_COLORS: [Color; NUM_COLORS] = [
+    Color::Black,
+    Color::Grey,
+    Color::Red,
+    Color::DarkYellow,
+    Color::Black,
+    Color::Red,
+    Color::Blue,
+    Color::Magenta,
+    Color::Green,
+    Color::Yellow,
+    Color::Cyan,
+    Color::White,
+    Color::DarkBlue,
+    Color::Black,
+    Color::DarkGreen,
+    Color::DarkRed,
+];
+
+// TUI frontend for terminals termion doesn't support (e.g. on Windows), which otherwise
+// behaves the same as the termion one
+//
+//   Where the terminal reports key releases (Windows consoles, and terminals speaking the
+//   kitty keyboard protocol), keys are held down until they're released; elsewhere key
+//   presses expire as with termion (see `Debounced`)
+//
+pub struct Crossterm {
+    // Terminal output; raw mode, the alternate screen, and mouse capture are set up on
+    // `new` and undone when dropped
+    screen: Stdout,
+    // Whether keyboard enhancement flags were pushed, to be popped when dropped
+    enhanced: bool,
+    // Terminal width and height used to center the display
+    term_size: (u16, u16), // (w, h)
+    // Dimensions of the last frame drawn; a change (e.g. rotation) requires clearing the screen
+    frame_dims: (usize, usize), // (w, h)
+    // Terminal size (w, h) the display needs, if the terminal is smaller than that
+    too_small: Option<(u16, u16)>,
+    // Commands drawing the next frame, reused across frames like termion's
+    framebuf: Vec<u8>,
+    // Keypad keys held, and the console command being typed
+    keyboard: Keyboard,
+    // Pixel colors; None uses the terminal's own
+    palette: Option<Palette>,
+    // How pixels are packed into character cells
+    cell_mode: CellMode,
+    // Profiles cycled through with the P hotkey
+    profiles: ProfileCycle,
+    // Top-left corner (0-based) of the on-screen keypad, if it is shown
+    keypad_origin: Option<(u16, u16)>,
+    // Keys the machine sees held and those FX0A is waiting on, if it is, as of the last
+    // status (see `StatusMsg`), and the top-left corner (0-based) they're drawn at
+    machine_keys: Option<(InputMsg, Option<InputMsg>)>,
+    key_state_origin: (u16, u16),
+    // Text panel (e.g. the debugger's) drawn to the right of everything else, and the
+    // top-left corner (0-based) it is drawn at
+    panel: Vec<String>,
+    panel_origin: (u16, u16),
+    // Notification shown in the top-right corner, and when it went up
+    toast: Option<(String, Instant)>,
+    // Live rates kept up in the top-left corner (see `receive_stats`); empty unless asked
+    // for
+    stats: String,
+    // Rung as sounds start, in place of the beep
+    bell: Bell,
+}
+
+impl Default for Crossterm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crossterm {
+    pub fn new() -> Self {
+        let mut screen = stdout();
+        terminal::enable_raw_mode().expect("TUI raw mode failed");
+        execute!(
+            screen,
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            cursor::Hide
+        )
+        .expect("TUI screen creation failed");
+
+        // Windows consoles always report key releases; other terminals only do with the
+        // kitty keyboard protocol, which needs every key reported as an escape code for
+        // releases of keys that type text
+        let enhanced = matches!(terminal::supports_keyboard_enhancement(), Ok(true));
+        if enhanced {
+            execute!(
+                screen,
+                PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                        | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
+                        | KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS
+                )
+            )
+            .unwrap();
+        }
+
+        Crossterm {
+            screen,
+            enhanced,
+            term_size: terminal::size().unwrap(),
+            frame_dims: (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+            too_small: None,
+            framebuf: Vec::new(),
+            keyboard: Keyboard::new(enhanced || cfg!(windows)),
+            palette: None,
+            cell_mode: CellMode::default(),
+            profiles: ProfileCycle::default(),
+            keypad_origin: None,
+            machine_keys: None,
+            key_state_origin: (0, 0),
+            panel: Vec::new(),
+            panel_origin: (0, 0),
+            toast: None,
+            stats: String::new(),
+            bell: Bell::default(),
+        }
+    }
+
+    // Apply the current profile, keeping the rest around for cycling
+    pub fn set_profiles(&mut self, profiles: Profiles) {
+        let profile = self.profiles.set(profiles);
+        self.apply_profile(&profile);
+    }
+
+    fn apply_profile(&mut self, profile: &Profile) {
+        self.keyboard.keymap = profile.keymap;
+        self.palette = profile.palette;
+        if self.cell_mode != profile.render {
+            self.cell_mode = profile.render;
+            self.frame_dims = (0, 0);
+        }
+        if self.keypad_origin.is_some() != profile.keypad {
+            self.show_keypad(profile.keypad);
+            // Force a clear on the next frame since the layout changes
+            self.frame_dims = (0, 0);
+        }
+    }
+
+    // Interactive remapping flow: prompt for the host key of each CHIP-8 key in turn.
+    // Returns None if the user cancels with Esc.
+    pub fn remap_keys(&mut self) -> Option<Keymap> {
+        let mut remap = Remap::default();
+        let keymap = loop {
+            let (w, h) = self.term_size;
+            let prompt = remap.prompt();
+            execute!(
+                self.screen,
+                Clear(ClearType::All),
+                MoveTo(
+                    w.saturating_sub(prompt.len() as u16) / 2,
+                    (h / 2).saturating_sub(1)
+                ),
+                Print(&prompt),
+                MoveTo(w.saturating_sub(Remap::HINT.len() as u16) / 2, h / 2 + 1),
+                Print(Remap::HINT),
+            )
+            .unwrap();
+
+            match remap.feed(read_remap_key()) {
+                RemapStep::Pending => (),
+                RemapStep::Done(keymap) => break Some(keymap),
+                RemapStep::Cancelled => break None,
+            }
+        };
+
+        execute!(self.screen, Clear(ClearType::All)).unwrap();
+        keymap
+    }
+
+    // Show a clickable CHIP-8 keypad next to the display
+    pub fn show_keypad(&mut self, show: bool) {
+        // The actual position is resolved on the next frame
+        self.keypad_origin = show.then_some((0, 0));
+    }
+
+    // Terminal color of pixel color `n`; colors 2 and 3 only show up with XO-CHIP's second
+    // plane
+    fn color(&self, n: usize) -> Color {
+        match self.palette {
+            Some(palette) => {
+                let (r, g, b) = palette.rgb(n);
+                Color::Rgb { r, g, b }
+            }
+            None => DEFAULT_COLORS[n],
+        }
+    }
+
+    // Draw the text panel
+    fn draw_panel(&mut self) {
+        let (ox, oy) = self.panel_origin;
+        queue!(self.screen, ResetColor).unwrap();
+        for (row, line) in tui_layout::padded_panel(&self.panel).enumerate() {
+            queue!(self.screen, MoveTo(ox, oy + row as u16), Print(line)).unwrap();
+        }
+    }
+
+    // Draw the notification in the top-right corner, or blank it out once it expires
+    fn draw_toast(&mut self, expired: bool) {
+        let Some((text, _)) = &self.toast else {
+            return;
+        };
+        let text = format!(" {text} ");
+        let x = self.term_size.0.saturating_sub(text.chars().count() as u16);
+
+        match expired {
+            true => queue!(
+                self.screen,
+                MoveTo(x, 0),
+                ResetColor,
+                Print(" ".repeat(text.chars().count()))
+            ),
+            false => queue!(
+                self.screen,
+                MoveTo(x, 0),
+                SetForegroundColor(Color::Black),
+                SetBackgroundColor(Color::Grey),
+                Print(text),
+                ResetColor
+            ),
+        }
+        .unwrap();
+        if expired {
+            self.toast = None;
+        }
+    }
+
+    // Draw the live rates in the top-left corner
+    fn draw_stats(&mut self) {
+        if self.stats.is_empty() {
+            return;
+        }
+        queue!(
+            self.screen,
+            MoveTo(0, 0),
+            SetForegroundColor(Color::Black),
+            SetBackgroundColor(Color::Grey),
+            Print(format!(" {} ", self.stats)),
+            ResetColor
+        )
+        .unwrap();
+    }
+
+    // Placeholder centered in place of the display while the terminal is smaller than the
+    // `(w, h)` it needs
+    fn draw_too_small(&mut self, (w, h): (u16, u16)) {
+        let text = format!(" Terminal too small (needs {w}x{h}) ");
+        let (term_w, term_h) = self.term_size;
+        queue!(
+            self.screen,
+            MoveTo(term_w.saturating_sub(text.len() as u16) / 2, term_h / 2),
+            SetForegroundColor(Color::Black),
+            SetBackgroundColor(Color::Grey),
+            Print(text),
+            ResetColor
+        )
+        .unwrap();
+    }
+
+    // Draw the console prompt on the bottom row, or clear it once the command is done
+    fn draw_prompt(&mut self) {
+        queue!(
+            self.screen,
+            MoveTo(0, self.term_size.1.saturating_sub(1)),
+            ResetColor,
+            Clear(ClearType::CurrentLine)
+        )
+        .unwrap();
+        if let Some(command) = self.keyboard.command.typing() {
+            queue!(self.screen, Print(format!(":{command}"))).unwrap();
+        }
+        self.screen.flush().unwrap();
+    }
+
+    // Draw the keypad overlay with currently pressed keys highlighted
+    fn draw_keypad(&mut self) {
+        let Some((ox, oy)) = self.keypad_origin else {
+            return;
+        };
+        let keys_down = self.keyboard.keys();
+
+        queue!(self.screen, ResetColor).unwrap();
+        for (row, keys) in KEYPAD_ORDER.chunks(4).enumerate() {
+            let y = oy + row as u16 * tui_layout::KEYPAD_CELL_HEIGHT;
+            queue!(
+                self.screen,
+                MoveTo(ox, y),
+                Print(tui_layout::KEYPAD_BORDER),
+                MoveTo(ox, y + 1),
+                Print('|')
+            )
+            .unwrap();
+            for &key in keys {
+                if keys_down[key] == KEY_DOWN {
+                    queue!(
+                        self.screen,
+                        SetForegroundColor(Color::Black),
+                        SetBackgroundColor(Color::Grey),
+                        Print(format!(" {key:X} ")),
+                        ResetColor,
+                        Print('|')
+                    )
+                    .unwrap();
+                } else {
+                    queue!(self.screen, Print(format!(" {key:X} |"))).unwrap();
+                }
+            }
+        }
+        queue!(
+            self.screen,
+            MoveTo(ox, oy + tui_layout::KEYPAD_HEIGHT - 1),
+            Print(tui_layout::KEYPAD_BORDER)
+        )
+        .unwrap();
+    }
+
+    // Draw the keys the machine sees held under the display, if the keypad is shown, with
+    // those FX0A is waiting on a release of picked out
+    fn draw_key_state(&mut self) {
+        let (Some(_), Some((held, wait))) = (self.keypad_origin, self.machine_keys) else {
+            return;
+        };
+        let (ox, oy) = self.key_state_origin;
+        let title = tui_layout::key_state_title(wait);
+
+        queue!(
+            self.screen,
+            ResetColor,
+            MoveTo(ox, oy),
+            Print(format!(
+                "{title:^width$}",
+                width = tui_layout::KEY_STATE_WIDTH as usize
+            ))
+        )
+        .unwrap();
+        for (row, keys) in KEYPAD_ORDER.chunks(4).enumerate() {
+            queue!(self.screen, MoveTo(ox, oy + 1 + row as u16)).unwrap();
+            for &key in keys {
+                let bg = tui_layout::key_mark(key, held, wait).map(|mark| match mark {
+                    KeyMark::Awaited => Color::Yellow,
+                    KeyMark::Held => Color::Grey,
+                });
+                match bg {
+                    Some(bg) => queue!(
+                        self.screen,
+                        SetForegroundColor(Color::Black),
+                        SetBackgroundColor(bg),
+                        Print(format!(" {key:X} ")),
+                        ResetColor
+                    ),
+                    None => queue!(self.screen, Print(format!(" {key:X} "))),
+                }
+                .unwrap();
+            }
+        }
+    }
+}
+
+impl Drop for Crossterm {
+    fn drop(&mut self) {
+        if self.enhanced {
+            let _ = execute!(self.screen, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(
+            self.screen,
+            ResetColor,
+            cursor::Show,
+            DisableMouseCapture,
+            LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl InputDevice for Crossterm {
+    // Host keys are translated to CHIP-8 keys through `Keymap` (QWERTY layout by default)
+    fn handle_inputs(&mut self) -> Signal {
+        let prev_state = self.keyboard.keys();
+        self.keyboard.start_poll();
+
+        // Checked here since frames don't necessarily keep coming in
+        if matches!(&self.toast, Some((_, shown)) if shown.elapsed() >= NOTIFICATION_DURATION) {
+            self.draw_toast(true);
+            self.screen.flush().unwrap();
+        }
+        // Emulator hotkeys take precedence over keypad updates
+        let mut hotkey = Signal::None;
+
+        // Drain all pending events
+        while let Ok(true) = event::poll(Duration::ZERO) {
+            let Ok(event) = event::read() else {
+                break;
+            };
+
+            let prompting = self.keyboard.command.typing().is_some();
+            let keypad_origin = self.keypad_origin;
+            match event {
+                // Force a clear on the next frame, which is centered in the new size, and have
+                // it sent right away
+                Event::Resize(w, h) => {
+                    self.term_size = (w, h);
+                    self.frame_dims = (0, 0);
+                    if hotkey == Signal::None {
+                        hotkey = Signal::Resized;
+                    }
+                }
+                event => match self.keyboard.handle_event(event, |x, y| {
+                    keypad_origin.and_then(|origin| tui_layout::keypad_hit(origin, (x, y)))
+                }) {
+                    Signal::ProgramExit => return Signal::ProgramExit,
+                    Signal::CycleProfile => {
+                        if let Some(profile) = self.profiles.cycle() {
+                            self.apply_profile(&profile);
+                            hotkey = Signal::CycleProfile;
+                        }
+                    }
+                    Signal::None => (),
+                    signal => hotkey = signal,
+                },
+            }
+            if prompting || self.keyboard.command.typing().is_some() {
+                self.draw_prompt();
+            }
+        }
+
+        let changed = self.keyboard.keys() != prev_state;
+        if changed {
+            self.draw_keypad();
+            self.screen.flush().unwrap();
+        }
+
+        if hotkey != Signal::None {
+            hotkey
+        } else if changed {
+            Signal::NewInputs
+        } else {
+            Signal::None
+        }
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        Some(self.keyboard.keys())
+    }
+
+    fn take_command(&mut self) -> Option<String> {
+        self.keyboard.command.take()
+    }
+
+    fn device_info(&self) -> InputInfo {
+        InputInfo::Crossterm
+    }
+}
+
+impl DisplayDevice for Crossterm {
+    fn receive_frame(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice {
+        if self.frame_dims != dims {
+            self.frame_dims = dims;
+            queue!(self.screen, Clear(ClearType::All)).unwrap();
+        }
+
+        // The keypad overlay, if shown, is centered together with the display, and so are
+        // the key state under it and the panel
+        let keypad = self.keypad_origin.is_some();
+        let panel_width = tui_layout::panel_width(&self.panel);
+        // Scale the display up as far as it fits beside the keypad and panel
+        let (term_w, term_h) = self.term_size;
+        let room = Layout::room(self.term_size, keypad, panel_width);
+        let scale = self.cell_mode.fit_scale(dims, room);
+        let (width, height) = self.cell_mode.cells(dims, scale.unwrap_or(1));
+        self.framebuf.clear();
+        // Nothing of the display is drawn while it doesn't fit, just a placeholder (see
+        // `draw_too_small`), so the screen starts over whenever that changes
+        let Some(scale) = scale else {
+            if self.too_small.is_none() {
+                queue!(self.screen, Clear(ClearType::All)).unwrap();
+            }
+            self.too_small = Some(Layout::needed((width, height), keypad, panel_width));
+            return self;
+        };
+        if self.too_small.take().is_some() {
+            queue!(self.screen, Clear(ClearType::All)).unwrap();
+        }
+        let layout = Layout::new(self.term_size, (width, height), keypad, panel_width);
+        let (x_offset, y_offset) = layout.display;
+        self.panel_origin = layout.panel;
+        self.key_state_origin = layout.key_state;
+        self.keypad_origin = layout.keypad;
+
+        let visible = (
+            width.min(term_w.saturating_sub(x_offset) as usize),
+            height.min(term_h.saturating_sub(y_offset) as usize),
+        );
+        for y in 0..visible.1 {
+            queue!(self.framebuf, MoveTo(x_offset, y_offset + y as u16)).unwrap();
+            for x in 0..visible.0 {
+                let cell = self.cell_mode.cell(planes, dims, scale, x, y);
+                let (fg, bg) = (self.color(cell.fg), self.color(cell.bg));
+                queue!(
+                    self.framebuf,
+                    SetForegroundColor(fg),
+                    SetBackgroundColor(bg),
+                    Print(cell.ch)
+                )
+                .unwrap();
+            }
+        }
+        queue!(self.framebuf, ResetColor).unwrap();
+
+        self
+    }
+
+    fn receive_panel(&mut self, lines: &[String]) {
+        if lines != self.panel {
+            let old_width = tui_layout::panel_width(&self.panel);
+            self.panel = lines.to_vec();
+            // Resizing the panel moves the display over, so start from a clean screen
+            if tui_layout::panel_width(&self.panel) != old_width {
+                self.frame_dims = (0, 0);
+            }
+        }
+    }
+
+    fn receive_status(&mut self, status: &StatusMsg) {
+        self.machine_keys = Some((status.keys, status.key_wait));
+    }
+
+    fn receive_notification(&mut self, text: &str) {
+        // Blank out the last one first, in case it was longer
+        self.draw_toast(true);
+        self.toast = Some((text.to_owned(), Instant::now()));
+        self.draw_toast(false);
+    }
+
+    fn receive_stats(&mut self, text: &str) {
+        if text == self.stats {
+            return;
+        }
+        // Blank out the last ones first, in case they were longer
+        let width = self.stats.chars().count() + 2;
+        if text.chars().count() + 2 < width {
+            queue!(
+                self.screen,
+                MoveTo(0, 0),
+                ResetColor,
+                Print(" ".repeat(width))
+            )
+            .unwrap();
+        }
+        self.stats = text.to_owned();
+    }
+
+    fn drive_display(&mut self) {
+        self.screen.write_all(&self.framebuf).unwrap();
+        match self.too_small {
+            // The display, keypad, and panel would land off screen
+            Some(size) => self.draw_too_small(size),
+            None => {
+                self.draw_keypad();
+                self.draw_key_state();
+                self.draw_panel();
+                self.draw_stats();
+            }
+        }
+        self.draw_toast(false);
+        self.screen.flush().unwrap();
+    }
+
+    fn device_info(&self) -> DisplayInfo {
+        DisplayInfo::Crossterm
+    }
+}
+
+impl AudioDevice for Crossterm {
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice {
+        if self.bell.ring(data) {
+            execute!(self.screen, Print('\x07')).unwrap();
+        }
+
+        self
+    }
+
+    fn play_audio(&mut self) {}
+
+    fn set_volume(&mut self, volume: f32) {
+        self.bell.set_volume(volume);
+    }
+
+    fn device_info(&self) -> AudioInfo {
+        AudioInfo::Crossterm
+    }
+}
+
+// Keyboard and mouse input read through crossterm, shared with the ratatui frontend: the
+// keypad keys held, the hotkeys pressed, and the debugger console command being typed
+pub struct Keyboard {
+    // Whether key releases are reported
+    key_release: bool,
+    // Tx input buffer, expired if releases aren't reported
+    keybuf: Debounced,
+    // Host key to CHIP-8 key bindings
+    pub keymap: Keymap,
+    // Keypad key currently held down with the mouse
+    mouse_key: Option<usize>,
+    // Whether the machine is being fast-forwarded
+    turbo: bool,
+    // Debugger console command being typed after `:`, and the last one entered
+    pub command: CommandLine,
+}
+
+impl Keyboard {
+    pub fn new(key_release: bool) -> Self {
+        Keyboard {
+            key_release,
+            keybuf: Debounced::default(),
+            keymap: Keymap::default(),
+            mouse_key: None,
+            turbo: false,
+            command: CommandLine::default(),
+        }
+    }
+
+    pub fn keys(&self) -> InputMsg {
+        self.keybuf.keys()
+    }
+
+    // Get ready for the events of a new poll
+    pub fn start_poll(&mut self) {
+        if !self.key_release {
+            self.keybuf.expire();
+        }
+        // Held mouse presses don't repeat, so keep them alive until released
+        if let Some(key) = self.mouse_key {
+            self.keybuf.press(key);
+        }
+    }
+
+    // Act on a key or mouse event, with `keypad_hit` giving the on-screen keypad key at a
+    // (column, row), if any; returns the hotkey pressed. `Signal::CycleProfile` is left to
+    // the frontend to act on, since profiles change how it draws.
+    pub fn handle_event(
+        &mut self,
+        event: Event,
+        keypad_hit: impl FnOnce(u16, u16) -> Option<usize>,
+    ) -> Signal {
+        // Typing a console command takes over the keyboard until it's entered or
+        // cancelled with Esc
+        if let (Event::Key(key), Some(_)) = (&event, self.command.typing()) {
+            let edit = match key.code {
+                _ if key.kind == KeyEventKind::Release => return Signal::None,
+                KeyCode::Enter => Edit::Submit,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Edit::Cancel,
+                KeyCode::Char(c) => Edit::Insert(c),
+                KeyCode::Backspace => Edit::Backspace,
+                KeyCode::Esc => Edit::Cancel,
+                _ => return Signal::None,
+            };
+            return match self.command.edit(edit) {
+                true => Signal::DebugCommand,
+                false => Signal::None,
+            };
+        }
+
+        match event {
+            // Only keypad keys are held; everything else acts on the press (or repeat)
+            Event::Key(KeyEvent {
+                code: KeyCode::Char(c),
+                kind: KeyEventKind::Release,
+                ..
+            }) => {
+                if let Some(key) = self.keymap.lookup(c) {
+                    self.keybuf.release(key);
+                }
+            }
+            Event::Key(KeyEvent {
+                code: KeyCode::Tab,
+                kind: KeyEventKind::Release,
+                ..
+            }) if self.turbo => {
+                self.turbo = false;
+                return Signal::TurboOff;
+            }
+            Event::Key(KeyEvent {
+                kind: KeyEventKind::Release,
+                ..
+            }) => (),
+            // Esc and ^C to signal program exit
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers,
+                ..
+            }) if modifiers.contains(KeyModifiers::CONTROL) => return Signal::ProgramExit,
+            Event::Key(KeyEvent { code, kind, .. }) => match code {
+                KeyCode::Char(c) => match self.keymap.lookup(c) {
+                    Some(key) => self.keybuf.press(key),
+                    None => match c {
+                        'm' => return Signal::MarkState,
+                        'n' => return Signal::RestoreMark,
+                        'o' => return Signal::RotateDisplay,
+                        'h' => return Signal::MirrorDisplay,
+                        'p' => return Signal::CycleProfile,
+                        ' ' => return Signal::Pause,
+                        '.' => return Signal::Step,
+                        ',' => return Signal::FrameAdvance,
+                        '=' | '+' => return Signal::SpeedUp,
+                        '-' => return Signal::SpeedDown,
+                        '0' => return Signal::VolumeUp,
+                        '9' => return Signal::VolumeDown,
+                        '8' => return Signal::ToggleMute,
+                        'b' => return Signal::ToggleBreakpoint,
+                        '[' => return Signal::ScrollMemoryUp,
+                        ']' => return Signal::ScrollMemoryDown,
+                        ':' => self.command.open(),
+                        _ => (),
+                    },
+                },
+                KeyCode::F(2) => return Signal::Reset,
+                KeyCode::F(5) => return Signal::SaveState,
+                KeyCode::F(7) => return Signal::LoadState,
+                KeyCode::F(8) => return Signal::SwitchFrontend,
+                KeyCode::F(9) => return Signal::ToggleRecording,
+                // Turbo lasts for as long as Tab is held where releases are reported,
+                // and is toggled by it otherwise
+                KeyCode::Tab if kind == KeyEventKind::Press => {
+                    self.turbo = !self.turbo;
+                    return match self.turbo {
+                        true => Signal::TurboOn,
+                        false => Signal::TurboOff,
+                    };
+                }
+                // Rewinding relies on key repeat, as with termion
+                KeyCode::Backspace => return Signal::Rewind,
+                KeyCode::Esc => return Signal::ProgramExit,
+                _ => (),
+            },
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                row,
+                ..
+            }) => {
+                self.mouse_key = keypad_hit(column, row);
+                if let Some(key) = self.mouse_key {
+                    self.keybuf.press(key);
+                }
+            }
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Up(_),
+                ..
+            }) => {
+                // Without expiry, the key has to be let go of here
+                if let (Some(key), true) = (self.mouse_key.take(), self.key_release) {
+                    self.keybuf.release(key);
+                }
+            }
+            _ => (),
+        }
+        Signal::None
+    }
+}
+
+// Wait for the host key to bind while remapping keys (see `Remap`); None if the user
+// cancels with Esc or ^C
+pub fn read_remap_key() -> Option<char> {
+    loop {
+        let Ok(Event::Key(key)) = event::read() else {
+            continue;
+        };
+        match key.code {
+            _ if key.kind == KeyEventKind::Release => (),
+            KeyCode::Esc => return None,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return None,
+            KeyCode::Char(c) => return Some(c),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// CRT post-processing, drawn over the display's viewport straight from the frame texture:
+// the screen bulges out (barrel distortion), each row of pixels is lit brightest through its
+// middle (scanlines), and lit pixels bleed light onto their neighbors (phosphor glow)
+
+struct VertexOutput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+}
+
+// One triangle covering the whole viewport, made up from the vertex index alone
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let position = vec2<f32>(f32(index == 1u) * 4.0 - 1.0, f32(index == 2u) * 4.0 - 1.0);
+
+    var out: VertexOutput;
+    out.tex_coord = fma(position, vec2<f32>(0.5, -0.5), vec2<f32>(0.5, 0.5));
+    out.position = vec4<f32>(position, 0.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0) var r_tex_color: texture_2d<f32>;
+@group(0) @binding(1) var r_tex_sampler: sampler;
+
+// How far the corners are pushed out; 0 keeps the screen flat
+const CURVATURE: f32 = 0.06;
+// Brightness lost at the top and bottom edges of each pixel row
+const SCANLINE_DEPTH: f32 = 0.5;
+// Strength of the light bled from neighboring pixels, and how far (in pixels) it reaches
+const GLOW: f32 = 0.4;
+const GLOW_RADIUS: f32 = 1.5;
+
+fn barrel(uv: vec2<f32>) -> vec2<f32> {
+    let centered = uv * 2.0 - 1.0;
+    let bent = centered * (1.0 + CURVATURE * dot(centered, centered));
+    return bent * 0.5 + 0.5;
+}
+
+@fragment
+fn fs_main(@location(0) tex_coord: vec2<f32>) -> @location(0) vec4<f32> {
+    let dims = vec2<f32>(textureDimensions(r_tex_color));
+    let uv = barrel(tex_coord);
+    let texel = GLOW_RADIUS / dims;
+
+    let color = textureSample(r_tex_color, r_tex_sampler, uv).rgb;
+
+    var glow = vec3<f32>(0.0);
+    for (var dy = -1; dy <= 1; dy++) {
+        for (var dx = -1; dx <= 1; dx++) {
+            let offset = vec2<f32>(f32(dx), f32(dy)) * texel;
+            glow += textureSample(r_tex_color, r_tex_sampler, uv + offset).rgb;
+        }
+    }
+    glow /= 9.0;
+
+    let row = fract(uv.y * dims.y) * 2.0 - 1.0;
+    let scanline = 1.0 - SCANLINE_DEPTH * row * row;
+
+    // Past the bent edges of the screen is the dark bezel
+    let on_screen = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    let lit = min(color * scanline + glow * GLOW, vec3<f32>(1.0));
+    return vec4<f32>(select(vec3<f32>(0.0), lit, on_screen), 1.0);
+}
@@ -0,0 +1,98 @@
+use std::sync::mpsc;
+
+use bitvec::bitarr;
+
+use crate::{
+    chip8::NUM_KEYS,
+    driver::{InputDevice, InputInfo, InputMsg, KEY_DOWN, KEY_UP},
+    emulator::Signal,
+};
+
+// Key change pushed to an `EventInput`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputEvent {
+    // Key 0-F went down; other keys are ignored
+    KeyDown(u8),
+    // Key 0-F came up
+    KeyUp(u8),
+    // End the session
+    Quit,
+}
+
+// Input device fed by events instead of polling: whatever produces them (another thread,
+// a callback, a hardware reader) sends `InputEvent`s through the sender from `new`, and
+// the emulator drains them all whenever it asks for inputs, once a frame
+//
+//   The device keeps the key states, so senders only report changes. A key that goes down
+//   and back up between drains is still seen held for one frame, so quick taps aren't
+//   lost. Once every sender is dropped, any keys left down are released.
+//
+pub struct EventInput {
+    events: mpsc::Receiver<InputEvent>,
+    keybuf: InputMsg,
+    // Keys tapped since the last drain, to release on the next one
+    tapped: InputMsg,
+}
+
+impl EventInput {
+    pub fn new() -> (mpsc::Sender<InputEvent>, Self) {
+        let (tx, events) = mpsc::channel();
+        (
+            tx,
+            EventInput {
+                events,
+                keybuf: bitarr![0; NUM_KEYS],
+                tapped: bitarr![0; NUM_KEYS],
+            },
+        )
+    }
+}
+
+impl InputDevice for EventInput {
+    fn handle_inputs(&mut self) -> Signal {
+        let before = self.keybuf;
+        for key in std::mem::take(&mut self.tapped).iter_ones() {
+            self.keybuf.set(key, KEY_UP);
+        }
+
+        let mut pressed: InputMsg = bitarr![0; NUM_KEYS];
+        loop {
+            match self.events.try_recv() {
+                Ok(InputEvent::KeyDown(key)) if (key as usize) < NUM_KEYS => {
+                    self.keybuf.set(key as usize, KEY_DOWN);
+                    self.tapped.set(key as usize, false);
+                    pressed.set(key as usize, true);
+                }
+                Ok(InputEvent::KeyUp(key)) if (key as usize) < NUM_KEYS => {
+                    // Hold it until the next drain if it only just went down
+                    match pressed[key as usize] {
+                        true => self.tapped.set(key as usize, true),
+                        false => self.keybuf.set(key as usize, KEY_UP),
+                    }
+                }
+                Ok(InputEvent::Quit) => return Signal::ProgramExit,
+                Ok(_) => (),
+                Err(mpsc::TryRecvError::Empty) => break,
+                // Nothing left to send events; don't leave any keys stuck down
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.keybuf.fill(KEY_UP);
+                    self.tapped.fill(false);
+                    break;
+                }
+            }
+        }
+
+        match self.keybuf != before {
+            true => Signal::NewInputs,
+            false => Signal::None,
+        }
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        Some(self.keybuf)
+    }
+
+    fn device_info(&self) -> InputInfo {
+        InputInfo::Events
+    }
+}
@@ -0,0 +1,91 @@
+use bitvec::bitarr;
+use gilrs::{Button, EventType, Gilrs};
+
+use crate::{
+    chip8::NUM_KEYS,
+    driver::{InputDevice, InputInfo, InputMsg},
+    emulator::Signal,
+};
+
+// Buttons and the CHIP-8 keys they press: the D-pad takes the keys most games move with
+// (2, 4, 6, and 8), and the face buttons the keys around them that games tend to use for
+// actions, with 5 (the usual fire key) on the bottom one
+//
+//   +------------+      D-pad        2 / 4 / 6 / 8 (up / left / right / down)
+//   | 1  2  3  C |      South (A)     5
+//   | 4  5  6  D |      East (B)      0
+//   | 7  8  9  E |      West (X)      7
+//   | A  0  B  F |      North (Y)     9
+//   +------------+      LB / RB       1 / 3
+//
+const LAYOUT: [(Button, usize); 10] = [
+    (Button::DPadUp, 0x2),
+    (Button::DPadLeft, 0x4),
+    (Button::DPadRight, 0x6),
+    (Button::DPadDown, 0x8),
+    (Button::South, 0x5),
+    (Button::East, 0x0),
+    (Button::West, 0x7),
+    (Button::North, 0x9),
+    (Button::LeftTrigger, 0x1),
+    (Button::RightTrigger, 0x3),
+];
+
+// Game controllers, any number of which can be plugged in (or out) at once; Start pauses
+// and resumes. See `MergedInput` to use them alongside a frontend's keys.
+pub struct Gamepad {
+    gilrs: Gilrs,
+    // Keys held across every controller
+    keybuf: InputMsg,
+}
+
+impl Gamepad {
+    pub fn new() -> Result<Self, String> {
+        let gilrs = Gilrs::new().map_err(|e| format!("gamepad support unavailable: {e}"))?;
+        Ok(Gamepad {
+            gilrs,
+            keybuf: bitarr![0; NUM_KEYS],
+        })
+    }
+}
+
+impl InputDevice for Gamepad {
+    fn handle_inputs(&mut self) -> Signal {
+        let mut hotkey = Signal::None;
+        while let Some(event) = self.gilrs.next_event() {
+            if let EventType::ButtonPressed(Button::Start, _) = event.event {
+                hotkey = Signal::Pause;
+            }
+        }
+        if hotkey != Signal::None {
+            return hotkey;
+        }
+
+        // Read back from the controllers' state rather than tracking events, so a button
+        // held on two controllers, or one unplugged mid-press, works out
+        let mut keys = bitarr![0; NUM_KEYS];
+        for (_, gamepad) in self.gilrs.gamepads() {
+            for &(button, key) in &LAYOUT {
+                if gamepad.is_pressed(button) {
+                    keys.set(key, true);
+                }
+            }
+        }
+
+        match keys != self.keybuf {
+            true => {
+                self.keybuf = keys;
+                Signal::NewInputs
+            }
+            false => Signal::None,
+        }
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        Some(self.keybuf)
+    }
+
+    fn device_info(&self) -> InputInfo {
+        InputInfo::Gamepad
+    }
+}
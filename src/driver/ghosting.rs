@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+
+use bitvec::{slice::BitSlice, vec::BitVec};
+use smallvec::SmallVec;
+
+use crate::driver::MAX_FRAME_PLANES;
+
+// Frames a pixel stays lit for after going dark
+const GHOST_FRAMES: usize = 2;
+
+// Bitplanes of one frame
+type Planes = SmallVec<[BitVec<usize>; MAX_FRAME_PLANES]>;
+
+// Anti-flicker stage frames pass through on their way to a display device, blending each
+// with the last few like the phosphor of a CRT: a pixel going dark stays lit for another
+// `GHOST_FRAMES` frames before it fades out
+//
+//   Drawing with XOR has most programs erase their sprites and draw them again every frame,
+//   so on a display that goes dark at once, like any but the VIP's TV, they flicker. The
+//   frames are told apart by timer tick, so a frame presented again (e.g. while paused)
+//   takes the place of the last one rather than fading it out.
+//
+#[derive(Default)]
+pub struct Ghosting {
+    // Frames received, newest first, and the timer tick the newest is for
+    history: VecDeque<Planes>,
+    tick: u64,
+    dims: (usize, usize),
+    // Scratch buffers holding the planes of the blended frame
+    blended: [BitVec<usize>; MAX_FRAME_PLANES],
+}
+
+impl Ghosting {
+    // Blend the frame `planes` of size `dims`, made by timer tick `tick`, with the ones
+    // before it
+    pub fn apply(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+        tick: u64,
+    ) -> SmallVec<[&BitSlice<usize>; MAX_FRAME_PLANES]> {
+        // Nothing to fade from after a change of resolution or colors
+        if dims != self.dims || self.history.front().map(SmallVec::len) != Some(planes.len()) {
+            self.history.clear();
+            self.dims = dims;
+        }
+        if tick != self.tick || self.history.is_empty() {
+            if self.history.len() > GHOST_FRAMES {
+                self.history.pop_back();
+            }
+            self.history.push_front(Planes::new());
+            self.tick = tick;
+        }
+        let newest = &mut self.history[0];
+        newest.clear();
+        newest.extend(planes.iter().map(|plane| plane.to_bitvec()));
+
+        for (n, blended) in self.blended.iter_mut().take(planes.len()).enumerate() {
+            blended.clear();
+            blended.resize(dims.0 * dims.1, false);
+            for frame in &self.history {
+                *blended |= frame[n].as_bitslice();
+            }
+        }
+        self.blended[..planes.len()]
+            .iter()
+            .map(BitVec::as_bitslice)
+            .collect()
+    }
+
+    // Whether any pixels are still fading out, so the frame has to be presented again on
+    // the next tick even if the program draws nothing
+    pub fn fading(&self) -> bool {
+        self.history
+            .iter()
+            .skip(1)
+            .any(|frame| frame != &self.history[0])
+    }
+}
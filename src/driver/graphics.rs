@@ -0,0 +1,174 @@
+use std::fmt::Write;
+
+use crate::driver::palette::{Palette, NUM_COLORS};
+
+// Terminal graphics protocols, through which terminals supporting one draw bitmaps where
+// they'd otherwise draw characters, so pixels can come out square and crisp
+//
+//   kitty    ESC _ G key=value,... ; base64 payload ESC \
+//            (https://sw.kovidgoyal.net/kitty/graphics-protocol/), here with PNG payloads
+//   sixel    ESC P q palette and bands of six rows of pixels ESC \
+//            (DEC's, spoken by e.g. xterm -ti vt340, foot, WezTerm, and mlterm)
+//
+//   Both draw at the cursor, the image's top-left corner landing on the cell it's in
+//
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+}
+
+// Query the terminal with a 1x1 kitty image, which only terminals speaking the protocol
+// answer, then for the primary device attributes every terminal answers, among which
+// sixel support (4) is listed
+pub const QUERY: &str = "\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[c";
+// Remove the image drawn with the kitty protocol, if any; clearing the screen leaves it up
+pub const KITTY_DELETE: &str = "\x1b_Ga=d,d=I,i=1,q=2\x1b\\";
+
+// ID of the image frames are drawn to, so each replaces the last (kitty)
+const IMAGE_ID: u32 = 1;
+// Most base64 bytes sent in one escape code (kitty)
+const CHUNK_LEN: usize = 4096;
+// Pixel rows each line of sixels covers
+const SIXEL_ROWS: usize = 6;
+
+// The protocol the terminal speaks, going by its answer to `QUERY`, preferring kitty's for
+// its smaller payloads; None until the device attributes that end the answer have come in
+pub fn detect(response: &[u8]) -> Option<Option<Protocol>> {
+    let mut rest = response;
+    while let Some(start) = rest.windows(3).position(|w| w == b"\x1b[?") {
+        rest = &rest[start + 3..];
+        let end = rest
+            .iter()
+            .position(|b| !(b.is_ascii_digit() || *b == b';'))?;
+        if rest[end] == b'c' {
+            let kitty = response.windows(9).any(|w| w == b"\x1b_Gi=31;O");
+            let sixel = rest[..end].split(|&b| b == b';').any(|param| param == b"4");
+            return Some(match (kitty, sixel) {
+                (true, _) => Some(Protocol::Kitty),
+                (false, true) => Some(Protocol::Sixel),
+                (false, false) => None,
+            });
+        }
+        rest = &rest[end..];
+    }
+    None
+}
+
+impl Protocol {
+    // Escape codes drawing `pixels`, color indices into `palette` in rows of `w`, at the
+    // cursor, in place of the image drawn last
+    pub fn encode(self, pixels: &[u8], w: usize, palette: &Palette) -> String {
+        match self {
+            Protocol::Kitty => kitty(pixels, w, palette),
+            Protocol::Sixel => sixel(pixels, w, palette),
+        }
+    }
+}
+
+fn kitty(pixels: &[u8], w: usize, palette: &Palette) -> String {
+    let h = pixels.len() / w;
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, w as u32, h as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(
+        (0..NUM_COLORS)
+            .flat_map(|n| <[u8; 3]>::from(palette.rgb(n)))
+            .collect::<Vec<_>>(),
+    );
+    // Writing to memory can't fail
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(pixels).unwrap();
+    writer.finish().unwrap();
+
+    // Sent in chunks, each saying whether more follow; the image goes under any text drawn
+    // over it (z=-1), the cursor stays put (C=1), and the terminal's replies are suppressed
+    // (q=2)
+    let data = base64(&png);
+    let mut chunks = data.as_bytes().chunks(CHUNK_LEN).peekable();
+    let mut out = String::new();
+    let mut first = true;
+    while let Some(chunk) = chunks.next() {
+        let more = chunks.peek().is_some() as u8;
+        // Always ASCII
+        let chunk = std::str::from_utf8(chunk).unwrap();
+        match first {
+            true => write!(
+                out,
+                "\x1b_Ga=T,f=100,i={IMAGE_ID},p=1,z=-1,C=1,q=2,m={more};{chunk}\x1b\\"
+            ),
+            false => write!(out, "\x1b_Gm={more};{chunk}\x1b\\"),
+        }
+        .unwrap();
+        first = false;
+    }
+    out
+}
+
+// Each sixel is a column of six pixels, one bit each, sent as a character from '?' (none
+// lit) up; a band of six rows is painted one color at a time, returning to its start with
+// `$` in between, and runs of the same sixel are shortened to `!` followed by their length
+fn sixel(pixels: &[u8], w: usize, palette: &Palette) -> String {
+    let h = pixels.len() / w;
+    let mut out = format!("\x1bP0;1;0q\"1;1;{w};{h}");
+    for n in 0..NUM_COLORS {
+        let (r, g, b) = palette.rgb(n);
+        let percent = |c: u8| c as u32 * 100 / 255;
+        write!(out, "#{n};2;{};{};{}", percent(r), percent(g), percent(b)).unwrap();
+    }
+
+    for band in (0..h).step_by(SIXEL_ROWS) {
+        let rows = band..(band + SIXEL_ROWS).min(h);
+        for color in 0..NUM_COLORS as u8 {
+            let sixels = (0..w).map(|x| {
+                rows.clone()
+                    .filter(|y| pixels[y * w + x] == color)
+                    .fold(0, |bits, y| bits | 1 << (y - band))
+            });
+            let mut runs = Vec::<(u8, usize)>::new();
+            for bits in sixels {
+                match runs.last_mut() {
+                    Some((last, len)) if *last == bits => *len += 1,
+                    _ => runs.push((bits, 1)),
+                }
+            }
+            if runs.iter().all(|&(bits, _)| bits == 0) {
+                continue;
+            }
+
+            write!(out, "#{color}").unwrap();
+            for (bits, len) in runs {
+                let ch = (b'?' + bits) as char;
+                match len {
+                    1..=3 => (0..len).for_each(|_| out.push(ch)),
+                    _ => write!(out, "!{len}{ch}").unwrap(),
+                }
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out + "\x1b\\"
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for group in data.chunks(3) {
+        let bytes = [
+            group[0],
+            *group.get(1).unwrap_or(&0),
+            *group.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            match i <= group.len() {
+                true => out.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,42 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+// Set once a termination signal comes in, if they're being caught
+static INTERRUPTED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+// Catch SIGINT, SIGTERM, and SIGHUP so a session killed from outside (raw mode keeps ^C
+// from sending SIGINT itself) ends as quitting does, with the frontend torn down and the
+// terminal restored; sessions pick it up through `interrupted`. A second signal, for when
+// nothing is polling, terminates the process right away
+#[cfg(unix)]
+pub fn catch_signals() -> io::Result<()> {
+    use signal_hook::{
+        consts::{SIGHUP, SIGINT, SIGTERM},
+        flag,
+    };
+
+    let interrupted = INTERRUPTED.get_or_init(Default::default);
+    for signal in [SIGINT, SIGTERM, SIGHUP] {
+        flag::register_conditional_shutdown(signal, 1, Arc::clone(interrupted))?;
+        flag::register(signal, Arc::clone(interrupted))?;
+    }
+    Ok(())
+}
+
+// Other platforms' consoles hand ^C to the frontend as a key
+#[cfg(not(unix))]
+pub fn catch_signals() -> io::Result<()> {
+    Ok(())
+}
+
+// Whether a termination signal came in
+pub fn interrupted() -> bool {
+    INTERRUPTED
+        .get()
+        .is_some_and(|interrupted| interrupted.load(Ordering::Relaxed))
+}
@@ -0,0 +1,348 @@
+use std::{fmt, fs, path::PathBuf, str::FromStr};
+
+use crate::{chip8::NUM_KEYS, config};
+
+// Order in which keys are laid out on the CHIP-8 keypad (left-to-right, top-to-bottom)
+pub const KEYPAD_ORDER: [usize; NUM_KEYS] = [
+    0x1, 0x2, 0x3, 0xC, //
+    0x4, 0x5, 0x6, 0xD, //
+    0x7, 0x8, 0x9, 0xE, //
+    0xA, 0x0, 0xB, 0xF, //
+];
+
+// Mapping of host keys (as lowercase characters) to the 16 CHIP-8 keys, shared by all
+// keyboard-driven input devices
+//
+//   Serialized as `host=KEY` pairs separated by commas or newlines, e.g. "1=1,2=2,...,v=F";
+//   keys left unspecified keep their default binding
+//
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Keymap {
+    // Host key bound to the (n as hex)th CHIP-8 key
+    keys: [char; NUM_KEYS],
+}
+
+//    Keyboard                   CHIP-8
+//    +---+---+---+---+          +---+---+---+---+
+//    | 1 | 2 | 3 | 4 |          | 1 | 2 | 3 | C |
+//    +---+---+---+---+          +---+---+---+---+
+//    | Q | W | E | R |          | 4 | 5 | 6 | D |
+//    +---+---+---+---+    =>    +---+---+---+---+
+//    | A | S | D | F |          | 7 | 8 | 9 | E |
+//    +---+---+---+---+          +---+---+---+---+
+//    | Z | X | C | V |          | A | 0 | B | F |
+//    +---+---+---+---+          +---+---+---+---+
+const QWERTY: Keymap = Keymap::from_rows([
+    '1', '2', '3', '4', //
+    'q', 'w', 'e', 'r', //
+    'a', 's', 'd', 'f', //
+    'z', 'x', 'c', 'v', //
+]);
+
+// Named keymaps for other keyboard layouts, binding the keys in the same places on the
+// keyboard that the default binds on QWERTY (`lefty` moves them under the right hand
+// instead, for players with the mouse in their left)
+pub const LAYOUTS: [(&str, Keymap); 4] = [
+    ("qwerty", QWERTY),
+    (
+        "azerty",
+        Keymap::from_rows([
+            '1', '2', '3', '4', //
+            'a', 'z', 'e', 'r', //
+            'q', 's', 'd', 'f', //
+            'w', 'x', 'c', 'v', //
+        ]),
+    ),
+    (
+        "colemak",
+        Keymap::from_rows([
+            '1', '2', '3', '4', //
+            'q', 'w', 'f', 'p', //
+            'a', 'r', 's', 't', //
+            'z', 'x', 'c', 'v', //
+        ]),
+    ),
+    // The comma can't be bound (it separates bindings), so N and M stand in for M and the
+    // comma
+    (
+        "lefty",
+        Keymap::from_rows([
+            '7', '8', '9', '0', //
+            'u', 'i', 'o', 'p', //
+            'j', 'k', 'l', ';', //
+            'n', 'm', '.', '/', //
+        ]),
+    ),
+];
+
+impl Default for Keymap {
+    fn default() -> Self {
+        QWERTY
+    }
+}
+
+impl Keymap {
+    // Keymap binding the host keys in `rows`, laid out as the keypad is (see `KEYPAD_ORDER`)
+    const fn from_rows(rows: [char; NUM_KEYS]) -> Keymap {
+        let mut keys = ['\0'; NUM_KEYS];
+        let mut n = 0;
+        while n < NUM_KEYS {
+            keys[KEYPAD_ORDER[n]] = rows[n];
+            n += 1;
+        }
+        Keymap { keys }
+    }
+
+    pub fn layout(name: &str) -> Option<Keymap> {
+        LAYOUTS
+            .iter()
+            .find(|(layout, _)| *layout == name)
+            .map(|&(_, keymap)| keymap)
+    }
+
+    // CHIP-8 key bound to host key `c`, if any
+    pub fn lookup(&self, c: char) -> Option<usize> {
+        let c = c.to_ascii_lowercase();
+        self.keys.iter().position(|key| *key == c)
+    }
+
+    // Location of the user's saved keymap
+    pub fn config_path() -> Option<PathBuf> {
+        config::config_dir().map(|dir| dir.join("keymap"))
+    }
+
+    // Load the user's saved keymap, or the default one if none has been saved
+    pub fn from_config() -> Result<Keymap, String> {
+        match Keymap::config_path().map(fs::read_to_string) {
+            Some(Ok(contents)) => contents.parse(),
+            _ => Ok(Keymap::default()),
+        }
+    }
+
+    // Keymap given as an argument: either the bindings themselves (see `FromStr`) or the
+    // path of a file holding them, in the same format as the saved keymap
+    pub fn from_arg(arg: &str) -> Result<Keymap, String> {
+        match arg.contains('=') {
+            true => arg.parse(),
+            false => fs::read_to_string(arg)
+                .map_err(|e| format!("failed to read keymap file '{arg}': {e}"))?
+                .parse(),
+        }
+    }
+
+    // `host=KEY` pairs in keypad order
+    fn bindings(&self) -> impl Iterator<Item = String> + '_ {
+        KEYPAD_ORDER
+            .iter()
+            .map(|&idx| format!("{}={:X}", self.keys[idx], idx))
+    }
+
+    pub fn save_config(&self) -> Result<(), String> {
+        let path = Keymap::config_path().ok_or("no configuration directory available")?;
+        let contents = self
+            .bindings()
+            .map(|binding| binding + "\n")
+            .collect::<String>();
+
+        path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, contents))
+            .map_err(|e| format!("failed to save keymap to {}: {e}", path.display()))
+    }
+}
+
+// Interactive building of a keymap, one CHIP-8 key at a time in keypad order: frontends
+// prompt for `key` and `feed` it the host key pressed, or None if the user cancelled. Host
+// keys that are already taken (or that can't be serialized or typed) are ignored, leaving
+// the same CHIP-8 key to be asked for again.
+#[derive(Default)]
+pub struct Remap {
+    keys: [char; NUM_KEYS],
+    // Number of CHIP-8 keys bound so far
+    bound: usize,
+}
+
+// Where remapping stands after being fed a host key
+#[derive(PartialEq, Eq, Debug)]
+pub enum RemapStep {
+    // Waiting on the host key for `Remap::key`
+    Pending,
+    Done(Keymap),
+    Cancelled,
+}
+
+impl Remap {
+    // Line shown under the prompt
+    pub const HINT: &'static str = "(Esc to cancel)";
+
+    // CHIP-8 key to prompt for next
+    pub fn key(&self) -> usize {
+        KEYPAD_ORDER[self.bound]
+    }
+
+    pub fn prompt(&self) -> String {
+        format!("Press the key for CHIP-8 key {:X}", self.key())
+    }
+
+    pub fn feed(&mut self, host: Option<char>) -> RemapStep {
+        let Some(c) = host.map(|c| c.to_ascii_lowercase()) else {
+            return RemapStep::Cancelled;
+        };
+        let taken = !c.is_ascii_graphic()
+            || matches!(c, ',' | '=' | '#')
+            || KEYPAD_ORDER[..self.bound]
+                .iter()
+                .any(|&prev| self.keys[prev] == c);
+        if !taken {
+            self.keys[self.key()] = c;
+            self.bound += 1;
+        }
+
+        match self.bound {
+            NUM_KEYS => RemapStep::Done(Keymap { keys: self.keys }),
+            _ => RemapStep::Pending,
+        }
+    }
+}
+
+impl FromStr for Keymap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keymap = Keymap::default();
+
+        for binding in s
+            .split([',', '\n'])
+            .map(str::trim)
+            .filter(|binding| !binding.is_empty() && !binding.starts_with('#'))
+        {
+            let (host, key) = binding.split_once('=').ok_or(format!(
+                "invalid key binding '{binding}' (expected `host=KEY`)"
+            ))?;
+
+            let mut host_chars = host.trim().chars();
+            let host = match (host_chars.next(), host_chars.next()) {
+                (Some(c), None) => c.to_ascii_lowercase(),
+                _ => return Err(format!("invalid host key '{host}' in binding '{binding}'")),
+            };
+            let key = usize::from_str_radix(key.trim(), 16)
+                .ok()
+                .filter(|key| *key < NUM_KEYS)
+                .ok_or(format!("invalid CHIP-8 key '{key}' in binding '{binding}'"))?;
+
+            // A host key can only be bound once; unbind it from wherever it was before
+            if let Some(prev) = keymap.lookup(host) {
+                keymap.keys[prev] = '\0';
+            }
+            keymap.keys[key] = host;
+        }
+
+        Ok(keymap)
+    }
+}
+
+// Single-line form of the keymap, e.g. "1=1,2=2,...,v=F"
+impl fmt::Display for Keymap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.bindings().collect::<Vec<_>>().join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bindings() {
+        let keymap = "# arrows\nI=5, k=8\nj=7,l=9\n".parse::<Keymap>().unwrap();
+        assert_eq!(keymap.lookup('i'), Some(0x5));
+        assert_eq!(keymap.lookup('L'), Some(0x9));
+        // The rest keep their default, and the keys bound over are freed
+        assert_eq!(keymap.lookup('v'), Some(0xF));
+        assert_eq!(keymap.lookup('w'), None);
+    }
+
+    #[test]
+    fn rebinding_a_host_key() {
+        let keymap = "1=C".parse::<Keymap>().unwrap();
+        assert_eq!(keymap.lookup('1'), Some(0xC));
+        assert_eq!(keymap.lookup('4'), None);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for (_, layout) in LAYOUTS {
+            assert_eq!(layout.to_string().parse(), Ok(layout));
+        }
+        assert_eq!(
+            Keymap::default().to_string(),
+            "1=1,2=2,3=3,4=C,q=4,w=5,e=6,r=D,a=7,s=8,d=9,f=E,z=A,x=0,c=B,v=F"
+        );
+    }
+
+    #[test]
+    fn parse_malformed() {
+        assert_eq!(
+            "q".parse::<Keymap>(),
+            Err("invalid key binding 'q' (expected `host=KEY`)".to_owned())
+        );
+        assert_eq!(
+            "up=5".parse::<Keymap>(),
+            Err("invalid host key 'up' in binding 'up=5'".to_owned())
+        );
+        assert_eq!(
+            "=5".parse::<Keymap>(),
+            Err("invalid host key '' in binding '=5'".to_owned())
+        );
+        assert_eq!(
+            "q=10".parse::<Keymap>(),
+            Err("invalid CHIP-8 key '10' in binding 'q=10'".to_owned())
+        );
+        assert_eq!(
+            "q=G".parse::<Keymap>(),
+            Err("invalid CHIP-8 key 'G' in binding 'q=G'".to_owned())
+        );
+    }
+
+    #[test]
+    fn remap_binds_keys_in_keypad_order() {
+        let mut remap = Remap::default();
+        assert_eq!(remap.key(), 0x1);
+        assert_eq!(remap.feed(Some('1')), RemapStep::Pending);
+        assert_eq!(remap.key(), 0x2);
+        assert_eq!(remap.feed(Some('Q')), RemapStep::Pending);
+        assert_eq!(remap.key(), 0x3);
+
+        let mut step = RemapStep::Pending;
+        for c in "3456789abcdefg".chars() {
+            assert_eq!(step, RemapStep::Pending);
+            step = remap.feed(Some(c));
+        }
+        let RemapStep::Done(keymap) = step else {
+            panic!("remapping didn't finish: {step:?}");
+        };
+        assert_eq!(keymap.lookup('1'), Some(0x1));
+        assert_eq!(keymap.lookup('q'), Some(0x2));
+        assert_eq!(keymap.lookup('4'), Some(0xC));
+        assert_eq!(keymap.lookup('g'), Some(0xF));
+    }
+
+    #[test]
+    fn remap_asks_again_for_taken_keys() {
+        let mut remap = Remap::default();
+        remap.feed(Some('1'));
+        for c in ['1', ',', '=', '#', ' '] {
+            assert_eq!(remap.feed(Some(c)), RemapStep::Pending);
+            assert_eq!(remap.key(), 0x2);
+        }
+        remap.feed(Some('2'));
+        assert_eq!(remap.key(), 0x3);
+    }
+
+    #[test]
+    fn remap_cancelled_partway() {
+        let mut remap = Remap::default();
+        remap.feed(Some('1'));
+        assert_eq!(remap.feed(None), RemapStep::Cancelled);
+    }
+}
@@ -0,0 +1,135 @@
+use termion::event::Key;
+
+// Kitty keyboard protocol (https://sw.kovidgoyal.net/kitty/keyboard-protocol/), through
+// which terminals supporting it report key releases and repeats on top of presses
+//
+//   Every key comes in as a CSI sequence once it's enabled with the flags below:
+//
+//     ESC [ code:shifted ; modifiers:action u      e.g. ESC [ 97;1:3u => 'a' released
+//     ESC [ number ; modifiers:action ~            e.g. ESC [ 15;1:1~ => F5 pressed
+//     ESC [ 1 ; modifiers:action A-D|H|F|P|Q|S     arrows, Home, End, F1, F2, and F4
+//
+
+// Ask for the flags in effect, then for the primary device attributes every terminal
+// answers; only those supporting the protocol answer the first, ahead of the second
+pub const QUERY: &str = "\x1b[?u\x1b[c";
+// Push flags disambiguating escape codes (1), reporting key actions (2) and shifted keys
+// (4), and reporting every key as an escape code (8), so text keys come up too
+pub const ENABLE: &str = "\x1b[>15u";
+// Pop them again, restoring whatever the terminal had before
+pub const DISABLE: &str = "\x1b[<u";
+
+// Modifier bits, once 1 is taken off the reported value
+const MOD_SHIFT: u32 = 0b001;
+const MOD_ALT: u32 = 0b010;
+const MOD_CTRL: u32 = 0b100;
+// Codes the protocol gives keys with no character (e.g. Caps Lock, the modifiers themselves)
+const FUNCTIONAL_KEYS: core::ops::RangeInclusive<u32> = 57344..=63743;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyAction {
+    Press,
+    Repeat,
+    Release,
+}
+
+// Whether the terminal supports the protocol, going by its answer to `QUERY`; None until
+// the device attributes that end the answer have come in
+pub fn supported(response: &[u8]) -> Option<bool> {
+    let mut flags = false;
+    let mut rest = response;
+    while let Some(start) = rest.windows(3).position(|w| w == b"\x1b[?") {
+        rest = &rest[start + 3..];
+        let end = rest
+            .iter()
+            .position(|b| !(b.is_ascii_digit() || *b == b';'))?;
+        match rest[end] {
+            b'u' => flags = true,
+            b'c' => return Some(flags),
+            _ => (),
+        }
+        rest = &rest[end..];
+    }
+    None
+}
+
+// Parse a key sequence at the start of `bytes`, returning the key, what happened to it,
+// and how many bytes it took up; None for anything else (e.g. mouse events), left for
+// termion to parse. Releases carry the key unshifted, in case Shift came up first
+pub fn parse_key(bytes: &[u8]) -> Option<(Key, KeyAction, usize)> {
+    let params = bytes.strip_prefix(b"\x1b[")?;
+    let end = params
+        .iter()
+        .position(|b| !(b.is_ascii_digit() || *b == b';' || *b == b':'))?;
+    let len = 2 + end + 1;
+    let (params, terminator) = (std::str::from_utf8(&params[..end]).ok()?, params[end]);
+
+    let mut fields = params.split(';');
+    let mut codes = fields.next().unwrap_or("").split(':');
+    let code = codes
+        .next()
+        .and_then(|c| c.parse::<u32>().ok())
+        .unwrap_or(1);
+    let shifted = codes.next().and_then(|c| c.parse::<u32>().ok());
+    let mut modifiers = fields.next().unwrap_or("").split(':');
+    let mods = modifiers
+        .next()
+        .and_then(|m| m.parse::<u32>().ok())
+        .unwrap_or(1)
+        .saturating_sub(1);
+    let action = match modifiers.next() {
+        None | Some("1") => KeyAction::Press,
+        Some("2") => KeyAction::Repeat,
+        Some("3") => KeyAction::Release,
+        Some(_) => return None,
+    };
+
+    let key = match terminator {
+        b'u' => match code {
+            27 => Key::Esc,
+            13 => Key::Char('\n'),
+            9 => Key::Char('\t'),
+            127 => Key::Backspace,
+            code if FUNCTIONAL_KEYS.contains(&code) => Key::Null,
+            code => {
+                let code = match shifted {
+                    Some(shifted) if mods & MOD_SHIFT != 0 && action != KeyAction::Release => {
+                        shifted
+                    }
+                    _ => code,
+                };
+                let c = char::from_u32(code)?;
+                if mods & MOD_CTRL != 0 {
+                    Key::Ctrl(c)
+                } else if mods & MOD_ALT != 0 {
+                    Key::Alt(c)
+                } else {
+                    Key::Char(c)
+                }
+            }
+        },
+        b'~' => match code {
+            2 => Key::Insert,
+            3 => Key::Delete,
+            5 => Key::PageUp,
+            6 => Key::PageDown,
+            1 | 7 => Key::Home,
+            4 | 8 => Key::End,
+            n @ 11..=15 => Key::F((n - 10) as u8),
+            n @ 17..=21 => Key::F((n - 11) as u8),
+            n @ 23..=24 => Key::F((n - 12) as u8),
+            _ => Key::Null,
+        },
+        b'A' => Key::Up,
+        b'B' => Key::Down,
+        b'C' => Key::Right,
+        b'D' => Key::Left,
+        b'H' => Key::Home,
+        b'F' => Key::End,
+        b'P' => Key::F(1),
+        b'Q' => Key::F(2),
+        b'S' => Key::F(4),
+        _ => return None,
+    };
+    Some((key, action, len))
+}
@@ -0,0 +1,80 @@
+use std::cell::RefCell;
+
+use crate::driver::{InputDevice, InputInfo, InputMsg};
+use crate::emulator::Signal;
+
+// Frontend's input device along with extra ones (e.g. a gamepad or hardware keypad), all
+// of whose keys count as pressed
+//
+//   Hotkeys and console commands come from the frontend; the extra devices' hotkeys are
+//   passed on too when the frontend has none to send
+//
+pub struct MergedInput<'a, I: InputDevice> {
+    frontend: &'a RefCell<I>,
+    extras: Vec<Box<dyn InputDevice>>,
+    // Whether an extra device's keys changed since the emulator last heard about it
+    changed: bool,
+    // Hotkey from an extra device held back for the frontend's
+    deferred: Option<Signal>,
+}
+
+impl<'a, I: InputDevice> MergedInput<'a, I> {
+    pub fn new(frontend: &'a RefCell<I>, extras: Vec<Box<dyn InputDevice>>) -> Self {
+        MergedInput {
+            frontend,
+            extras,
+            changed: false,
+            deferred: None,
+        }
+    }
+
+    // Let go of the frontend's input device, e.g. to merge the extra ones with another's
+    pub fn into_extras(self) -> Vec<Box<dyn InputDevice>> {
+        self.extras
+    }
+}
+
+impl<I: InputDevice> InputDevice for MergedInput<'_, I> {
+    fn handle_inputs(&mut self) -> Signal {
+        for extra in &mut self.extras {
+            match extra.handle_inputs() {
+                Signal::None => (),
+                Signal::NewInputs => self.changed = true,
+                hotkey => self.deferred = Some(hotkey),
+            }
+        }
+
+        let signal = match self.frontend.borrow_mut().handle_inputs() {
+            Signal::None => self.deferred.take().unwrap_or(Signal::None),
+            signal => signal,
+        };
+        match signal {
+            Signal::None if self.changed => {
+                self.changed = false;
+                Signal::NewInputs
+            }
+            Signal::NewInputs => {
+                self.changed = false;
+                Signal::NewInputs
+            }
+            signal => signal,
+        }
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        let frontend = self.frontend.borrow().send_inputs();
+        self.extras
+            .iter()
+            .filter_map(|extra| extra.send_inputs())
+            .chain(frontend)
+            .reduce(|keys, more| keys | more)
+    }
+
+    fn take_command(&mut self) -> Option<String> {
+        self.frontend.borrow_mut().take_command()
+    }
+
+    fn device_info(&self) -> InputInfo {
+        self.frontend.borrow().device_info()
+    }
+}
@@ -1,89 +1,620 @@
+use std::{
+    io::{stdin, Read},
+    sync::{mpsc, Arc, Weak},
+    thread,
+    time::{Duration, Instant},
+};
+
 use bitvec::{bitarr, slice::BitSlice, BitArr};
 
 use crate::{
     chip8::{DISPLAY_HEIGHT, DISPLAY_WIDTH, NUM_KEYS},
-    driver::{DisplayDevice, DisplayInfo, InputDevice, InputInfo, InputMsg},
-    driver::{KEY_DOWN, KEY_UP, PX_OFF, PX_ON},
+    config::desktop::{self, ICON_SIZE},
+    config::profile::{Profile, ProfileCycle, Profiles},
+    diagnostics,
+    driver::{
+        digit_pixels, pixel_color, print_panel, remap_pixels, Rect, KEY_DOWN, KEY_UP,
+        NOTIFICATION_DURATION,
+    },
+    driver::{
+        keymap::{Keymap, Remap, RemapStep, KEYPAD_ORDER},
+        palette::Palette,
+        upscale::Filter,
+    },
+    driver::{DisplayDevice, DisplayInfo, InputDevice, InputInfo, InputMsg, StatusMsg},
     emulator::Signal,
 };
 
-// minifb::Window pixels use ARGB encoding;
-// alpha-channel (MSB) is ignored => 0RGB
-const PX_OFF_COLOR: u32 = 0x1E1C2D;
-const PX_ON_COLOR: u32 = 0xE0DEF4;
+// Shortest time between window updates: a frame of a 60Hz monitor, whatever the timer rate,
+// as updating any faster only shows frames the monitor never will (frames at slower rates
+// are all shown as they come)
+const UPDATE_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
+// How much sooner than that a frame can still be presented, as frames running in real time
+// come in about `UPDATE_INTERVAL` apart, give or take the host's timer jitter, and one
+// coming in just early mustn't be held back until the next
+const UPDATE_SLACK: Duration = Duration::from_millis(2);
 
 pub struct Minifb {
     // GUI window
     window: minifb::Window,
-    // Auxiliary frame buffer to convert pixels to 32-bit format expected by minifb::Window
-    framebuf: [u32; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+    // Auxiliary frame buffer to convert pixels to 32-bit format expected by minifb::Window;
+    // sized to the incoming frame (e.g. 64x32 or 128x64 in SCHIP hi-res mode)
+    framebuf: Vec<u32>,
+    // Dimensions (w, h) of the frame currently held in `framebuf`
+    frame_dims: (usize, usize),
+    // Region of the next frame that changed since the last one (see `receive_damage`); only
+    // its pixels are converted
+    damage: Option<Rect>,
+    // Whether all of `framebuf` has to be converted on the next frame, having been drawn
+    // over or recolored since the last one
+    redraw: bool,
+    // Window-sized copy of `framebuf` that's shown, scaled up by the largest whole factor
+    // that fits and centered on the background color, so pixels stay square and crisp
+    // however the window is resized
+    scaled: Vec<u32>,
+    // Window size `scaled` is laid out for; (0, 0) to lay it out anew
+    window_dims: (usize, usize),
+    // Region of `framebuf` yet to be copied over to `scaled`
+    unscaled: Option<Rect>,
+    // Whether a frame came in that isn't shown yet, when one last was, when the window last
+    // took in events (presenting a frame or not), and whether those are yet to be handled
+    // (see `present_pending` and `pump_events`)
+    frame_pending: bool,
+    presented: Instant,
+    pumped: Instant,
+    polled: bool,
+    // Pixel-art upscaler `framebuf` is run through ahead of `scaled`, if any, and its output
+    filter: Option<Filter>,
+    filtered: Vec<u32>,
     // Tx input buffer
     keybuf: BitArr!(for NUM_KEYS),
+    // Whether Tab was held down at the last poll, fast-forwarding the machine
+    turbo: bool,
+    // Host key to CHIP-8 key bindings
+    keymap: Keymap,
+    // Pixel colors; minifb::Window pixels use ARGB encoding with the alpha-channel (MSB)
+    // ignored => 0RGB, which is what `Palette` holds
+    palette: Palette,
+    // Profiles cycled through with the P hotkey
+    profiles: ProfileCycle,
+    // Text panel last received; the window can't show text, so it goes to stderr instead
+    panel: Vec<String>,
+    // Debugger console commands, read from stdin lines for the same reason, and the last
+    // one entered
+    commands: mpsc::Receiver<String>,
+    entered: Option<String>,
+    // Held for as long as the window is around, for the thread reading commands to tell
+    _open: Arc<()>,
+    // Window title: the program's name, then how fast it runs against the clock rate (or
+    // that it's paused); notifications are appended to it until they expire
+    name: String,
+    title: String,
+    toast_shown: Option<Instant>,
+    // Clickable keypad in a window of its own, if shown
+    keypad: Option<Keypad>,
+}
+
+// Pixels a keypad key takes up in its window, gap included (see `Keypad::draw`)
+const KEYPAD_CELL_SIZE: usize = 10;
+const KEYPAD_SIZE: usize = 4 * KEYPAD_CELL_SIZE;
+
+// The CHIP-8 keypad, drawn with the keys held highlighted; holding the mouse button down
+// over a key presses it
+struct Keypad {
+    window: minifb::Window,
+    framebuf: Vec<u32>,
+    // Keys highlighted in `framebuf`; None to draw it anew
+    drawn: Option<InputMsg>,
+}
+
+// Pass lines typed into stdin on as console commands until the window is closed; whatever
+// is typed next (e.g. into a terminal frontend switched back to) still goes to the read
+// pending then, but nothing after it
+fn read_commands(tx: mpsc::Sender<String>, window_open: Weak<()>) {
+    let mut typed = Vec::new();
+    let mut buf = [0; 256];
+    while let Ok(n @ 1..) = stdin().read(&mut buf) {
+        if window_open.strong_count() == 0 {
+            return;
+        }
+        typed.extend_from_slice(&buf[..n]);
+        while let Some(end) = typed.iter().position(|&byte| byte == b'\n') {
+            let line = typed.drain(..=end).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line).trim_end().to_owned();
+            if tx.send(line).is_err() {
+                return;
+            }
+        }
+    }
 }
 
 impl Minifb {
-    pub fn new(name: &str) -> Self {
-        Minifb {
-            window: minifb::Window::new(
-                &("CHIP-8: ".to_owned() + name),
-                DISPLAY_WIDTH,
-                DISPLAY_HEIGHT,
-                minifb::WindowOptions {
-                    resize: true,
-                    scale: minifb::Scale::X16,
-                    ..Default::default()
+    // `scale` is the window scale factor, one of `profile::SCALES`
+    pub fn new(name: &str, scale: usize) -> Result<Self, String> {
+        let title = "CHIP-8: ".to_owned() + name;
+        let window = minifb::Window::new(
+            &title,
+            DISPLAY_WIDTH,
+            DISPLAY_HEIGHT,
+            minifb::WindowOptions {
+                resize: true,
+                scale: match scale {
+                    1 => minifb::Scale::X1,
+                    2 => minifb::Scale::X2,
+                    4 => minifb::Scale::X4,
+                    8 => minifb::Scale::X8,
+                    32 => minifb::Scale::X32,
+                    _ => minifb::Scale::X16,
                 },
-            )
-            .expect("GUI window creation failed"),
+                ..Default::default()
+            },
+        )
+        .map_err(|e| format!("GUI window creation failed: {e}"))?;
+
+        let open = Arc::new(());
+        let (tx, commands) = mpsc::channel();
+        let window_open = Arc::downgrade(&open);
+        thread::spawn(move || read_commands(tx, window_open));
 
-            framebuf: [0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+        Ok(Minifb {
+            window,
+            framebuf: vec![0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            frame_dims: (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+            damage: None,
+            redraw: true,
+            scaled: Vec::new(),
+            window_dims: (0, 0),
+            unscaled: None,
+            frame_pending: false,
+            presented: Instant::now(),
+            pumped: Instant::now(),
+            polled: false,
+            filter: None,
+            filtered: Vec::new(),
             keybuf: bitarr![0; NUM_KEYS],
+            turbo: false,
+            keymap: Keymap::default(),
+            palette: Palette::default(),
+            profiles: ProfileCycle::default(),
+            panel: Vec::new(),
+            commands,
+            entered: None,
+            _open: open,
+            name: title.clone(),
+            title,
+            toast_shown: None,
+            keypad: None,
+        })
+    }
+
+    // Apply the current profile, keeping the rest around for cycling
+    pub fn set_profiles(&mut self, profiles: Profiles) {
+        let profile = self.profiles.set(profiles);
+        self.apply_profile(&profile);
+    }
+
+    // The window scale is fixed once the window is open, so it is left as is here
+    fn apply_profile(&mut self, profile: &Profile) {
+        self.keymap = profile.keymap;
+        self.palette = profile.palette.unwrap_or_default();
+        self.redraw = true;
+        self.update_icon();
+        match (&mut self.keypad, profile.keypad) {
+            (Some(keypad), true) => keypad.drawn = None,
+            (None, true) => match Keypad::new() {
+                Ok(keypad) => self.keypad = Some(keypad),
+                Err(e) => diagnostics::warn("minifb", e),
+            },
+            (_, false) => self.keypad = None,
+        }
+    }
+
+    // Show the application icon (see `desktop::ICON`) in the palette's colors, scaled up
+    // so window managers don't blur it; X11 takes it as the width and height followed by
+    // ARGB pixels, each in a `c_ulong`
+    fn update_icon(&mut self) {
+        #[cfg(target_os = "linux")]
+        {
+            const SCALE: usize = 4;
+            let size = ICON_SIZE * SCALE;
+
+            let mut icon = vec![size as u64, size as u64];
+            for y in 0..size {
+                for x in 0..size {
+                    let lit = desktop::icon_pixel(x / SCALE, y / SCALE);
+                    icon.push(0xFF00_0000 | self.palette.colors[lit as usize] as u64);
+                }
+            }
+            if let Ok(icon) = minifb::Icon::try_from(icon.as_slice()) {
+                self.window.set_icon(icon);
+            }
+        }
+    }
+
+    // Interactive remapping flow: the CHIP-8 key to be bound is drawn in the window
+    // while waiting for the host key. Returns None if the user cancels with Esc or
+    // closes the window.
+    pub fn remap_keys(&mut self, title: &str) -> Option<Keymap> {
+        let mut remap = Remap::default();
+        let keymap = loop {
+            self.window
+                .set_title(&format!("CHIP-8: {} {}", remap.prompt(), Remap::HINT));
+
+            // Draw the hex digit being bound
+            self.framebuf.clear();
+            self.framebuf
+                .resize(DISPLAY_WIDTH * DISPLAY_HEIGHT, self.palette.colors[0]);
+            self.frame_dims = (DISPLAY_WIDTH, DISPLAY_HEIGHT);
+            self.window_dims = (0, 0);
+            self.redraw = true;
+            for (x, y) in remap_pixels(remap.key()) {
+                self.framebuf[y * DISPLAY_WIDTH + x] = self.palette.colors[1];
+            }
+
+            let host = 'read: loop {
+                if !self.window.is_open() {
+                    break None;
+                }
+                self.present().unwrap();
+
+                for key in self.window.get_keys_pressed(minifb::KeyRepeat::No) {
+                    match key {
+                        minifb::Key::Escape => break 'read None,
+                        key => {
+                            if let Some(c) = key_char(key) {
+                                break 'read Some(c);
+                            }
+                        }
+                    }
+                }
+            };
+            match remap.feed(host) {
+                RemapStep::Pending => (),
+                RemapStep::Done(keymap) => break Some(keymap),
+                RemapStep::Cancelled => break None,
+            }
+        };
+
+        self.window.set_title(&("CHIP-8: ".to_owned() + title));
+        keymap
+    }
+
+    // Run frames through `filter` before they're scaled up to the window
+    pub fn set_filter(&mut self, filter: Option<Filter>) {
+        self.filter = filter;
+        self.window_dims = (0, 0);
+    }
+
+    // Show the last frame that came in if it isn't shown yet, at most once per
+    // `UPDATE_INTERVAL` however often frames come in (e.g. fast-forwarding); the window
+    // takes in events as it does
+    fn present_pending(&mut self) {
+        if !self.frame_pending || self.presented.elapsed() + UPDATE_SLACK < UPDATE_INTERVAL {
+            return;
+        }
+        self.frame_pending = false;
+        self.presented = Instant::now();
+        self.pumped = self.presented;
+        self.polled = true;
+        if let Err(e) = self.present() {
+            diagnostics::warn("minifb", format!("failed to update the window: {e}"));
+        }
+    }
+
+    // Have the window take in events without a frame to show (e.g. a ROM waiting on a key
+    // without drawing), at most once per `UPDATE_INTERVAL`; a pending frame is left to
+    // `present_pending`, which takes them in as well
+    fn pump_events(&mut self) {
+        if self.frame_pending || self.pumped.elapsed() < UPDATE_INTERVAL {
+            return;
+        }
+        self.pumped = Instant::now();
+        self.polled = true;
+        self.window.update();
+    }
+
+    // Show `framebuf` in the window, scaling whatever changed in it over to `scaled` first
+    fn present(&mut self) -> minifb::Result<()> {
+        let (w, h) = self.window.get_size();
+        let (fw, fh) = self.frame_dims;
+        // Too small for even one window pixel per frame pixel (e.g. minimized), so let
+        // minifb squeeze the frame in
+        if w < fw || h < fh {
+            return self.window.update_with_buffer(&self.framebuf, fw, fh);
+        }
+
+        let mut region = self.unscaled.take();
+        if self.window_dims != (w, h) {
+            self.window_dims = (w, h);
+            self.scaled.clear();
+            self.scaled.resize(w * h, self.palette.colors[0]);
+            region = Some(Rect::full(self.frame_dims));
+        }
+        // The filter's output is scaled up in place of the frame, if the window has room for
+        // it; as each of its pixels depends on the ones around it, it's run over the whole
+        // frame whenever any of it changed
+        let filter = self
+            .filter
+            .filter(|filter| w >= fw * filter.factor() && h >= fh * filter.factor());
+        let (frame, (fw, fh), region) = match filter {
+            Some(filter) => {
+                if region.is_some() {
+                    filter.apply(&self.framebuf, self.frame_dims, &mut self.filtered);
+                }
+                let dims = (fw * filter.factor(), fh * filter.factor());
+                (&self.filtered, dims, region.map(|_| Rect::full(dims)))
+            }
+            None => (&self.framebuf, (fw, fh), region),
+        };
+        let scale = (w / fw).min(h / fh);
+        let (ox, oy) = ((w - fw * scale) / 2, (h - fh * scale) / 2);
+        if let Some(region) = region {
+            for y in region.y..(region.y + region.h).min(fh) {
+                for x in region.x..(region.x + region.w).min(fw) {
+                    let color = frame[y * fw + x];
+                    for py in oy + y * scale..oy + (y + 1) * scale {
+                        let start = py * w + ox + x * scale;
+                        self.scaled[start..start + scale].fill(color);
+                    }
+                }
+            }
+        }
+
+        self.window.update_with_buffer(&self.scaled, w, h)
+    }
+}
+
+// Character a host key corresponds to, for lookups in a `Keymap`
+fn key_char(key: minifb::Key) -> Option<char> {
+    use minifb::Key;
+
+    let c = match key {
+        Key::Key0 => '0',
+        Key::Key1 => '1',
+        Key::Key2 => '2',
+        Key::Key3 => '3',
+        Key::Key4 => '4',
+        Key::Key5 => '5',
+        Key::Key6 => '6',
+        Key::Key7 => '7',
+        Key::Key8 => '8',
+        Key::Key9 => '9',
+        Key::A => 'a',
+        Key::B => 'b',
+        Key::C => 'c',
+        Key::D => 'd',
+        Key::E => 'e',
+        Key::F => 'f',
+        Key::G => 'g',
+        Key::H => 'h',
+        Key::I => 'i',
+        Key::J => 'j',
+        Key::K => 'k',
+        Key::L => 'l',
+        Key::M => 'm',
+        Key::N => 'n',
+        Key::O => 'o',
+        Key::P => 'p',
+        Key::Q => 'q',
+        Key::R => 'r',
+        Key::S => 's',
+        Key::T => 't',
+        Key::U => 'u',
+        Key::V => 'v',
+        Key::W => 'w',
+        Key::X => 'x',
+        Key::Y => 'y',
+        Key::Z => 'z',
+        Key::Apostrophe => '\'',
+        Key::Backquote => '`',
+        Key::Backslash => '\\',
+        Key::Comma => ',',
+        Key::Equal => '=',
+        Key::LeftBracket => '[',
+        Key::Minus => '-',
+        Key::Period => '.',
+        Key::RightBracket => ']',
+        Key::Semicolon => ';',
+        Key::Slash => '/',
+        _ => return None,
+    };
+
+    Some(c)
+}
+
+impl Keypad {
+    fn new() -> Result<Self, String> {
+        let window = minifb::Window::new(
+            "CHIP-8 keypad",
+            KEYPAD_SIZE,
+            KEYPAD_SIZE,
+            minifb::WindowOptions {
+                scale: minifb::Scale::X8,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| format!("keypad window creation failed: {e}"))?;
+
+        Ok(Keypad {
+            window,
+            framebuf: vec![0; KEYPAD_SIZE * KEYPAD_SIZE],
+            drawn: None,
+        })
+    }
+
+    // Key the mouse button is held down over, if any
+    fn clicked(&self) -> Option<usize> {
+        if !self.window.get_mouse_down(minifb::MouseButton::Left) {
+            return None;
+        }
+        let (x, y) = self.window.get_mouse_pos(minifb::MouseMode::Discard)?;
+        let (x, y) = (x as usize, y as usize);
+        // Clicks landing between keys don't count
+        if [x, y].iter().any(|n| matches!(n % KEYPAD_CELL_SIZE, 0 | 9)) {
+            return None;
+        }
+        KEYPAD_ORDER
+            .get(y / KEYPAD_CELL_SIZE * 4 + x / KEYPAD_CELL_SIZE)
+            .copied()
+    }
+
+    // Show the keypad with the keys in `held` highlighted, redrawing it only if they
+    // changed; each key is an outlined 8x8 face with its hex digit in the middle, filled in
+    // while held
+    fn draw(&mut self, held: InputMsg, palette: &Palette) {
+        if self.drawn == Some(held) {
+            self.window.update();
+            return;
+        }
+        self.drawn = Some(held);
+
+        let [off, on] = [palette.colors[0], palette.colors[1]];
+        self.framebuf.fill(off);
+        for (n, &key) in KEYPAD_ORDER.iter().enumerate() {
+            let (ox, oy) = (n % 4 * KEYPAD_CELL_SIZE, n / 4 * KEYPAD_CELL_SIZE);
+            let (face, digit) = match held[key] {
+                KEY_DOWN => (on, off),
+                KEY_UP => (off, on),
+            };
+            for y in 1..KEYPAD_CELL_SIZE - 1 {
+                for x in 1..KEYPAD_CELL_SIZE - 1 {
+                    let edge = matches!(x, 1 | 8) || matches!(y, 1 | 8);
+                    self.framebuf[(oy + y) * KEYPAD_SIZE + ox + x] = if edge { on } else { face };
+                }
+            }
+            for (x, y) in digit_pixels(key, 1) {
+                self.framebuf[(oy + 3 + y) * KEYPAD_SIZE + ox + 3 + x] = digit;
+            }
+        }
+
+        if let Err(e) = self
+            .window
+            .update_with_buffer(&self.framebuf, KEYPAD_SIZE, KEYPAD_SIZE)
+        {
+            diagnostics::warn("minifb", format!("failed to update the keypad window: {e}"));
         }
     }
 }
 
 impl InputDevice for Minifb {
-    //
-    //    Keyboard                   CHIP-8
-    //    +---+---+---+---+          +---+---+---+---+
-    //    | 1 | 2 | 3 | 4 |          | 1 | 2 | 3 | C |
-    //    +---+---+---+---+          +---+---+---+---+
-    //    | Q | W | E | R |          | 4 | 5 | 6 | D |
-    //    +---+---+---+---+    =>    +---+---+---+---+
-    //    | A | S | D | F |          | 7 | 8 | 9 | E |
-    //    +---+---+---+---+          +---+---+---+---+
-    //    | Z | X | C | V |          | A | 0 | B | F |
-    //    +---+---+---+---+          +---+---+---+---+
-    //
+    // Host keys are translated to CHIP-8 keys through `Keymap` (QWERTY layout by default)
     fn handle_inputs(&mut self) -> Signal {
         if !self.window.is_open() {
             return Signal::ProgramExit;
         }
 
+        // Checked here since frames don't necessarily keep coming in
+        if self
+            .toast_shown
+            .is_some_and(|shown| shown.elapsed() >= NOTIFICATION_DURATION)
+        {
+            self.window.set_title(&self.title);
+            self.toast_shown = None;
+        }
+
+        if let Ok(command) = self.commands.try_recv() {
+            self.entered = Some(command);
+            return Signal::DebugCommand;
+        }
+
+        // Nothing new comes in between window updates, and keys taken in by one are only
+        // reported once
+        self.present_pending();
+        self.pump_events();
+        if !std::mem::take(&mut self.polled) {
+            return Signal::None;
+        }
+
         let prev_state = self.keybuf;
         self.keybuf.fill(KEY_UP);
 
-        self.window.get_keys().iter().for_each(|key| match key {
-            minifb::Key::Key1 => self.keybuf.set(0x1, KEY_DOWN),
-            minifb::Key::Key2 => self.keybuf.set(0x2, KEY_DOWN),
-            minifb::Key::Key3 => self.keybuf.set(0x3, KEY_DOWN),
-            minifb::Key::Key4 => self.keybuf.set(0xC, KEY_DOWN),
-            minifb::Key::Q => self.keybuf.set(0x4, KEY_DOWN),
-            minifb::Key::W => self.keybuf.set(0x5, KEY_DOWN),
-            minifb::Key::E => self.keybuf.set(0x6, KEY_DOWN),
-            minifb::Key::R => self.keybuf.set(0xD, KEY_DOWN),
-            minifb::Key::A => self.keybuf.set(0x7, KEY_DOWN),
-            minifb::Key::S => self.keybuf.set(0x8, KEY_DOWN),
-            minifb::Key::D => self.keybuf.set(0x9, KEY_DOWN),
-            minifb::Key::F => self.keybuf.set(0xE, KEY_DOWN),
-            minifb::Key::Z => self.keybuf.set(0xA, KEY_DOWN),
-            minifb::Key::X => self.keybuf.set(0x0, KEY_DOWN),
-            minifb::Key::C => self.keybuf.set(0xB, KEY_DOWN),
-            minifb::Key::V => self.keybuf.set(0xF, KEY_DOWN),
-            _ => (),
-        });
+        for key in self.window.get_keys() {
+            if let Some(idx) = key_char(key).and_then(|c| self.keymap.lookup(c)) {
+                self.keybuf.set(idx, KEY_DOWN);
+            }
+        }
+        // Closing the keypad window just hides the keypad
+        if self
+            .keypad
+            .as_ref()
+            .is_some_and(|keypad| !keypad.window.is_open())
+        {
+            self.keypad = None;
+        }
+        if let Some(keypad) = &mut self.keypad {
+            if let Some(idx) = keypad.clicked() {
+                self.keybuf.set(idx, KEY_DOWN);
+            }
+            keypad.draw(self.keybuf, &self.palette);
+        }
+
+        // Rewinding lasts for as long as the key is held, freezing the keypad meanwhile
+        if self.window.is_key_down(minifb::Key::Backspace) {
+            self.keybuf = prev_state;
+            return Signal::Rewind;
+        }
+
+        // Fast-forwarding lasts for as long as the key is held
+        if self.window.is_key_down(minifb::Key::Tab) != self.turbo {
+            self.keybuf = prev_state;
+            self.turbo = !self.turbo;
+            return match self.turbo {
+                true => Signal::TurboOn,
+                false => Signal::TurboOff,
+            };
+        }
+
+        // Emulator hotkeys take precedence over keypad updates
+        let hotkey = self
+            .window
+            .get_keys_pressed(minifb::KeyRepeat::No)
+            .iter()
+            .filter(|key| {
+                key_char(**key)
+                    .and_then(|c| self.keymap.lookup(c))
+                    .is_none()
+            })
+            .find_map(|key| match key {
+                minifb::Key::M => Some(Signal::MarkState),
+                minifb::Key::N => Some(Signal::RestoreMark),
+                minifb::Key::O => Some(Signal::RotateDisplay),
+                minifb::Key::H => Some(Signal::MirrorDisplay),
+                minifb::Key::P => Some(Signal::CycleProfile),
+                minifb::Key::F2 => Some(Signal::Reset),
+                minifb::Key::F5 => Some(Signal::SaveState),
+                minifb::Key::F7 => Some(Signal::LoadState),
+                minifb::Key::F8 => Some(Signal::SwitchFrontend),
+                minifb::Key::F9 => Some(Signal::ToggleRecording),
+                minifb::Key::Space => Some(Signal::Pause),
+                minifb::Key::Period => Some(Signal::Step),
+                minifb::Key::Comma => Some(Signal::FrameAdvance),
+                minifb::Key::Equal | minifb::Key::NumPadPlus => Some(Signal::SpeedUp),
+                minifb::Key::Minus | minifb::Key::NumPadMinus => Some(Signal::SpeedDown),
+                minifb::Key::Key0 => Some(Signal::VolumeUp),
+                minifb::Key::Key9 => Some(Signal::VolumeDown),
+                minifb::Key::Key8 => Some(Signal::ToggleMute),
+                minifb::Key::B => Some(Signal::ToggleBreakpoint),
+                minifb::Key::LeftBracket => Some(Signal::ScrollMemoryUp),
+                minifb::Key::RightBracket => Some(Signal::ScrollMemoryDown),
+                _ => None,
+            })
+            .unwrap_or(Signal::None);
+        let hotkey = match hotkey {
+            Signal::CycleProfile => match self.profiles.cycle() {
+                Some(profile) => {
+                    self.apply_profile(&profile);
+                    hotkey
+                }
+                None => Signal::None,
+            },
+            hotkey => hotkey,
+        };
 
-        if self.keybuf != prev_state {
+        if hotkey != Signal::None {
+            // Hold off on reporting keypad changes until the next poll
+            self.keybuf = prev_state;
+            hotkey
+        } else if self.keybuf != prev_state {
             Signal::NewInputs
         } else {
             Signal::None
@@ -94,28 +625,86 @@ impl InputDevice for Minifb {
         Some(self.keybuf)
     }
 
+    fn take_command(&mut self) -> Option<String> {
+        self.entered.take()
+    }
+
     fn device_info(&self) -> InputInfo {
         InputInfo::Minifb
     }
 }
 
 impl DisplayDevice for Minifb {
-    fn receive_frame(&mut self, frame: &BitSlice<usize>) -> &mut dyn DisplayDevice {
-        frame
-            .iter()
-            .enumerate()
-            .for_each(|(idx, pixel)| match *pixel {
-                PX_OFF => self.framebuf[idx] = PX_OFF_COLOR,
-                PX_ON => self.framebuf[idx] = PX_ON_COLOR,
-            });
+    fn receive_damage(&mut self, damage: Rect) -> &mut dyn DisplayDevice {
+        self.damage = Some(damage);
+        self
+    }
+
+    fn receive_frame(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice {
+        // Only the pixels the frame changed in need converting, unless the buffer is stale
+        let region = match self.damage.take() {
+            Some(damage) if !self.redraw && self.frame_dims == dims => damage,
+            _ => {
+                // The letterbox may have moved or been recolored too
+                self.window_dims = (0, 0);
+                Rect::full(dims)
+            }
+        };
+        self.redraw = false;
+        self.frame_dims = dims;
+        self.framebuf
+            .resize(dims.0 * dims.1, self.palette.colors[0]);
+        for y in region.y..(region.y + region.h).min(dims.1) {
+            let row = y * dims.0;
+            for idx in row + region.x..row + (region.x + region.w).min(dims.0) {
+                self.framebuf[idx] = self.palette.color(pixel_color(planes, idx));
+            }
+        }
+        self.unscaled = Some(match self.unscaled {
+            Some(unscaled) => unscaled.union(region),
+            None => region,
+        });
 
         self
     }
 
+    fn receive_panel(&mut self, lines: &[String]) {
+        if lines != self.panel {
+            self.panel = lines.to_vec();
+            print_panel(lines);
+        }
+    }
+
+    // The speed is only as fresh as the rates, which are measured once a second
+    fn receive_status(&mut self, status: &StatusMsg) {
+        let title = match status {
+            StatusMsg { paused: true, .. } => format!("{} (paused)", self.name),
+            // Nothing measured yet
+            StatusMsg { ips, .. } if *ips == 0.0 => self.name.clone(),
+            StatusMsg {
+                ips, clock_rate, ..
+            } => format!("{} ({:.0}%)", self.name, 100.0 * ips / clock_rate),
+        };
+        if title != self.title {
+            self.title = title;
+            if self.toast_shown.is_none() {
+                self.window.set_title(&self.title);
+            }
+        }
+    }
+
+    fn receive_notification(&mut self, text: &str) {
+        self.window.set_title(&format!("{} - {text}", self.title));
+        self.toast_shown = Some(Instant::now());
+    }
+
     fn drive_display(&mut self) {
-        self.window
-            .update_with_buffer(&self.framebuf, DISPLAY_WIDTH, DISPLAY_HEIGHT)
-            .unwrap();
+        self.frame_pending = true;
+        self.present_pending();
     }
 
     fn device_info(&self) -> DisplayInfo {
@@ -1,24 +1,86 @@
 pub mod ansiterm;
+pub mod cells;
+#[cfg(feature = "async")]
+pub mod channel;
+#[cfg(any(all(unix, feature = "termion"), feature = "crossterm"))]
+pub mod command_line;
+#[cfg(all(not(target_arch = "wasm32"), feature = "cpal"))]
+pub mod cpal;
+#[cfg(feature = "crossterm")]
+pub mod crossterm;
+pub mod events;
+#[cfg(all(not(target_arch = "wasm32"), feature = "gamepad"))]
+pub mod gamepad;
+pub mod ghosting;
+#[cfg(all(unix, feature = "termion"))]
+pub mod graphics;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod interrupt;
+pub mod keymap;
+#[cfg(all(unix, feature = "termion"))]
+pub mod kitty;
+pub mod merged;
+#[cfg(all(not(target_arch = "wasm32"), feature = "minifb"))]
 pub mod minifb;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod net;
+pub mod orientation;
+pub mod palette;
+#[cfg(feature = "pixels")]
+pub mod pixels;
+#[cfg(feature = "rich-tui")]
+pub mod ratatui;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod resize;
+#[cfg(all(not(target_arch = "wasm32"), feature = "rodio"))]
 pub mod rodio;
+#[cfg(feature = "sdl2")]
+pub mod sdl2;
+pub mod serial;
+#[cfg(all(unix, feature = "termion"))]
 pub mod termion;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod threaded;
+#[cfg(any(all(unix, feature = "termion"), feature = "crossterm"))]
+pub mod tui_layout;
+pub mod upscale;
+pub mod waveform;
+#[cfg(feature = "web")]
+pub mod web;
 
-use bitvec::{slice::BitSlice, BitArr};
+use std::time::Duration;
 
-use crate::chip8::NUM_KEYS;
+use bitvec::slice::BitSlice;
+
+use crate::chip8::{self, DISPLAY_HEIGHT, DISPLAY_WIDTH, NUM_DATA_REGS};
+use crate::diagnostics;
 use crate::emulator::Signal;
 
-// A 16-bit CHIP-8 input message representing the incoming, updated key states
-// where the nth bit corresponds to the (n as hex) key status
-//
-//   Example: 0b1000_0001_0000_1101
-//         => keys 0, 1, 3, 8, and F are in the down state
-//            and all other keys in the up state
-//
-pub type InputMsg = BitArr!(for NUM_KEYS);
+pub use crate::chip8::{AudioMsg, InputMsg, KeyEvents, Rect, KEY_DOWN, KEY_UP};
+
+// Live machine state, sent along with each frame to display devices that show it
+#[derive(Clone, Copy, Debug)]
+pub struct StatusMsg {
+    // Frames presented, and instructions executed, per second of wall-clock time
+    pub fps: f32,
+    pub ips: f32,
+    pub paused: bool,
+    // Guest clock speed in Hz, and whether it's being fast-forwarded
+    pub clock_rate: f32,
+    pub turbo: bool,
+    pub pc: u16,
+    pub i: u16,
+    pub v: [u8; NUM_DATA_REGS],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    // Keys the machine sees held down, which may lag behind the input device's
+    pub keys: InputMsg,
+    // Keys `FX0A` is waiting on a release of, if it's waiting (see `Chip8::key_wait`)
+    pub key_wait: Option<InputMsg>,
+}
 
-pub const KEY_UP: bool = false;
-pub const KEY_DOWN: bool = true;
+// How long a notification stays up (see `DisplayDevice::receive_notification`)
+pub const NOTIFICATION_DURATION: Duration = Duration::from_millis(1500);
 
 // Model input device (e.g. keypad, keyboard, touchscreen, etc.) interfacing with our CHIP-8 system
 pub trait InputDevice {
@@ -27,48 +89,253 @@ pub trait InputDevice {
     fn handle_inputs(&mut self) -> Signal;
 
     fn send_inputs(&self) -> Option<InputMsg>;
+
+    // Debugger console command typed in by the user, taken once `handle_inputs` returns
+    // `Signal::DebugCommand`
+    fn take_command(&mut self) -> Option<String> {
+        None
+    }
 }
 
-pub const PX_OFF: bool = false;
-pub const PX_ON: bool = true;
+// Input device picked at runtime (e.g. for `EmulatorBuilder`)
+impl InputDevice for Box<dyn InputDevice> {
+    fn device_info(&self) -> InputInfo {
+        (**self).device_info()
+    }
+
+    fn handle_inputs(&mut self) -> Signal {
+        (**self).handle_inputs()
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        (**self).send_inputs()
+    }
+
+    fn take_command(&mut self) -> Option<String> {
+        (**self).take_command()
+    }
+}
+
+// Most bitplanes in a frame sent to display devices: XO-CHIP draws to 2, and CHIP-8X's
+// colors take 4 (see `palette::chip8x_color`)
+pub const MAX_FRAME_PLANES: usize = 4;
+
+// Color index (0 = off) of the pixel at `idx` across a frame's bitplanes
+pub fn pixel_color(planes: &[&BitSlice<usize>], idx: usize) -> usize {
+    planes
+        .iter()
+        .enumerate()
+        .fold(0, |color, (n, plane)| color | (plane[idx] as usize) << n)
+}
+
+// Pixels (x, y) lit by hex digit `idx` in the guest's font, each blown up to a `scale` x
+// `scale` square, relative to the digit's top-left corner; lets frontends draw key labels
+pub fn digit_pixels(idx: usize, scale: usize) -> impl Iterator<Item = (usize, usize)> {
+    let sprite = chip8::font_sprite(idx as u8);
+    sprite.iter().enumerate().flat_map(move |(dy, &byte)| {
+        (0..4)
+            .filter(move |dx| byte & (0x80 >> dx) != 0)
+            .flat_map(move |dx| {
+                (0..scale * scale).map(move |n| (dx * scale + n % scale, dy * scale + n / scale))
+            })
+    })
+}
+
+// Pixels (x, y) of the CHIP-8 key being remapped (see `keymap::Remap`), scaled up and
+// centered on a display-sized frame, as the windowed frontends draw it
+pub fn remap_pixels(idx: usize) -> impl Iterator<Item = (usize, usize)> {
+    const SCALE: usize = 4;
+    let (ox, oy) = (
+        (DISPLAY_WIDTH - 4 * SCALE) / 2,
+        (DISPLAY_HEIGHT - chip8::font_sprite(0).len() * SCALE) / 2,
+    );
+    digit_pixels(idx, SCALE).map(move |(x, y)| (ox + x, oy + y))
+}
+
+// Print a text panel (see `DisplayDevice::receive_panel`) for devices with nowhere on screen
+// to show it
+pub fn print_panel(lines: &[String]) {
+    if !lines.is_empty() {
+        diagnostics::print("panel", &lines.join("\n"));
+    }
+}
 
 // Model display device (e.g. UI library window, physical screen, etc.) interfacing with our CHIP-8 system
 pub trait DisplayDevice {
     fn device_info(&self) -> DisplayInfo;
 
-    fn receive_frame(&mut self, frame: &BitSlice<usize>) -> &mut dyn DisplayDevice;
+    // Receive the bitplanes of a row-major frame of `dims` (w, h) pixels; a pixel's color
+    // index combines its bit in each plane (plane n => bit n)
+    fn receive_frame(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice;
+
+    // Hint of the region of the upcoming frame that changed since the last one, so devices
+    // that can may update only that part of the display
+    fn receive_damage(&mut self, _damage: Rect) -> &mut dyn DisplayDevice
+    where
+        Self: Sized,
+    {
+        self
+    }
+
+    // Lines of text to show beside the display (e.g. the debugger's machine state), or
+    // none to hide it; devices that can't show text ignore it
+    fn receive_panel(&mut self, _lines: &[String]) {}
+
+    // Short message for the user (e.g. "State saved"), shown off to a corner of the display
+    // for `NOTIFICATION_DURATION` or until the next one comes in; devices that can't show
+    // text ignore it
+    fn receive_notification(&mut self, _text: &str) {}
+
+    // Live machine state (see `StatusMsg`), sent ahead of each frame; devices without room
+    // for it ignore it
+    fn receive_status(&mut self, _status: &StatusMsg) {}
+
+    // Line of live rates to keep up in a corner of the display (see `--stats`), sent ahead
+    // of each frame while asked for; devices that can't show text ignore it
+    fn receive_stats(&mut self, _text: &str) {}
 
     fn drive_display(&mut self);
 }
 
+// Display device picked at runtime (e.g. for `EmulatorBuilder`); damage hints can't be
+// passed through to it, so it redraws whole frames
+impl DisplayDevice for Box<dyn DisplayDevice> {
+    fn device_info(&self) -> DisplayInfo {
+        (**self).device_info()
+    }
+
+    fn receive_frame(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice {
+        (**self).receive_frame(planes, dims)
+    }
+
+    fn receive_panel(&mut self, lines: &[String]) {
+        (**self).receive_panel(lines)
+    }
+
+    fn receive_notification(&mut self, text: &str) {
+        (**self).receive_notification(text)
+    }
+
+    fn receive_status(&mut self, status: &StatusMsg) {
+        (**self).receive_status(status)
+    }
+
+    fn receive_stats(&mut self, text: &str) {
+        (**self).receive_stats(text)
+    }
+
+    fn drive_display(&mut self) {
+        (**self).drive_display()
+    }
+}
+
 // Model audio device (e.g. audio drivers, beeper, etc.) interfacing with our CHIP-8 system
 pub trait AudioDevice {
     fn device_info(&self) -> AudioInfo;
 
-    fn receive_signal(&mut self, data: bool) -> &mut dyn AudioDevice;
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice;
 
     fn play_audio(&mut self);
+
+    // Volume to play at from now on, from 0 (muted) to 1 (full, the default); devices that
+    // can't vary it only go quiet at 0
+    fn set_volume(&mut self, _volume: f32) {}
+}
+
+// Terminal bell standing in for the beep; as it can't be held for as long as a sound
+// plays, it's rung once as each one starts
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Bell {
+    // Whether a sound is playing
+    on: bool,
+    // Whether the volume is down to 0, silencing the bell
+    muted: bool,
+}
+
+impl Bell {
+    // Whether to ring the bell, given the sound now being made
+    pub fn ring(&mut self, data: AudioMsg) -> bool {
+        let start = data.is_on() && !self.on;
+        self.on = data.is_on();
+        start && !self.muted
+    }
+
+    // The bell has no volume of its own, so it's either rung or not
+    pub fn set_volume(&mut self, volume: f32) {
+        self.muted = volume <= 0.0;
+    }
+}
+
+// Audio device picked at runtime (e.g. from the command line)
+impl AudioDevice for Box<dyn AudioDevice> {
+    fn device_info(&self) -> AudioInfo {
+        (**self).device_info()
+    }
+
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice {
+        (**self).receive_signal(data)
+    }
+
+    fn play_audio(&mut self) {
+        (**self).play_audio()
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        (**self).set_volume(volume)
+    }
 }
 
 #[derive(Clone, Copy)]
 pub enum InputInfo {
+    Channel,
+    Crossterm,
+    Events,
+    Gamepad,
     Minifb,
+    Net,
+    Pixels,
+    Ratatui,
+    Sdl2,
+    Serial,
     Termion,
+    Web,
     None,
 }
 
 #[derive(Clone, Copy)]
 pub enum DisplayInfo {
+    Channel,
+    Crossterm,
     Minifb,
+    Net,
+    Pixels,
+    Ratatui,
+    Sdl2,
     Termion,
+    Web,
     None,
 }
 
 #[derive(Clone, Copy)]
 pub enum AudioInfo {
     AnsiTerm,
+    Channel,
+    Cpal,
+    Crossterm,
+    Net,
+    Ratatui,
     Termion,
     Rodio,
+    Sdl2,
+    Web,
     None,
 }
 
@@ -96,22 +363,22 @@ impl DisplayDevice for NullDevice {
     fn device_info(&self) -> DisplayInfo {
         DisplayInfo::None
     }
-    fn receive_frame(&mut self, _frame: &BitSlice<usize>) -> &mut dyn DisplayDevice {
+    fn receive_frame(
+        &mut self,
+        _planes: &[&BitSlice<usize>],
+        _dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice {
         self
     }
-    fn drive_display(&mut self) {
-        eprintln!("Nothing to display to!");
-    }
+    fn drive_display(&mut self) {}
 }
 
 impl AudioDevice for NullDevice {
     fn device_info(&self) -> AudioInfo {
         AudioInfo::None
     }
-    fn receive_signal(&mut self, _data: bool) -> &mut dyn AudioDevice {
+    fn receive_signal(&mut self, _data: AudioMsg) -> &mut dyn AudioDevice {
         self
     }
-    fn play_audio(&mut self) {
-        eprintln!("Nothing to play audio through!");
-    }
+    fn play_audio(&mut self) {}
 }
@@ -0,0 +1,336 @@
+use std::{
+    cell::RefCell,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use bitvec::{bitarr, slice::BitSlice, vec::BitVec};
+use smallvec::SmallVec;
+
+use crate::{
+    chip8::NUM_KEYS,
+    driver::{
+        AudioDevice, AudioInfo, AudioMsg, DisplayDevice, DisplayInfo, InputDevice, InputInfo,
+        InputMsg, Rect, MAX_FRAME_PLANES,
+    },
+    emulator::Signal,
+};
+
+// Sent by both ends on connecting
+const HELLO: &[u8; 5] = b"C8RD\x01";
+// Longest message either end accepts; frames take up at most 4101 bytes
+const MAX_MESSAGE_LEN: usize = 1 << 16;
+// How often the client's input device is polled
+const POLL_INTERVAL: Duration = Duration::from_millis(4);
+
+// Message tags: server to client
+const TAG_FRAME: u8 = b'F';
+const TAG_NOTIFICATION: u8 = b'N';
+const TAG_SOUND: u8 = b'S';
+// Client to server
+const TAG_KEYS: u8 = b'K';
+
+// Remote play: the emulator runs on one machine (e.g. a headless box) serving its display
+// and sound to a client on another, which sends back its keys
+//
+//   Each message is its length (u32 LE), then that many bytes: a tag and what follows it.
+//   The server sends
+//     'F' a frame: width and height (u16 LE), the number of planes (u8), then each plane
+//         row-major, 8 pixels to a byte, first pixel in the lowest bit
+//     'N' a notification, as UTF-8 text
+//     'S' whether sound is playing (u8, 0 or 1)
+//   and the client
+//     'K' the keys it holds, bit n for key n (u16 LE)
+//   Both ends start by sending HELLO (unprefixed). Only whole frames are sent; at most a
+//   few KiB each, they fit any link that can play at all.
+//
+pub struct NetServer {
+    out: BufWriter<TcpStream>,
+    // Key states read from the client; disconnected once it leaves
+    events: mpsc::Receiver<InputMsg>,
+    keybuf: InputMsg,
+    // Frame waiting on `drive_display`, encoded
+    frame: Vec<u8>,
+    // Whether sound is playing, and whether the client knows that yet
+    sound: bool,
+    sound_sent: bool,
+    // Whether the connection is still up; writes stop once one fails
+    connected: bool,
+}
+
+impl NetServer {
+    // Wait for a client to connect at `addr` (e.g. `0.0.0.0:7778`)
+    pub fn listen(addr: &str) -> io::Result<Self> {
+        let (stream, _) = TcpListener::bind(addr)?.accept()?;
+        let stream = handshake(stream)?;
+
+        let (tx, events) = mpsc::channel();
+        let mut reader = BufReader::new(stream.try_clone()?);
+        thread::spawn(move || {
+            while let Ok((tag, payload)) = read_message(&mut reader) {
+                let keys = match (tag, &payload[..]) {
+                    (TAG_KEYS, &[lo, hi]) => decode_keys(u16::from_le_bytes([lo, hi])),
+                    _ => continue,
+                };
+                if tx.send(keys).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(NetServer {
+            out: BufWriter::new(stream),
+            events,
+            keybuf: bitarr![0; NUM_KEYS],
+            frame: Vec::new(),
+            sound: false,
+            sound_sent: false,
+            connected: true,
+        })
+    }
+
+    fn send(&mut self, tag: u8, payload: &[u8]) {
+        if self.connected {
+            self.connected = write_message(&mut self.out, tag, payload).is_ok();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.connected {
+            self.connected = self.out.flush().is_ok();
+        }
+    }
+}
+
+impl InputDevice for NetServer {
+    fn device_info(&self) -> InputInfo {
+        InputInfo::Net
+    }
+
+    fn handle_inputs(&mut self) -> Signal {
+        let mut changed = false;
+        loop {
+            match self.events.try_recv() {
+                Ok(keys) => {
+                    changed |= keys != self.keybuf;
+                    self.keybuf = keys;
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                // Nobody left to play
+                Err(mpsc::TryRecvError::Disconnected) => return Signal::ProgramExit,
+            }
+        }
+
+        match changed {
+            true => Signal::NewInputs,
+            false => Signal::None,
+        }
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        Some(self.keybuf)
+    }
+}
+
+impl DisplayDevice for NetServer {
+    fn device_info(&self) -> DisplayInfo {
+        DisplayInfo::Net
+    }
+
+    fn receive_frame(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice {
+        self.frame.clear();
+        self.frame.extend((dims.0 as u16).to_le_bytes());
+        self.frame.extend((dims.1 as u16).to_le_bytes());
+        self.frame.push(planes.len() as u8);
+        for plane in planes {
+            let pixels = &plane[..dims.0 * dims.1];
+            self.frame.extend(
+                pixels
+                    .chunks(8)
+                    .map(|byte| byte.iter_ones().fold(0_u8, |bits, n| bits | 1 << n)),
+            );
+        }
+        self
+    }
+
+    fn receive_notification(&mut self, text: &str) {
+        self.send(TAG_NOTIFICATION, text.as_bytes());
+        self.flush();
+    }
+
+    fn drive_display(&mut self) {
+        let frame = std::mem::take(&mut self.frame);
+        self.send(TAG_FRAME, &frame);
+        self.frame = frame;
+        self.flush();
+    }
+}
+
+impl AudioDevice for NetServer {
+    fn device_info(&self) -> AudioInfo {
+        AudioInfo::Net
+    }
+
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice {
+        if data.is_on() != self.sound {
+            self.sound = data.is_on();
+            self.sound_sent = false;
+        }
+        self
+    }
+
+    fn play_audio(&mut self) {
+        if !self.sound_sent {
+            self.send(TAG_SOUND, &[self.sound as u8]);
+            self.flush();
+            self.sound_sent = true;
+        }
+    }
+}
+
+// Play on the server at `addr` through local devices: its frames and sound are presented
+// on them, and their keys sent back, until the server ends the session or the input
+// device asks to exit
+pub fn run_client<I, D, A>(
+    addr: &str,
+    (input, display, audio): (&RefCell<I>, &RefCell<D>, &RefCell<A>),
+) -> io::Result<()>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    let stream = handshake(TcpStream::connect(addr)?)?;
+    let (tx, messages) = mpsc::channel();
+    let mut reader = BufReader::new(stream.try_clone()?);
+    thread::spawn(move || {
+        while let Ok(message) = read_message(&mut reader) {
+            if tx.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    let mut out = BufWriter::new(stream);
+
+    loop {
+        match input.borrow_mut().handle_inputs() {
+            Signal::ProgramExit => return Ok(()),
+            Signal::NewInputs => {
+                let keys = input.borrow().send_inputs().unwrap_or_default();
+                write_message(&mut out, TAG_KEYS, &encode_keys(&keys).to_le_bytes())?;
+                out.flush()?;
+            }
+            // The machine's controls are the server's to use
+            _ => (),
+        }
+
+        match messages.recv_timeout(POLL_INTERVAL) {
+            Ok((TAG_FRAME, payload)) => {
+                if let Some((planes, dims)) = decode_frame(&payload) {
+                    let planes = planes
+                        .iter()
+                        .map(BitVec::as_bitslice)
+                        .collect::<SmallVec<[&BitSlice<usize>; MAX_FRAME_PLANES]>>();
+                    display
+                        .borrow_mut()
+                        .receive_damage(Rect::full(dims))
+                        .receive_frame(&planes, dims)
+                        .drive_display();
+                }
+            }
+            Ok((TAG_NOTIFICATION, payload)) => display
+                .borrow_mut()
+                .receive_notification(&String::from_utf8_lossy(&payload)),
+            Ok((TAG_SOUND, payload)) => audio
+                .borrow_mut()
+                .receive_signal(AudioMsg::Beep(payload.first() == Some(&1)))
+                .play_audio(),
+            Ok(_) | Err(RecvTimeoutError::Timeout) => (),
+            // The session is over
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+// Trade HELLOs with the other end
+fn handshake(mut stream: TcpStream) -> io::Result<TcpStream> {
+    stream.set_nodelay(true)?;
+    stream.write_all(HELLO)?;
+    let mut hello = [0; HELLO.len()];
+    stream.read_exact(&mut hello)?;
+    match hello == *HELLO {
+        true => Ok(stream),
+        false => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the other end isn't a compatible remote play session",
+        )),
+    }
+}
+
+fn write_message(out: &mut impl Write, tag: u8, payload: &[u8]) -> io::Result<()> {
+    out.write_all(&(payload.len() as u32 + 1).to_le_bytes())?;
+    out.write_all(&[tag])?;
+    out.write_all(payload)
+}
+
+fn read_message(reader: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if !(1..=MAX_MESSAGE_LEN).contains(&len) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid message length {len}"),
+        ));
+    }
+    let mut message = vec![0; len];
+    reader.read_exact(&mut message)?;
+    let payload = message.split_off(1);
+    Ok((message[0], payload))
+}
+
+// Bitplanes of a frame, and its dims (w, h)
+type Frame = (Vec<BitVec<usize>>, (usize, usize));
+
+// Frame sent as a 'F' message (see `NetServer`), if it's well-formed
+fn decode_frame(payload: &[u8]) -> Option<Frame> {
+    let (&[w0, w1, h0, h1, num_planes], data) = payload.split_first_chunk::<5>()?;
+    let dims = (
+        u16::from_le_bytes([w0, w1]) as usize,
+        u16::from_le_bytes([h0, h1]) as usize,
+    );
+    let plane_len = (dims.0 * dims.1).div_ceil(8);
+    if num_planes as usize > MAX_FRAME_PLANES || data.len() != num_planes as usize * plane_len {
+        return None;
+    }
+
+    let planes = data
+        .chunks(plane_len.max(1))
+        .take(num_planes as usize)
+        .map(|bytes| {
+            (0..dims.0 * dims.1)
+                .map(|n| bytes[n / 8] & 1 << (n % 8) != 0)
+                .collect()
+        })
+        .collect();
+    Some((planes, dims))
+}
+
+fn encode_keys(keys: &InputMsg) -> u16 {
+    keys.iter_ones().fold(0, |bits, key| bits | 1 << key)
+}
+
+fn decode_keys(bits: u16) -> InputMsg {
+    let mut keys = bitarr![0; NUM_KEYS];
+    for key in 0..NUM_KEYS {
+        keys.set(key, bits & 1 << key != 0);
+    }
+    keys
+}
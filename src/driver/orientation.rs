@@ -0,0 +1,132 @@
+use std::{fmt, str::FromStr};
+
+use bitvec::{slice::BitSlice, vec::BitVec};
+
+use crate::driver::Rect;
+
+// Clockwise rotation applied to frames on their way to a display device
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Rotation {
+    #[default]
+    R0,
+    R90,
+    R180,
+    R270,
+}
+
+impl Rotation {
+    // Next rotation step (90 degrees clockwise)
+    pub fn next(self) -> Self {
+        match self {
+            Rotation::R0 => Rotation::R90,
+            Rotation::R90 => Rotation::R180,
+            Rotation::R180 => Rotation::R270,
+            Rotation::R270 => Rotation::R0,
+        }
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(Rotation::R0),
+            "90" => Ok(Rotation::R90),
+            "180" => Ok(Rotation::R180),
+            "270" => Ok(Rotation::R270),
+            _ => Err(format!(
+                "invalid rotation '{s}' (expected 0, 90, 180, or 270)"
+            )),
+        }
+    }
+}
+
+// Degrees, as accepted by `FromStr`
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let degrees = match self {
+            Rotation::R0 => 0,
+            Rotation::R90 => 90,
+            Rotation::R180 => 180,
+            Rotation::R270 => 270,
+        };
+        write!(f, "{degrees}")
+    }
+}
+
+// Display orientation, useful for vertical cabinet and handheld setups where the
+// physical screen is mounted sideways or viewed through a mirror
+//
+//   Rotation is applied first, followed by an optional horizontal mirror
+//
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Orientation {
+    pub rotation: Rotation,
+    pub mirror: bool,
+}
+
+impl Orientation {
+    pub fn is_identity(&self) -> bool {
+        self.rotation == Rotation::R0 && !self.mirror
+    }
+
+    // Dimensions (w, h) of a frame of size `dims` after being transformed
+    pub fn dims(&self, dims: (usize, usize)) -> (usize, usize) {
+        match self.rotation {
+            Rotation::R0 | Rotation::R180 => dims,
+            Rotation::R90 | Rotation::R270 => (dims.1, dims.0),
+        }
+    }
+
+    // Transform a row-major `frame` of size `dims` into `out`, returning the new dimensions
+    pub fn apply(
+        &self,
+        frame: &BitSlice<usize>,
+        dims: (usize, usize),
+        out: &mut BitVec<usize>,
+    ) -> (usize, usize) {
+        let w = dims.0;
+        let (out_w, out_h) = self.dims(dims);
+
+        out.clear();
+        out.resize(out_w * out_h, false);
+
+        for idx in frame.iter_ones() {
+            let (ox, oy) = self.point((idx % w, idx / w), dims);
+            out.set(oy * out_w + ox, true);
+        }
+
+        (out_w, out_h)
+    }
+
+    // Transform a region of a frame of size `dims`
+    pub fn apply_rect(&self, rect: Rect, dims: (usize, usize)) -> Rect {
+        if rect.w == 0 || rect.h == 0 {
+            return rect;
+        }
+        let (ax, ay) = self.point((rect.x, rect.y), dims);
+        let (bx, by) = self.point((rect.x + rect.w - 1, rect.y + rect.h - 1), dims);
+        Rect {
+            x: ax.min(bx),
+            y: ay.min(by),
+            w: ax.abs_diff(bx) + 1,
+            h: ay.abs_diff(by) + 1,
+        }
+    }
+
+    // Transform the coordinates of a single pixel of a frame of size `dims`
+    fn point(&self, (x, y): (usize, usize), dims: (usize, usize)) -> (usize, usize) {
+        let (w, h) = dims;
+        let (mut ox, oy) = match self.rotation {
+            Rotation::R0 => (x, y),
+            Rotation::R90 => (h - 1 - y, x),
+            Rotation::R180 => (w - 1 - x, h - 1 - y),
+            Rotation::R270 => (y, w - 1 - x),
+        };
+        if self.mirror {
+            ox = self.dims(dims).0 - 1 - ox;
+        }
+        (ox, oy)
+    }
+}
@@ -0,0 +1,148 @@
+use std::{fmt, str::FromStr};
+
+// Colors (0RGB) that display devices paint pixels with, indexed by pixel color
+// (see `pixel_color`); the last two only show up with XO-CHIP's second plane, and CHIP-8X
+// paints in colors of its own past these (see `color`)
+//
+//   Serialized as comma-separated hex colors, e.g. "1e1c2d,e0def4,eb6f92,f6c177";
+//   colors left unspecified keep their default. The name of a preset (see `PRESETS`)
+//   parses too.
+//
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Palette {
+    pub colors: [u32; 4],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            colors: [0x1E1C2D, 0xE0DEF4, 0xEB6F92, 0xF6C177],
+        }
+    }
+}
+
+// Pixel colors in all: those of a `Palette`, then CHIP-8X's
+pub const NUM_COLORS: usize = 4 + CHIP8X_COLORS.len();
+// The first of CHIP-8X's pixel colors, lit pixels in its 8 foreground colors; unlit ones
+// in its 4 background colors follow (see `chip8x_color`)
+const CHIP8X_FOREGROUND: usize = 4;
+const CHIP8X_BACKGROUND: usize = CHIP8X_FOREGROUND + 8;
+
+// The VP-590 color board's colors, which CHIP-8X ROMs pick between themselves: black, red,
+// blue, violet, green, yellow, aqua, and white for foregrounds, then dark blue, black,
+// dark green, and dark red for backgrounds
+const CHIP8X_COLORS: [u32; 12] = [
+    0x000000, 0xFF0000, 0x0000FF, 0xFF00FF, 0x00FF00, 0xFFFF00, 0x00FFFF, 0xFFFFFF, 0x000080,
+    0x000000, 0x008000, 0x800000,
+];
+
+// Pixel color of a CHIP-8X pixel, lit in `foreground` (0-7) or unlit on `background` (0-3)
+pub fn chip8x_color(lit: bool, foreground: u8, background: u8) -> usize {
+    match lit {
+        true => CHIP8X_FOREGROUND + foreground as usize,
+        false => CHIP8X_BACKGROUND + background as usize,
+    }
+}
+
+// Whether pixel color `n` is a lit pixel's, rather than background
+pub fn is_lit(n: usize) -> bool {
+    n != 0 && n < CHIP8X_BACKGROUND
+}
+
+// Named palettes, (off, on) then XO-CHIP's two extra colors
+pub const PRESETS: [(&str, Palette); 4] = [
+    (
+        "green",
+        Palette {
+            colors: [0x041A04, 0x33FF33, 0x1A8C1A, 0xA8FFA8],
+        },
+    ),
+    (
+        "amber",
+        Palette {
+            colors: [0x1A0F00, 0xFFB000, 0x8C5A00, 0xFFDC9A],
+        },
+    ),
+    // The original Game Boy's four shades of green, lit pixels darkest
+    (
+        "gameboy",
+        Palette {
+            colors: [0x9BBC0F, 0x0F380F, 0x8BAC0F, 0x306230],
+        },
+    ),
+    (
+        "high-contrast",
+        Palette {
+            colors: [0x000000, 0xFFFFFF, 0xFFFF00, 0x00FFFF],
+        },
+    ),
+];
+
+impl Palette {
+    pub fn preset(name: &str) -> Option<Palette> {
+        PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|&(_, palette)| palette)
+    }
+
+    // Pixel color `n` (< NUM_COLORS), CHIP-8X's fixed ones included
+    pub fn color(&self, n: usize) -> u32 {
+        match self.colors.get(n) {
+            Some(&color) => color,
+            None => CHIP8X_COLORS[n - CHIP8X_FOREGROUND],
+        }
+    }
+
+    // Color `n` split into its (r, g, b) components
+    pub fn rgb(&self, n: usize) -> (u8, u8, u8) {
+        let [_, r, g, b] = self.color(n).to_be_bytes();
+        (r, g, b)
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(preset) = Palette::preset(s) {
+            return Ok(preset);
+        }
+
+        let mut palette = Palette::default();
+        let colors = s.split(',').map(str::trim).collect::<Vec<_>>();
+
+        if colors.len() > palette.colors.len() {
+            return Err(format!("too many colors in palette '{s}' (at most 4)"));
+        }
+        if let [name] = colors[..] {
+            if !name.starts_with('#') && name.len() != 6 {
+                let presets = PRESETS.map(|(name, _)| name).join(", ");
+                return Err(format!(
+                    "invalid palette '{name}' (expected one of {presets}, or RRGGBB colors)"
+                ));
+            }
+        }
+        for (slot, color) in palette.colors.iter_mut().zip(colors) {
+            *slot = parse_color(color)?;
+        }
+
+        Ok(palette)
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let colors = self.colors.map(|color| format!("{color:06x}"));
+        write!(f, "{}", colors.join(","))
+    }
+}
+
+// A hex color, RRGGBB with an optional leading `#`
+pub fn parse_color(color: &str) -> Result<u32, String> {
+    let hex = color.trim_start_matches('#');
+    u32::from_str_radix(hex, 16)
+        .ok()
+        .filter(|_| hex.len() == 6)
+        .ok_or(format!("invalid color '{color}' (expected RRGGBB)"))
+}
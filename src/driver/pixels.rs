@@ -0,0 +1,567 @@
+use std::{
+    io::{stdin, BufRead},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use bitvec::{bitarr, slice::BitSlice, BitArr};
+use pixels::{wgpu, PixelsBuilder, SurfaceTexture};
+use winit::{
+    dpi::LogicalSize,
+    event::{ElementState, Event, KeyEvent, WindowEvent},
+    event_loop::EventLoop,
+    keyboard::{Key, NamedKey},
+    platform::pump_events::EventLoopExtPumpEvents,
+    window::{Icon, Window, WindowBuilder},
+};
+
+use crate::{
+    chip8::{DISPLAY_HEIGHT, DISPLAY_WIDTH, NUM_KEYS},
+    config::desktop::{self, ICON_SIZE},
+    config::profile::{Profile, ProfileCycle, Profiles},
+    diagnostics,
+    driver::{
+        keymap::{Keymap, Remap, RemapStep},
+        palette::Palette,
+    },
+    driver::{pixel_color, print_panel, remap_pixels, NOTIFICATION_DURATION},
+    driver::{DisplayDevice, DisplayInfo, InputDevice, InputInfo, InputMsg},
+    emulator::Signal,
+};
+
+// A native window drawn on the GPU through wgpu: frames are scaled up by the largest integer
+// factor that fits the window, optionally through a CRT effect (see `crt.wgsl`), and keys are
+// held down from their press event until their release event
+pub struct Pixels {
+    // Dropped ahead of the window its surface draws to
+    pixels: pixels::Pixels,
+    // Post-processing pass, if CRT effects are on; it reads the frame texture, so it's
+    // rebuilt whenever the frame dimensions change
+    crt: Option<Crt>,
+    window: Window,
+    // Polled rather than run, so the emulator keeps hold of the main loop
+    event_loop: EventLoop<()>,
+    // Dimensions (w, h) of the frame currently held in `pixels`
+    frame_dims: (usize, usize),
+    // Keys currently held down, and the key states last reported with `Signal::NewInputs`
+    keybuf: BitArr!(for NUM_KEYS),
+    reported: InputMsg,
+    // Whether Backspace is held down, rewinding the machine
+    rewinding: bool,
+    // Whether Tab is held down, fast-forwarding the machine, and whether that was last
+    // reported with `Signal::TurboOn`
+    turbo: bool,
+    turbo_reported: bool,
+    // Host key to CHIP-8 key bindings
+    keymap: Keymap,
+    palette: Palette,
+    // Profiles cycled through with the P hotkey
+    profiles: ProfileCycle,
+    // Text panel last received; the window can't show text, so it goes to stderr instead
+    panel: Vec<String>,
+    // Debugger console commands, read from stdin lines for the same reason, and the last
+    // one entered
+    commands: mpsc::Receiver<String>,
+    entered: Option<String>,
+    // Window title; notifications are appended to it until they expire
+    title: String,
+    toast_shown: Option<Instant>,
+}
+
+// Render pipeline for `crt.wgsl`, bound to the frame texture of a `pixels::Pixels`
+struct Crt {
+    bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Crt {
+    fn new(pixels: &pixels::Pixels) -> Self {
+        let device = pixels.device();
+        let module = device.create_shader_module(wgpu::include_wgsl!("crt.wgsl"));
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("crt_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let texture_view = pixels
+            .texture()
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("crt_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("crt_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("crt_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("crt_pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: pixels.render_texture_format(),
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Crt {
+            bind_group,
+            render_pipeline,
+        }
+    }
+
+    // Draw the frame texture into `clip_rect` (x, y, w, h) of `render_target`, the rest of
+    // it black
+    fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        render_target: &wgpu::TextureView,
+        clip_rect: (u32, u32, u32, u32),
+    ) {
+        let (x, y, w, h) = clip_rect;
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("crt_render_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: render_target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_viewport(x as f32, y as f32, w as f32, h as f32, 0.0, 1.0);
+        rpass.set_scissor_rect(x, y, w, h);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+impl Pixels {
+    // `scale` is the window's initial scale factor, one of `profile::SCALES`; `crt` turns on
+    // CRT effects; with `vsync`, frames are presented in step with the monitor's refresh so
+    // they don't tear
+    pub fn new(name: &str, scale: usize, crt: bool, vsync: bool) -> Result<Self, String> {
+        let event_loop = EventLoop::new().map_err(|e| e.to_string())?;
+        let title = "CHIP-8: ".to_owned() + name;
+
+        let window = WindowBuilder::new()
+            .with_title(&title)
+            .with_inner_size(LogicalSize::new(
+                (DISPLAY_WIDTH * scale) as f64,
+                (DISPLAY_HEIGHT * scale) as f64,
+            ))
+            .with_min_inner_size(LogicalSize::new(
+                DISPLAY_WIDTH as f64,
+                DISPLAY_HEIGHT as f64,
+            ))
+            .build(&event_loop)
+            .map_err(|e| e.to_string())?;
+        let size = window.inner_size();
+        let pixels = PixelsBuilder::new(
+            DISPLAY_WIDTH as u32,
+            DISPLAY_HEIGHT as u32,
+            SurfaceTexture::new(size.width, size.height, &window),
+        )
+        .present_mode(match vsync {
+            true => wgpu::PresentMode::AutoVsync,
+            false => wgpu::PresentMode::AutoNoVsync,
+        })
+        .build()
+        .map_err(|e| e.to_string())?;
+
+        let (tx, commands) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdin().lock().lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Pixels {
+            crt: crt.then(|| Crt::new(&pixels)),
+            pixels,
+            window,
+            event_loop,
+            frame_dims: (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+            keybuf: bitarr![0; NUM_KEYS],
+            reported: bitarr![0; NUM_KEYS],
+            rewinding: false,
+            turbo: false,
+            turbo_reported: false,
+            keymap: Keymap::default(),
+            palette: Palette::default(),
+            profiles: ProfileCycle::default(),
+            panel: Vec::new(),
+            commands,
+            entered: None,
+            title,
+            toast_shown: None,
+        })
+    }
+
+    // Apply the current profile, keeping the rest around for cycling
+    pub fn set_profiles(&mut self, profiles: Profiles) {
+        let profile = self.profiles.set(profiles);
+        self.apply_profile(&profile);
+    }
+
+    // The window's scale is the user's to change by resizing it, so it is left as is here
+    fn apply_profile(&mut self, profile: &Profile) {
+        self.keymap = profile.keymap;
+        self.palette = profile.palette.unwrap_or_default();
+        let (r, g, b) = self.palette.rgb(0);
+        self.pixels.clear_color(wgpu::Color {
+            r: r as f64 / 255.0,
+            g: g as f64 / 255.0,
+            b: b as f64 / 255.0,
+            a: 1.0,
+        });
+        self.update_icon();
+    }
+
+    // Show the application icon (see `desktop::ICON`) in the palette's colors
+    fn update_icon(&mut self) {
+        let mut rgba = Vec::with_capacity(ICON_SIZE * ICON_SIZE * 4);
+        for y in 0..ICON_SIZE {
+            for x in 0..ICON_SIZE {
+                let (r, g, b) = self.palette.rgb(desktop::icon_pixel(x, y) as usize);
+                rgba.extend([r, g, b, 0xFF]);
+            }
+        }
+        if let Ok(icon) = Icon::from_rgba(rgba, ICON_SIZE as u32, ICON_SIZE as u32) {
+            self.window.set_window_icon(Some(icon));
+        }
+    }
+
+    // Window events since the last poll, waiting up to `timeout` for the first one; window
+    // resizes are taken care of along the way
+    fn poll_events(&mut self, timeout: Duration) -> Vec<WindowEvent> {
+        let mut events = Vec::new();
+        self.event_loop.pump_events(Some(timeout), |event, _| {
+            if let Event::WindowEvent { event, .. } = event {
+                events.push(event);
+            }
+        });
+
+        for event in &events {
+            if let WindowEvent::Resized(size) = event {
+                if let Err(e) = self.pixels.resize_surface(size.width, size.height) {
+                    diagnostics::warn("pixels", format!("failed to resize the display: {e}"));
+                }
+            }
+        }
+        events
+    }
+
+    // Interactive remapping flow: the CHIP-8 key to be bound is drawn in the window
+    // while waiting for the host key. Returns None if the user cancels with Esc or
+    // closes the window.
+    pub fn remap_keys(&mut self, title: &str) -> Option<Keymap> {
+        let mut remap = Remap::default();
+        let keymap = loop {
+            self.window
+                .set_title(&format!("CHIP-8: {} {}", remap.prompt(), Remap::HINT));
+
+            // Draw the hex digit being bound
+            self.resize_frame((DISPLAY_WIDTH, DISPLAY_HEIGHT));
+            let (off, on) = (rgba(self.palette, 0), rgba(self.palette, 1));
+            let frame = self.pixels.frame_mut();
+            for pixel in frame.chunks_exact_mut(4) {
+                pixel.copy_from_slice(&off);
+            }
+            for (x, y) in remap_pixels(remap.key()) {
+                frame[(y * DISPLAY_WIDTH + x) * 4..][..4].copy_from_slice(&on);
+            }
+
+            let host = 'read: loop {
+                self.drive_display();
+                for event in self.poll_events(Duration::from_millis(16)) {
+                    match event {
+                        WindowEvent::CloseRequested => break 'read None,
+                        WindowEvent::KeyboardInput {
+                            event:
+                                KeyEvent {
+                                    logical_key,
+                                    state: ElementState::Pressed,
+                                    ..
+                                },
+                            ..
+                        } => match logical_key {
+                            Key::Named(NamedKey::Escape) => break 'read None,
+                            key => {
+                                if let Some(c) = key_char(&key) {
+                                    break 'read Some(c);
+                                }
+                            }
+                        },
+                        _ => {}
+                    }
+                }
+            };
+            match remap.feed(host) {
+                RemapStep::Pending => (),
+                RemapStep::Done(keymap) => break Some(keymap),
+                RemapStep::Cancelled => break None,
+            }
+        };
+
+        self.window.set_title(&("CHIP-8: ".to_owned() + title));
+        keymap
+    }
+
+    // Size the frame buffer for frames of `dims` (w, h)
+    fn resize_frame(&mut self, dims: (usize, usize)) {
+        if dims == self.frame_dims {
+            return;
+        }
+        match self.pixels.resize_buffer(dims.0 as u32, dims.1 as u32) {
+            Ok(()) => {
+                self.frame_dims = dims;
+                if self.crt.is_some() {
+                    self.crt = Some(Crt::new(&self.pixels));
+                }
+            }
+            Err(e) => diagnostics::warn("pixels", format!("failed to resize the display: {e}")),
+        }
+    }
+}
+
+// Color `n` of `palette` as the frame buffer's RGBA bytes
+fn rgba(palette: Palette, n: usize) -> [u8; 4] {
+    let (r, g, b) = palette.rgb(n);
+    [r, g, b, 0xFF]
+}
+
+// Character a host key corresponds to, for lookups in a `Keymap`
+fn key_char(key: &Key) -> Option<char> {
+    match key {
+        Key::Character(s) => {
+            let mut chars = s.chars().flat_map(char::to_lowercase);
+            chars.next().filter(|_| chars.next().is_none())
+        }
+        _ => None,
+    }
+}
+
+// Emulator hotkey bound to a host key, if any
+fn hotkey(key: &Key) -> Signal {
+    match key {
+        Key::Named(NamedKey::Space) => Signal::Pause,
+        Key::Named(NamedKey::F2) => Signal::Reset,
+        Key::Named(NamedKey::F5) => Signal::SaveState,
+        Key::Named(NamedKey::F7) => Signal::LoadState,
+        Key::Named(NamedKey::F8) => Signal::SwitchFrontend,
+        Key::Named(NamedKey::F9) => Signal::ToggleRecording,
+        key => match key_char(key) {
+            Some('m') => Signal::MarkState,
+            Some('n') => Signal::RestoreMark,
+            Some('o') => Signal::RotateDisplay,
+            Some('h') => Signal::MirrorDisplay,
+            Some('p') => Signal::CycleProfile,
+            Some('.') => Signal::Step,
+            Some(',') => Signal::FrameAdvance,
+            Some('=' | '+') => Signal::SpeedUp,
+            Some('-') => Signal::SpeedDown,
+            Some('0') => Signal::VolumeUp,
+            Some('9') => Signal::VolumeDown,
+            Some('8') => Signal::ToggleMute,
+            Some('b') => Signal::ToggleBreakpoint,
+            Some('[') => Signal::ScrollMemoryUp,
+            Some(']') => Signal::ScrollMemoryDown,
+            _ => Signal::None,
+        },
+    }
+}
+
+impl InputDevice for Pixels {
+    // Host keys are translated to CHIP-8 keys through `Keymap` (QWERTY layout by default)
+    fn handle_inputs(&mut self) -> Signal {
+        // Checked here since frames don't necessarily keep coming in
+        if self
+            .toast_shown
+            .is_some_and(|shown| shown.elapsed() >= NOTIFICATION_DURATION)
+        {
+            self.window.set_title(&self.title);
+            self.toast_shown = None;
+        }
+
+        if let Ok(command) = self.commands.try_recv() {
+            self.entered = Some(command);
+            return Signal::DebugCommand;
+        }
+
+        let mut signal = Signal::None;
+        for event in self.poll_events(Duration::ZERO) {
+            let (key, state, repeat) = match event {
+                WindowEvent::CloseRequested => return Signal::ProgramExit,
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            logical_key,
+                            state,
+                            repeat,
+                            ..
+                        },
+                    ..
+                } => (logical_key, state, repeat),
+                _ => continue,
+            };
+
+            let pressed = state == ElementState::Pressed;
+            match key_char(&key).and_then(|c| self.keymap.lookup(c)) {
+                Some(idx) => self.keybuf.set(idx, pressed),
+                None if key == Key::Named(NamedKey::Backspace) => self.rewinding = pressed,
+                None if key == Key::Named(NamedKey::Tab) => self.turbo = pressed,
+                // Only the first hotkey of a poll is taken, and only on its press
+                None if pressed && !repeat && signal == Signal::None => signal = hotkey(&key),
+                None => {}
+            }
+        }
+
+        // Rewinding lasts for as long as the key is held; keypad changes meanwhile, like
+        // those alongside a hotkey, are held off until a later poll
+        if self.rewinding {
+            return Signal::Rewind;
+        }
+        let signal = match signal {
+            Signal::CycleProfile => match self.profiles.cycle() {
+                Some(profile) => {
+                    self.apply_profile(&profile);
+                    signal
+                }
+                None => Signal::None,
+            },
+            signal => signal,
+        };
+
+        if signal != Signal::None {
+            signal
+        } else if self.turbo != self.turbo_reported {
+            self.turbo_reported = self.turbo;
+            match self.turbo {
+                true => Signal::TurboOn,
+                false => Signal::TurboOff,
+            }
+        } else if self.keybuf != self.reported {
+            self.reported = self.keybuf;
+            Signal::NewInputs
+        } else {
+            Signal::None
+        }
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        Some(self.reported)
+    }
+
+    fn take_command(&mut self) -> Option<String> {
+        self.entered.take()
+    }
+
+    fn device_info(&self) -> InputInfo {
+        InputInfo::Pixels
+    }
+}
+
+impl DisplayDevice for Pixels {
+    fn receive_frame(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice {
+        self.resize_frame(dims);
+        let palette = self.palette;
+        for (idx, pixel) in self.pixels.frame_mut().chunks_exact_mut(4).enumerate() {
+            pixel.copy_from_slice(&rgba(palette, pixel_color(planes, idx)));
+        }
+
+        self
+    }
+
+    fn receive_panel(&mut self, lines: &[String]) {
+        if lines != self.panel {
+            self.panel = lines.to_vec();
+            print_panel(lines);
+        }
+    }
+
+    fn receive_notification(&mut self, text: &str) {
+        self.window.set_title(&format!("{} - {text}", self.title));
+        self.toast_shown = Some(Instant::now());
+    }
+
+    fn drive_display(&mut self) {
+        let crt = &self.crt;
+        let drawn = self.pixels.render_with(|encoder, render_target, context| {
+            let clip_rect = context.scaling_renderer.clip_rect();
+            match crt {
+                Some(crt) => crt.render(encoder, render_target, clip_rect),
+                None => context.scaling_renderer.render(encoder, render_target),
+            }
+            Ok(())
+        });
+        if let Err(e) = drawn {
+            diagnostics::warn("pixels", format!("failed to update the window: {e}"));
+        }
+    }
+
+    fn device_info(&self) -> DisplayInfo {
+        DisplayInfo::Pixels
+    }
+}
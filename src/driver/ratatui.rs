@@ -0,0 +1,561 @@
+use std::{
+    io::{stdout, Stdout},
+    time::{Duration, Instant},
+};
+
+use bitvec::slice::BitSlice;
+use crossterm::{
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute,
+    style::Print,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    buffer::Buffer,
+    layout::{self, Alignment, Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{block::Title, Block, Borders, Paragraph, Widget},
+    Frame, Terminal,
+};
+
+use crate::{
+    config::profile::{Profile, ProfileCycle, Profiles},
+    driver::{
+        cells::{Cell, CellMode},
+        crossterm::{read_remap_key, Keyboard},
+        keymap::{Keymap, Remap, RemapStep, KEYPAD_ORDER},
+        palette::{Palette, NUM_COLORS},
+        tui_layout::{self, KeyMark},
+        AudioDevice, AudioInfo, AudioMsg, Bell, DisplayDevice, DisplayInfo, InputDevice, InputInfo,
+        InputMsg, StatusMsg, NOTIFICATION_DURATION,
+    },
+    emulator::Signal,
+};
+
+// Width of the machine state column, borders included ("V0 00 V1 00 V2 00 V3 00")
+const SIDE_WIDTH: u16 = 25;
+// Heights of the machine state and keypad panels, borders included
+const MACHINE_HEIGHT: u16 = 9;
+const KEYPAD_HEIGHT: u16 = 6;
+// Columns taken by each key in the keypad panel (" [X]")
+const KEYPAD_KEY_WIDTH: u16 = 4;
+
+// Default pixel colors, as in the termion driver
+const DEFAULT_COLORS: [Color; NUM_COLORS] = [
+    Color::Black,
+    Color::Gray,
+    Color::LightRed,
+    Color::Yellow,
+    Color::Black,
+    Color::LightRed,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightCyan,
+    Color::White,
+    Color::Blue,
+    Color::Black,
+    Color::Green,
+    Color::Red,
+];
+
+// Rich TUI frontend: the display in a frame with live panels beside it showing the frame
+// and instruction rates, registers, timers, and the keys the machine sees pressed
+//
+//   ┌ CHIP-8 ────────┐┌ Machine ───────────────┐
+//   │                ││ 60.0 FPS  720 IPS      │
+//   │    display     ││ PC 0200  I 0000 ...    │
+//   │                │└────────────────────────┘
+//   │                │┌ Keypad ────────────────┐
+//   └────────────────┘│ 1 2 3 C ...            │
+//
+//   The debugger's panel goes in a third column while paused. Keys are read through
+//   crossterm, which reports releases in some terminals (see the crossterm driver), and
+//   the keypad panel's keys can be clicked with the mouse.
+//
+pub struct Ratatui {
+    // Terminal drawn to through ratatui, which only rewrites the cells that changed
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    // What gets drawn, kept apart from the terminal so the two can be borrowed together
+    view: View,
+    // Whether keyboard enhancement flags were pushed, to be popped when dropped
+    enhanced: bool,
+    // Keypad keys held, and the console command being typed, read as by the crossterm
+    // frontend
+    keyboard: Keyboard,
+    // Profiles cycled through with the P hotkey
+    profiles: ProfileCycle,
+    // Rung as sounds start, in place of the beep
+    bell: Bell,
+}
+
+// Everything shown on screen
+struct View {
+    // Cells of the last frame (row-major), and how many (w, h) of them
+    cells: Vec<Cell>,
+    dims: (usize, usize),
+    // Pixel colors; None uses the terminal's own
+    palette: Option<Palette>,
+    // How pixels are packed into character cells
+    cell_mode: CellMode,
+    status: Option<StatusMsg>,
+    // Live rates shown on the display's top border (see `receive_stats`)
+    stats: String,
+    // Where the keypad panel's keys were last drawn, for mouse clicks to land on
+    keypad_area: layout::Rect,
+    // Debugger panel lines
+    panel: Vec<String>,
+    // Notification shown on the bottom row, and when it went up
+    toast: Option<(String, Instant)>,
+}
+
+impl Default for Ratatui {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ratatui {
+    pub fn new() -> Self {
+        let mut screen = stdout();
+        terminal::enable_raw_mode().expect("TUI raw mode failed");
+        execute!(screen, EnterAlternateScreen, EnableMouseCapture)
+            .expect("TUI screen creation failed");
+
+        let enhanced = matches!(terminal::supports_keyboard_enhancement(), Ok(true));
+        if enhanced {
+            execute!(
+                screen,
+                PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                        | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
+                        | KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS
+                )
+            )
+            .unwrap();
+        }
+
+        let mut terminal =
+            Terminal::new(CrosstermBackend::new(screen)).expect("TUI screen creation failed");
+        terminal.hide_cursor().unwrap();
+
+        Ratatui {
+            terminal,
+            view: View {
+                cells: Vec::new(),
+                dims: (0, 0),
+                palette: None,
+                cell_mode: CellMode::default(),
+                status: None,
+                stats: String::new(),
+                keypad_area: layout::Rect::default(),
+                panel: Vec::new(),
+                toast: None,
+            },
+            enhanced,
+            keyboard: Keyboard::new(enhanced || cfg!(windows)),
+            profiles: ProfileCycle::default(),
+            bell: Bell::default(),
+        }
+    }
+
+    // Apply the current profile, keeping the rest around for cycling
+    pub fn set_profiles(&mut self, profiles: Profiles) {
+        let profile = self.profiles.set(profiles);
+        self.apply_profile(&profile);
+    }
+
+    fn apply_profile(&mut self, profile: &Profile) {
+        self.keyboard.keymap = profile.keymap;
+        self.view.palette = profile.palette;
+        self.view.cell_mode = profile.render;
+    }
+
+    // Interactive remapping flow: prompt for the host key of each CHIP-8 key in turn.
+    // Returns None if the user cancels with Esc.
+    pub fn remap_keys(&mut self) -> Option<Keymap> {
+        let mut remap = Remap::default();
+        loop {
+            let prompt = Paragraph::new(vec![
+                Line::from(remap.prompt()),
+                Line::from(""),
+                Line::from(Remap::HINT),
+            ])
+            .alignment(Alignment::Center);
+            self.terminal
+                .draw(|f| {
+                    let area = f.size();
+                    let y = (area.height / 2).saturating_sub(1);
+                    let rows = area.height.saturating_sub(y).min(3);
+                    f.render_widget(prompt, layout::Rect::new(0, y, area.width, rows));
+                })
+                .unwrap();
+
+            match remap.feed(read_remap_key()) {
+                RemapStep::Pending => (),
+                RemapStep::Done(keymap) => return Some(keymap),
+                RemapStep::Cancelled => return None,
+            }
+        }
+    }
+
+    fn draw(&mut self) {
+        let prompt = self.keyboard.command.typing();
+        self.terminal.draw(|f| self.view.render(f, prompt)).unwrap();
+    }
+}
+
+impl View {
+    // `prompt` is the console command being typed, if any
+    fn render(&mut self, f: &mut Frame, prompt: Option<&str>) {
+        let area = f.size();
+        let (width, height) = (self.dims.0 as u16 + 2, self.dims.1 as u16 + 2);
+        let panel_width = match self.panel.iter().map(|line| line.chars().count()).max() {
+            Some(w) => w as u16 + 2,
+            None => 0,
+        };
+
+        // Everything is centered together, leaving the bottom row for the prompt
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+        let content_height = height
+            .max(MACHINE_HEIGHT + KEYPAD_HEIGHT)
+            .max(match panel_width {
+                0 => 0,
+                _ => self.panel.len() as u16 + 2,
+            });
+        let content = centered(rows[0], width + SIDE_WIDTH + panel_width, content_height);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(width),
+                Constraint::Length(SIDE_WIDTH),
+                Constraint::Length(panel_width),
+            ])
+            .split(content);
+
+        let title = match self.status {
+            Some(StatusMsg { paused: true, .. }) => " CHIP-8 (paused) ",
+            _ => " CHIP-8 ",
+        };
+        let mut screen = Block::default().borders(Borders::ALL).title(title);
+        if !self.stats.is_empty() {
+            screen =
+                screen.title(Title::from(format!(" {} ", self.stats)).alignment(Alignment::Right));
+        }
+        let screen_area = layout::Rect {
+            height: height.min(columns[0].height),
+            ..columns[0]
+        };
+        f.render_widget(
+            Screen {
+                cells: &self.cells,
+                width: self.dims.0,
+                palette: self.palette,
+            },
+            screen.inner(screen_area),
+        );
+        f.render_widget(screen, screen_area);
+
+        let side = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(MACHINE_HEIGHT),
+                Constraint::Length(KEYPAD_HEIGHT),
+                Constraint::Min(0),
+            ])
+            .split(columns[1]);
+        if let Some(status) = &self.status {
+            f.render_widget(machine_panel(status), side[0]);
+            f.render_widget(keypad_panel(status), side[1]);
+            self.keypad_area = side[1].inner(&layout::Margin::new(1, 1));
+        }
+
+        if panel_width > 0 {
+            let lines = self.panel.iter().map(|line| Line::from(line.as_str()));
+            f.render_widget(
+                Paragraph::new(lines.collect::<Vec<_>>())
+                    .block(Block::default().borders(Borders::ALL).title(" Debugger ")),
+                columns[2],
+            );
+        }
+
+        let bottom = match (prompt, &self.toast) {
+            (Some(command), _) => Line::from(format!(":{command}")),
+            (None, Some((text, shown))) if shown.elapsed() < NOTIFICATION_DURATION => {
+                Line::from(Span::styled(
+                    format!(" {text} "),
+                    Style::default().add_modifier(Modifier::REVERSED),
+                ))
+            }
+            _ => Line::from("Esc quit  Space pause  F5 save  F7 load  F9 record  Backspace rewind"),
+        };
+        f.render_widget(Paragraph::new(bottom), rows[1]);
+    }
+
+    // Map a terminal cell to the keypad panel key drawn there, if any
+    fn keypad_hit(&self, x: u16, y: u16) -> Option<usize> {
+        let area = self.keypad_area;
+        if !area.contains(layout::Position { x, y }) {
+            return None;
+        }
+        let (dx, dy) = (x - area.x, y - area.y);
+        // Clicks landing between keys don't count
+        if dx % KEYPAD_KEY_WIDTH == 0 || dx >= 4 * KEYPAD_KEY_WIDTH || dy >= 4 {
+            return None;
+        }
+        Some(KEYPAD_ORDER[(dy * 4 + dx / KEYPAD_KEY_WIDTH) as usize])
+    }
+}
+
+// Frame and instruction rates, registers, and timers
+fn machine_panel(status: &StatusMsg) -> Paragraph<'static> {
+    let mut lines = vec![
+        Line::from(format!("{:.1} FPS  {:.0} IPS", status.fps, status.ips)),
+        Line::from(match status.turbo {
+            true => format!("{:.0} Hz  TURBO", status.clock_rate),
+            false => format!("{:.0} Hz", status.clock_rate),
+        }),
+        Line::from(format!("PC {:04X}  I {:04X}", status.pc, status.i)),
+        Line::from(format!(
+            "DT {:02X}    ST {:02X}",
+            status.delay_timer, status.sound_timer
+        )),
+    ];
+    for (row, regs) in status.v.chunks(4).enumerate() {
+        let regs = regs
+            .iter()
+            .enumerate()
+            .map(|(n, v)| format!("V{:X} {v:02X}", row * 4 + n));
+        lines.push(Line::from(regs.collect::<Vec<_>>().join(" ")));
+    }
+
+    Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Machine "))
+}
+
+// The CHIP-8 keypad with the keys the machine sees pressed highlighted
+fn keypad_panel(status: &StatusMsg) -> Paragraph<'static> {
+    let lines = KEYPAD_ORDER.chunks(4).map(|keys| {
+        let spans = keys.iter().flat_map(|&key| {
+            // Keys FX0A would take on release stand out from those merely held
+            let style = match tui_layout::key_mark(key, status.keys, status.key_wait) {
+                Some(KeyMark::Awaited) => Style::default().fg(Color::Black).bg(Color::Yellow),
+                Some(KeyMark::Held) => Style::default().add_modifier(Modifier::REVERSED),
+                None => Style::default(),
+            };
+            [Span::raw(" "), Span::styled(format!(" {key:X} "), style)]
+        });
+        Line::from(spans.collect::<Vec<_>>())
+    });
+    let title = match status.key_wait {
+        Some(_) => " Keypad (FX0A) ",
+        None => " Keypad ",
+    };
+
+    Paragraph::new(lines.collect::<Vec<_>>())
+        .block(Block::default().borders(Borders::ALL).title(title))
+}
+
+// Area of (at most) `w` x `h` cells in the middle of `area`
+fn centered(area: layout::Rect, w: u16, h: u16) -> layout::Rect {
+    let (w, h) = (w.min(area.width), h.min(area.height));
+    layout::Rect::new(
+        area.x + (area.width - w) / 2,
+        area.y + (area.height - h) / 2,
+        w,
+        h,
+    )
+}
+
+// The display (see `CellMode`); whatever doesn't fit in the area is cut off
+struct Screen<'a> {
+    cells: &'a [Cell],
+    width: usize,
+    palette: Option<Palette>,
+}
+
+impl Widget for Screen<'_> {
+    fn render(self, area: layout::Rect, buf: &mut Buffer) {
+        if self.width == 0 {
+            return;
+        }
+        // Colors 2 and 3 only show up with XO-CHIP's second plane
+        let color = |n: usize| match self.palette {
+            Some(palette) => {
+                let (r, g, b) = palette.rgb(n);
+                Color::Rgb(r, g, b)
+            }
+            None => DEFAULT_COLORS[n],
+        };
+        for (idx, cell) in self.cells.iter().enumerate() {
+            let (x, y) = ((idx % self.width) as u16, (idx / self.width) as u16);
+            if x >= area.width || y >= area.height {
+                continue;
+            }
+            buf.get_mut(area.x + x, area.y + y)
+                .set_char(cell.ch)
+                .set_fg(color(cell.fg))
+                .set_bg(color(cell.bg));
+        }
+    }
+}
+
+impl Drop for Ratatui {
+    fn drop(&mut self) {
+        let screen = self.terminal.backend_mut();
+        if self.enhanced {
+            let _ = execute!(screen, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(screen, DisableMouseCapture, LeaveAlternateScreen);
+        let _ = self.terminal.show_cursor();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl InputDevice for Ratatui {
+    // Host keys are translated to CHIP-8 keys through `Keymap` (QWERTY layout by default)
+    fn handle_inputs(&mut self) -> Signal {
+        let prev_state = self.keyboard.keys();
+        self.keyboard.start_poll();
+
+        // Emulator hotkeys take precedence over keypad updates
+        let mut hotkey = Signal::None;
+        // Whether something on screen changed outside of a frame
+        let mut redraw = matches!(
+            &self.view.toast,
+            Some((_, shown)) if shown.elapsed() >= NOTIFICATION_DURATION
+        );
+        if redraw {
+            self.view.toast = None;
+        }
+
+        // Drain all pending events
+        while let Ok(true) = event::poll(Duration::ZERO) {
+            let Ok(event) = event::read() else {
+                break;
+            };
+
+            let prompting = self.keyboard.command.typing().is_some();
+            match event {
+                Event::Resize(..) => redraw = true,
+                event => match self
+                    .keyboard
+                    .handle_event(event, |x, y| self.view.keypad_hit(x, y))
+                {
+                    Signal::ProgramExit => return Signal::ProgramExit,
+                    Signal::CycleProfile => {
+                        if let Some(profile) = self.profiles.cycle() {
+                            self.apply_profile(&profile);
+                            hotkey = Signal::CycleProfile;
+                        }
+                    }
+                    Signal::None => (),
+                    signal => hotkey = signal,
+                },
+            }
+            redraw |= prompting || self.keyboard.command.typing().is_some();
+        }
+
+        if redraw {
+            self.draw();
+        }
+
+        if hotkey != Signal::None {
+            hotkey
+        } else if self.keyboard.keys() != prev_state {
+            Signal::NewInputs
+        } else {
+            Signal::None
+        }
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        Some(self.keyboard.keys())
+    }
+
+    fn take_command(&mut self) -> Option<String> {
+        self.keyboard.command.take()
+    }
+
+    fn device_info(&self) -> InputInfo {
+        InputInfo::Ratatui
+    }
+}
+
+impl DisplayDevice for Ratatui {
+    fn receive_frame(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice {
+        let mode = self.view.cell_mode;
+        let (width, height) = mode.cells(dims, 1);
+        self.view.dims = (width, height);
+        self.view.cells.clear();
+        for y in 0..height {
+            self.view
+                .cells
+                .extend((0..width).map(|x| mode.cell(planes, dims, 1, x, y)));
+        }
+
+        self
+    }
+
+    fn receive_panel(&mut self, lines: &[String]) {
+        if lines != self.view.panel {
+            self.view.panel = lines.to_vec();
+        }
+    }
+
+    fn receive_notification(&mut self, text: &str) {
+        self.view.toast = Some((text.to_owned(), Instant::now()));
+        self.draw();
+    }
+
+    fn receive_status(&mut self, status: &StatusMsg) {
+        self.view.status = Some(*status);
+    }
+
+    fn receive_stats(&mut self, text: &str) {
+        if text != self.view.stats {
+            self.view.stats = text.to_owned();
+        }
+    }
+
+    fn drive_display(&mut self) {
+        self.draw();
+    }
+
+    fn device_info(&self) -> DisplayInfo {
+        DisplayInfo::Ratatui
+    }
+}
+
+impl AudioDevice for Ratatui {
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice {
+        if self.bell.ring(data) {
+            execute!(self.terminal.backend_mut(), Print('\x07')).unwrap();
+        }
+
+        self
+    }
+
+    fn play_audio(&mut self) {}
+
+    fn set_volume(&mut self, volume: f32) {
+        self.bell.set_volume(volume);
+    }
+
+    fn device_info(&self) -> AudioInfo {
+        AudioInfo::Ratatui
+    }
+}
@@ -0,0 +1,38 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+// Set as the terminal is resized, until taken
+static RESIZED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+// Catch SIGWINCH so terminal frontends learn of resizes as they happen rather than on the
+// next frame drawn, which may be a while coming (e.g. while paused, or with a still
+// display); they pick it up through `take`. Watching more than once is harmless
+#[cfg(unix)]
+pub fn watch() -> io::Result<()> {
+    use signal_hook::{consts::SIGWINCH, flag};
+
+    if RESIZED.get().is_some() {
+        return Ok(());
+    }
+    let resized = RESIZED.get_or_init(Default::default);
+    flag::register(SIGWINCH, Arc::clone(resized))?;
+    Ok(())
+}
+
+// Other platforms' terminals are only checked for resizes as frames are drawn
+#[cfg(not(unix))]
+pub fn watch() -> io::Result<()> {
+    Ok(())
+}
+
+// Whether the terminal was resized since the last call
+pub fn take() -> bool {
+    RESIZED
+        .get()
+        .is_some_and(|resized| resized.swap(false, Ordering::Relaxed))
+}
@@ -1,44 +1,109 @@
-use crate::driver::{AudioDevice, AudioInfo};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::driver::{
+    waveform::{Playback, Synth, Waveform},
+    AudioDevice, AudioInfo, AudioMsg,
+};
+
+// Rate the sound is synthesized at; rodio resamples it to the output device's
+const SAMPLE_RATE: u32 = 48_000;
+// Samples played between checks for a change of sound (~1ms)
+const POLL_SAMPLES: u32 = 48;
 
 pub struct Rodio {
     // Output audio source
     _stream: rodio::OutputStream,
     // Handle to audio device which controls playback
-    // TODO: rodio only provides sine wave synthesis, but we can approximate
-    // square/triangle/sawtooth waves for a more retro feel
-    //   see https://observablehq.com/@freedmand/sounds
-    sink: rodio::Sink,
+    _sink: rodio::Sink,
+    // Sound that should be playing, and its volume, read by the `Sound` source
+    playback: Arc<Mutex<Playback>>,
 }
 
 impl Rodio {
-    pub fn new() -> Self {
-        use rodio::Source;
-
-        let (stream, handle) = rodio::OutputStream::try_default().unwrap();
-        let sink = rodio::Sink::try_new(&handle).unwrap();
+    pub fn new(waveform: Waveform) -> Result<Self, String> {
+        let (stream, handle) = rodio::OutputStream::try_default()
+            .map_err(|e| format!("audio output stream creation failed: {e}"))?;
+        let sink = rodio::Sink::try_new(&handle)
+            .map_err(|e| format!("audio output failed to start: {e}"))?;
+        let playback = Arc::new(Mutex::new(Playback::default()));
 
-        // F4 pure tone
-        let source = rodio::source::SineWave::new(349.23).amplify(0.1);
-        sink.append(source);
-        sink.pause();
+        sink.append(Sound {
+            synth: Synth::new(waveform, SAMPLE_RATE),
+            playback: Arc::clone(&playback),
+            playing: Playback::default(),
+            until_poll: 0,
+        });
 
-        Rodio {
+        Ok(Rodio {
             _stream: stream,
-            sink,
-        }
+            _sink: sink,
+            playback,
+        })
     }
 }
 
-impl AudioDevice for Rodio {
-    fn receive_signal(&mut self, data: bool) -> &mut dyn AudioDevice {
-        match data {
-            true => self.sink.play(),
-            false => self.sink.pause(),
+// Endless mono source playing whatever `playback` is set to (see `Synth`), since rodio only
+// comes with a sine wave source
+struct Sound {
+    synth: Synth,
+    playback: Arc<Mutex<Playback>>,
+    // Copy of `playback` being played, and the samples left before it's checked again
+    playing: Playback,
+    until_poll: u32,
+}
+
+impl Iterator for Sound {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.until_poll == 0 {
+            if let Ok(playback) = self.playback.try_lock() {
+                self.playing = *playback;
+            }
+            self.until_poll = POLL_SAMPLES;
         }
+        self.until_poll -= 1;
+        Some(self.synth.play(self.playing))
+    }
+}
+
+impl rodio::Source for Sound {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
 
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl AudioDevice for Rodio {
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice {
+        self.playback
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .sound = data;
         self
     }
 
+    fn set_volume(&mut self, volume: f32) {
+        self.playback
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .volume = volume;
+    }
+
     fn play_audio(&mut self) {}
 
     fn device_info(&self) -> AudioInfo {
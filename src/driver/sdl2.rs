@@ -0,0 +1,467 @@
+use std::{
+    io::{stdin, BufRead},
+    sync::mpsc,
+    thread,
+    time::Instant,
+};
+
+use bitvec::{bitarr, slice::BitSlice, BitArr};
+use sdl2::{
+    audio::{AudioCallback, AudioSpecDesired},
+    event::Event,
+    keyboard::Keycode,
+    pixels::{Color, PixelFormatEnum},
+    render::Canvas,
+    surface::Surface,
+    video::Window,
+    EventPump,
+};
+
+use crate::{
+    chip8::{DISPLAY_HEIGHT, DISPLAY_WIDTH, NUM_KEYS},
+    config::desktop::{self, ICON_SIZE},
+    config::profile::{Profile, ProfileCycle, Profiles},
+    diagnostics,
+    driver::{
+        keymap::{Keymap, Remap, RemapStep},
+        palette::Palette,
+        waveform::{Synth, Waveform},
+    },
+    driver::{pixel_color, print_panel, remap_pixels, KEY_DOWN, KEY_UP, NOTIFICATION_DURATION},
+    driver::{AudioDevice, AudioInfo, AudioMsg},
+    driver::{DisplayDevice, DisplayInfo, InputDevice, InputInfo, InputMsg},
+    emulator::Signal,
+};
+
+// Output sample rate asked of the audio device; SDL converts if it can't do it natively
+const SAMPLE_RATE: i32 = 44100;
+
+// A native window through SDL2 that is display, input, and audio device at once: frames are
+// scaled up by the GPU to fill the window (letterboxed to keep their aspect), keys are held
+// down from their press event until their release event, and sound is synthesized in SDL's
+// audio callback
+pub struct Sdl2 {
+    // Window, as the target frames are copied to and scaled onto
+    canvas: Canvas<Window>,
+    events: EventPump,
+    // Playback stops once it's dropped; the callback's sound is set through its lock
+    audio: sdl2::audio::AudioDevice<Beeper>,
+    // Frame in the texture's XRGB8888 pixel format, 4 bytes per pixel
+    framebuf: Vec<u8>,
+    // Dimensions (w, h) of the frame currently held in `framebuf`
+    frame_dims: (usize, usize),
+    // Keys currently held down, and the key states last reported with `Signal::NewInputs`
+    keybuf: BitArr!(for NUM_KEYS),
+    reported: InputMsg,
+    // Whether Backspace is held down, rewinding the machine
+    rewinding: bool,
+    // Whether Tab is held down, fast-forwarding the machine, and whether that was last
+    // reported with `Signal::TurboOn`
+    turbo: bool,
+    turbo_reported: bool,
+    // Host key to CHIP-8 key bindings
+    keymap: Keymap,
+    palette: Palette,
+    // Profiles cycled through with the P hotkey
+    profiles: ProfileCycle,
+    // Text panel last received; the window can't show text, so it goes to stderr instead
+    panel: Vec<String>,
+    // Debugger console commands, read from stdin lines for the same reason, and the last
+    // one entered
+    commands: mpsc::Receiver<String>,
+    entered: Option<String>,
+    // Window title; notifications are appended to it until they expire
+    title: String,
+    toast_shown: Option<Instant>,
+}
+
+// SDL audio callback state: the sound to play, and the synth to play it with
+struct Beeper {
+    synth: Synth,
+    sound: AudioMsg,
+}
+
+impl AudioCallback for Beeper {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = self.synth.next_sample(self.sound);
+        }
+    }
+}
+
+impl Sdl2 {
+    // `scale` is the window's initial scale factor, one of `profile::SCALES`; with `vsync`,
+    // frames are presented in step with the monitor's refresh so they don't tear
+    pub fn new(name: &str, scale: usize, waveform: Waveform, vsync: bool) -> Result<Self, String> {
+        let context = sdl2::init()?;
+        let title = "CHIP-8: ".to_owned() + name;
+
+        let window = context
+            .video()?
+            .window(
+                &title,
+                (DISPLAY_WIDTH * scale) as u32,
+                (DISPLAY_HEIGHT * scale) as u32,
+            )
+            .position_centered()
+            .resizable()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let mut canvas = window.into_canvas().accelerated();
+        if vsync {
+            canvas = canvas.present_vsync();
+        }
+        let canvas = canvas.build().map_err(|e| e.to_string())?;
+
+        let audio = context.audio()?.open_playback(
+            None,
+            &AudioSpecDesired {
+                freq: Some(SAMPLE_RATE),
+                channels: Some(1),
+                samples: None,
+            },
+            |spec| Beeper {
+                synth: Synth::new(waveform, spec.freq as u32),
+                sound: AudioMsg::Beep(false),
+            },
+        )?;
+        audio.resume();
+
+        let (tx, commands) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdin().lock().lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Sdl2 {
+            canvas,
+            events: context.event_pump()?,
+            audio,
+            framebuf: vec![0; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4],
+            frame_dims: (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+            keybuf: bitarr![0; NUM_KEYS],
+            reported: bitarr![0; NUM_KEYS],
+            rewinding: false,
+            turbo: false,
+            turbo_reported: false,
+            keymap: Keymap::default(),
+            palette: Palette::default(),
+            profiles: ProfileCycle::default(),
+            panel: Vec::new(),
+            commands,
+            entered: None,
+            title,
+            toast_shown: None,
+        })
+    }
+
+    // Apply the current profile, keeping the rest around for cycling
+    pub fn set_profiles(&mut self, profiles: Profiles) {
+        let profile = self.profiles.set(profiles);
+        self.apply_profile(&profile);
+    }
+
+    // The window's scale is the user's to change by resizing it, so it is left as is here
+    fn apply_profile(&mut self, profile: &Profile) {
+        self.keymap = profile.keymap;
+        self.palette = profile.palette.unwrap_or_default();
+        self.update_icon();
+    }
+
+    // Show the application icon (see `desktop::ICON`) in the palette's colors
+    fn update_icon(&mut self) {
+        let Ok(mut icon) = Surface::new(
+            ICON_SIZE as u32,
+            ICON_SIZE as u32,
+            PixelFormatEnum::ARGB8888,
+        ) else {
+            return;
+        };
+        let pitch = icon.pitch() as usize;
+        icon.with_lock_mut(|pixels| {
+            for y in 0..ICON_SIZE {
+                for x in 0..ICON_SIZE {
+                    let lit = desktop::icon_pixel(x, y);
+                    let argb = 0xFF00_0000 | self.palette.colors[lit as usize];
+                    pixels[y * pitch + x * 4..][..4].copy_from_slice(&argb.to_ne_bytes());
+                }
+            }
+        });
+        self.canvas.window_mut().set_icon(icon);
+    }
+
+    fn set_title(&mut self, title: &str) {
+        // Titles never contain NUL bytes
+        let _ = self.canvas.window_mut().set_title(title);
+    }
+
+    // Fill `framebuf` with color `n`
+    fn clear_frame(&mut self, n: usize) {
+        let pixel = self.palette.colors[n].to_ne_bytes();
+        let (w, h) = self.frame_dims;
+        self.framebuf.clear();
+        self.framebuf
+            .extend(std::iter::repeat_n(pixel, w * h).flatten());
+    }
+
+    // Interactive remapping flow: the CHIP-8 key to be bound is drawn in the window
+    // while waiting for the host key. Returns None if the user cancels with Esc or
+    // closes the window.
+    pub fn remap_keys(&mut self, title: &str) -> Option<Keymap> {
+        let mut remap = Remap::default();
+        let keymap = loop {
+            self.set_title(&format!("CHIP-8: {} {}", remap.prompt(), Remap::HINT));
+
+            // Draw the hex digit being bound
+            self.frame_dims = (DISPLAY_WIDTH, DISPLAY_HEIGHT);
+            self.clear_frame(0);
+            let lit = self.palette.colors[1].to_ne_bytes();
+            for (x, y) in remap_pixels(remap.key()) {
+                self.framebuf[(y * DISPLAY_WIDTH + x) * 4..][..4].copy_from_slice(&lit);
+            }
+            self.drive_display();
+
+            let host = loop {
+                match self.events.wait_event() {
+                    Event::Quit { .. }
+                    | Event::KeyDown {
+                        keycode: Some(Keycode::ESCAPE),
+                        ..
+                    } => break None,
+                    Event::KeyDown {
+                        keycode: Some(key), ..
+                    } => {
+                        if let Some(c) = key_char(key) {
+                            break Some(c);
+                        }
+                    }
+                    // The window may have been uncovered or resized
+                    _ => self.drive_display(),
+                }
+            };
+            match remap.feed(host) {
+                RemapStep::Pending => (),
+                RemapStep::Done(keymap) => break Some(keymap),
+                RemapStep::Cancelled => break None,
+            }
+        };
+
+        self.set_title(&("CHIP-8: ".to_owned() + title));
+        keymap
+    }
+}
+
+// Character a host key corresponds to, for lookups in a `Keymap`; SDL's keycodes for keys
+// that type a character are that (unshifted) character
+fn key_char(key: Keycode) -> Option<char> {
+    u8::try_from(key.into_i32())
+        .ok()
+        .map(char::from)
+        .filter(char::is_ascii_graphic)
+}
+
+// Emulator hotkey bound to a host key, if any
+fn hotkey(key: Keycode) -> Signal {
+    match key {
+        Keycode::M => Signal::MarkState,
+        Keycode::N => Signal::RestoreMark,
+        Keycode::O => Signal::RotateDisplay,
+        Keycode::H => Signal::MirrorDisplay,
+        Keycode::P => Signal::CycleProfile,
+        Keycode::F2 => Signal::Reset,
+        Keycode::F5 => Signal::SaveState,
+        Keycode::F7 => Signal::LoadState,
+        Keycode::F8 => Signal::SwitchFrontend,
+        Keycode::F9 => Signal::ToggleRecording,
+        Keycode::SPACE => Signal::Pause,
+        Keycode::PERIOD => Signal::Step,
+        Keycode::COMMA => Signal::FrameAdvance,
+        Keycode::EQUALS | Keycode::PLUS | Keycode::KP_PLUS => Signal::SpeedUp,
+        Keycode::MINUS | Keycode::KP_MINUS => Signal::SpeedDown,
+        Keycode::NUM_0 => Signal::VolumeUp,
+        Keycode::NUM_9 => Signal::VolumeDown,
+        Keycode::NUM_8 => Signal::ToggleMute,
+        Keycode::B => Signal::ToggleBreakpoint,
+        Keycode::LEFTBRACKET => Signal::ScrollMemoryUp,
+        Keycode::RIGHTBRACKET => Signal::ScrollMemoryDown,
+        _ => Signal::None,
+    }
+}
+
+impl InputDevice for Sdl2 {
+    // Host keys are translated to CHIP-8 keys through `Keymap` (QWERTY layout by default)
+    fn handle_inputs(&mut self) -> Signal {
+        // Checked here since frames don't necessarily keep coming in
+        if self
+            .toast_shown
+            .is_some_and(|shown| shown.elapsed() >= NOTIFICATION_DURATION)
+        {
+            let title = self.title.clone();
+            self.set_title(&title);
+            self.toast_shown = None;
+        }
+
+        if let Ok(command) = self.commands.try_recv() {
+            self.entered = Some(command);
+            return Signal::DebugCommand;
+        }
+
+        let mut signal = Signal::None;
+        for event in self.events.poll_iter() {
+            match event {
+                Event::Quit { .. } => return Signal::ProgramExit,
+                Event::KeyDown {
+                    keycode: Some(key),
+                    repeat,
+                    ..
+                } => match key_char(key).and_then(|c| self.keymap.lookup(c)) {
+                    Some(idx) => self.keybuf.set(idx, KEY_DOWN),
+                    None if key == Keycode::BACKSPACE => self.rewinding = true,
+                    None if key == Keycode::TAB => self.turbo = true,
+                    // Only the first hotkey of a poll is taken, and only on its press
+                    None if !repeat && signal == Signal::None => signal = hotkey(key),
+                    None => {}
+                },
+                Event::KeyUp {
+                    keycode: Some(key), ..
+                } => match key_char(key).and_then(|c| self.keymap.lookup(c)) {
+                    Some(idx) => self.keybuf.set(idx, KEY_UP),
+                    None if key == Keycode::BACKSPACE => self.rewinding = false,
+                    None if key == Keycode::TAB => self.turbo = false,
+                    None => {}
+                },
+                _ => {}
+            }
+        }
+
+        // Rewinding lasts for as long as the key is held; keypad changes meanwhile, like
+        // those alongside a hotkey, are held off until a later poll
+        if self.rewinding {
+            return Signal::Rewind;
+        }
+        let signal = match signal {
+            Signal::CycleProfile => match self.profiles.cycle() {
+                Some(profile) => {
+                    self.apply_profile(&profile);
+                    signal
+                }
+                None => Signal::None,
+            },
+            signal => signal,
+        };
+
+        if signal != Signal::None {
+            signal
+        } else if self.turbo != self.turbo_reported {
+            self.turbo_reported = self.turbo;
+            match self.turbo {
+                true => Signal::TurboOn,
+                false => Signal::TurboOff,
+            }
+        } else if self.keybuf != self.reported {
+            self.reported = self.keybuf;
+            Signal::NewInputs
+        } else {
+            Signal::None
+        }
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        Some(self.reported)
+    }
+
+    fn take_command(&mut self) -> Option<String> {
+        self.entered.take()
+    }
+
+    fn device_info(&self) -> InputInfo {
+        InputInfo::Sdl2
+    }
+}
+
+impl DisplayDevice for Sdl2 {
+    fn receive_frame(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice {
+        self.frame_dims = dims;
+        self.framebuf.clear();
+        for idx in 0..dims.0 * dims.1 {
+            let color = self.palette.color(pixel_color(planes, idx));
+            self.framebuf.extend(color.to_ne_bytes());
+        }
+
+        self
+    }
+
+    fn receive_panel(&mut self, lines: &[String]) {
+        if lines != self.panel {
+            self.panel = lines.to_vec();
+            print_panel(lines);
+        }
+    }
+
+    fn receive_notification(&mut self, text: &str) {
+        let title = format!("{} - {text}", self.title);
+        self.set_title(&title);
+        self.toast_shown = Some(Instant::now());
+    }
+
+    fn drive_display(&mut self) {
+        let (w, h) = (self.frame_dims.0 as u32, self.frame_dims.1 as u32);
+        // Scale the frame to fit the window, leaving bars of the off color around it
+        if self.canvas.logical_size() != (w, h) {
+            if let Err(e) = self.canvas.set_logical_size(w, h) {
+                diagnostics::warn("sdl2", format!("failed to scale the display: {e}"));
+            }
+        }
+        let (r, g, b) = self.palette.rgb(0);
+        self.canvas.set_draw_color(Color::RGB(r, g, b));
+        self.canvas.clear();
+
+        // The texture is made anew for each frame, as it can't outlive the creator it
+        // borrows from; at CHIP-8 resolutions that costs next to nothing
+        let creator = self.canvas.texture_creator();
+        let drawn = creator
+            .create_texture_streaming(PixelFormatEnum::RGB888, w, h)
+            .map_err(|e| e.to_string())
+            .and_then(|mut texture| {
+                texture
+                    .update(None, &self.framebuf, w as usize * 4)
+                    .map_err(|e| e.to_string())?;
+                self.canvas.copy(&texture, None, None)
+            });
+        if let Err(e) = drawn {
+            diagnostics::warn("sdl2", format!("failed to update the window: {e}"));
+        }
+
+        self.canvas.present();
+    }
+
+    fn device_info(&self) -> DisplayInfo {
+        DisplayInfo::Sdl2
+    }
+}
+
+impl AudioDevice for Sdl2 {
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice {
+        self.audio.lock().sound = data;
+        self
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.audio.lock().synth.set_volume(volume);
+    }
+
+    fn play_audio(&mut self) {}
+
+    fn device_info(&self) -> AudioInfo {
+        AudioInfo::Sdl2
+    }
+}
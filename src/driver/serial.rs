@@ -0,0 +1,76 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    thread,
+};
+
+use crate::{
+    chip8::NUM_KEYS,
+    driver::{
+        events::{EventInput, InputEvent},
+        InputDevice, InputInfo, InputMsg,
+    },
+    emulator::Signal,
+};
+
+// Hex keypad wired up to a microcontroller (e.g. an Arduino scanning a 4x4 key matrix)
+// that reports key changes over a serial port; see `MergedInput` to use it alongside a
+// frontend's keys
+//
+//   The protocol is one line per key change: the key as a hex digit, then 1 if it went
+//   down or 0 if it came up, e.g. `A 1` then `A 0` for a press of key A. Other lines
+//   are skipped, so the board can print whatever it likes on startup.
+//
+//   The port is read as a plain file, so it has to be set up beforehand; on Linux,
+//   `stty -F /dev/ttyACM0 115200 raw` for a board sending at 115200 baud
+//
+pub struct SerialKeypad {
+    // Key changes read from the port; its sender goes once the port closes
+    events: EventInput,
+}
+
+impl SerialKeypad {
+    pub fn new(port: File) -> Self {
+        let (tx, events) = EventInput::new();
+        thread::spawn(move || {
+            for line in BufReader::new(port).lines().map_while(Result::ok) {
+                let Some(event) = parse_event(&line) else {
+                    continue;
+                };
+                if tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        SerialKeypad { events }
+    }
+}
+
+// Key change in the form `A 1` (see `SerialKeypad`)
+fn parse_event(line: &str) -> Option<InputEvent> {
+    let (key, state) = line.trim().split_once(char::is_whitespace)?;
+    let key = u8::from_str_radix(key, 16)
+        .ok()
+        .filter(|&key| (key as usize) < NUM_KEYS)?;
+    match state.trim() {
+        "1" => Some(InputEvent::KeyDown(key)),
+        "0" => Some(InputEvent::KeyUp(key)),
+        _ => None,
+    }
+}
+
+impl InputDevice for SerialKeypad {
+    // Unplugging it releases any keys left down
+    fn handle_inputs(&mut self) -> Signal {
+        self.events.handle_inputs()
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        self.events.send_inputs()
+    }
+
+    fn device_info(&self) -> InputInfo {
+        InputInfo::Serial
+    }
+}
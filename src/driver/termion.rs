@@ -1,45 +1,197 @@
 use std::{
     fmt::Write as _,
-    io::{stdout, Read, Stdout, Write},
-    time::Instant,
+    fs::File,
+    io::{self, stdin, stdout, Read, Stdout, Write},
+    sync::{mpsc, Mutex, MutexGuard, OnceLock},
+    thread,
+    time::{Duration, Instant},
 };
 
-use bitvec::{bitarr, slice::BitSlice, BitArr};
+use bitvec::slice::BitSlice;
 
 use crate::{
-    chip8::{DISPLAY_HEIGHT, DISPLAY_WIDTH, NUM_KEYS},
+    chip8::{DISPLAY_HEIGHT, DISPLAY_WIDTH},
+    config::profile::{Profile, ProfileCycle, Profiles},
     driver::{
-        AudioDevice, AudioInfo, DisplayDevice, DisplayInfo, InputDevice, InputInfo, InputMsg,
-        KEY_DOWN, KEY_UP, PX_OFF, PX_ON,
+        cells::{Cell, CellMode},
+        command_line::{CommandLine, Edit},
+        graphics::{self, Protocol},
+        keymap::{Keymap, Remap, RemapStep, KEYPAD_ORDER},
+        kitty::{self, KeyAction},
+        palette::{Palette, NUM_COLORS},
+        pixel_color, resize,
+        tui_layout::{self, Debounced, KeyMark, Layout},
+        AudioDevice, AudioInfo, AudioMsg, Bell, DisplayDevice, DisplayInfo, InputDevice, InputInfo,
+        InputMsg, Rect, StatusMsg, KEY_DOWN, NOTIFICATION_DURATION,
     },
     emulator::Signal,
 };
+use termion::{
+    color,
+    event::{Event, Key, MouseButton, MouseEvent},
+};
+
+// How long the terminal gets to answer each query about what it supports
+const QUERY_TIMEOUT: Duration = Duration::from_millis(200);
+
+// Default pixel colors (https://en.wikipedia.org/wiki/ANSI_escape_code#8-bit): black,
+// white, and for XO-CHIP's second plane, light red and yellow; then CHIP-8X's foreground
+// colors in bright ANSI colors, and its background colors in standard ones
+const DEFAULT_COLORS: [color::AnsiValue; NUM_COLORS] = [
+    color::AnsiValue(0),
+    color::AnsiValue(7),
+    color::AnsiValue(9),
+    color::AnsiValue(3),
+    color::AnsiValue(0),
+    color::AnsiValue(9),
+    color::AnsiValue(12),
+    color::AnsiValue(13),
+    color::AnsiValue(10),
+    color::AnsiValue(11),
+    color::AnsiValue(14),
+    color::AnsiValue(15),
+    color::AnsiValue(4),
+    color::AnsiValue(0),
+    color::AnsiValue(2),
+    color::AnsiValue(1),
+];
+// The same colors in RGB, as xterm shows them, for images drawn with no palette
+const DEFAULT_IMAGE_PALETTE: Palette = Palette {
+    colors: [0x000000, 0xE5E5E5, 0xFF0000, 0xCDCD00],
+};
+
+// Input byte stream from the tty, shared by every `Termion` there has been: the thread
+// reading it can't be stopped, and one per device would go on to eat the next byte typed
+// once its device is gone (e.g. after switching to a window and back)
+fn tty_input() -> MutexGuard<'static, Box<dyn Read + Send>> {
+    static INPUT: OnceLock<Mutex<Box<dyn Read + Send>>> = OnceLock::new();
+    INPUT
+        .get_or_init(|| {
+            // With a ROM piped in (see `emulator::rom::read_rom`), stdin isn't the tty, so
+            // keys are read from the tty directly
+            let input: Box<dyn Read + Send> = match termion::is_tty(&stdin()) {
+                true => Box::new(termion::async_stdin()),
+                false => match termion::get_tty() {
+                    Ok(tty) => Box::new(AsyncTty::new(tty)),
+                    Err(_) => Box::new(termion::async_stdin()),
+                },
+            };
+            Mutex::new(input)
+        })
+        .lock()
+        .unwrap()
+}
 
-const DEBOUNCE_TIMEOUT: u32 = 100; // ms
+// Bytes read from the tty on a thread of its own, handed out without blocking like
+// `termion::AsyncReader` does those read from stdin
+struct AsyncTty(mpsc::Receiver<u8>);
+
+impl AsyncTty {
+    fn new(mut tty: File) -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = [0; 64];
+            while let Ok(n @ 1..) = tty.read(&mut buf) {
+                if buf[..n].iter().any(|&byte| tx.send(byte).is_err()) {
+                    return;
+                }
+            }
+        });
+        AsyncTty(rx)
+    }
+}
+
+impl Read for AsyncTty {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut n = 0;
+        while n < buf.len() {
+            match self.0.try_recv() {
+                Ok(byte) => buf[n] = byte,
+                Err(_) => break,
+            }
+            n += 1;
+        }
+        Ok(n)
+    }
+}
 
 pub struct Termion {
-    // Input byte stream from tty stdin
-    stdin: termion::AsyncReader,
     // TUI window - redirects all writes to an alternate screen and restores
     // existing terminal state upon being dropped. Raw mode is required because
     // in canonical mode, inputs are buffered until a newline or EOF is reached.
     // This means that users would have to manually hit return/enter for their
     // inputs to be received by the reader, which is not practical.
+    // Mouse reporting is enabled on top of that so the keypad overlay can be clicked.
     //   - https://en.wikipedia.org/wiki/Terminal_mode
     //   - https://stackoverflow.com/questions/77397499
-    screen: termion::screen::AlternateScreen<termion::raw::RawTerminal<Stdout>>,
+    screen: termion::input::MouseTerminal<
+        termion::screen::AlternateScreen<termion::raw::RawTerminal<Stdout>>,
+    >,
+    // Whether the terminal speaks the kitty keyboard protocol (see `driver::kitty`), whose
+    // flags were pushed to have key releases reported and are popped when dropped; keys are
+    // then held down until they're released rather than expiring (see `Debounced`)
+    enhanced: bool,
+    // Graphics protocol the terminal speaks, if any, for `CellMode::Image` to draw with
+    graphics: Option<Protocol>,
     // Terminal width and height used to detect resizes and center accordingly
     term_size: (u16, u16), // (w, h)
+    // Dimensions of the last frame drawn; a change (e.g. rotation) requires clearing the screen
+    frame_dims: (usize, usize), // (w, h)
+    // Terminal size (w, h) the display needs, if the terminal is smaller than that
+    too_small: Option<(u16, u16)>,
     // Frame buffer used to write to screen. This is embedded within the struct
     // instead of created at each frame refresh because we get to reuse the
     // space allocated (which is roughly constant) with String::clear()
     framebuf: String,
-    // Tx input buffer
-    keybuf: BitArr!(for NUM_KEYS),
-    // Since inputs come as a byte stream, we don't have convenient key up/down
-    // states to relay; having corresponding timers to "expire" key presses will
-    // serve that purpose and make inputs more predictable
-    key_expire: [Instant; NUM_KEYS],
+    // Region of the next frame that changed since the last one (see `receive_damage`); only
+    // the cells covering it are written out
+    damage: Option<Rect>,
+    // Cells last written to the screen, row-major over the display; None where what's on
+    // screen isn't known (e.g. after a clear)
+    drawn: Vec<Option<Cell>>,
+    // Whether the whole display has to be written out on the next frame, having been
+    // cleared or drawn over (e.g. by a notification) since the last one
+    redraw: bool,
+    // Tx input buffer. Since inputs come as a byte stream, we don't have convenient key
+    // up/down states to relay, so key presses are expired instead
+    keybuf: Debounced,
+    // Host key to CHIP-8 key bindings
+    keymap: Keymap,
+    // Pixel colors; None uses the terminal's own black and white
+    palette: Option<Palette>,
+    // How pixels are packed into character cells
+    cell_mode: CellMode,
+    // Profiles cycled through with the P hotkey
+    profiles: ProfileCycle,
+    // Top-left corner (1-based) of the on-screen keypad, if it is shown
+    keypad_origin: Option<(u16, u16)>,
+    // Keypad key currently held down with the mouse
+    mouse_key: Option<usize>,
+    // Keys the machine sees held and those FX0A is waiting on, if it is, as of the last
+    // status (see `StatusMsg`), and the top-left corner (1-based) they're drawn at
+    machine_keys: Option<(InputMsg, Option<InputMsg>)>,
+    key_state_origin: (u16, u16),
+    // Whether the machine is being fast-forwarded
+    turbo: bool,
+    // Text panel (e.g. the debugger's) drawn to the right of everything else, and the
+    // top-left corner (1-based) it is drawn at
+    panel: Vec<String>,
+    panel_origin: (u16, u16),
+    // Debugger console command being typed after `:`, and the last one entered
+    command: CommandLine,
+    // Notification shown in the top-right corner, and when it went up
+    toast: Option<(String, Instant)>,
+    // Live rates kept up in the top-left corner (see `receive_stats`); empty unless asked
+    // for
+    stats: String,
+    // Rung as sounds start, in place of the beep
+    bell: Bell,
+}
+
+impl Default for Termion {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Termion {
@@ -48,88 +200,559 @@ impl Termion {
         use termion::screen::IntoAlternateScreen;
 
         let mut t = Termion {
-            stdin: termion::async_stdin(),
-            screen: stdout()
-                .into_raw_mode()
-                .unwrap()
-                .into_alternate_screen()
-                .expect("TUI screen creation failed"),
+            screen: termion::input::MouseTerminal::from(
+                stdout()
+                    .into_raw_mode()
+                    .unwrap()
+                    .into_alternate_screen()
+                    .expect("TUI screen creation failed"),
+            ),
+            enhanced: false,
+            graphics: None,
             term_size: termion::terminal_size().unwrap(),
+            frame_dims: (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+            too_small: None,
             framebuf: String::new(),
-            keybuf: bitarr![0; NUM_KEYS],
-            key_expire: [Instant::now(); NUM_KEYS],
+            damage: None,
+            drawn: Vec::new(),
+            redraw: true,
+            keybuf: Debounced::default(),
+            keymap: Keymap::default(),
+            palette: None,
+            cell_mode: CellMode::default(),
+            profiles: ProfileCycle::default(),
+            keypad_origin: None,
+            mouse_key: None,
+            machine_keys: None,
+            key_state_origin: (1, 1),
+            turbo: false,
+            panel: Vec::new(),
+            panel_origin: (1, 1),
+            command: CommandLine::default(),
+            toast: None,
+            stats: String::new(),
+            bell: Bell::default(),
         };
 
+        // Resizes are still caught as frames are drawn without it
+        let _ = resize::watch();
         write!(t.screen, "{}", termion::cursor::Hide).unwrap();
+        t.enhanced = t.query(kitty::QUERY, kitty::supported).unwrap_or(false);
+        t.graphics = t.query(graphics::QUERY, graphics::detect).flatten();
+        if t.enhanced {
+            write!(t.screen, "{}", kitty::ENABLE).unwrap();
+        }
         t.screen.flush().unwrap();
 
         t
     }
 
-    #[inline]
-    fn expire_key_presses(&mut self) {
-        for (mut key, timer) in self
-            .keybuf
-            .iter_mut()
-            .zip(self.key_expire.iter_mut())
-            .filter(|(key, timer)| {
-                *key == KEY_DOWN && timer.elapsed().as_millis() >= DEBOUNCE_TIMEOUT as u128
-            })
-        {
-            *key = KEY_UP;
-            *timer = Instant::now();
+    // Send the terminal `query`, giving it up to `QUERY_TIMEOUT` to answer; `answer` makes
+    // sense of what has come back so far, once there's enough of it
+    fn query<T>(&mut self, query: &str, answer: fn(&[u8]) -> Option<T>) -> Option<T> {
+        write!(self.screen, "{query}").unwrap();
+        self.screen.flush().unwrap();
+
+        let start = Instant::now();
+        let mut response = Vec::new();
+        while start.elapsed() < QUERY_TIMEOUT {
+            tty_input().read_to_end(&mut response).unwrap();
+            if let Some(answer) = answer(&response) {
+                return Some(answer);
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        None
+    }
+
+    // Pick up a change in the terminal size, clearing the screen for the next frame to be
+    // laid out from scratch; returns whether there was one
+    fn check_resize(&mut self) -> bool {
+        match termion::terminal_size() {
+            Ok(term_size) if term_size != self.term_size => {
+                self.term_size = term_size;
+                self.clear_screen();
+                self.redraw = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Clear the screen, kitty images included
+    fn clear_screen(&mut self) {
+        write!(self.screen, "{}", termion::clear::All).unwrap();
+        if self.graphics == Some(Protocol::Kitty) {
+            write!(self.screen, "{}", graphics::KITTY_DELETE).unwrap();
+        }
+    }
+
+    // Parse the bytes read from the tty into events, along with what happened to the key
+    // for key events; without the kitty keyboard protocol, every key event is a press
+    fn parse_inputs(&self, inputs: &[u8]) -> Vec<(Event, KeyAction)> {
+        let mut events = Vec::new();
+        let mut rest = inputs;
+        while let Some((&byte, tail)) = rest.split_first() {
+            if self.enhanced {
+                if let Some((key, action, len)) = kitty::parse_key(rest) {
+                    events.push((Event::Key(key), action));
+                    rest = &rest[len..];
+                    continue;
+                }
+            }
+
+            let mut bytes = tail.iter().map(|b| Ok(*b));
+            if let Ok(event) = termion::event::parse_event(byte, &mut bytes) {
+                events.push((event, KeyAction::Press));
+            }
+            rest = &tail[tail.len() - bytes.len()..];
+        }
+        events
+    }
+
+    // Apply the current profile, keeping the rest around for cycling
+    pub fn set_profiles(&mut self, profiles: Profiles) {
+        let profile = self.profiles.set(profiles);
+        self.apply_profile(&profile);
+    }
+
+    fn apply_profile(&mut self, profile: &Profile) {
+        self.keymap = profile.keymap;
+        self.palette = profile.palette;
+        self.redraw = true;
+        if self.cell_mode != profile.render {
+            self.cell_mode = profile.render;
+            self.frame_dims = (0, 0);
+        }
+        if self.keypad_origin.is_some() != profile.keypad {
+            self.show_keypad(profile.keypad);
+            // Force a clear on the next frame since the layout changes
+            self.frame_dims = (0, 0);
+        }
+    }
+
+    // Interactive remapping flow: prompt for the host key of each CHIP-8 key in turn.
+    // Returns None if the user cancels with Esc.
+    pub fn remap_keys(&mut self) -> Option<Keymap> {
+        use termion::cursor::Goto;
+
+        let mut remap = Remap::default();
+        let keymap = loop {
+            let (w, h) = self.term_size;
+            let prompt = remap.prompt();
+            self.clear_screen();
+            write!(
+                self.screen,
+                "{}{}{}{}",
+                Goto(w.saturating_sub(prompt.len() as u16) / 2 + 1, h / 2),
+                prompt,
+                Goto(
+                    w.saturating_sub(Remap::HINT.len() as u16) / 2 + 1,
+                    h / 2 + 2
+                ),
+                Remap::HINT,
+            )
+            .unwrap();
+            self.screen.flush().unwrap();
+
+            let host = 'read: loop {
+                let mut inputs = Vec::new();
+                tty_input().read_to_end(&mut inputs).unwrap();
+
+                for event in self.parse_inputs(&inputs) {
+                    match event {
+                        (Event::Key(Key::Char(c)), KeyAction::Press) => break 'read Some(c),
+                        (Event::Key(Key::Esc | Key::Ctrl('c')), KeyAction::Press) => {
+                            break 'read None
+                        }
+                        _ => (),
+                    }
+                }
+
+                thread::sleep(Duration::from_millis(10));
+            };
+            match remap.feed(host) {
+                RemapStep::Pending => (),
+                RemapStep::Done(keymap) => break Some(keymap),
+                RemapStep::Cancelled => break None,
+            }
+        };
+
+        self.clear_screen();
+        self.redraw = true;
+        keymap
+    }
+
+    // Show a clickable CHIP-8 keypad next to the display
+    pub fn show_keypad(&mut self, show: bool) {
+        // The actual position is resolved on the next frame
+        self.keypad_origin = show.then_some((0, 0));
+    }
+
+    // Draw the text panel
+    fn draw_panel(&mut self) {
+        use termion::cursor::Goto;
+
+        let (ox, oy) = self.panel_origin;
+        write!(
+            self.screen,
+            "{}{}",
+            color::Fg(color::Reset),
+            color::Bg(color::Reset)
+        )
+        .unwrap();
+        for (row, line) in tui_layout::padded_panel(&self.panel).enumerate() {
+            write!(self.screen, "{}{line}", Goto(ox, oy + row as u16)).unwrap();
+        }
+    }
+
+    // Draw the notification in the top-right corner, or blank it out once it expires
+    fn draw_toast(&mut self, expired: bool) {
+        use termion::cursor::Goto;
+
+        let Some((text, _)) = &self.toast else {
+            return;
+        };
+        let text = format!(" {text} ");
+        let x = self.term_size.0.saturating_sub(text.chars().count() as u16) + 1;
+
+        match expired {
+            true => write!(
+                self.screen,
+                "{}{}{}{:width$}",
+                Goto(x, 1),
+                color::Fg(color::Reset),
+                color::Bg(color::Reset),
+                "",
+                width = text.chars().count()
+            ),
+            false => write!(
+                self.screen,
+                "{}{}{}{text}{}{}",
+                Goto(x, 1),
+                color::Fg(color::Black),
+                color::Bg(color::White),
+                color::Fg(color::Reset),
+                color::Bg(color::Reset)
+            ),
+        }
+        .unwrap();
+        if expired {
+            self.toast = None;
+            // It may have been over the display
+            self.redraw = true;
+        }
+    }
+
+    // Draw the live rates in the top-left corner
+    fn draw_stats(&mut self) {
+        use termion::cursor::Goto;
+
+        if self.stats.is_empty() {
+            return;
+        }
+        write!(
+            self.screen,
+            "{}{}{} {} {}{}",
+            Goto(1, 1),
+            color::Fg(color::Black),
+            color::Bg(color::White),
+            self.stats,
+            color::Fg(color::Reset),
+            color::Bg(color::Reset)
+        )
+        .unwrap();
+    }
+
+    // Placeholder centered in place of the display while the terminal is smaller than the
+    // `(w, h)` it needs
+    fn draw_too_small(&mut self, (w, h): (u16, u16)) {
+        use termion::cursor::Goto;
+
+        let text = format!(" Terminal too small (needs {w}x{h}) ");
+        let (term_w, term_h) = self.term_size;
+        write!(
+            self.screen,
+            "{}{}{}{text}{}{}",
+            Goto(
+                term_w.saturating_sub(text.len() as u16) / 2 + 1,
+                term_h / 2 + 1
+            ),
+            color::Fg(color::Black),
+            color::Bg(color::White),
+            color::Fg(color::Reset),
+            color::Bg(color::Reset)
+        )
+        .unwrap();
+    }
+
+    // Draw the console prompt on the bottom row, or clear it once the command is done
+    fn draw_prompt(&mut self) {
+        use termion::{clear, cursor::Goto};
+
+        write!(
+            self.screen,
+            "{}{}{}{}",
+            Goto(1, self.term_size.1),
+            color::Fg(color::Reset),
+            color::Bg(color::Reset),
+            clear::CurrentLine
+        )
+        .unwrap();
+        self.redraw = true;
+        if let Some(command) = self.command.typing() {
+            write!(self.screen, ":{command}").unwrap();
+        }
+    }
+
+    // Feed a key to the console command being typed; returns whether it was entered
+    fn edit_command(&mut self, key: Key) -> bool {
+        let edit = match key {
+            Key::Char('\n') => Edit::Submit,
+            Key::Char(c) => Edit::Insert(c),
+            Key::Backspace => Edit::Backspace,
+            Key::Esc | Key::Ctrl('c') => Edit::Cancel,
+            _ => return false,
+        };
+        let entered = self.command.edit(edit);
+        self.draw_prompt();
+        entered
+    }
+
+    // Draw the keypad overlay with currently pressed keys highlighted
+    fn draw_keypad(&mut self) {
+        use termion::cursor::Goto;
+
+        let Some((ox, oy)) = self.keypad_origin else {
+            return;
+        };
+        let (border, keys_down) = (tui_layout::KEYPAD_BORDER, self.keybuf.keys());
+
+        write!(
+            self.screen,
+            "{}{}",
+            color::Fg(color::Reset),
+            color::Bg(color::Reset)
+        )
+        .unwrap();
+        for (row, keys) in KEYPAD_ORDER.chunks(4).enumerate() {
+            let y = oy + row as u16 * tui_layout::KEYPAD_CELL_HEIGHT;
+            write!(self.screen, "{}{}{}|", Goto(ox, y), border, Goto(ox, y + 1)).unwrap();
+            for &key in keys {
+                if keys_down[key] == KEY_DOWN {
+                    write!(
+                        self.screen,
+                        "{}{} {:X} {}{}|",
+                        color::Fg(color::Black),
+                        color::Bg(color::White),
+                        key,
+                        color::Fg(color::Reset),
+                        color::Bg(color::Reset),
+                    )
+                    .unwrap();
+                } else {
+                    write!(self.screen, " {:X} |", key).unwrap();
+                }
+            }
         }
+        write!(
+            self.screen,
+            "{}{}",
+            Goto(ox, oy + tui_layout::KEYPAD_HEIGHT - 1),
+            border
+        )
+        .unwrap();
     }
 
-    #[inline]
-    fn set_and_time_key(&mut self, idx: usize) {
-        self.keybuf.set(idx, KEY_DOWN);
-        self.key_expire[idx] = Instant::now();
+    // Draw the keys the machine sees held under the display, if the keypad is shown; set
+    // against the keypad's, they show what debouncing and the keymap make of key presses
+    fn draw_key_state(&mut self) {
+        use termion::cursor::Goto;
+
+        let (Some(_), Some((held, wait))) = (self.keypad_origin, self.machine_keys) else {
+            return;
+        };
+        let (ox, oy) = self.key_state_origin;
+        let title = tui_layout::key_state_title(wait);
+
+        write!(
+            self.screen,
+            "{}{}{}{title:^width$}",
+            color::Fg(color::Reset),
+            color::Bg(color::Reset),
+            Goto(ox, oy),
+            width = tui_layout::KEY_STATE_WIDTH as usize
+        )
+        .unwrap();
+        for (row, keys) in KEYPAD_ORDER.chunks(4).enumerate() {
+            write!(self.screen, "{}", Goto(ox, oy + 1 + row as u16)).unwrap();
+            for &key in keys {
+                match tui_layout::key_mark(key, held, wait) {
+                    Some(KeyMark::Awaited) => write!(
+                        self.screen,
+                        "{}{} {:X} {}{}",
+                        color::Fg(color::Black),
+                        color::Bg(color::Yellow),
+                        key,
+                        color::Fg(color::Reset),
+                        color::Bg(color::Reset),
+                    ),
+                    Some(KeyMark::Held) => write!(
+                        self.screen,
+                        "{}{} {:X} {}{}",
+                        color::Fg(color::Black),
+                        color::Bg(color::White),
+                        key,
+                        color::Fg(color::Reset),
+                        color::Bg(color::Reset),
+                    ),
+                    None => write!(self.screen, " {:X} ", key),
+                }
+                .unwrap();
+            }
+        }
+    }
+}
+
+// Size (w, h) in pixels of each cell of a terminal `term_size` cells large, if the terminal
+// says how many pixels it is
+fn cell_pixels(term_size: (u16, u16)) -> Option<(usize, usize)> {
+    let (w, h) = termion::terminal_size_pixels().ok()?;
+    let cell = (
+        (w / term_size.0.max(1)) as usize,
+        (h / term_size.1.max(1)) as usize,
+    );
+    (cell.0 > 0 && cell.1 > 0).then_some(cell)
+}
+
+impl Drop for Termion {
+    fn drop(&mut self) {
+        // The rest of the terminal state is restored as `screen` is dropped
+        if self.enhanced {
+            let _ = write!(self.screen, "{}", kitty::DISABLE);
+        }
+        if self.graphics == Some(Protocol::Kitty) {
+            let _ = write!(self.screen, "{}", graphics::KITTY_DELETE);
+        }
+        let _ = self.screen.flush();
     }
 }
 
 impl InputDevice for Termion {
-    //
-    //    Keyboard                   CHIP-8
-    //    +---+---+---+---+          +---+---+---+---+
-    //    | 1 | 2 | 3 | 4 |          | 1 | 2 | 3 | C |
-    //    +---+---+---+---+          +---+---+---+---+
-    //    | Q | W | E | R |          | 4 | 5 | 6 | D |
-    //    +---+---+---+---+    =>    +---+---+---+---+
-    //    | A | S | D | F |          | 7 | 8 | 9 | E |
-    //    +---+---+---+---+          +---+---+---+---+
-    //    | Z | X | C | V |          | A | 0 | B | F |
-    //    +---+---+---+---+          +---+---+---+---+
-    //
+    // Host keys are translated to CHIP-8 keys through `Keymap` (QWERTY layout by default)
     fn handle_inputs(&mut self) -> Signal {
-        let prev_state = self.keybuf;
-        self.expire_key_presses();
+        let prev_state = self.keybuf.keys();
+        if !self.enhanced {
+            self.keybuf.expire();
+        }
+
+        // Checked here since frames don't necessarily keep coming in
+        if matches!(&self.toast, Some((_, shown)) if shown.elapsed() >= NOTIFICATION_DURATION) {
+            self.draw_toast(true);
+            self.screen.flush().unwrap();
+        }
+        // Emulator hotkeys take precedence over keypad updates
+        let mut hotkey = Signal::None;
+        // Have the frame sent again right away, however long the next one takes to come
+        if resize::take() && self.check_resize() {
+            hotkey = Signal::Resized;
+        }
 
         let mut inputs = Vec::new();
         // Drain all inputs from stdin
-        self.stdin.read_to_end(&mut inputs).unwrap();
-        inputs.dedup();
-
-        for byte in inputs {
-            match byte {
-                b'1' => self.set_and_time_key(0x1),
-                b'2' => self.set_and_time_key(0x2),
-                b'3' => self.set_and_time_key(0x3),
-                b'4' => self.set_and_time_key(0xC),
-                b'q' => self.set_and_time_key(0x4),
-                b'w' => self.set_and_time_key(0x5),
-                b'e' => self.set_and_time_key(0x6),
-                b'r' => self.set_and_time_key(0xD),
-                b'a' => self.set_and_time_key(0x7),
-                b's' => self.set_and_time_key(0x8),
-                b'd' => self.set_and_time_key(0x9),
-                b'f' => self.set_and_time_key(0xE),
-                b'z' => self.set_and_time_key(0xA),
-                b'x' => self.set_and_time_key(0x0),
-                b'c' => self.set_and_time_key(0xB),
-                b'v' => self.set_and_time_key(0xF),
-                // Esc (ASCII 0x1B) and ^C (ASCII 0x03) to signal program exit
-                0x03 | 0x1B => {
+        tty_input().read_to_end(&mut inputs).unwrap();
+
+        // Held mouse presses don't repeat, so keep them alive until released
+        if let Some(key) = self.mouse_key {
+            self.keybuf.press(key);
+        }
+
+        for (event, action) in self.parse_inputs(&inputs) {
+            // Typing a console command takes over the keyboard until it's entered or
+            // cancelled with Esc
+            if let (Event::Key(key), Some(_)) = (&event, self.command.typing()) {
+                if action != KeyAction::Release && self.edit_command(*key) {
+                    hotkey = Signal::DebugCommand;
+                }
+                continue;
+            }
+
+            match event {
+                // Only keypad keys are held; everything else acts on the press (or repeat)
+                Event::Key(Key::Char(c)) if action == KeyAction::Release => {
+                    if let Some(key) = self.keymap.lookup(c) {
+                        self.keybuf.release(key);
+                    }
+                    if c == '\t' && self.turbo {
+                        self.turbo = false;
+                        hotkey = Signal::TurboOff;
+                    }
+                }
+                Event::Key(_) if action == KeyAction::Release => (),
+                Event::Key(Key::Char(c)) => match self.keymap.lookup(c) {
+                    Some(key) => self.keybuf.press(key),
+                    None => match c {
+                        'm' => hotkey = Signal::MarkState,
+                        'n' => hotkey = Signal::RestoreMark,
+                        'o' => hotkey = Signal::RotateDisplay,
+                        'h' => hotkey = Signal::MirrorDisplay,
+                        'p' => {
+                            if let Some(profile) = self.profiles.cycle() {
+                                self.apply_profile(&profile);
+                                hotkey = Signal::CycleProfile;
+                            }
+                        }
+                        ' ' => hotkey = Signal::Pause,
+                        '.' => hotkey = Signal::Step,
+                        ',' => hotkey = Signal::FrameAdvance,
+                        '=' | '+' => hotkey = Signal::SpeedUp,
+                        '-' => hotkey = Signal::SpeedDown,
+                        '0' => hotkey = Signal::VolumeUp,
+                        '9' => hotkey = Signal::VolumeDown,
+                        '8' => hotkey = Signal::ToggleMute,
+                        // Turbo lasts for as long as Tab is held where releases are reported,
+                        // and is toggled by it otherwise
+                        '\t' if action == KeyAction::Press => {
+                            self.turbo = !self.turbo;
+                            hotkey = match self.turbo {
+                                true => Signal::TurboOn,
+                                false => Signal::TurboOff,
+                            };
+                        }
+                        'b' => hotkey = Signal::ToggleBreakpoint,
+                        '[' => hotkey = Signal::ScrollMemoryUp,
+                        ']' => hotkey = Signal::ScrollMemoryDown,
+                        ':' => {
+                            self.command.open();
+                            self.draw_prompt();
+                        }
+                        _ => (),
+                    },
+                },
+                Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y)) => {
+                    // Mouse events are 1-based, like `keypad_origin`
+                    self.mouse_key = self
+                        .keypad_origin
+                        .and_then(|origin| tui_layout::keypad_hit(origin, (x, y)));
+                    if let Some(key) = self.mouse_key {
+                        self.keybuf.press(key);
+                    }
+                }
+                Event::Mouse(MouseEvent::Release(..)) => {
+                    // Without expiry, the key has to be let go of here
+                    if let (Some(key), true) = (self.mouse_key.take(), self.enhanced) {
+                        self.keybuf.release(key);
+                    }
+                }
+                Event::Key(Key::F(2)) => hotkey = Signal::Reset,
+                Event::Key(Key::F(5)) => hotkey = Signal::SaveState,
+                Event::Key(Key::F(7)) => hotkey = Signal::LoadState,
+                Event::Key(Key::F(8)) => hotkey = Signal::SwitchFrontend,
+                Event::Key(Key::F(9)) => hotkey = Signal::ToggleRecording,
+                // Rewinding relies on key repeat, whether or not releases are reported
+                Event::Key(Key::Backspace) => hotkey = Signal::Rewind,
+                // Esc and ^C to signal program exit
+                Event::Key(Key::Esc) | Event::Key(Key::Ctrl('c')) => {
                     write!(self.screen, "{}", termion::cursor::Show).unwrap();
                     return Signal::ProgramExit;
                 }
@@ -137,7 +760,14 @@ impl InputDevice for Termion {
             }
         }
 
-        if self.keybuf != prev_state {
+        let changed = self.keybuf.keys() != prev_state;
+        if changed {
+            self.draw_keypad();
+        }
+
+        if hotkey != Signal::None {
+            hotkey
+        } else if changed {
             Signal::NewInputs
         } else {
             Signal::None
@@ -145,7 +775,11 @@ impl InputDevice for Termion {
     }
 
     fn send_inputs(&self) -> Option<InputMsg> {
-        Some(self.keybuf)
+        Some(self.keybuf.keys())
+    }
+
+    fn take_command(&mut self) -> Option<String> {
+        self.command.take()
     }
 
     fn device_info(&self) -> InputInfo {
@@ -154,53 +788,224 @@ impl InputDevice for Termion {
 }
 
 impl DisplayDevice for Termion {
-    fn receive_frame(&mut self, frame: &BitSlice<usize>) -> &mut dyn DisplayDevice {
-        use termion::color;
+    fn receive_damage(&mut self, damage: Rect) -> &mut dyn DisplayDevice {
+        self.damage = Some(damage);
+        self
+    }
+
+    fn receive_frame(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice {
         // Clear screen before sending next frame if terminal has resized
-        // TODO: if-let chains (https://github.com/rust-lang/rust/issues/53667)
-        if let Ok(term_size) = termion::terminal_size() {
-            if self.term_size != term_size {
-                self.term_size = term_size;
-                write!(self.screen, "{}", termion::clear::All).unwrap();
-            }
-        }
+        self.check_resize();
 
-        let (x_offset, y_offset) = (
-            self.term_size.0.saturating_sub(DISPLAY_WIDTH as u16) / 2,
-            self.term_size.1.saturating_sub(DISPLAY_HEIGHT as u16) / 2,
-        );
+        if self.frame_dims != dims {
+            self.frame_dims = dims;
+            self.clear_screen();
+            self.redraw = true;
+        }
 
+        // The keypad overlay, if shown, is centered together with the display, and so are
+        // the key state under it and the panel
+        let keypad = self.keypad_origin.is_some();
+        let panel_width = tui_layout::panel_width(&self.panel);
+        // Scale the display up as far as it fits beside the keypad and panel
+        let (term_w, term_h) = self.term_size;
+        let room = Layout::room(self.term_size, keypad, panel_width);
+        // Images are laid out in cells too, going by the size in pixels the terminal gives
+        // them; they're drawn as halfblock cells where it has no say
+        let image = match (self.cell_mode, self.graphics) {
+            (CellMode::Image, Some(protocol)) => {
+                cell_pixels(self.term_size).map(|cell| (protocol, cell))
+            }
+            _ => None,
+        };
+        let (scale, (width, height)) = match image {
+            Some((protocol, cell)) => {
+                // A sixel image reaching down to the bottom row scrolls the screen up
+                let rows = match protocol {
+                    Protocol::Sixel => room.1.saturating_sub(1),
+                    Protocol::Kitty => room.1,
+                };
+                let scale = Some((room.0 * cell.0 / dims.0).min(rows * cell.1 / dims.1))
+                    .filter(|&scale| scale > 0);
+                let (w, h) = (dims.0 * scale.unwrap_or(1), dims.1 * scale.unwrap_or(1));
+                (scale, (w.div_ceil(cell.0), h.div_ceil(cell.1)))
+            }
+            None => {
+                let scale = self.cell_mode.fit_scale(dims, room);
+                (scale, self.cell_mode.cells(dims, scale.unwrap_or(1)))
+            }
+        };
         self.framebuf.clear();
+        // Nothing of the display is drawn while it doesn't fit, just a placeholder (see
+        // `draw_too_small`), so the screen starts over whenever that changes
+        let Some(scale) = scale else {
+            if self.too_small.is_none() {
+                self.clear_screen();
+            }
+            self.too_small = Some(Layout::needed((width, height), keypad, panel_width));
+            self.damage = None;
+            self.redraw = true;
+            return self;
+        };
+        if self.too_small.take().is_some() {
+            self.clear_screen();
+        }
+
+        // Laid out 0-based, while termion's cursor positions are 1-based
+        let layout = Layout::new(self.term_size, (width, height), keypad, panel_width);
+        let (x_offset, y_offset) = layout.display;
+        let one_based = |(x, y): (u16, u16)| (x + 1, y + 1);
+        self.panel_origin = one_based(layout.panel);
+        self.key_state_origin = one_based(layout.key_state);
+        self.keypad_origin = layout.keypad.map(one_based);
 
-        for (idx, pixel) in frame.iter().enumerate() {
-            // TODO: dynamic scaling with self.term_size?
-            if idx % DISPLAY_WIDTH == 0 {
+        if let Some((protocol, _)) = image {
+            // Redrawn whenever the frame changes, as a whole
+            let changed = self.damage.take().is_some() || self.redraw;
+            if changed {
+                let (w, h) = (dims.0 * scale, dims.1 * scale);
+                let pixels = (0..w * h)
+                    .map(|i| pixel_color(planes, (i / w / scale) * dims.0 + i % w / scale) as u8)
+                    .collect::<Vec<_>>();
+                let palette = self.palette.unwrap_or(DEFAULT_IMAGE_PALETTE);
                 write!(
                     self.framebuf,
-                    "{}",
-                    termion::cursor::Goto(
-                        x_offset + 1,
-                        y_offset + 1 + (idx / DISPLAY_WIDTH) as u16
-                    )
+                    "{}{}",
+                    termion::cursor::Goto(x_offset + 1, y_offset + 1),
+                    protocol.encode(&pixels, w, &palette)
                 )
                 .unwrap();
+                self.redraw = false;
             }
-            // https://en.wikipedia.org/wiki/ANSI_escape_code#8-bit
-            match *pixel {
-                PX_OFF => {
-                    self.framebuf += &format!("{}█", color::Fg(color::Black));
+            self.drawn.clear();
+            return self;
+        }
+
+        let visible = (
+            width.min(term_w.saturating_sub(x_offset) as usize),
+            height.min(term_h.saturating_sub(y_offset) as usize),
+        );
+        // Only the cells the frame changed in need looking at, unless the screen is stale
+        let region = match (self.damage.take(), self.redraw) {
+            (Some(damage), false) => self.cell_mode.cell_rect(damage, scale),
+            _ => Rect::full((width, height)),
+        };
+        if self.redraw || self.drawn.len() != width * height {
+            self.drawn.clear();
+            self.drawn.resize(width * height, None);
+        }
+        self.redraw = false;
+        // Of those, cells already showing what they should are skipped, and the cursor and
+        // colors are only sent when they change
+        let mut cursor = None;
+        let mut pen = None;
+        let cols = region.x..(region.x + region.w).min(visible.0);
+        for y in region.y..(region.y + region.h).min(visible.1) {
+            for x in cols.clone() {
+                let cell = self.cell_mode.cell(planes, dims, scale, x, y);
+                let drawn = &mut self.drawn[y * width + x];
+                if *drawn == Some(cell) {
+                    continue;
                 }
-                PX_ON => {
-                    self.framebuf += &format!("{}█", color::Fg(color::White));
+                *drawn = Some(cell);
+
+                if cursor != Some((x, y)) {
+                    write!(
+                        self.framebuf,
+                        "{}",
+                        termion::cursor::Goto(x_offset + 1 + x as u16, y_offset + 1 + y as u16)
+                    )
+                    .unwrap();
                 }
+                if pen != Some((cell.fg, cell.bg)) {
+                    match self.palette {
+                        Some(palette) => {
+                            let (fg, bg) = (palette.rgb(cell.fg), palette.rgb(cell.bg));
+                            write!(
+                                self.framebuf,
+                                "{}{}",
+                                color::Fg(color::Rgb(fg.0, fg.1, fg.2)),
+                                color::Bg(color::Rgb(bg.0, bg.1, bg.2))
+                            )
+                        }
+                        None => write!(
+                            self.framebuf,
+                            "{}{}",
+                            color::Fg(DEFAULT_COLORS[cell.fg]),
+                            color::Bg(DEFAULT_COLORS[cell.bg])
+                        ),
+                    }
+                    .unwrap();
+                    pen = Some((cell.fg, cell.bg));
+                }
+                self.framebuf.push(cell.ch);
+                cursor = Some((x + 1, y));
             }
         }
+        write!(
+            self.framebuf,
+            "{}{}",
+            color::Fg(color::Reset),
+            color::Bg(color::Reset)
+        )
+        .unwrap();
 
         self
     }
 
+    fn receive_panel(&mut self, lines: &[String]) {
+        if lines != self.panel {
+            let old_width = tui_layout::panel_width(&self.panel);
+            self.panel = lines.to_vec();
+            // Resizing the panel moves the display over, so start from a clean screen
+            if tui_layout::panel_width(&self.panel) != old_width {
+                self.frame_dims = (0, 0);
+            }
+        }
+    }
+
+    fn receive_status(&mut self, status: &StatusMsg) {
+        self.machine_keys = Some((status.keys, status.key_wait));
+    }
+
+    fn receive_notification(&mut self, text: &str) {
+        // Blank out the last one first, in case it was longer
+        self.draw_toast(true);
+        self.toast = Some((text.to_owned(), Instant::now()));
+        self.draw_toast(false);
+    }
+
+    fn receive_stats(&mut self, text: &str) {
+        if text == self.stats {
+            return;
+        }
+        // Blank out the last ones first, in case they were longer
+        let width = self.stats.chars().count() + 2;
+        if text.chars().count() + 2 < width {
+            write!(self.screen, "{}{:width$}", termion::cursor::Goto(1, 1), "").unwrap();
+            // They may have been over the display
+            self.redraw = true;
+        }
+        self.stats = text.to_owned();
+    }
+
     fn drive_display(&mut self) {
         write!(self.screen, "{}", self.framebuf).unwrap();
+        match self.too_small {
+            // The display, keypad, and panel would land off screen
+            Some(size) => self.draw_too_small(size),
+            None => {
+                self.draw_keypad();
+                self.draw_key_state();
+                self.draw_panel();
+                self.draw_stats();
+            }
+        }
+        self.draw_toast(false);
     }
 
     fn device_info(&self) -> DisplayInfo {
@@ -209,8 +1014,8 @@ impl DisplayDevice for Termion {
 }
 
 impl AudioDevice for Termion {
-    fn receive_signal(&mut self, data: bool) -> &mut dyn AudioDevice {
-        if data {
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice {
+        if self.bell.ring(data) {
             write!(self.screen, "\x07").unwrap();
         }
 
@@ -219,6 +1024,10 @@ impl AudioDevice for Termion {
 
     fn play_audio(&mut self) {}
 
+    fn set_volume(&mut self, volume: f32) {
+        self.bell.set_volume(volume);
+    }
+
     fn device_info(&self) -> AudioInfo {
         AudioInfo::Termion
     }
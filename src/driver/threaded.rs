@@ -0,0 +1,470 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use bitvec::{bitarr, slice::BitSlice, vec::BitVec};
+use smallvec::SmallVec;
+
+use crate::{
+    chip8::NUM_KEYS,
+    driver::{interrupt, AudioMsg, InputMsg, Rect, StatusMsg, MAX_FRAME_PLANES},
+    driver::{AudioDevice, AudioInfo, DisplayDevice, DisplayInfo, InputDevice, InputInfo},
+    emulator::Signal,
+};
+
+// How often the frontend's input device is polled
+const POLL_INTERVAL: Duration = Duration::from_millis(4);
+
+// Devices that let the emulator run on a thread of its own, away from the frontend's
+// devices: the frontend's thread polls its input device and passes what comes up to the
+// emulator's thread, which sends back frames and sound to be presented over channels
+//
+//   Slow terminal writes and window updates then hold up only the frontend's thread, not
+//   the machine's timing, and frontends whose event loops have to run on the main thread
+//   get to keep it. Frames the frontend can't keep up with are skipped.
+//
+//   let (frontend, (input, display, audio)) = threaded::split(&tui, &tui, &audio);
+//   thread::scope(|s| {
+//       s.spawn(move || { /* run an `Emulator` with `input`, `display`, and `audio` */ });
+//       let mut link = frontend.run();
+//       // The user asked to switch frontends (`Signal::SwitchFrontend`)
+//       while let Some(next) = link {
+//           link = next.attach(&gui, &gui, &audio).run();
+//       }
+//   });
+//
+
+// Sent from the frontend's thread to the emulator's
+enum Input {
+    // Updated state of every key
+    Keys(InputMsg),
+    // Emulator hotkey, e.g. `Signal::Pause`
+    Hotkey(Signal),
+    // Debugger console command (see `InputDevice::take_command`)
+    Command(String),
+}
+
+// Sent from the emulator's thread to the frontend's
+enum Output {
+    Frame(FrameMsg),
+    Panel(Vec<String>),
+    Notification(String),
+    Status(StatusMsg),
+    Stats(String),
+    Audio(AudioMsg),
+    Volume(f32),
+}
+
+// What the frontend's devices say they are, passed off as the emulator's devices' own
+#[derive(Clone, Copy)]
+struct Infos {
+    input: InputInfo,
+    display: DisplayInfo,
+    audio: AudioInfo,
+}
+
+// Bitplanes of a frame of `dims` (w, h) pixels, and the region that changed since the last
+struct FrameMsg {
+    planes: Vec<BitVec<usize>>,
+    dims: (usize, usize),
+    damage: Rect,
+}
+
+// Link the frontend's devices to devices an emulator can be run with on another thread;
+// the frontend's end has to be `run` on this thread meanwhile
+pub fn split<'a, I, D, A>(
+    input: &'a RefCell<I>,
+    display: &'a RefCell<D>,
+    audio: &'a RefCell<A>,
+) -> (
+    Frontend<'a, I, D, A>,
+    (ThreadInput, ThreadDisplay, ThreadAudio),
+)
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    let (link, devices) = link();
+    (link.attach(input, display, audio), devices)
+}
+
+// Devices an emulator can be run with on another thread, linked to no frontend yet (see
+// `Link::attach`)
+pub fn link() -> (Link, (ThreadInput, ThreadDisplay, ThreadAudio)) {
+    let (input_tx, input_rx) = mpsc::channel();
+    let (output_tx, output_rx) = mpsc::channel();
+    let infos = Arc::new(Mutex::new(Infos {
+        input: InputInfo::None,
+        display: DisplayInfo::None,
+        audio: AudioInfo::None,
+    }));
+
+    let devices = (
+        ThreadInput {
+            rx: input_rx,
+            keybuf: bitarr![0; NUM_KEYS],
+            reported: bitarr![0; NUM_KEYS],
+            pending: VecDeque::new(),
+            command: None,
+            infos: Arc::clone(&infos),
+        },
+        ThreadDisplay {
+            tx: output_tx.clone(),
+            damage: None,
+            panel: Vec::new(),
+            infos: Arc::clone(&infos),
+        },
+        ThreadAudio {
+            tx: output_tx,
+            tone: AudioMsg::Beep(false),
+            infos: Arc::clone(&infos),
+        },
+    );
+    let link = Link {
+        tx: input_tx,
+        rx: output_rx,
+        infos,
+        panel: Vec::new(),
+        notice: None,
+    };
+    (link, devices)
+}
+
+// Frontend's end of the link between threads, while no frontend is attached to it
+pub struct Link {
+    tx: Sender<Input>,
+    rx: Receiver<Output>,
+    infos: Arc<Mutex<Infos>>,
+    // Panel last received, for the next frontend to pick up from
+    panel: Vec<String>,
+    // Notification for the next frontend to show
+    notice: Option<String>,
+}
+
+impl Link {
+    // Have the frontend's devices take over from any attached before, sent the current frame
+    // and sound
+    pub fn attach<'a, I, D, A>(
+        self,
+        input: &'a RefCell<I>,
+        display: &'a RefCell<D>,
+        audio: &'a RefCell<A>,
+    ) -> Frontend<'a, I, D, A>
+    where
+        I: InputDevice,
+        D: DisplayDevice,
+        A: AudioDevice,
+    {
+        *self.infos.lock().unwrap() = Infos {
+            input: input.borrow().device_info(),
+            display: display.borrow().device_info(),
+            audio: audio.borrow().device_info(),
+        };
+        {
+            let mut display = display.borrow_mut();
+            display.receive_panel(&self.panel);
+            if let Some(text) = &self.notice {
+                display.receive_notification(text);
+            }
+        }
+        // Keys held down on the devices attached before can't be let go of on these
+        let _ = self.tx.send(Input::Keys(bitarr![0; NUM_KEYS]));
+        let _ = self.tx.send(Input::Hotkey(Signal::DisplayAttached));
+
+        Frontend {
+            input,
+            display,
+            audio,
+            link: Link {
+                notice: None,
+                ..self
+            },
+        }
+    }
+
+    // Leave a notification for the next frontend attached
+    pub fn notify(&mut self, text: &str) {
+        self.notice = Some(text.to_owned());
+    }
+}
+
+// Frontend's end of the link, driving its devices on the thread they belong to
+pub struct Frontend<'a, I, D, A> {
+    input: &'a RefCell<I>,
+    display: &'a RefCell<D>,
+    audio: &'a RefCell<A>,
+    link: Link,
+}
+
+impl<I, D, A> Frontend<'_, I, D, A>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    // Pass inputs on and present what comes back until the emulator's devices are dropped,
+    // i.e. once it's done running; the link is handed back if the user asks to switch
+    // frontends instead, for others to be attached to it
+    pub fn run(mut self) -> Option<Link> {
+        let mut next_poll = Instant::now();
+        loop {
+            let now = Instant::now();
+            if now >= next_poll {
+                if self.poll_input() {
+                    return Some(self.link);
+                }
+                next_poll = now + POLL_INTERVAL;
+            }
+
+            match self
+                .link
+                .rx
+                .recv_timeout(next_poll.saturating_duration_since(now))
+            {
+                Ok(output) => self.present(output),
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+
+    // Pass on what came in from the input device; returns whether the user asked to switch
+    // frontends
+    fn poll_input(&self) -> bool {
+        let mut input = self.input.borrow_mut();
+        let message = match input.handle_inputs() {
+            Signal::None => return false,
+            Signal::SwitchFrontend => return true,
+            Signal::NewInputs => match input.send_inputs() {
+                Some(keys) => Input::Keys(keys),
+                None => return false,
+            },
+            Signal::DebugCommand => match input.take_command() {
+                Some(command) => Input::Command(command),
+                None => return false,
+            },
+            hotkey => Input::Hotkey(hotkey),
+        };
+        // Fails only once the emulator is done, which `run` finds out about by itself
+        let _ = self.link.tx.send(message);
+        false
+    }
+
+    // Present `first` along with whatever else is waiting; of the frames waiting, only the
+    // latest is drawn
+    fn present(&mut self, first: Output) {
+        let mut frame: Option<FrameMsg> = None;
+
+        // Borrowed one output at a time, as the display may be the audio device too
+        for output in std::iter::once(first).chain(self.link.rx.try_iter()) {
+            match output {
+                Output::Frame(mut next) => {
+                    // Skipped frames' damage still has to be redrawn
+                    next.damage = match frame {
+                        Some(prev) if prev.dims == next.dims => prev.damage.union(next.damage),
+                        Some(_) => Rect::full(next.dims),
+                        None => next.damage,
+                    };
+                    frame = Some(next);
+                }
+                Output::Panel(lines) => {
+                    self.display.borrow_mut().receive_panel(&lines);
+                    self.link.panel = lines;
+                }
+                Output::Notification(text) => self.display.borrow_mut().receive_notification(&text),
+                Output::Status(status) => self.display.borrow_mut().receive_status(&status),
+                Output::Stats(text) => self.display.borrow_mut().receive_stats(&text),
+                Output::Audio(data) => self.audio.borrow_mut().receive_signal(data).play_audio(),
+                Output::Volume(volume) => self.audio.borrow_mut().set_volume(volume),
+            }
+        }
+
+        if let Some(frame) = frame {
+            let planes = frame
+                .planes
+                .iter()
+                .map(BitVec::as_bitslice)
+                .collect::<SmallVec<[&BitSlice<usize>; MAX_FRAME_PLANES]>>();
+            self.display
+                .borrow_mut()
+                .receive_damage(frame.damage)
+                .receive_frame(&planes, frame.dims)
+                .drive_display();
+        }
+    }
+}
+
+// Emulator's input device, taking inputs from the frontend's
+pub struct ThreadInput {
+    rx: Receiver<Input>,
+    // Key states last received, and last reported with `Signal::NewInputs`
+    keybuf: InputMsg,
+    reported: InputMsg,
+    // Hotkeys received that have yet to be handled, oldest first
+    pending: VecDeque<Signal>,
+    // Console command last received, until the emulator takes it
+    command: Option<String>,
+    infos: Arc<Mutex<Infos>>,
+}
+
+impl ThreadInput {
+    // Block until a frontend is first attached to the link (see `Link::attach`), or it's
+    // dropped, so the emulator doesn't run unseen meanwhile
+    pub fn wait_attached(&mut self) {
+        if let Ok(input) = self.rx.recv() {
+            self.receive(input);
+        }
+    }
+
+    fn receive(&mut self, input: Input) {
+        match input {
+            Input::Keys(keys) => self.keybuf = keys,
+            // Held hotkeys (e.g. `Signal::Rewind`) are sent on every poll of the frontend,
+            // which may be more often than the emulator calls in
+            Input::Hotkey(hotkey) if self.pending.back() == Some(&hotkey) => (),
+            Input::Hotkey(hotkey) => self.pending.push_back(hotkey),
+            Input::Command(command) => {
+                self.command = Some(command);
+                self.pending.push_back(Signal::DebugCommand);
+            }
+        }
+    }
+}
+
+impl InputDevice for ThreadInput {
+    // Takes in everything the frontend sent since the last call, handing hotkeys out one per
+    // call; the program exits once the frontend is gone, or the process is asked to
+    // terminate (see `interrupt`)
+    fn handle_inputs(&mut self) -> Signal {
+        if interrupt::interrupted() {
+            return Signal::ProgramExit;
+        }
+        loop {
+            match self.rx.try_recv() {
+                Ok(input) => self.receive(input),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => return Signal::ProgramExit,
+            }
+        }
+
+        if let Some(hotkey) = self.pending.pop_front() {
+            hotkey
+        } else if self.keybuf != self.reported {
+            self.reported = self.keybuf;
+            Signal::NewInputs
+        } else {
+            Signal::None
+        }
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        Some(self.reported)
+    }
+
+    fn take_command(&mut self) -> Option<String> {
+        self.command.take()
+    }
+
+    fn device_info(&self) -> InputInfo {
+        self.infos.lock().unwrap().input
+    }
+}
+
+// Emulator's display device, sending what it receives to the frontend's
+pub struct ThreadDisplay {
+    tx: Sender<Output>,
+    // Damage hint for the frame being received
+    damage: Option<Rect>,
+    // Panel last sent, as it's received with every frame but rarely changes
+    panel: Vec<String>,
+    infos: Arc<Mutex<Infos>>,
+}
+
+impl ThreadDisplay {
+    // Sends fail only once the frontend is gone, which the emulator hears about through
+    // `ThreadInput`
+    fn send(&self, output: Output) {
+        let _ = self.tx.send(output);
+    }
+}
+
+impl DisplayDevice for ThreadDisplay {
+    fn receive_damage(&mut self, damage: Rect) -> &mut dyn DisplayDevice {
+        self.damage = Some(damage);
+        self
+    }
+
+    fn receive_frame(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice {
+        let damage = self.damage.take().unwrap_or(Rect::full(dims));
+        self.send(Output::Frame(FrameMsg {
+            planes: planes.iter().map(|plane| plane.to_bitvec()).collect(),
+            dims,
+            damage,
+        }));
+        self
+    }
+
+    fn receive_panel(&mut self, lines: &[String]) {
+        if lines != self.panel {
+            self.panel = lines.to_vec();
+            self.send(Output::Panel(self.panel.clone()));
+        }
+    }
+
+    fn receive_notification(&mut self, text: &str) {
+        self.send(Output::Notification(text.to_owned()));
+    }
+
+    fn receive_status(&mut self, status: &StatusMsg) {
+        self.send(Output::Status(*status));
+    }
+
+    fn receive_stats(&mut self, text: &str) {
+        self.send(Output::Stats(text.to_owned()));
+    }
+
+    // Frames are sent as they're received
+    fn drive_display(&mut self) {}
+
+    fn device_info(&self) -> DisplayInfo {
+        self.infos.lock().unwrap().display
+    }
+}
+
+// Emulator's audio device, sending what it receives to the frontend's
+pub struct ThreadAudio {
+    tx: Sender<Output>,
+    // Sound that should be playing
+    tone: AudioMsg,
+    infos: Arc<Mutex<Infos>>,
+}
+
+impl AudioDevice for ThreadAudio {
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice {
+        self.tone = data;
+        self
+    }
+
+    fn play_audio(&mut self) {
+        let _ = self.tx.send(Output::Audio(self.tone));
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        let _ = self.tx.send(Output::Volume(volume));
+    }
+
+    fn device_info(&self) -> AudioInfo {
+        self.infos.lock().unwrap().audio
+    }
+}
@@ -0,0 +1,311 @@
+use std::time::Instant;
+
+use bitvec::bitarr;
+
+use crate::{
+    chip8::NUM_KEYS,
+    driver::{keymap::KEYPAD_ORDER, InputMsg, KEY_DOWN, KEY_UP},
+};
+
+// Layout and keypad state shared by the TUI backends (termion and crossterm), which draw
+// the same screen through different terminal libraries
+
+const DEBOUNCE_TIMEOUT: u32 = 100; // ms
+
+// On-screen keypad overlay, drawn to the right of the display:
+//
+//    +---+---+---+---+
+//    | 1 | 2 | 3 | C |
+//    +---+---+---+---+
+//    | 4 | 5 | 6 | D |
+//    +---+---+---+---+
+//    | 7 | 8 | 9 | E |
+//    +---+---+---+---+
+//    | A | 0 | B | F |
+//    +---+---+---+---+
+//
+pub const KEYPAD_CELL_HEIGHT: u16 = 2; // border row + key row
+const KEYPAD_CELL_WIDTH: u16 = 4; // "| X "
+const KEYPAD_WIDTH: u16 = 4 * KEYPAD_CELL_WIDTH + 1;
+pub const KEYPAD_HEIGHT: u16 = 4 * KEYPAD_CELL_HEIGHT + 1;
+const KEYPAD_MARGIN: u16 = 2; // Columns between the display and the keypad
+const PANEL_MARGIN: u16 = 2; // Columns between the display (or keypad) and the text panel
+
+// Row between the keypad's keys
+pub const KEYPAD_BORDER: &str = "+---+---+---+---+";
+
+// Keys the machine sees held, drawn under the display along with the keypad overlay; keys
+// FX0A is waiting on a release of are picked out, and it's titled "FX0A waiting" meanwhile
+//
+//    Machine keys
+//     1  2  3  C
+//     4  5  6  D
+//     7  8  9  E
+//     A  0  B  F
+//
+pub const KEY_STATE_WIDTH: u16 = 4 * 3; // " X " per key
+const KEY_STATE_HEIGHT: u16 = 5; // title + 4 key rows
+const KEY_STATE_MARGIN: u16 = 1; // Rows between the display and the key state
+
+// Where everything goes on the screen, as top-left corners (0-based): the display, the
+// keypad and the key state under the display if the keypad is shown, and the text panel
+// (e.g. the debugger's) to the right of everything else
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Layout {
+    pub display: (u16, u16),
+    pub keypad: Option<(u16, u16)>,
+    pub key_state: (u16, u16),
+    pub panel: (u16, u16),
+}
+
+impl Layout {
+    // Room (w, h) in cells the display has on a terminal `term_size` large, beside the
+    // keypad (if shown) and a panel `panel_width` columns wide
+    pub fn room(term_size: (u16, u16), keypad: bool, panel_width: u16) -> (usize, usize) {
+        let (extra_w, extra_h) = extras(keypad, panel_width);
+        (
+            term_size.0.saturating_sub(extra_w) as usize,
+            term_size.1.saturating_sub(extra_h) as usize,
+        )
+    }
+
+    // Terminal size (w, h) needed to fit a display `display` cells large and the rest
+    pub fn needed(display: (usize, usize), keypad: bool, panel_width: u16) -> (u16, u16) {
+        let (extra_w, extra_h) = extras(keypad, panel_width);
+        (display.0 as u16 + extra_w, display.1 as u16 + extra_h)
+    }
+
+    // Everything centered together on a terminal `term_size` large, around a display
+    // `display` cells large
+    pub fn new(
+        term_size: (u16, u16),
+        display: (usize, usize),
+        keypad: bool,
+        panel_width: u16,
+    ) -> Layout {
+        let (width, height) = (display.0 as u16, display.1 as u16);
+        let (extra_w, extra_h) = extras(keypad, panel_width);
+        let (x, y) = (
+            term_size.0.saturating_sub(width + extra_w) / 2,
+            term_size.1.saturating_sub(height + extra_h) / 2,
+        );
+        let keypad_width = match keypad {
+            true => KEYPAD_MARGIN + KEYPAD_WIDTH,
+            false => 0,
+        };
+
+        Layout {
+            display: (x, y),
+            keypad: keypad.then_some((
+                x + width + KEYPAD_MARGIN,
+                (y + height / 2).saturating_sub(KEYPAD_HEIGHT / 2),
+            )),
+            key_state: (
+                x + width.saturating_sub(KEY_STATE_WIDTH) / 2,
+                y + height + KEY_STATE_MARGIN,
+            ),
+            panel: (x + width + keypad_width + PANEL_MARGIN, y),
+        }
+    }
+}
+
+// Columns and rows taken up beside and under the display by the keypad (if shown) and a
+// panel `panel_width` columns wide
+fn extras(keypad: bool, panel_width: u16) -> (u16, u16) {
+    let panel_width = match panel_width {
+        0 => 0,
+        w => PANEL_MARGIN + w,
+    };
+    match keypad {
+        true => (
+            KEYPAD_MARGIN + KEYPAD_WIDTH + panel_width,
+            KEY_STATE_MARGIN + KEY_STATE_HEIGHT,
+        ),
+        false => (panel_width, 0),
+    }
+}
+
+// Keypad key drawn at terminal cell (x, y) of a keypad drawn at `origin`, if any
+pub fn keypad_hit(origin: (u16, u16), (x, y): (u16, u16)) -> Option<usize> {
+    let (dx, dy) = (x.checked_sub(origin.0)?, y.checked_sub(origin.1)?);
+
+    // Clicks landing on the keypad's borders don't count
+    if dx >= KEYPAD_WIDTH || dy >= KEYPAD_HEIGHT {
+        return None;
+    }
+    if dx % KEYPAD_CELL_WIDTH == 0 || dy % KEYPAD_CELL_HEIGHT == 0 {
+        return None;
+    }
+
+    Some(KEYPAD_ORDER[(dy / KEYPAD_CELL_HEIGHT * 4 + dx / KEYPAD_CELL_WIDTH) as usize])
+}
+
+// Columns taken up by a text panel of `lines`
+pub fn panel_width(lines: &[String]) -> u16 {
+    let width = lines.iter().map(|line| line.chars().count());
+    width.max().unwrap_or(0) as u16
+}
+
+// Lines of a text panel padded to its width, so nothing is left over from the last one
+pub fn padded_panel(lines: &[String]) -> impl Iterator<Item = String> + '_ {
+    let width = panel_width(lines) as usize;
+    lines.iter().map(move |line| format!("{line:<width$}"))
+}
+
+// How a key of the key state is picked out
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyMark {
+    // FX0A is waiting on its release
+    Awaited,
+    // The machine sees it held
+    Held,
+}
+
+// Title of the key state, given the keys FX0A is waiting on, if it is
+pub fn key_state_title(wait: Option<InputMsg>) -> &'static str {
+    match wait {
+        Some(_) => "FX0A waiting",
+        None => "Machine keys",
+    }
+}
+
+// How `key` is picked out in the key state, if it is, given the keys held and those FX0A
+// is waiting on
+pub fn key_mark(key: usize, held: InputMsg, wait: Option<InputMsg>) -> Option<KeyMark> {
+    match (wait.is_some_and(|wait| wait[key]), held[key]) {
+        (true, _) => Some(KeyMark::Awaited),
+        (false, KEY_DOWN) => Some(KeyMark::Held),
+        (false, KEY_UP) => None,
+    }
+}
+
+// Keypad keys held down; where the terminal doesn't report key releases, keys pressed come
+// as a stream of presses (and repeats), so each is let go of `DEBOUNCE_TIMEOUT` after its
+// last one, making inputs more predictable
+pub struct Debounced {
+    keys: InputMsg,
+    // When each key was last pressed
+    pressed: [Instant; NUM_KEYS],
+}
+
+impl Default for Debounced {
+    fn default() -> Self {
+        Debounced {
+            keys: bitarr![0; NUM_KEYS],
+            pressed: [Instant::now(); NUM_KEYS],
+        }
+    }
+}
+
+impl Debounced {
+    pub fn keys(&self) -> InputMsg {
+        self.keys
+    }
+
+    pub fn press(&mut self, idx: usize) {
+        self.keys.set(idx, KEY_DOWN);
+        self.pressed[idx] = Instant::now();
+    }
+
+    pub fn release(&mut self, idx: usize) {
+        self.keys.set(idx, KEY_UP);
+    }
+
+    // Let go of the keys held for `DEBOUNCE_TIMEOUT` since their last press
+    pub fn expire(&mut self) {
+        for (mut key, pressed) in
+            self.keys
+                .iter_mut()
+                .zip(self.pressed.iter_mut())
+                .filter(|(key, pressed)| {
+                    *key == KEY_DOWN && pressed.elapsed().as_millis() >= DEBOUNCE_TIMEOUT as u128
+                })
+        {
+            *key = KEY_UP;
+            *pressed = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keypad_hits() {
+        let origin = (10, 5);
+        // Inside "| 1 |" and "| F |"
+        assert_eq!(keypad_hit(origin, (12, 6)), Some(0x1));
+        assert_eq!(keypad_hit(origin, (10 + 13, 5 + 7)), Some(0xF));
+        // Borders, and outside the keypad
+        assert_eq!(keypad_hit(origin, (10, 6)), None);
+        assert_eq!(keypad_hit(origin, (12, 5)), None);
+        assert_eq!(keypad_hit(origin, (9, 6)), None);
+        assert_eq!(keypad_hit(origin, (10 + KEYPAD_WIDTH, 6)), None);
+    }
+
+    #[test]
+    fn layout_fits_together() {
+        let (display, panel_width) = ((64, 32), 20);
+        let needed = Layout::needed(display, true, panel_width);
+        assert_eq!(Layout::room(needed, true, panel_width), display);
+
+        // Exactly fitting, everything starts at the edges and touches the far ones
+        let layout = Layout::new(needed, display, true, panel_width);
+        assert_eq!(layout.display, (0, 0));
+        assert_eq!(layout.panel.0 + panel_width, needed.0);
+        assert_eq!(layout.key_state.1 + KEY_STATE_HEIGHT, needed.1);
+
+        // With room to spare, it's all centered
+        let term_size = (needed.0 + 10, needed.1 + 4);
+        let layout = Layout::new(term_size, display, true, panel_width);
+        assert_eq!(layout.display, (5, 2));
+        assert_eq!(layout.keypad, Some((5 + 64 + KEYPAD_MARGIN, 2 + 16 - 4)));
+
+        // Without the keypad or panel, the display gets the whole terminal
+        assert_eq!(Layout::room((80, 24), false, 0), (80, 24));
+        assert_eq!(Layout::new((80, 24), (64, 16), false, 0).keypad, None);
+    }
+
+    #[test]
+    fn panels_are_padded() {
+        let lines = ["PC 0200".to_owned(), "I".to_owned()];
+        assert_eq!(panel_width(&lines), 7);
+        assert_eq!(
+            padded_panel(&lines).collect::<Vec<_>>(),
+            ["PC 0200", "I      "]
+        );
+        assert_eq!(panel_width(&[]), 0);
+    }
+
+    #[test]
+    fn awaited_keys_stand_out() {
+        let mut held = InputMsg::ZERO;
+        held.set(0x5, KEY_DOWN);
+        let mut wait = InputMsg::ZERO;
+        wait.set(0x5, true);
+        wait.set(0xA, true);
+
+        assert_eq!(key_mark(0x5, held, None), Some(KeyMark::Held));
+        assert_eq!(key_mark(0x5, held, Some(wait)), Some(KeyMark::Awaited));
+        assert_eq!(key_mark(0xA, held, Some(wait)), Some(KeyMark::Awaited));
+        assert_eq!(key_mark(0x1, held, Some(wait)), None);
+        assert_eq!(key_state_title(Some(wait)), "FX0A waiting");
+    }
+
+    #[test]
+    fn presses_expire() {
+        let mut keys = Debounced::default();
+        keys.press(0x3);
+        keys.expire();
+        assert_eq!(keys.keys()[0x3], KEY_DOWN);
+
+        keys.pressed[0x3] -= std::time::Duration::from_millis(DEBOUNCE_TIMEOUT as u64);
+        keys.expire();
+        assert_eq!(keys.keys()[0x3], KEY_UP);
+
+        keys.press(0x3);
+        keys.release(0x3);
+        assert_eq!(keys.keys(), InputMsg::ZERO);
+    }
+}
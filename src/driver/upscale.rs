@@ -0,0 +1,120 @@
+use std::str::FromStr;
+
+// Pixel-art upscaler run over a frame before it's scaled up to the window (see
+// `--filter`), rounding off the staircases of diagonal lines for a softer look than
+// nearest-neighbor alone gives
+//
+//   These are the Scale2x/Scale3x family (https://www.scale2x.it): each pixel becomes a
+//   2x2 (or 3x3) block, and a corner of the block takes its neighbors' color where they
+//   meet in an edge across it. Scale4x is Scale2x run twice. Colors are only ever
+//   copied, never blended, so the output stays in the palette's colors.
+//
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Filter {
+    Scale2x,
+    Scale3x,
+    Scale4x,
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scale2x" => Ok(Filter::Scale2x),
+            "scale3x" => Ok(Filter::Scale3x),
+            "scale4x" => Ok(Filter::Scale4x),
+            _ => Err(format!(
+                "unknown filter '{s}' (expected scale2x, scale3x, or scale4x)"
+            )),
+        }
+    }
+}
+
+impl Filter {
+    // How many times wider and taller the output is than the frame
+    pub fn factor(self) -> usize {
+        match self {
+            Filter::Scale2x => 2,
+            Filter::Scale3x => 3,
+            Filter::Scale4x => 4,
+        }
+    }
+
+    // Upscale `frame`, of size `dims`, into `out`, which is resized to fit
+    pub fn apply(self, frame: &[u32], dims: (usize, usize), out: &mut Vec<u32>) {
+        match self {
+            Filter::Scale2x => scale2x(frame, dims, out),
+            Filter::Scale3x => scale3x(frame, dims, out),
+            Filter::Scale4x => {
+                let mut half = Vec::new();
+                scale2x(frame, dims, &mut half);
+                scale2x(&half, (dims.0 * 2, dims.1 * 2), out);
+            }
+        }
+    }
+}
+
+// The pixel at (x, y) and its 8 neighbors, row by row; those past the edges repeat the
+// edge pixels
+fn neighborhood(frame: &[u32], (w, h): (usize, usize), x: usize, y: usize) -> [u32; 9] {
+    let cols = [x.saturating_sub(1), x, (x + 1).min(w - 1)];
+    let rows = [y.saturating_sub(1), y, (y + 1).min(h - 1)];
+    let mut pixels = [0; 9];
+    for (n, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = frame[rows[n / 3] * w + cols[n % 3]];
+    }
+    pixels
+}
+
+fn scale2x(frame: &[u32], (w, h): (usize, usize), out: &mut Vec<u32>) {
+    out.clear();
+    out.resize(w * h * 4, 0);
+    for y in 0..h {
+        for x in 0..w {
+            let [_, b, _, d, e, f, _, h_, _] = neighborhood(frame, (w, h), x, y);
+            let or_e = |edge: bool, color| if edge { color } else { e };
+            let block = match b != h_ && d != f {
+                true => [
+                    or_e(d == b, d),
+                    or_e(b == f, f),
+                    or_e(d == h_, d),
+                    or_e(h_ == f, f),
+                ],
+                false => [e; 4],
+            };
+            let (ox, oy) = (x * 2, y * 2);
+            out[oy * w * 2 + ox..][..2].copy_from_slice(&block[..2]);
+            out[(oy + 1) * w * 2 + ox..][..2].copy_from_slice(&block[2..]);
+        }
+    }
+}
+
+fn scale3x(frame: &[u32], (w, h): (usize, usize), out: &mut Vec<u32>) {
+    out.clear();
+    out.resize(w * h * 9, 0);
+    for y in 0..h {
+        for x in 0..w {
+            let [a, b, c, d, e, f, g, h_, i] = neighborhood(frame, (w, h), x, y);
+            let or_e = |edge: bool, color| if edge { color } else { e };
+            let block = match b != h_ && d != f {
+                true => [
+                    or_e(d == b, d),
+                    or_e((d == b && e != c) || (b == f && e != a), b),
+                    or_e(b == f, f),
+                    or_e((d == b && e != g) || (d == h_ && e != a), d),
+                    e,
+                    or_e((b == f && e != i) || (h_ == f && e != c), f),
+                    or_e(d == h_, d),
+                    or_e((d == h_ && e != i) || (h_ == f && e != g), h_),
+                    or_e(h_ == f, f),
+                ],
+                false => [e; 9],
+            };
+            let (ox, oy) = (x * 3, y * 3);
+            for (row, pixels) in block.chunks(3).enumerate() {
+                out[(oy + row) * w * 3 + ox..][..3].copy_from_slice(pixels);
+            }
+        }
+    }
+}
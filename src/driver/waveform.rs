@@ -0,0 +1,170 @@
+use std::{collections::VecDeque, f32::consts::TAU, str::FromStr};
+
+use crate::{chip8::PATTERN_LEN, driver::AudioMsg};
+
+// F4, the tone played while the sound timer is active
+pub const TONE_FREQ: f32 = 349.23;
+// Peak amplitude of the tone
+pub const AMPLITUDE: f32 = 0.1;
+// Length of the fade in and out of every sound (the attack and release), which keeps it
+// from popping as it starts and stops; sounds are played this far behind, so a fade out
+// can start ahead of the end of a sound and finish right as it does
+const ENVELOPE_SECS: f32 = 0.005;
+
+// What a native audio device's stream plays, set from the emulator's side as it changes
+#[derive(Clone, Copy, Debug)]
+pub struct Playback {
+    pub sound: AudioMsg,
+    // Volume, in [0, 1]
+    pub volume: f32,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            sound: AudioMsg::Beep(false),
+            volume: 1.0,
+        }
+    }
+}
+
+// Shape of the beep synthesized by native audio devices
+//
+//   Generated directly rather than summed from harmonics, so the square and sawtooth
+//   waves alias a little at high sample rates; at this pitch it's hard to hear, and
+//   the hard edges are a good part of the retro sound anyway
+//
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+}
+
+impl Waveform {
+    // Sample at `phase` through the cycle, in [0, 1), scaled to `AMPLITUDE`
+    pub fn sample(self, phase: f32) -> f32 {
+        let value = match self {
+            Waveform::Sine => (phase * TAU).sin(),
+            Waveform::Square => match phase < 0.5 {
+                true => 1.0,
+                false => -1.0,
+            },
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+        };
+        value * AMPLITUDE
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sine" => Ok(Waveform::Sine),
+            "square" => Ok(Waveform::Square),
+            "triangle" => Ok(Waveform::Triangle),
+            "sawtooth" => Ok(Waveform::Sawtooth),
+            _ => Err(format!(
+                "invalid waveform '{s}' (expected sine, square, triangle, or sawtooth)"
+            )),
+        }
+    }
+}
+
+// Playback rate (samples/s) of an XO-CHIP audio pattern at `pitch` (see FX3A)
+pub fn pattern_rate(pitch: u8) -> f32 {
+    4000.0 * 2_f32.powf((pitch as f32 - 64.0) / 48.0)
+}
+
+// Sample generator for native audio devices, playing whatever `AudioMsg` it's given at
+// `sample_rate`; its place in the tone and pattern carries over from one sample to the
+// next, so the sound doesn't click when the device's buffers join up
+//
+//   Sounds are faded in and out over `ENVELOPE_SECS`, and played that much later than
+//   they're given
+//
+pub struct Synth {
+    waveform: Waveform,
+    sample_rate: f32,
+    // Position through the tone's current cycle, in [0, 1)
+    phase: f32,
+    // Position through the audio pattern, in pattern samples
+    pattern_pos: f32,
+    // Sounds given for the samples still to be played, oldest first
+    lookahead: VecDeque<AudioMsg>,
+    // Volume of the sound being played, in [0, 1], and how much it changes by per sample
+    // while fading
+    gain: f32,
+    gain_step: f32,
+    // Volume the sound is played at (see `AudioDevice::set_volume`)
+    volume: f32,
+}
+
+impl Synth {
+    pub fn new(waveform: Waveform, sample_rate: u32) -> Self {
+        let envelope_len = (ENVELOPE_SECS * sample_rate as f32).ceil().max(1.0);
+        Synth {
+            waveform,
+            sample_rate: sample_rate as f32,
+            phase: 0.0,
+            pattern_pos: 0.0,
+            lookahead: VecDeque::from(vec![AudioMsg::Beep(false); envelope_len as usize]),
+            gain: 0.0,
+            gain_step: 1.0 / envelope_len,
+            volume: 1.0,
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    // Next sample of `playback`'s sound, played at its volume
+    pub fn play(&mut self, playback: Playback) -> f32 {
+        self.set_volume(playback.volume);
+        self.next_sample(playback.sound)
+    }
+
+    // Next sample, given the sound that should be playing now
+    pub fn next_sample(&mut self, sound: AudioMsg) -> f32 {
+        self.lookahead.push_back(sound);
+        let playing = self.lookahead.pop_front().unwrap_or(sound);
+
+        // Faded out as soon as the sound is due to stop, so it's silent by the time it does
+        let target = match playing.is_on() && sound.is_on() {
+            true => 1.0,
+            false => 0.0,
+        };
+        self.gain = match self.gain < target {
+            true => (self.gain + self.gain_step).min(target),
+            false => (self.gain - self.gain_step).max(target),
+        };
+        self.synthesize(playing) * self.gain * self.volume
+    }
+
+    fn synthesize(&mut self, sound: AudioMsg) -> f32 {
+        match sound {
+            AudioMsg::Beep(false) => 0.0,
+            AudioMsg::Beep(true) => {
+                let sample = self.waveform.sample(self.phase);
+                self.phase = (self.phase + TONE_FREQ / self.sample_rate).fract();
+                sample
+            }
+            // Set bits are high and clear bits silent, so an empty pattern is quiet
+            AudioMsg::Pattern { bits, pitch } => {
+                let n = self.pattern_pos as usize;
+                let high = bits[n / 8] >> (7 - n % 8) & 1 != 0;
+                self.pattern_pos = (self.pattern_pos + pattern_rate(pitch) / self.sample_rate)
+                    % (PATTERN_LEN * 8) as f32;
+                match high {
+                    true => AMPLITUDE,
+                    false => 0.0,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use bitvec::{bitarr, slice::BitSlice, BitArr};
+use wasm_bindgen::{prelude::Closure, Clamped, JsCast, JsValue};
+use web_sys::{
+    AudioContext, CanvasRenderingContext2d, Document, GainNode, HtmlCanvasElement, ImageData,
+    KeyboardEvent, OscillatorType, Window,
+};
+
+use crate::{
+    chip8::{DISPLAY_HEIGHT, DISPLAY_WIDTH, NUM_KEYS},
+    config::profile::{Profile, ProfileCycle, Profiles},
+    diagnostics,
+    driver::{
+        keymap::Keymap,
+        palette::Palette,
+        waveform::{AMPLITUDE, TONE_FREQ},
+    },
+    driver::{pixel_color, AudioDevice, AudioInfo, AudioMsg, NOTIFICATION_DURATION},
+    driver::{DisplayDevice, DisplayInfo, InputDevice, InputInfo, InputMsg},
+    emulator::Signal,
+};
+
+// Key events as they come in from the page: the key's value (see `KeyboardEvent.key`),
+// whether it went down or up, and whether it's an auto-repeat
+type KeyQueue = Rc<RefCell<Vec<(String, bool, bool)>>>;
+type KeyListener = Closure<dyn FnMut(KeyboardEvent)>;
+
+// Time constant of the tone's volume changes
+const RAMP_TIME: Duration = Duration::from_millis(5);
+
+// A browser page that is display, input, and audio device at once: frames are drawn to a
+// canvas at their native resolution (scale it up with CSS, e.g. `image-rendering: pixelated`),
+// keys come from the page's keyboard events, and sound is a square wave through Web Audio
+//
+//   Only usable in builds for wasm32-unknown-unknown (see examples/web.rs); XO-CHIP sample
+//   patterns play as the standard beep
+//
+pub struct Web {
+    window: Window,
+    document: Document,
+    canvas: HtmlCanvasElement,
+    context: CanvasRenderingContext2d,
+    // Key events since the last poll, and the page listeners queueing them, removed again
+    // once the device is dropped
+    key_events: KeyQueue,
+    listeners: [(&'static str, KeyListener); 2],
+    // Browsers keep audio suspended until the user interacts with the page, so the context
+    // is resumed on key presses; the gain node switches the tone on and off, at `volume`
+    audio: AudioContext,
+    gain: GainNode,
+    volume: f32,
+    // Frame in the canvas' RGBA format, 4 bytes per pixel
+    framebuf: Vec<u8>,
+    // Dimensions (w, h) of the frame currently held in `framebuf`
+    frame_dims: (usize, usize),
+    // Keys currently held down, and the key states last reported with `Signal::NewInputs`
+    keybuf: BitArr!(for NUM_KEYS),
+    reported: InputMsg,
+    // Whether Backspace is held down, rewinding the machine
+    rewinding: bool,
+    // Whether Tab is held down, fast-forwarding the machine, and whether that was last
+    // reported with `Signal::TurboOn`
+    turbo: bool,
+    turbo_reported: bool,
+    // Host key to CHIP-8 key bindings
+    keymap: Keymap,
+    palette: Palette,
+    // Profiles cycled through with the P hotkey
+    profiles: ProfileCycle,
+    // Page title; notifications are appended to it until they expire
+    title: String,
+    toast_shown: Option<f64>,
+}
+
+impl Web {
+    // Draw to the canvas element with id `canvas_id`, taking keys from the whole page
+    pub fn new(canvas_id: &str, name: &str) -> Result<Self, JsValue> {
+        let window = web_sys::window().ok_or("no window to run in")?;
+        let document = window.document().ok_or("no document to run in")?;
+        let canvas = document
+            .get_element_by_id(canvas_id)
+            .ok_or_else(|| format!("no element with id '{canvas_id}'"))?
+            .dyn_into::<HtmlCanvasElement>()?;
+        let context = canvas
+            .get_context("2d")?
+            .ok_or("canvas has no 2D context")?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+        canvas.set_width(DISPLAY_WIDTH as u32);
+        canvas.set_height(DISPLAY_HEIGHT as u32);
+
+        let audio = AudioContext::new()?;
+        let oscillator = audio.create_oscillator()?;
+        oscillator.set_type(OscillatorType::Square);
+        oscillator.frequency().set_value(TONE_FREQ);
+        let gain = audio.create_gain()?;
+        gain.gain().set_value(0.0);
+        oscillator.connect_with_audio_node(&gain)?;
+        gain.connect_with_audio_node(&audio.destination())?;
+        oscillator.start()?;
+
+        let key_events = KeyQueue::default();
+        let listener = |down: bool| {
+            let key_events = Rc::clone(&key_events);
+            let audio = audio.clone();
+            KeyListener::new(move |event: KeyboardEvent| {
+                // Leave the browser's own shortcuts alone
+                if event.ctrl_key() || event.meta_key() || event.alt_key() {
+                    return;
+                }
+                event.prevent_default();
+                if down {
+                    let _ = audio.resume();
+                }
+                key_events
+                    .borrow_mut()
+                    .push((event.key(), down, event.repeat()));
+            })
+        };
+        let listeners = [("keydown", listener(true)), ("keyup", listener(false))];
+        for (kind, listener) in &listeners {
+            window.add_event_listener_with_callback(kind, listener.as_ref().unchecked_ref())?;
+        }
+
+        let title = "CHIP-8: ".to_owned() + name;
+        document.set_title(&title);
+
+        Ok(Web {
+            window,
+            document,
+            canvas,
+            context,
+            key_events,
+            listeners,
+            audio,
+            gain,
+            volume: 1.0,
+            framebuf: vec![0; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4],
+            frame_dims: (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+            keybuf: bitarr![0; NUM_KEYS],
+            reported: bitarr![0; NUM_KEYS],
+            rewinding: false,
+            turbo: false,
+            turbo_reported: false,
+            keymap: Keymap::default(),
+            palette: Palette::default(),
+            profiles: ProfileCycle::default(),
+            title,
+            toast_shown: None,
+        })
+    }
+
+    // Apply the current profile, keeping the rest around for cycling
+    pub fn set_profiles(&mut self, profiles: Profiles) {
+        let profile = self.profiles.set(profiles);
+        self.apply_profile(&profile);
+    }
+
+    // The canvas is scaled by the page, so the profile's scale is left alone
+    fn apply_profile(&mut self, profile: &Profile) {
+        self.keymap = profile.keymap;
+        self.palette = profile.palette.unwrap_or_default();
+    }
+
+    // Milliseconds since the page loaded
+    fn now(&self) -> f64 {
+        self.window
+            .performance()
+            .map_or(0.0, |performance| performance.now())
+    }
+}
+
+impl Drop for Web {
+    fn drop(&mut self) {
+        for (kind, listener) in &self.listeners {
+            let _ = self
+                .window
+                .remove_event_listener_with_callback(kind, listener.as_ref().unchecked_ref());
+        }
+        let _ = self.audio.close();
+    }
+}
+
+// Character a key's value corresponds to, for lookups in a `Keymap`
+fn key_char(key: &str) -> Option<char> {
+    let mut chars = key.chars().flat_map(char::to_lowercase);
+    chars.next().filter(|_| chars.next().is_none())
+}
+
+// Emulator hotkey bound to a key's value, if any; the browser keeps F5 for itself, and there
+// is no save state file to go with F7
+fn hotkey(key: &str) -> Signal {
+    match key {
+        " " => Signal::Pause,
+        "F2" => Signal::Reset,
+        key => match key_char(key) {
+            Some('m') => Signal::MarkState,
+            Some('n') => Signal::RestoreMark,
+            Some('o') => Signal::RotateDisplay,
+            Some('h') => Signal::MirrorDisplay,
+            Some('p') => Signal::CycleProfile,
+            Some('.') => Signal::Step,
+            Some(',') => Signal::FrameAdvance,
+            Some('=' | '+') => Signal::SpeedUp,
+            Some('-') => Signal::SpeedDown,
+            Some('0') => Signal::VolumeUp,
+            Some('9') => Signal::VolumeDown,
+            Some('8') => Signal::ToggleMute,
+            Some('b') => Signal::ToggleBreakpoint,
+            Some('[') => Signal::ScrollMemoryUp,
+            Some(']') => Signal::ScrollMemoryDown,
+            _ => Signal::None,
+        },
+    }
+}
+
+impl InputDevice for Web {
+    // Keys are translated to CHIP-8 keys through `Keymap` (QWERTY layout by default)
+    fn handle_inputs(&mut self) -> Signal {
+        // Checked here since frames don't necessarily keep coming in
+        if self
+            .toast_shown
+            .is_some_and(|shown| self.now() - shown >= NOTIFICATION_DURATION.as_secs_f64() * 1000.0)
+        {
+            self.document.set_title(&self.title);
+            self.toast_shown = None;
+        }
+
+        let mut signal = Signal::None;
+        for (key, down, repeat) in self.key_events.take() {
+            match key_char(&key).and_then(|c| self.keymap.lookup(c)) {
+                Some(idx) => self.keybuf.set(idx, down),
+                None if key == "Backspace" => self.rewinding = down,
+                None if key == "Tab" => self.turbo = down,
+                // Only the first hotkey of a poll is taken, and only on its press
+                None if down && !repeat && signal == Signal::None => signal = hotkey(&key),
+                None => {}
+            }
+        }
+
+        // Rewinding lasts for as long as the key is held; keypad changes meanwhile, like
+        // those alongside a hotkey, are held off until a later poll
+        if self.rewinding {
+            return Signal::Rewind;
+        }
+        let signal = match signal {
+            Signal::CycleProfile => match self.profiles.cycle() {
+                Some(profile) => {
+                    self.apply_profile(&profile);
+                    signal
+                }
+                None => Signal::None,
+            },
+            signal => signal,
+        };
+
+        if signal != Signal::None {
+            signal
+        } else if self.turbo != self.turbo_reported {
+            self.turbo_reported = self.turbo;
+            match self.turbo {
+                true => Signal::TurboOn,
+                false => Signal::TurboOff,
+            }
+        } else if self.keybuf != self.reported {
+            self.reported = self.keybuf;
+            Signal::NewInputs
+        } else {
+            Signal::None
+        }
+    }
+
+    fn send_inputs(&self) -> Option<InputMsg> {
+        Some(self.reported)
+    }
+
+    fn device_info(&self) -> InputInfo {
+        InputInfo::Web
+    }
+}
+
+impl DisplayDevice for Web {
+    fn receive_frame(
+        &mut self,
+        planes: &[&BitSlice<usize>],
+        dims: (usize, usize),
+    ) -> &mut dyn DisplayDevice {
+        self.frame_dims = dims;
+        self.framebuf.clear();
+        for idx in 0..dims.0 * dims.1 {
+            let (r, g, b) = self.palette.rgb(pixel_color(planes, idx));
+            self.framebuf.extend([r, g, b, 0xFF]);
+        }
+
+        self
+    }
+
+    fn receive_notification(&mut self, text: &str) {
+        self.document.set_title(&format!("{} - {text}", self.title));
+        self.toast_shown = Some(self.now());
+    }
+
+    fn drive_display(&mut self) {
+        let (w, h) = (self.frame_dims.0 as u32, self.frame_dims.1 as u32);
+        if (self.canvas.width(), self.canvas.height()) != (w, h) {
+            self.canvas.set_width(w);
+            self.canvas.set_height(h);
+        }
+
+        let drawn = ImageData::new_with_u8_clamped_array_and_sh(Clamped(&self.framebuf), w, h)
+            .and_then(|image| self.context.put_image_data(&image, 0.0, 0.0));
+        if let Err(e) = drawn {
+            diagnostics::warn("web", format!("failed to draw to the canvas: {e:?}"));
+        }
+    }
+
+    fn device_info(&self) -> DisplayInfo {
+        DisplayInfo::Web
+    }
+}
+
+impl AudioDevice for Web {
+    fn receive_signal(&mut self, data: AudioMsg) -> &mut dyn AudioDevice {
+        let volume = match data.is_on() {
+            true => AMPLITUDE * self.volume,
+            false => 0.0,
+        };
+        // Ramped over a few milliseconds rather than set outright, which would click
+        let _ = self.gain.gain().set_target_at_time(
+            volume,
+            self.audio.current_time(),
+            RAMP_TIME.as_secs_f64(),
+        );
+        self
+    }
+
+    fn play_audio(&mut self) {}
+
+    // Takes effect with the next sound sent, at most a frame later
+    fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    fn device_info(&self) -> AudioInfo {
+        AudioInfo::Web
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use crate::{
+    chip8::{self, Chip8},
+    emulator::{Events, MachineTime, DEFAULT_CLOCK_FREQ},
+};
+
+// Cycles an instance runs for before yielding its worker to the next instance in line
+const QUANTUM: u64 = 10_000;
+
+// A ROM to be run headless with a fixed budget of cycles
+pub struct BatchJob {
+    pub name: String,
+    pub rom: Vec<u8>,
+    pub platform: chip8::Platform,
+    pub cycle_budget: u64,
+    // RNG seed, for results that are the same from one batch to the next
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+    // Ran for the entire cycle budget
+    BudgetExhausted,
+    // The ROM exited on its own (SCHIP 00FD)
+    Exited,
+    // The core gave up on the ROM
+    Crashed(String),
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::BudgetExhausted => write!(f, "budget exhausted"),
+            Outcome::Exited => write!(f, "exited"),
+            Outcome::Crashed(reason) => write!(f, "crashed ({reason})"),
+        }
+    }
+}
+
+pub struct BatchResult {
+    pub name: String,
+    pub cycles: u64,
+    pub outcome: Outcome,
+    // Hash of the final display contents, handy for spotting regressions across runs
+    pub display_hash: u64,
+}
+
+// A job in flight, passed between workers one quantum at a time
+struct Instance {
+    id: usize,
+    name: String,
+    system: Chip8,
+    cycle_budget: u64,
+    // Headless instances have no wall-clock to follow, so they run purely on emulated
+    // time at the default clock speed
+    time: MachineTime,
+}
+
+impl Instance {
+    // Run for up to one quantum; returns the outcome once the instance is done
+    fn run_quantum(&mut self) -> Option<Outcome> {
+        let budget = self.cycle_budget;
+        let end = budget.min(self.time.cycles + QUANTUM);
+        let system = &mut self.system;
+        let time = &mut self.time;
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            while time.cycles < end {
+                let events = match system.step() {
+                    Ok(events) => events,
+                    Err(err) => return Some(Outcome::Crashed(err.to_string())),
+                };
+                let tick_due = match events.contains(Events::DISPLAY_WAIT) {
+                    true => time.step_to_tick(1),
+                    false => time.step(1),
+                };
+                if tick_due {
+                    system.tick_timers();
+                }
+                if events.contains(Events::PROGRAM_EXIT) {
+                    return Some(Outcome::Exited);
+                }
+            }
+
+            (time.cycles >= budget).then_some(Outcome::BudgetExhausted)
+        }));
+
+        result.unwrap_or_else(|payload| Some(Outcome::Crashed(panic_message(payload))))
+    }
+
+    fn into_result(self, outcome: Outcome) -> BatchResult {
+        BatchResult {
+            name: self.name,
+            cycles: self.time.cycles,
+            outcome,
+            display_hash: self.system.display_hash(),
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|msg| msg.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_owned())
+}
+
+// Run every job headless across a pool of `workers` threads. Instances are scheduled
+// cooperatively: a worker runs an instance for one quantum and then puts it at the back
+// of the queue, so long-running ROMs can't starve the rest of the batch.
+// Results are returned in the same order as `jobs`.
+pub fn run_batch(jobs: Vec<BatchJob>, workers: usize) -> Vec<BatchResult> {
+    let num_jobs = jobs.len();
+    let (tx, rx) = mpsc::channel();
+    let mut queue = VecDeque::new();
+
+    for (id, job) in jobs.into_iter().enumerate() {
+        let mut system = Chip8::new();
+        system.set_platform(&job.platform);
+        if let Some(seed) = job.seed {
+            system.set_rng_seed(seed);
+        }
+
+        let loaded = system.load_rom(&job.rom);
+        let instance = Instance {
+            id,
+            name: job.name,
+            system,
+            cycle_budget: job.cycle_budget,
+            time: MachineTime::new(DEFAULT_CLOCK_FREQ),
+        };
+        match loaded {
+            Ok(()) => queue.push_back(instance),
+            Err(err) => {
+                let outcome = Outcome::Crashed(err.to_string());
+                tx.send((id, instance.into_result(outcome))).unwrap();
+            }
+        }
+    }
+
+    let queue = Arc::new(Mutex::new(queue));
+    let handles = (0..workers.max(1))
+        .map(|_| {
+            let queue = Arc::clone(&queue);
+            let tx = tx.clone();
+            thread::spawn(move || loop {
+                let Some(mut instance) = queue.lock().unwrap().pop_front() else {
+                    break;
+                };
+                match instance.run_quantum() {
+                    Some(outcome) => tx
+                        .send((instance.id, instance.into_result(outcome)))
+                        .unwrap(),
+                    None => queue.lock().unwrap().push_back(instance),
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(tx);
+
+    let mut results = rx.iter().collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    debug_assert_eq!(results.len(), num_jobs);
+
+    results.sort_by_key(|(id, _)| *id);
+    results.into_iter().map(|(_, result)| result).collect()
+}
@@ -0,0 +1,55 @@
+use crate::driver::{AudioDevice, DisplayDevice, InputDevice, NullDevice};
+
+use super::Emulator;
+
+// Emulator assembled from peripherals picked at runtime, which it owns
+pub type BoxedEmulator =
+    Emulator<'static, Box<dyn InputDevice>, Box<dyn DisplayDevice>, Box<dyn AudioDevice>>;
+
+// Builds an `Emulator` that owns its peripherals, for callers that don't need to share a
+// device between them; whichever isn't given is a `NullDevice`
+//
+//   let mut emu = EmulatorBuilder::new().display(Box::new(window)).build();
+//   emu.load_program("roms/retro/INVADERS")?;
+//   emu.run()?;
+//
+pub struct EmulatorBuilder {
+    input: Box<dyn InputDevice>,
+    display: Box<dyn DisplayDevice>,
+    audio: Box<dyn AudioDevice>,
+}
+
+impl Default for EmulatorBuilder {
+    fn default() -> Self {
+        EmulatorBuilder {
+            input: Box::new(NullDevice::Input),
+            display: Box::new(NullDevice::Display),
+            audio: Box::new(NullDevice::Audio),
+        }
+    }
+}
+
+impl EmulatorBuilder {
+    pub fn new() -> Self {
+        EmulatorBuilder::default()
+    }
+
+    pub fn input(mut self, input: Box<dyn InputDevice>) -> Self {
+        self.input = input;
+        self
+    }
+
+    pub fn display(mut self, display: Box<dyn DisplayDevice>) -> Self {
+        self.display = display;
+        self
+    }
+
+    pub fn audio(mut self, audio: Box<dyn AudioDevice>) -> Self {
+        self.audio = audio;
+        self
+    }
+
+    pub fn build(self) -> BoxedEmulator {
+        Emulator::with_owned_peripherals(self.input, self.display, self.audio)
+    }
+}
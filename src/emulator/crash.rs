@@ -0,0 +1,141 @@
+use std::{collections::VecDeque, fmt::Write as _, fs, io, path::Path};
+
+use crate::chip8::{disasm, Chip8, ChipError, FaultInfo, Mode};
+
+// Instructions kept for a crash dump
+const HISTORY_LEN: usize = 64;
+// Bytes of memory per line of a crash dump
+const MEM_ROW_LEN: usize = 16;
+
+// The last instructions executed, as (address, opcode), oldest first; kept so a crash dump
+// shows how the program got where it halted
+pub struct History {
+    entries: VecDeque<(u16, u16)>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History {
+            entries: VecDeque::with_capacity(HISTORY_LEN),
+        }
+    }
+
+    pub fn push(&mut self, pc: u16, opcode: u16) {
+        if self.entries.len() == HISTORY_LEN {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((pc, opcode));
+    }
+}
+
+// Lines of the fault screen shown beside the display once the ROM halts the machine
+// (see `Signal::Fault`), e.g.
+//
+//   ROM crashed
+//   unrecognized instruction FFFF at 0x2A4
+//
+//   PC 0x2A4  I 0x300
+//   FFFF  DW 0xFFFF
+//   V0 00 V1 05 V2 00 V3 1F
+//   ...
+//
+//   F2 to reset, Esc to quit
+//
+pub fn fault_panel(fault: &FaultInfo, mode: Mode) -> Vec<String> {
+    let mut lines = vec![
+        "ROM crashed".to_owned(),
+        fault.err.to_string(),
+        String::new(),
+        format!("PC {:#05X}  I {:#05X}", fault.pc, fault.i_reg),
+        format!(
+            "{:04X}  {}",
+            fault.opcode,
+            disasm::mnemonic(fault.opcode, mode)
+        ),
+    ];
+    for (row, regs) in fault.v_reg.chunks(4).enumerate() {
+        let regs = regs
+            .iter()
+            .enumerate()
+            .map(|(n, v)| format!("V{:X} {v:02X}", row * 4 + n));
+        lines.push(regs.collect::<Vec<_>>().join(" "));
+    }
+    lines.push(String::new());
+    lines.push("F2 to reset, Esc to quit".to_owned());
+    lines
+}
+
+// Write what's needed to debug the ROM `system` halted on with `err` to `path`, as text:
+// the error, registers, call stack, the instructions leading up to it, and all of memory
+//
+//   invalid ROM: return with an empty call stack at 0x208
+//
+//   PC 0x208  I 0x22A  DT 00  ST 00  mode Chip8
+//   V0 00 V1 05 V2 00 V3 1F  ...
+//   Stack (empty)
+//
+//   Last 64 instructions, oldest first:
+//   0x200  A22A  LD I, 0x22A
+//   ...
+//
+//   Memory (4096 bytes):
+//   0000  F0 90 90 90 F0 20 60 20 20 70 F0 10 F0 80 F0 F0
+//   ...
+//
+pub fn write_dump(
+    path: &Path,
+    system: &Chip8,
+    err: &ChipError,
+    history: &History,
+) -> io::Result<()> {
+    let state = system.dump_state();
+    let mut dump = String::new();
+
+    writeln!(dump, "invalid ROM: {err}\n").unwrap();
+    writeln!(
+        dump,
+        "PC {:#05X}  I {:#05X}  DT {:02X}  ST {:02X}  mode {:?}",
+        state.pc, state.i_reg, state.delay_timer, state.sound_timer, state.mode
+    )
+    .unwrap();
+    for (row, regs) in state.v_reg.chunks(4).enumerate() {
+        let regs = regs
+            .iter()
+            .enumerate()
+            .map(|(n, v)| format!("V{:X} {v:02X}", row * 4 + n))
+            .collect::<Vec<_>>();
+        writeln!(dump, "{}", regs.join(" ")).unwrap();
+    }
+    match &state.stack[..] {
+        [] => writeln!(dump, "Stack (empty)").unwrap(),
+        stack => {
+            let stack = stack.iter().map(|addr| format!("{addr:#05X}"));
+            writeln!(dump, "Stack {}", stack.collect::<Vec<_>>().join(" ")).unwrap();
+        }
+    }
+
+    writeln!(
+        dump,
+        "\nLast {} instructions, oldest first:",
+        history.entries.len()
+    )
+    .unwrap();
+    for &(pc, opcode) in &history.entries {
+        let mnemonic = disasm::mnemonic(opcode, state.mode);
+        writeln!(dump, "{pc:#05X}  {opcode:04X}  {mnemonic}").unwrap();
+    }
+
+    writeln!(dump, "\nMemory ({} bytes):", state.memory.len()).unwrap();
+    for (row, bytes) in state.memory.chunks(MEM_ROW_LEN).enumerate() {
+        let bytes = bytes.iter().map(|byte| format!("{byte:02X}"));
+        writeln!(
+            dump,
+            "{:04X}  {}",
+            row * MEM_ROW_LEN,
+            bytes.collect::<Vec<_>>().join(" ")
+        )
+        .unwrap();
+    }
+
+    fs::write(path, dump)
+}
@@ -0,0 +1,452 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fmt,
+    str::FromStr,
+};
+
+use crate::chip8::{Chip8, Register, Watchpoint, MAX_WATCHPOINTS};
+
+// Rows shown by the memory viewer, and bytes per row
+const MEM_ROWS: u16 = 8;
+const MEM_ROW_LEN: u16 = 8;
+// Lines of console output shown in the panel
+const CONSOLE_LINES: usize = 6;
+
+// Opcode to break on, with wildcard digits: each of the four characters is either a hex
+// digit that must match or anything else for a wildcard, so the usual opcode notation
+// works as is (e.g. `DXYN`, `FX55`, `00E0`, or `F?1E`)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct OpcodePattern {
+    // Bits that have to match, and the value they have to match
+    mask: u16,
+    value: u16,
+}
+
+impl OpcodePattern {
+    pub fn matches(&self, opcode: u16) -> bool {
+        opcode & self.mask == self.value
+    }
+}
+
+impl FromStr for OpcodePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.chars().count() != 4 {
+            return Err(format!(
+                "invalid opcode pattern '{s}' (expected 4 characters, e.g. DXYN)"
+            ));
+        }
+
+        let (mut mask, mut value) = (0, 0);
+        for c in s.chars() {
+            mask <<= 4;
+            value <<= 4;
+            if let Some(digit) = c.to_digit(16) {
+                mask |= 0xF;
+                value |= digit as u16;
+            }
+        }
+
+        Ok(OpcodePattern { mask, value })
+    }
+}
+
+impl fmt::Display for OpcodePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for shift in [12, 8, 4, 0] {
+            match (self.mask >> shift) & 0xF {
+                0 => write!(f, "?")?,
+                _ => write!(f, "{:X}", (self.value >> shift) & 0xF)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Why the debugger stopped the machine
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Break {
+    Address(u16),
+    Opcode {
+        pc: u16,
+        pattern: OpcodePattern,
+    },
+    // Hit by the instruction `opcode` at `pc`, which has been executed
+    Watch {
+        pc: u16,
+        opcode: u16,
+        watchpoint: Watchpoint,
+    },
+}
+
+// Write made from the console, along with what it overwrote so it can be undone
+#[derive(Clone, Debug)]
+enum Change {
+    Memory { addr: u16, old: Vec<u8> },
+    Register { reg: Register, old: u16 },
+}
+
+// Breakpoints and the machine state panel shown while paused
+//
+//   Hitting a breakpoint pauses the run loop; from there the machine can be stepped one
+//   instruction at a time or resumed with the usual hotkeys
+//
+//   While paused, memory and registers can also be read and written from a console (see
+//   `execute`), with every write journaled so it can be undone
+//
+#[derive(Default)]
+pub struct Debugger {
+    // Whether the panel is shown while paused; setting any breakpoint turns it on
+    enabled: bool,
+    // Addresses to break at before executing the instruction there
+    breakpoints: BTreeSet<u16>,
+    // Opcodes to break at before executing
+    patterns: Vec<OpcodePattern>,
+    // Breakpoint that paused the machine, until it resumes
+    last_break: Option<Break>,
+    // Start of the memory viewer; None follows I
+    mem_view: Option<u16>,
+    // Console writes, oldest first
+    journal: Vec<Change>,
+    // Most recent console output
+    console: VecDeque<String>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.enabled = true;
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn add_opcode_break(&mut self, pattern: OpcodePattern) {
+        self.enabled = true;
+        self.patterns.push(pattern);
+    }
+
+    // Set a breakpoint at `addr`, or clear the one already there; returns whether it's set
+    pub fn toggle_breakpoint(&mut self, addr: u16) -> bool {
+        let set = !self.breakpoints.remove(&addr);
+        if set {
+            self.add_breakpoint(addr);
+        }
+        set
+    }
+
+    // Whether to break before executing the next instruction of `system`
+    pub fn check(&mut self, system: &Chip8) -> bool {
+        let pc = system.pc();
+        let opcode = u16::from_be_bytes([system.read_mem(pc), system.read_mem(pc.wrapping_add(1))]);
+
+        self.last_break = if self.breakpoints.contains(&pc) {
+            Some(Break::Address(pc))
+        } else {
+            self.patterns
+                .iter()
+                .find(|pattern| pattern.matches(opcode))
+                .map(|&pattern| Break::Opcode { pc, pattern })
+        };
+        self.last_break.is_some()
+    }
+
+    // Set a watchpoint on `system`, which stops the machine right after an instruction
+    // hits it
+    pub fn add_watchpoint(
+        &mut self,
+        system: &mut Chip8,
+        watchpoint: Watchpoint,
+    ) -> Result<(), String> {
+        self.enabled = true;
+        match system.add_watchpoint(watchpoint) {
+            true => Ok(()),
+            false => Err(format!("can't set more than {MAX_WATCHPOINTS} watchpoints")),
+        }
+    }
+
+    // Note that the instruction `opcode` at `pc` hit `watchpoint`
+    pub fn watch_hit(&mut self, pc: u16, opcode: u16, watchpoint: Watchpoint) {
+        self.last_break = Some(Break::Watch {
+            pc,
+            opcode,
+            watchpoint,
+        });
+    }
+
+    pub fn resume(&mut self) {
+        self.last_break = None;
+    }
+
+    // Run a console command against `system`, logging it and its output to the panel;
+    // returns whether to resume the machine
+    //
+    //   peek ADDR [LEN]    show LEN (default: 1) bytes of memory from ADDR
+    //   poke ADDR VAL...   write bytes to memory from ADDR
+    //   get REG            show a register: V0-VF, I, PC, DT, or ST
+    //   set REG VAL        write a register
+    //   undo               revert the last poke or set
+    //   watch WATCHPOINT   stop after an instruction reads or writes memory, or changes
+    //                      a register (see `Watchpoint`'s `FromStr`)
+    //   unwatch WATCHPOINT clear a watchpoint
+    //
+    //   and, as in a machine code monitor:
+    //
+    //   m ADDR [LEN]       hex dump LEN (default: 8) bytes of memory from ADDR
+    //   w ADDR VAL...      same as poke
+    //   r                  show all the registers
+    //   g                  resume
+    //
+    //   Numbers are decimal, or hex with a `0x` prefix
+    //
+    pub fn execute(&mut self, system: &mut Chip8, command: &str) -> bool {
+        self.log(format!("> {command}"));
+        if command.split_whitespace().eq(["g"]) {
+            return true;
+        }
+
+        let output = self
+            .run_command(system, command)
+            .unwrap_or_else(|e| format!("error: {e}"));
+        for line in output.lines() {
+            self.log(line.to_owned());
+        }
+        false
+    }
+
+    // Add a line to the console output
+    pub fn log(&mut self, line: String) {
+        if self.console.len() == CONSOLE_LINES {
+            self.console.pop_front();
+        }
+        self.console.push_back(line);
+    }
+
+    fn run_command(&mut self, system: &mut Chip8, command: &str) -> Result<String, String> {
+        let words = command.split_whitespace().collect::<Vec<_>>();
+
+        match words[..] {
+            ["peek", addr] | ["peek", addr, _] => {
+                let addr = parse_num(addr)?;
+                let len = words.get(2).map_or(Ok(1), |len| parse_num(len))?;
+                let bytes = (0..len)
+                    .map(|n| format!("{:02X}", system.read_mem(addr.saturating_add(n))))
+                    .collect::<Vec<_>>();
+                Ok(format!("{addr:04X}  {}", bytes.join(" ")))
+            }
+            ["m", addr] | ["m", addr, _] => {
+                let addr = parse_num(addr)?;
+                let len = words
+                    .get(2)
+                    .map_or(Ok(MEM_ROW_LEN), |len| parse_num(len))?;
+                let rows = (0..len).step_by(MEM_ROW_LEN as usize).map(|offset| {
+                    let start = addr.saturating_add(offset);
+                    let bytes = (offset..len.min(offset.saturating_add(MEM_ROW_LEN)))
+                        .map(|n| format!("{:02X}", system.read_mem(addr.saturating_add(n))))
+                        .collect::<Vec<_>>();
+                    format!("{start:04X}  {}", bytes.join(" "))
+                });
+                Ok(rows.collect::<Vec<_>>().join("\n"))
+            }
+            ["r"] => {
+                let regs = system
+                    .v_reg()
+                    .iter()
+                    .enumerate()
+                    .map(|(n, v)| format!("V{n:X} {v:02X}"))
+                    .collect::<Vec<_>>();
+                Ok(format!(
+                    "PC {:#06X}  I {:#06X}  DT {:02X}  ST {:02X}\n{}\n{}",
+                    system.pc(),
+                    system.i_reg(),
+                    system.delay_timer(),
+                    system.sound_timer(),
+                    regs[..8].join(" "),
+                    regs[8..].join(" ")
+                ))
+            }
+            ["poke" | "w", addr, ref values @ ..] if !values.is_empty() => {
+                let addr = parse_num(addr)?;
+                let values = values
+                    .iter()
+                    .map(|value| {
+                        u8::try_from(parse_num(value)?)
+                            .map_err(|_| format!("'{value}' doesn't fit in a byte"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let mut old = Vec::new();
+                for (n, &value) in values.iter().enumerate() {
+                    let at = addr.wrapping_add(n as u16);
+                    let prev = system.read_mem(at);
+                    if let Err(e) = system.write_mem(at, value) {
+                        // Leave memory as it was if the write runs off the end
+                        for (n, &byte) in old.iter().enumerate() {
+                            let _ = system.write_mem(addr.wrapping_add(n as u16), byte);
+                        }
+                        return Err(e.to_string());
+                    }
+                    old.push(prev);
+                }
+                self.journal.push(Change::Memory { addr, old });
+                Ok(format!("wrote {} byte(s) at {addr:#06X}", values.len()))
+            }
+            ["get", reg] => {
+                let reg = reg.parse::<Register>()?;
+                Ok(format!("{reg} = {:#06X}", system.register(reg)))
+            }
+            ["set", reg, value] => {
+                let reg = reg.parse::<Register>()?;
+                let value = parse_num(value)?;
+                self.journal.push(Change::Register {
+                    reg,
+                    old: system.register(reg),
+                });
+                system.set_register(reg, value);
+                Ok(format!("{reg} = {:#06X}", system.register(reg)))
+            }
+            ["watch", watchpoint] => {
+                let watchpoint = watchpoint.parse::<Watchpoint>()?;
+                self.add_watchpoint(system, watchpoint)?;
+                Ok(format!("watching {watchpoint}"))
+            }
+            ["unwatch", watchpoint] => {
+                let watchpoint = watchpoint.parse::<Watchpoint>()?;
+                match system.remove_watchpoint(watchpoint) {
+                    true => Ok(format!("cleared {watchpoint}")),
+                    false => Err(format!("{watchpoint} isn't being watched")),
+                }
+            }
+            ["undo"] => match self.journal.pop() {
+                Some(Change::Memory { addr, old }) => {
+                    for (n, &byte) in old.iter().enumerate() {
+                        system
+                            .write_mem(addr.wrapping_add(n as u16), byte)
+                            .map_err(|e| e.to_string())?;
+                    }
+                    Ok(format!("restored {} byte(s) at {addr:#06X}", old.len()))
+                }
+                Some(Change::Register { reg, old }) => {
+                    system.set_register(reg, old);
+                    Ok(format!("{reg} = {old:#06X}"))
+                }
+                None => Ok("nothing to undo".to_owned()),
+            },
+            _ => Err(format!(
+                "unrecognized command '{command}' (expected peek, poke, get, set, undo, watch, unwatch, m, w, r, or g)"
+            )),
+        }
+    }
+
+    // Move the memory viewer by `rows` rows (negative scrolls up)
+    pub fn scroll_memory(&mut self, system: &Chip8, rows: i32) {
+        let start = self.mem_start(system) as i32 + rows * MEM_ROW_LEN as i32;
+        self.mem_view = Some(start.clamp(0, u16::MAX as i32) as u16);
+    }
+
+    fn mem_start(&self, system: &Chip8) -> u16 {
+        self.mem_view
+            .unwrap_or(system.i_reg() - system.i_reg() % MEM_ROW_LEN)
+    }
+
+    // Lines of text showing the state of `system`: registers, timers, the call stack,
+    // breakpoints, and a hex dump of memory
+    pub fn panel(&self, system: &Chip8) -> Vec<String> {
+        let mut lines = Vec::new();
+        let pc = system.pc();
+
+        lines.push(match self.last_break {
+            Some(Break::Address(addr)) => format!("BREAK at {addr:#06X}"),
+            Some(Break::Opcode { pc, pattern }) => format!("BREAK on {pattern} at {pc:#06X}"),
+            Some(Break::Watch {
+                pc,
+                opcode,
+                watchpoint,
+            }) => format!("WATCH {watchpoint} by {opcode:04X} at {pc:#06X}"),
+            None => "PAUSED".to_owned(),
+        });
+        lines.push(format!(
+            "PC {pc:#06X}  {:02X}{:02X}",
+            system.read_mem(pc),
+            system.read_mem(pc.wrapping_add(1))
+        ));
+        lines.push(format!(
+            "I  {:#06X}  DT {:02X}  ST {:02X}",
+            system.i_reg(),
+            system.delay_timer(),
+            system.sound_timer()
+        ));
+        for (row, regs) in system.v_reg().chunks(4).enumerate() {
+            let regs = regs
+                .iter()
+                .enumerate()
+                .map(|(n, v)| format!("V{:X} {v:02X}", row * 4 + n))
+                .collect::<Vec<_>>();
+            lines.push(regs.join(" "));
+        }
+        lines.push(match system.stack() {
+            [] => "Stack (empty)".to_owned(),
+            stack => format!("Stack {}", hex_list(stack.iter())),
+        });
+        if !self.breakpoints.is_empty() || !self.patterns.is_empty() {
+            let patterns = self.patterns.iter().map(|pattern| pattern.to_string());
+            lines.push(format!(
+                "Breaks {} {}",
+                hex_list(self.breakpoints.iter()),
+                patterns.collect::<Vec<_>>().join(" ")
+            ));
+        }
+        if !system.watchpoints().is_empty() {
+            let watchpoints = system.watchpoints().iter().map(|w| w.to_string());
+            lines.push(format!(
+                "Watch {}",
+                watchpoints.collect::<Vec<_>>().join(" ")
+            ));
+        }
+
+        lines.push(String::new());
+        let start = self.mem_start(system);
+        for row in 0..MEM_ROWS {
+            let addr = start.saturating_add(row * MEM_ROW_LEN);
+            let bytes = (0..MEM_ROW_LEN)
+                .map(|n| format!("{:02X}", system.read_mem(addr.saturating_add(n))))
+                .collect::<Vec<_>>();
+            lines.push(format!("{addr:04X}  {}", bytes.join(" ")));
+        }
+
+        if !self.console.is_empty() {
+            lines.push(String::new());
+            lines.extend(self.console.iter().cloned());
+        }
+
+        lines.push(String::new());
+        lines.push(". step  Space resume  B break  [ ] scroll  : console".to_owned());
+        lines
+    }
+}
+
+// Decimal, or hex with a `0x` prefix
+fn parse_num(num: &str) -> Result<u16, String> {
+    match num.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => num.parse(),
+    }
+    .map_err(|_| format!("invalid number '{num}'"))
+}
+
+fn hex_list<'a>(addrs: impl Iterator<Item = &'a u16>) -> String {
+    addrs
+        .map(|addr| format!("{addr:#06X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
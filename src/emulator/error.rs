@@ -0,0 +1,85 @@
+use std::{error, fmt, io, ops::Range};
+
+use crate::chip8::ChipError;
+
+// Errors that end an emulator session early
+#[derive(Debug)]
+pub enum EmuError {
+    // The ROM file couldn't be read
+    ReadRom { path: String, source: io::Error },
+    // The ROM file holds something other than a program that can be run
+    RomFormat { path: String, reason: String },
+    // The guest system halted on a bad ROM
+    Chip(ChipError),
+    // The same, with the machine state dumped to a file (see `Emulator::set_crash_path`)
+    Crashed { err: ChipError, dump: String },
+    // A save state file couldn't be read or written
+    StateIo { path: String, source: io::Error },
+    // A save state file couldn't be loaded
+    BadState { path: String, err: ChipError },
+    // The netplay session broke down
+    Netplay(io::Error),
+    // The connection to the remote play server broke down
+    Remote(io::Error),
+    // A recording couldn't be started or written
+    Recording { path: String, source: io::Error },
+    // The flag registers' file couldn't be read or written
+    FlagsIo { path: String, source: io::Error },
+    // A patch (see `Emulator::set_patch`) changes `addr`, which isn't part of the ROM
+    // loaded at `rom`
+    PatchOutsideRom { addr: usize, rom: Range<usize> },
+}
+
+impl fmt::Display for EmuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmuError::ReadRom { path, source } => write!(f, "failed to read '{path}': {source}"),
+            EmuError::RomFormat { path, reason } => write!(f, "can't run '{path}': {reason}"),
+            EmuError::Chip(err) => write!(f, "invalid ROM: {err}"),
+            EmuError::Crashed { err, dump } => {
+                write!(f, "invalid ROM: {err} (machine state dumped to '{dump}')")
+            }
+            EmuError::StateIo { path, source } => {
+                write!(f, "failed to access save state '{path}': {source}")
+            }
+            EmuError::BadState { path, err } => write!(f, "'{path}': {err}"),
+            EmuError::Netplay(err) => write!(f, "netplay: {err}"),
+            EmuError::Remote(err) => write!(f, "remote play: {err}"),
+            EmuError::Recording { path, source } => {
+                write!(f, "failed to record to '{path}': {source}")
+            }
+            EmuError::FlagsIo { path, source } => {
+                write!(f, "failed to access flag registers file '{path}': {source}")
+            }
+            EmuError::PatchOutsideRom { addr, rom } => write!(
+                f,
+                "patch at {addr:#05X} is outside the ROM ({} bytes at {:#05X})",
+                rom.len(),
+                rom.start
+            ),
+        }
+    }
+}
+
+impl error::Error for EmuError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            EmuError::ReadRom { source, .. } => Some(source),
+            EmuError::Chip(err)
+            | EmuError::Crashed { err, .. }
+            | EmuError::BadState { err, .. } => Some(err),
+            EmuError::StateIo { source, .. }
+            | EmuError::Recording { source, .. }
+            | EmuError::FlagsIo { source, .. }
+            | EmuError::Netplay(source)
+            | EmuError::Remote(source) => Some(source),
+            EmuError::RomFormat { .. } | EmuError::PatchOutsideRom { .. } => None,
+        }
+    }
+}
+
+impl From<ChipError> for EmuError {
+    fn from(err: ChipError) -> Self {
+        EmuError::Chip(err)
+    }
+}
@@ -0,0 +1,186 @@
+use std::{fmt, time::Duration};
+
+use super::{time::Instant, Emulator};
+use crate::driver::{AudioDevice, DisplayDevice, InputDevice};
+
+// Wall-clock window instruction counts are measured over
+const WINDOW: Duration = Duration::from_secs(1);
+// Upper bounds (µs) of the sleep overshoot histogram's buckets; one more catches the rest
+const JITTER_BUCKETS: [u64; 8] = [100, 250, 500, 1_000, 2_000, 4_000, 8_000, 16_000];
+// Width of the longest histogram bar
+const BAR_WIDTH: u64 = 40;
+
+// Instrumentation for checking that the run loop delivers the requested number of
+// instructions per wall-clock second, e.g. to validate pacing changes across platforms
+//
+//   Instructions are counted over one-second windows of wall-clock time, and every sleep
+//   the loop takes is timed against how long it asked for. Windows the machine spent
+//   paused in are left out.
+//
+pub struct FairnessMonitor {
+    // Largest acceptable deviation from the clock rate, as a fraction of it
+    tolerance: f64,
+    // Start of the current window, once the run has started, and the cycle count then
+    window_start: Option<Instant>,
+    window_cycles: u64,
+    // Instructions per second measured over each complete window
+    rates: Vec<f64>,
+    // Sleeps by how far they overshot, bucketed per `JITTER_BUCKETS`
+    jitter: [u64; JITTER_BUCKETS.len() + 1],
+}
+
+impl FairnessMonitor {
+    pub fn new(tolerance: f64) -> Self {
+        FairnessMonitor {
+            tolerance,
+            window_start: None,
+            window_cycles: 0,
+            rates: Vec::new(),
+            jitter: [0; JITTER_BUCKETS.len() + 1],
+        }
+    }
+
+    // Start a fresh window at `cycles`, dropping the current one
+    fn restart(&mut self, cycles: u64) {
+        self.window_start = Some(Instant::now());
+        self.window_cycles = cycles;
+    }
+
+    // Close the current window if it has run its course, with `cycles` executed so far
+    fn observe_cycles(&mut self, cycles: u64) {
+        let Some(elapsed) = self.window_start.map(|start| start.elapsed()) else {
+            self.restart(cycles);
+            return;
+        };
+        if elapsed >= WINDOW {
+            let count = cycles - self.window_cycles;
+            self.rates.push(count as f64 / elapsed.as_secs_f64());
+            self.restart(cycles);
+        }
+    }
+
+    fn observe_sleep(&mut self, requested: Duration, actual: Duration) {
+        let overshoot = actual.saturating_sub(requested).as_micros() as u64;
+        let bucket = JITTER_BUCKETS
+            .iter()
+            .position(|&bound| overshoot < bound)
+            .unwrap_or(JITTER_BUCKETS.len());
+        self.jitter[bucket] += 1;
+    }
+}
+
+// Results of a `FairnessMonitor`, checked against the clock rate that was asked for
+#[derive(Clone, Debug)]
+pub struct FairnessReport {
+    pub clock_rate: f64,
+    pub tolerance: f64,
+    // Instructions per second over each one-second window
+    pub rates: Vec<f64>,
+    // Sleep overshoot histogram (see `JITTER_BUCKETS`)
+    pub jitter: Vec<u64>,
+}
+
+impl FairnessReport {
+    // Largest deviation of any window from the clock rate, as a fraction of it
+    pub fn worst_deviation(&self) -> f64 {
+        self.rates
+            .iter()
+            .map(|rate| (rate - self.clock_rate).abs() / self.clock_rate)
+            .fold(0.0, f64::max)
+    }
+
+    // Whether every window was within tolerance; a run too short to measure doesn't pass
+    pub fn passed(&self) -> bool {
+        !self.rates.is_empty() && self.worst_deviation() <= self.tolerance
+    }
+}
+
+impl fmt::Display for FairnessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "PACING REPORT:")?;
+        writeln!(
+            f,
+            "    Target              {} IPS (within {:.1}%)",
+            self.clock_rate,
+            self.tolerance * 100.0
+        )?;
+        writeln!(f, "    Seconds measured    {}", self.rates.len())?;
+        if !self.rates.is_empty() {
+            let min = self.rates.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = self.rates.iter().copied().fold(0.0, f64::max);
+            let avg = self.rates.iter().sum::<f64>() / self.rates.len() as f64;
+            writeln!(
+                f,
+                "    Instructions/sec    min {min:.1}, avg {avg:.1}, max {max:.1}"
+            )?;
+            writeln!(
+                f,
+                "    Worst deviation     {:.2}%",
+                self.worst_deviation() * 100.0
+            )?;
+        }
+        writeln!(
+            f,
+            "    Result              {}",
+            match self.passed() {
+                true => "PASS",
+                false => "FAIL",
+            }
+        )?;
+
+        write!(f, "    Sleep overshoot")?;
+        let most = self.jitter.iter().copied().max().unwrap_or(0).max(1);
+        for (n, &count) in self.jitter.iter().enumerate() {
+            let label = match JITTER_BUCKETS.get(n) {
+                Some(bound) => format!("< {:.2}ms", *bound as f64 / 1000.0),
+                None => format!(">= {:.2}ms", JITTER_BUCKETS[n - 1] as f64 / 1000.0),
+            };
+            let line = format!("{label:<10} {count:>8} ");
+            let bar = "#".repeat((count * BAR_WIDTH).div_ceil(most) as usize);
+            write!(f, "\n        {}", (line + bar.as_str()).trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, I, D, A> Emulator<'a, I, D, A>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    // Measure how closely the run keeps to the clock rate, within `tolerance` (a fraction
+    // of the clock rate); see `fairness_report`
+    pub fn check_fairness(&mut self, tolerance: f64) {
+        self.fairness = Some(FairnessMonitor::new(tolerance));
+    }
+
+    pub fn fairness_report(&self) -> Option<FairnessReport> {
+        self.fairness.as_ref().map(|monitor| FairnessReport {
+            clock_rate: self.time.clock_rate() as f64,
+            tolerance: monitor.tolerance,
+            rates: monitor.rates.clone(),
+            jitter: monitor.jitter.to_vec(),
+        })
+    }
+
+    // Called by the run loop after each pass, with how long it asked to sleep for and
+    // how long it actually did
+    pub(super) fn observe_pacing(&mut self, sleep: Option<(Duration, Duration)>) {
+        if let Some((requested, actual)) = sleep {
+            self.profile_sleep(requested, actual);
+        }
+        let Some(monitor) = &mut self.fairness else {
+            return;
+        };
+
+        if self.paused {
+            monitor.restart(self.time.cycles);
+            return;
+        }
+        if let Some((requested, actual)) = sleep {
+            monitor.observe_sleep(requested, actual);
+        }
+        monitor.observe_cycles(self.time.cycles);
+    }
+}
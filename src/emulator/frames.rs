@@ -0,0 +1,96 @@
+use super::{EmuError, Emulator, Flow};
+use crate::driver::{AudioDevice, DisplayDevice, InputDevice};
+
+// What came of a call to `Emulator::run_frame`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FrameReport {
+    // 60Hz timer ticks so far, which number the frames of emulated time
+    pub tick: u64,
+    // Whether a frame was sent to the display device
+    pub presented: bool,
+    // Whether the machine is paused (by the user, a breakpoint, or a watchpoint), so no
+    // emulated time passed
+    pub paused: bool,
+}
+
+impl<'a, I, D, A> Emulator<'a, I, D, A>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    // Run one 60Hz frame of emulated time: poll the input device, execute the frame's worth
+    // of instructions, tick the timers, and present the frame and sound to the devices; for
+    // hosts that own the event loop and set the pace themselves (e.g. a GUI toolkit's redraw
+    // callback). Returns `None` once the program exits or the user quits.
+    //
+    //   Unlike `run_slice`, the wall clock is never consulted: each call runs a whole frame
+    //   however long it has been since the last one. While paused (or rewinding), a call
+    //   only handles inputs and redraws. Calls can be mixed with `run_slice`, which carries
+    //   on from the same state.
+    //
+    pub fn run_frame(&mut self) -> Result<Option<FrameReport>, EmuError> {
+        let mut state = self.take_slice_state();
+        let (tick, frames) = (self.summary.timer_ticks, self.summary.frames);
+
+        let result = loop {
+            let cycles = self.summary.cycles;
+            match self.run_pass(&mut state) {
+                Ok(Flow::Wait(_)) => {
+                    self.observe_pacing(None);
+                    // Done once the timers tick, or when nothing ran at all
+                    if self.summary.timer_ticks != tick || self.summary.cycles == cycles {
+                        break Ok(true);
+                    }
+                }
+                Ok(Flow::Exit) => break Ok(false),
+                Err(e) => break Err(e),
+            }
+        };
+
+        let running = self.end_slice(state, result)?;
+        Ok(running.then_some(FrameReport {
+            tick: self.summary.timer_ticks,
+            presented: self.summary.frames != frames,
+            paused: self.paused,
+        }))
+    }
+
+    // Iterator over `run_frame`, ending once the program exits or the user quits (or after
+    // an error)
+    pub fn frames(&mut self) -> Frames<'_, 'a, I, D, A> {
+        Frames {
+            emulator: self,
+            done: false,
+        }
+    }
+}
+
+// See `Emulator::frames`
+pub struct Frames<'e, 'a, I, D, A>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    emulator: &'e mut Emulator<'a, I, D, A>,
+    done: bool,
+}
+
+impl<I, D, A> Iterator for Frames<'_, '_, I, D, A>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    type Item = Result<FrameReport, EmuError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let frame = self.emulator.run_frame().transpose();
+        self.done = !matches!(frame, Some(Ok(_)));
+        frame
+    }
+}
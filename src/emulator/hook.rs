@@ -0,0 +1,80 @@
+use bitvec::slice::BitSlice;
+
+use super::Emulator;
+use crate::{
+    chip8::{opcode, MachineState, Opcode},
+    driver::{AudioDevice, DisplayDevice, InputDevice},
+};
+
+// Observer of a run, for tooling built on the emulator (e.g. tracers, coverage tools,
+// achievement triggers, or agents playing the game) that needs to see what the machine does
+// without taking over the run loop; see `Emulator::add_hook`
+//
+//   Every method does nothing by default, so a hook only implements what it watches. The
+//   machine state is only put together while hooks are registered, so a run without any
+//   costs nothing more.
+//
+pub trait ExecutionHook {
+    // Before each instruction executes, with the machine state it executes on (PC pointing
+    // at it); unrecognized instructions aren't seen
+    fn on_instruction(&mut self, _state: &MachineState, _opcode: Opcode) {}
+
+    // At the end of each 60Hz frame of emulated time, once the timers have ticked
+    fn on_frame(&mut self, _state: &MachineState) {}
+
+    // As each frame is presented, with its bitplanes and dims (w, h) as the display device
+    // gets them, but for the orientation; frames are presented again unchanged at times
+    // (e.g. while paused)
+    fn on_draw(&mut self, _planes: &[&BitSlice<usize>], _dims: (usize, usize)) {}
+}
+
+impl<H: ExecutionHook + ?Sized> ExecutionHook for Box<H> {
+    fn on_instruction(&mut self, state: &MachineState, opcode: Opcode) {
+        (**self).on_instruction(state, opcode);
+    }
+
+    fn on_frame(&mut self, state: &MachineState) {
+        (**self).on_frame(state);
+    }
+
+    fn on_draw(&mut self, planes: &[&BitSlice<usize>], dims: (usize, usize)) {
+        (**self).on_draw(planes, dims);
+    }
+}
+
+impl<'a, I, D, A> Emulator<'a, I, D, A>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    // Register `hook` to observe the run from here on, after any registered already
+    pub fn add_hook(&mut self, hook: impl ExecutionHook + 'a) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    // Called by `exec_cycle` before every instruction executed
+    pub(super) fn hook_instruction(&mut self, opcode: u16) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        let Ok(opcode) = opcode::decode(opcode, self.system.mode()) else {
+            return;
+        };
+        let state = self.system.dump_state();
+        for hook in &mut self.hooks {
+            hook.on_instruction(&state, opcode);
+        }
+    }
+
+    // Called by the run loop as the timers tick
+    pub(super) fn hook_frame(&mut self) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        let state = self.system.dump_state();
+        for hook in &mut self.hooks {
+            hook.on_frame(&state);
+        }
+    }
+}
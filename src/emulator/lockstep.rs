@@ -0,0 +1,180 @@
+use std::fmt;
+
+use crate::{
+    chip8::{disasm, Chip8, ChipError, Events, Mode, Platform, NUM_DATA_REGS},
+    emulator::MachineTime,
+};
+
+// Something that differs between the two instances, with the first's value, then the
+// second's
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Difference {
+    Pc(u16, u16),
+    I(u16, u16),
+    V(usize, u8, u8),
+    Stack(Vec<u16>, Vec<u16>),
+    // The display contents (or resolution)
+    Display,
+    // One of them halted on an error (or exited), and the other didn't
+    Halted(Option<String>, Option<String>),
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let halted = |reason: &Option<String>| reason.clone().unwrap_or("running".to_owned());
+        match self {
+            Difference::Pc(a, b) => write!(f, "PC  {a:#05X} vs {b:#05X}"),
+            Difference::I(a, b) => write!(f, "I   {a:#05X} vs {b:#05X}"),
+            Difference::V(n, a, b) => write!(f, "V{n:X}  {a:02X} vs {b:02X}"),
+            Difference::Stack(a, b) => write!(f, "stack {a:03X?} vs {b:03X?}"),
+            Difference::Display => write!(f, "display"),
+            Difference::Halted(a, b) => write!(f, "{} vs {}", halted(a), halted(b)),
+        }
+    }
+}
+
+// Where two runs of a ROM first parted ways
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Divergence {
+    // Instructions each had executed, counting the one that set them apart
+    pub steps: u64,
+    // That instruction, as the first instance saw it
+    pub pc: u16,
+    pub opcode: u16,
+    pub mode: Mode,
+    pub differences: Vec<Difference>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "diverged after {} instructions, at {:#05X}  {:04X}  {}",
+            self.steps,
+            self.pc,
+            self.opcode,
+            disasm::mnemonic(self.opcode, self.mode)
+        )?;
+        for difference in &self.differences {
+            write!(f, "\n    {difference}")?;
+        }
+        Ok(())
+    }
+}
+
+// How a lockstep run ended
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Diverged(Divergence),
+    // Both ran the whole budget of instructions without parting ways
+    BudgetExhausted,
+    // Both exited (SCHIP 00FD) at the same point
+    Exited(u64),
+    // Both halted on the same error at the same point
+    Crashed(u64, ChipError),
+}
+
+// Run `rom` on two machines set up as `platforms` in lockstep, one instruction at a time,
+// until their PC, registers, stack, or display differ, or `budget` instructions have run
+// on each; for finding out which quirk (or platform) a misbehaving ROM expects
+//
+//   Both see the same RNG `seed` and no keys pressed, and their timers tick at the first
+//   platform's clock rate, so they can only part ways over how they execute instructions.
+//   Memory isn't compared; what's written differently shows once it's read back.
+//
+pub fn run_lockstep(
+    rom: &[u8],
+    platforms: [&Platform; 2],
+    seed: u64,
+    budget: u64,
+) -> Result<Outcome, ChipError> {
+    let mut systems = platforms.map(|platform| {
+        let mut system = Chip8::new();
+        system.set_platform(platform);
+        system.set_rng_seed(seed);
+        system
+    });
+    for system in &mut systems {
+        system.load_rom(rom)?;
+    }
+    let mut times = [(); 2].map(|()| MachineTime::new(platforms[0].clock_hz));
+
+    for steps in 1..=budget {
+        let first = &systems[0];
+        let pc = first.pc();
+        let opcode = u16::from_be_bytes([first.read_mem(pc), first.read_mem(pc.wrapping_add(1))]);
+        let mode = first.mode();
+
+        let [result_a, result_b] = [0, 1].map(|n| step(&mut systems[n], &mut times[n]));
+        let differences = match (result_a, result_b) {
+            (Err(a), Err(b)) if a == b => return Ok(Outcome::Crashed(steps, a)),
+            (Ok(a), Ok(b))
+                if a.contains(Events::PROGRAM_EXIT) == b.contains(Events::PROGRAM_EXIT) =>
+            {
+                let redrawn = (a | b).contains(Events::REFRESH_DISPLAY);
+                let differences = compare(&systems, redrawn);
+                if differences.is_empty() && a.contains(Events::PROGRAM_EXIT) {
+                    return Ok(Outcome::Exited(steps));
+                }
+                differences
+            }
+            (a, b) => vec![Difference::Halted(halted(a), halted(b))],
+        };
+
+        if !differences.is_empty() {
+            return Ok(Outcome::Diverged(Divergence {
+                steps,
+                pc,
+                opcode,
+                mode,
+                differences,
+            }));
+        }
+    }
+    Ok(Outcome::BudgetExhausted)
+}
+
+// Execute one instruction, ticking the timers when due
+fn step(system: &mut Chip8, time: &mut MachineTime) -> Result<Events, ChipError> {
+    let events = system.step()?;
+    let tick_due = match events.contains(Events::DISPLAY_WAIT) {
+        true => time.step_to_tick(1),
+        false => time.step(1),
+    };
+    if tick_due {
+        system.tick_timers();
+    }
+    Ok(events)
+}
+
+// Why a machine stopped, if it did
+fn halted(result: Result<Events, ChipError>) -> Option<String> {
+    match result {
+        Ok(events) if events.contains(Events::PROGRAM_EXIT) => Some("exited".to_owned()),
+        Ok(_) => None,
+        Err(err) => Some(err.to_string()),
+    }
+}
+
+fn compare([a, b]: &[Chip8; 2], redrawn: bool) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    if a.pc() != b.pc() {
+        differences.push(Difference::Pc(a.pc(), b.pc()));
+    }
+    if a.i_reg() != b.i_reg() {
+        differences.push(Difference::I(a.i_reg(), b.i_reg()));
+    }
+    for n in 0..NUM_DATA_REGS {
+        if a.v_reg()[n] != b.v_reg()[n] {
+            differences.push(Difference::V(n, a.v_reg()[n], b.v_reg()[n]));
+        }
+    }
+    if a.stack() != b.stack() {
+        differences.push(Difference::Stack(a.stack().to_vec(), b.stack().to_vec()));
+    }
+    // Only hashed when something was drawn, as that's most of the time spent
+    if redrawn && (a.display_dims() != b.display_dims() || a.display_hash() != b.display_hash()) {
+        differences.push(Difference::Display);
+    }
+    differences
+}
@@ -0,0 +1,1564 @@
+pub mod batch;
+mod builder;
+mod crash;
+mod debugger;
+mod error;
+mod fairness;
+mod frames;
+mod hook;
+pub mod lockstep;
+mod netplay;
+mod patch;
+mod picker;
+mod practice;
+mod profiler;
+#[cfg(feature = "recording")]
+mod recorder;
+mod reload;
+mod rewind;
+pub mod rom;
+pub mod romdb;
+#[cfg(feature = "scripting")]
+mod script;
+pub mod snapshot;
+mod stats;
+mod summary;
+#[cfg(feature = "async")]
+mod task;
+mod time;
+mod timers;
+pub mod trace;
+
+use std::{cell::RefCell, fs, io, ops::Deref, path::PathBuf, time::Duration};
+
+use bitvec::{slice::BitSlice, vec::BitVec};
+use smallvec::SmallVec;
+
+#[cfg(feature = "recording")]
+use crate::driver::palette::Palette;
+use crate::{
+    chip8,
+    chip8::Chip8,
+    diagnostics,
+    driver::{
+        ghosting::Ghosting, orientation::Orientation, palette, AudioDevice, DisplayDevice,
+        InputDevice, Rect, StatusMsg, MAX_FRAME_PLANES,
+    },
+};
+use crash::History;
+use fairness::FairnessMonitor;
+use practice::Practice;
+use profiler::Profiler;
+#[cfg(feature = "recording")]
+use recorder::Recorder;
+use reload::RomWatcher;
+use rewind::Rewind;
+use stats::LiveStats;
+use time::{Instant, Pacer, Sleeper};
+
+pub use builder::{BoxedEmulator, EmulatorBuilder};
+pub use debugger::{Debugger, OpcodePattern};
+pub use error::EmuError;
+pub use fairness::FairnessReport;
+pub use frames::{FrameReport, Frames};
+pub use hook::ExecutionHook;
+pub use netplay::{Netplay, DEFAULT_INPUT_DELAY};
+pub use patch::Patch;
+pub use picker::RomPicker;
+pub use practice::LoopTrigger;
+pub use profiler::ProfileReport;
+#[cfg(feature = "recording")]
+pub use recorder::RecordFormat;
+#[cfg(feature = "scripting")]
+pub use script::Script;
+pub use summary::RunSummary;
+pub use time::{MachineTime, Pacing};
+
+pub use crate::chip8::Events;
+
+// Emulator I/O signals; this is equivalent to ret codes / interrupts in embedded environments
+#[derive(PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum Signal {
+    None, // No new events
+    ProgramExit,
+    NewInputs,
+    MarkState,
+    RestoreMark,
+    RotateDisplay,
+    MirrorDisplay,
+    CycleProfile,
+    SaveState,
+    LoadState,
+    Reset, // Starts the program over from the top, as it was loaded
+    Rewind,
+    Pause, // Pauses, or resumes if already paused
+    Resume,
+    Step,
+    FrameAdvance, // Runs to the end of the current 60Hz frame while paused
+    SpeedUp,      // Doubles the clock speed
+    SpeedDown,    // Halves the clock speed
+    TurboOn,      // Fast-forwards until `TurboOff`
+    TurboOff,
+    VolumeUp,   // Raises the volume a step
+    VolumeDown, // Lowers the volume a step
+    ToggleMute,
+    ToggleBreakpoint,
+    ScrollMemoryUp,
+    ScrollMemoryDown,
+    DebugCommand, // A console command is ready (see `driver::InputDevice::take_command`)
+    ToggleRecording,
+    SwitchFrontend,          // Moves the session between the terminal and a window
+    DisplayAttached,         // Another display took over; it's sent the current frame
+    Resized,                 // The terminal was resized; the current frame is sent again to fit
+    Fault(chip8::FaultInfo), // The ROM halted the machine; the fault screen comes up
+}
+
+// Designs for controlling the flow of I/O can vary greatly in both layout
+// and complexity depending on the environment. For our purposes, the emulator
+// will act as a simple messaging interface between the guest system and
+// connected peripheral devices while serving the host system loop.
+//
+// For more info:
+//   - https://en.wikipedia.org/wiki/Emulator#Input/output_(I/O)
+//   - https://en.wikipedia.org/wiki/Memory-mapped_I/O_and_port-mapped_I/O
+//   - https://en.wikipedia.org/wiki/Autonomous_peripheral_operation
+//
+// A CHIP-8 emulator
+pub struct Emulator<'a, I, D, A>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    // The (guest) system being emulated
+    system: Chip8,
+    // File that save states are written to and read from
+    state_path: Option<PathBuf>,
+    // File the flag registers are kept in between runs, if they are
+    flags_path: Option<PathBuf>,
+    // The flag registers as last read from or written to that file
+    saved_flags: [u8; chip8::NUM_FLAGS],
+    // File that recordings are made to, the colors they're made in, and the recording
+    // in progress, if any
+    #[cfg(feature = "recording")]
+    record_path: Option<PathBuf>,
+    #[cfg(feature = "recording")]
+    record_palette: Palette,
+    #[cfg(feature = "recording")]
+    recorder: Option<Recorder>,
+    // Emulated time; the guest's clock speed is set here
+    time: MachineTime,
+    // How many clock cycles each instruction takes
+    timing: chip8::Timing,
+    // How emulated time is kept in step with the wall clock
+    pacing: Pacing,
+    // Marked machine state for A/B practice looping
+    practice: Practice,
+    // Recent machine states to rewind through, one per timer tick, and how many seconds'
+    // worth that is
+    rewind: Rewind,
+    rewind_secs: usize,
+    // Whether execution is paused; the display and hotkeys stay live meanwhile
+    paused: bool,
+    // Whether the turbo key is held, fast-forwarding the machine
+    turbo: bool,
+    // Volume the audio device plays at, in [0, 1], and whether it's muted meanwhile
+    volume: f32,
+    muted: bool,
+    // Breakpoints, and the machine state panel shown while paused
+    debugger: Debugger,
+    // Whether unrecognized instructions are skipped over rather than halting the machine
+    lenient: bool,
+    // File the machine state is dumped to if the program crashes, and the instructions
+    // leading up to it
+    crash_path: Option<PathBuf>,
+    history: History,
+    // Whether the program crashing brings up the fault screen rather than ending the run,
+    // and the fault it's up for, with the error to end the run with if the user quits
+    fault_screen: bool,
+    fault: Option<(chip8::FaultInfo, EmuError)>,
+    // Observers of the run (see `add_hook`)
+    hooks: Vec<Box<dyn ExecutionHook + 'a>>,
+    // Whether inputs are polled at the start of every frame only, rather than on a wall-clock
+    // schedule (see `set_frame_locked_input`)
+    frame_locked_input: bool,
+    // Whether loops polling the delay timer are skipped through like other idle loops (see
+    // `idle_loop`)
+    skip_timer_polls: bool,
+    // Cycles, and emulated time, after which the run ends
+    cycle_limit: Option<u64>,
+    time_limit: Option<Duration>,
+    // Measurements of how well the run keeps to the clock rate, if asked for
+    fairness: Option<FairnessMonitor>,
+    // Counts of the instructions executed, if asked for
+    profiler: Option<Profiler>,
+    // Peer to play in lockstep with, if any
+    netplay: Option<Netplay>,
+    // Rotation/mirroring applied to frames before they reach the display device
+    orientation: Orientation,
+    // Anti-flicker blending of frames with the ones before them, if on
+    ghosting: Option<Ghosting>,
+    // Scratch buffers holding the planes of the frame colored for CHIP-8X, and transformed
+    color_frame: [BitVec<usize>; MAX_FRAME_PLANES],
+    oriented_frame: [BitVec<usize>; MAX_FRAME_PLANES],
+    // Program as it was loaded, to load again on a reset, and the file it's reloaded from
+    // when it changes, if watched
+    rom: Vec<u8>,
+    rom_watcher: Option<RomWatcher>,
+    // Changes made to the program as it's loaded
+    patch: Patch,
+    // Statistics reported at the end of a session
+    summary: RunSummary,
+    // Frame and instruction rates shown while running, and whether the display keeps them
+    // up in a corner
+    stats: LiveStats,
+    show_stats: bool,
+    // Main loop state between calls to `run_slice` or `run_frame`, once either has been
+    // called
+    slice_state: Option<LoopState>,
+    // --- Peripherals ---
+    input: Peripheral<'a, I>,
+    display: Peripheral<'a, D>,
+    audio: Peripheral<'a, A>,
+}
+
+// Peripheral the emulator either borrows, so it can be shared (e.g. one window as both
+// input and display device), or owns outright
+enum Peripheral<'a, T> {
+    Borrowed(&'a RefCell<T>),
+    Owned(RefCell<T>),
+}
+
+impl<T> Deref for Peripheral<'_, T> {
+    type Target = RefCell<T>;
+
+    fn deref(&self) -> &RefCell<T> {
+        match self {
+            Peripheral::Borrowed(device) => device,
+            Peripheral::Owned(device) => device,
+        }
+    }
+}
+
+pub const DEFAULT_CLOCK_FREQ: f32 = chip8::Platform::CHIP8.clock_hz;
+pub const MAX_CLOCK_FREQ: f32 = 1_000_000.0;
+pub const MAX_TIMER_FREQ: f32 = 1000.0;
+// Factor the clock speed changes by per press of the speed hotkeys
+const SPEED_STEP: f32 = 2.0;
+// Amount the volume changes by per press of the volume hotkeys
+const VOLUME_STEP: f32 = 0.1;
+// How many times faster than real time the machine runs while the turbo key is held
+const TURBO_SPEED: f64 = 8.0;
+// How often inputs are polled (and the display redrawn) while paused; a 60Hz display's
+// refresh, whatever the timer rate, as no emulated time goes by
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_micros(16_667);
+// Shortest time between input polls otherwise (250Hz), which keep to a wall-clock schedule
+// of their own: they go on through the waits between frames, and frames run back to back
+// (e.g. fast-forwarding, or unthrottled) share them rather than each making one
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(4);
+// Seconds of emulated time that can be rewound by default
+pub const DEFAULT_REWIND_SECS: usize = 10;
+// Longest `run_slice` runs for without being asked to wait, so the host's frame is never
+// held up (e.g. when unthrottled, or catching up on lag); the host's frames are its
+// display's (60Hz, typically), not the emulated ones, so this is whatever the timer rate
+const MAX_SLICE: Duration = Duration::from_micros(16_667);
+
+// State carried between passes of the main loop
+struct LoopState {
+    // Whether anything was drawn since the last frame was presented
+    frame_pending: bool,
+    // Whether execution just resumed, so the breakpoint it stopped at doesn't fire again
+    resumed: bool,
+    // When inputs were last polled (see `INPUT_POLL_INTERVAL`), and a signal polled during
+    // a wait, which the next pass handles
+    polled: Option<Instant>,
+    signal: Option<Signal>,
+    // Wall-clock synchronization - all other timing derives from emulated time
+    pacer: Pacer,
+    // Clock cycles the instruction executed last took, and whether it waits out the rest
+    // of the frame, for the vertical blank (see `Quirks::display_wait`) or in an idle loop
+    // (see `idle_loop`)
+    cost: u32,
+    frame_wait: bool,
+    // Whether the instruction executed last hit a watchpoint, pausing the machine
+    watch_hit: bool,
+    // Whether the program stored the flag registers since they were last saved; they're
+    // written out once per pass rather than on every FX75
+    flags_stored: bool,
+    // What the instruction executed last halted the machine with, to bring up the fault
+    // screen for
+    fault: Option<chip8::FaultInfo>,
+}
+
+// What the main loop does after a pass
+enum Flow {
+    // Carry on once this much wall-clock time has passed (zero to carry on right away)
+    Wait(Duration),
+    Exit,
+}
+
+impl<'a, I, D, A> Emulator<'a, I, D, A>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    pub fn with_peripherals<'p: 'a>(
+        input: &'p RefCell<I>,
+        display: &'p RefCell<D>,
+        audio: &'p RefCell<A>,
+    ) -> Emulator<'a, I, D, A> {
+        Emulator::new(
+            Peripheral::Borrowed(input),
+            Peripheral::Borrowed(display),
+            Peripheral::Borrowed(audio),
+        )
+    }
+
+    // Take ownership of the peripherals, which then can't be shared with anything else; see
+    // `EmulatorBuilder` for assembling them at runtime
+    pub fn with_owned_peripherals(input: I, display: D, audio: A) -> Emulator<'a, I, D, A> {
+        Emulator::new(
+            Peripheral::Owned(RefCell::new(input)),
+            Peripheral::Owned(RefCell::new(display)),
+            Peripheral::Owned(RefCell::new(audio)),
+        )
+    }
+
+    // Swap in other peripherals mid-run (e.g. a window for the terminal), which are brought
+    // up to date with the machine right away; the ones swapped out are let go of
+    pub fn attach_peripherals<'p: 'a>(
+        &mut self,
+        input: &'p RefCell<I>,
+        display: &'p RefCell<D>,
+        audio: &'p RefCell<A>,
+    ) {
+        self.attach(
+            Peripheral::Borrowed(input),
+            Peripheral::Borrowed(display),
+            Peripheral::Borrowed(audio),
+        );
+    }
+
+    pub fn attach_owned_peripherals(&mut self, input: I, display: D, audio: A) {
+        self.attach(
+            Peripheral::Owned(RefCell::new(input)),
+            Peripheral::Owned(RefCell::new(display)),
+            Peripheral::Owned(RefCell::new(audio)),
+        );
+    }
+
+    fn attach(
+        &mut self,
+        input: Peripheral<'a, I>,
+        display: Peripheral<'a, D>,
+        audio: Peripheral<'a, A>,
+    ) {
+        self.input = input;
+        self.display = display;
+        self.audio = audio;
+        self.sync_peripherals();
+    }
+
+    fn new(
+        input: Peripheral<'a, I>,
+        display: Peripheral<'a, D>,
+        audio: Peripheral<'a, A>,
+    ) -> Emulator<'a, I, D, A> {
+        Emulator {
+            system: Chip8::new(),
+            state_path: None,
+            flags_path: None,
+            saved_flags: [0; chip8::NUM_FLAGS],
+            #[cfg(feature = "recording")]
+            record_path: None,
+            #[cfg(feature = "recording")]
+            record_palette: Palette::default(),
+            #[cfg(feature = "recording")]
+            recorder: None,
+            time: MachineTime::new(DEFAULT_CLOCK_FREQ),
+            timing: chip8::Timing::default(),
+            pacing: Pacing::default(),
+            practice: Practice::new(LoopTrigger::Key),
+            // A state per tick at the default timer rate; `set_timer_freq` resizes it
+            rewind: Rewind::new(DEFAULT_REWIND_SECS * chip8::TIMER_FREQ as usize),
+            rewind_secs: DEFAULT_REWIND_SECS,
+            paused: false,
+            turbo: false,
+            volume: 1.0,
+            muted: false,
+            debugger: Debugger::new(),
+            lenient: false,
+            crash_path: None,
+            history: History::new(),
+            fault_screen: false,
+            fault: None,
+            hooks: Vec::new(),
+            frame_locked_input: false,
+            skip_timer_polls: true,
+            cycle_limit: None,
+            time_limit: None,
+            fairness: None,
+            profiler: None,
+            netplay: None,
+            orientation: Orientation::default(),
+            ghosting: None,
+            color_frame: Default::default(),
+            oriented_frame: Default::default(),
+            rom: Vec::new(),
+            rom_watcher: None,
+            patch: Patch::default(),
+            summary: RunSummary::new(),
+            stats: LiveStats::new(),
+            show_stats: false,
+            slice_state: None,
+            input,
+            display,
+            audio,
+        }
+    }
+
+    // Guest clock speed in Hz; emulated time (and so the CHIP-8 timers) is derived from it
+    pub fn set_clock_speed(&mut self, freq: f32) {
+        self.time.set_clock_rate(freq);
+    }
+
+    // How many times a second of emulated time the delay and sound timers tick, and so how
+    // long a frame is; 60Hz (`chip8::TIMER_FREQ`) unless experimenting, or for the 50Hz of
+    // PAL machines
+    pub fn set_timer_freq(&mut self, freq: f32) {
+        self.time.set_timer_rate(freq);
+        self.summary.timer_freq = freq;
+        self.set_rewind_length(self.rewind_secs);
+    }
+
+    // How long instructions take; with anything but flat timing the clock speed should be
+    // set to match (e.g. `chip8::VIP_CYCLE_RATE`)
+    pub fn set_timing(&mut self, timing: chip8::Timing) {
+        self.timing = timing;
+    }
+
+    pub fn set_pacing(&mut self, pacing: Pacing) {
+        self.pacing = pacing;
+    }
+
+    // Poll inputs at the start of every frame only, however fast frames go by, rather than
+    // every `INPUT_POLL_INTERVAL` of wall-clock time, between frames too; for inputs that
+    // have to land on the same frames from run to run (e.g. a script's, running headless)
+    pub fn set_frame_locked_input(&mut self, on: bool) {
+        self.frame_locked_input = on;
+    }
+
+    // Skip the rest of the frame once the program settles into polling the delay timer
+    // until it runs out (on by default), as is always done for jumps to self and FX0A
+    pub fn set_skip_timer_polls(&mut self, on: bool) {
+        self.skip_timer_polls = on;
+    }
+
+    pub fn set_loop_trigger(&mut self, trigger: LoopTrigger) {
+        self.practice.set_trigger(trigger);
+    }
+
+    // How many seconds of emulated time can be rewound; 0 disables rewinding
+    pub fn set_rewind_length(&mut self, secs: usize) {
+        self.rewind_secs = secs;
+        let ticks = secs as f32 * self.time.timer_rate();
+        self.rewind.set_capacity(ticks.round() as usize);
+    }
+
+    // Skip unrecognized instructions (logging them in the summary) instead of stopping; ROM
+    // dumps often contain data that the program counter occasionally wanders into
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    // Dump the machine state (registers, stack, the last instructions executed, and memory)
+    // to `path` if the program crashes, e.g. on an unrecognized instruction, for debugging
+    // the ROM
+    pub fn set_crash_path(&mut self, path: impl Into<PathBuf>) {
+        self.crash_path = Some(path.into());
+    }
+
+    // Show a fault screen (the error, PC, and registers) beside the display if the program
+    // crashes, and hold the machine there until the user resets it or quits, rather than
+    // ending the run with the error right away
+    pub fn set_fault_screen(&mut self, on: bool) {
+        self.fault_screen = on;
+    }
+
+    // Have the display keep the achieved instruction, frame, and timer tick rates up in a
+    // corner, to tell whether it keeps up with the clock rate
+    pub fn set_show_stats(&mut self, show: bool) {
+        self.show_stats = show;
+    }
+
+    // Keep a log of sound timer activations in the summary, for `RunSummary::sound_log_csv`
+    pub fn set_sound_log(&mut self, on: bool) {
+        self.summary.set_sound_log(on);
+    }
+
+    // Volume to play sound at, from 0 (silent) to 1 (full)
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        self.push_volume();
+    }
+
+    pub fn set_debugger(&mut self, debugger: Debugger) {
+        self.debugger = debugger;
+    }
+
+    // Pause (or resume) the machine; set before running, the ROM waits at its first
+    // instruction, e.g. for breakpoints in its startup code to be set
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    // Set a watchpoint (see `Debugger::add_watchpoint`), after `set_debugger`
+    pub fn add_watchpoint(&mut self, watchpoint: chip8::Watchpoint) -> Result<(), String> {
+        self.debugger.add_watchpoint(&mut self.system, watchpoint)
+    }
+
+    // End the run once either limit is reached
+    pub fn set_run_limits(&mut self, cycles: Option<u64>, time: Option<Duration>) {
+        self.cycle_limit = cycles;
+        self.time_limit = time;
+    }
+
+    // Hash of the current display contents (see `Chip8::display_hash`)
+    pub fn display_hash(&self) -> u64 {
+        self.system.display_hash()
+    }
+
+    // Play in lockstep with the peer of `netplay`; call once the program (and any save
+    // state) is loaded, as the peer has to start from the same state
+    pub fn start_netplay(&mut self, mut netplay: Netplay) -> Result<(), EmuError> {
+        self.system.set_rng_seed(netplay.seed());
+        netplay
+            .start(&self.system.save_state(), self.time.clock_rate())
+            .map_err(EmuError::Netplay)?;
+        self.netplay = Some(netplay);
+        Ok(())
+    }
+
+    // Briefly show `text` on the display device, e.g. to confirm a hotkey did something
+    pub fn notify(&self, text: &str) {
+        self.display.borrow_mut().receive_notification(text);
+    }
+
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    // Blend frames with the last few so pixels fade out rather than flicker (see `Ghosting`)
+    pub fn set_ghosting(&mut self, on: bool) {
+        self.ghosting = on.then(Ghosting::default);
+    }
+
+    pub fn set_mode(&mut self, mode: chip8::Mode) {
+        self.system.set_mode(mode);
+    }
+
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.system.set_rng_seed(seed);
+    }
+
+    pub fn set_quirks(&mut self, quirks: chip8::Quirks) {
+        self.system.set_quirks(quirks);
+    }
+
+    // Set up the machine, clock rate included, as `platform` was
+    pub fn set_platform(&mut self, platform: &chip8::Platform) {
+        self.system.set_platform(platform);
+        self.set_clock_speed(platform.clock_hz);
+    }
+
+    // See `Chip8::set_memory_size`; after `set_platform`
+    pub fn set_memory_size(&mut self, size: usize) -> bool {
+        self.system.set_memory_size(size)
+    }
+
+    pub fn set_stack_depth(&mut self, depth: usize) -> bool {
+        self.system.set_stack_depth(depth)
+    }
+
+    pub fn set_trainer(&mut self, trainer: chip8::Trainer) {
+        self.system.set_trainer(trainer);
+    }
+
+    // Patch programs loaded from here on (including ROMs reloaded as they change) with
+    // `patch`; loading fails if it doesn't fit the program
+    pub fn set_patch(&mut self, patch: Patch) {
+        self.patch = patch;
+    }
+
+    pub fn load_program(&mut self, filepath: &str) -> Result<(), EmuError> {
+        let rom = rom::read_rom(filepath)?;
+        self.load_rom(&rom)
+    }
+
+    // Load program bytes already read (e.g. by `rom::read_rom`) as they go into memory
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), EmuError> {
+        let rom = self.patched(rom.to_vec())?;
+        self.system.load_rom(&rom)?;
+        self.rom = rom;
+        Ok(())
+    }
+
+    fn patched(&self, mut rom: Vec<u8>) -> Result<Vec<u8>, EmuError> {
+        self.patch.apply(&mut rom, self.system.mode().rom_start())?;
+        Ok(rom)
+    }
+
+    // Load a program already in memory (e.g. picked in a browser), in any of the formats
+    // `load_program` accepts; `name` stands in for its path in errors
+    pub fn load_program_data(&mut self, name: &str, data: Vec<u8>) -> Result<(), EmuError> {
+        let (rom, _) = rom::decode(data).map_err(|reason| EmuError::RomFormat {
+            path: name.to_owned(),
+            reason,
+        })?;
+        self.load_rom(&rom)
+    }
+
+    // Reload the program from the file at `path` whenever it changes (e.g. is assembled
+    // again), starting it over; `path` is where it was loaded from
+    pub fn watch_rom(&mut self, path: impl Into<PathBuf>) {
+        self.rom_watcher = Some(RomWatcher::new(path));
+    }
+
+    // Start the program over (the reset hotkey): the machine is powered back on (see
+    // `Chip8::reset`) and the program loaded again; a practice mark goes with the run it
+    // was set in
+    pub fn reset(&mut self) -> Result<(), EmuError> {
+        self.practice.clear();
+        self.system.reset();
+        self.system.load_rom(&self.rom)?;
+        self.system.receive_input(self.input.borrow().send_inputs());
+        self.redraw_display();
+        self.push_audio();
+        Ok(())
+    }
+
+    pub fn set_state_path(&mut self, path: impl Into<PathBuf>) {
+        self.state_path = Some(path.into());
+    }
+
+    // Keep the flag registers (SCHIP FX75/FX85) in the file at `path`, starting from what
+    // it holds if it exists
+    pub fn set_flags_path(&mut self, path: impl Into<PathBuf>) -> Result<(), EmuError> {
+        let path = path.into();
+        match fs::read(&path) {
+            Ok(data) => {
+                let mut flags = [0; chip8::NUM_FLAGS];
+                let len = data.len().min(chip8::NUM_FLAGS);
+                flags[..len].copy_from_slice(&data[..len]);
+                self.system.set_flags(flags);
+                self.saved_flags = flags;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(source) => {
+                return Err(EmuError::FlagsIo {
+                    path: path.display().to_string(),
+                    source,
+                })
+            }
+        }
+        self.flags_path = Some(path);
+        Ok(())
+    }
+
+    // Write the flag registers to their file, making its directory if need be, unless it
+    // already holds them
+    fn save_flags(&mut self) -> Result<(), EmuError> {
+        let Some(path) = &self.flags_path else {
+            return Ok(());
+        };
+        let flags = *self.system.flags();
+        if flags == self.saved_flags {
+            return Ok(());
+        }
+        path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(path, flags))
+            .map_err(|source| EmuError::FlagsIo {
+                path: path.display().to_string(),
+                source,
+            })?;
+        self.saved_flags = flags;
+        Ok(())
+    }
+
+    // Save the flag registers if the program stored them (FX75) since they last were
+    fn flush_flags(&mut self, state: &mut LoopState) {
+        // Losing the flags is no reason to stop the game
+        if std::mem::take(&mut state.flags_stored) {
+            if let Err(e) = self.save_flags() {
+                diagnostics::warn("flags", e.to_string());
+            }
+        }
+    }
+
+    // Write the machine state to the save state file
+    pub fn save_state(&self) -> Result<(), EmuError> {
+        let Some(path) = &self.state_path else {
+            return Ok(());
+        };
+        fs::write(path, self.system.save_state()).map_err(|source| EmuError::StateIo {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+
+    // Resume from the save state file; returns false if there is nothing to load
+    pub fn load_state(&mut self) -> Result<bool, EmuError> {
+        let Some(path) = &self.state_path else {
+            return Ok(false);
+        };
+        let state = match fs::read(path) {
+            Ok(state) => state,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(source) => {
+                return Err(EmuError::StateIo {
+                    path: path.display().to_string(),
+                    source,
+                })
+            }
+        };
+        self.system
+            .load_state(&state)
+            .map_err(|err| EmuError::BadState {
+                path: path.display().to_string(),
+                err,
+            })?;
+
+        // Key states belong to the host, not the saved machine
+        self.system.receive_input(self.input.borrow().send_inputs());
+        self.redraw_display();
+        Ok(true)
+    }
+
+    // File to record to (as an animated GIF or APNG, going by its extension), in `palette`'s
+    // colors; recording starts with `start_recording` or the recording hotkey
+    #[cfg(feature = "recording")]
+    pub fn set_record_path(&mut self, path: impl Into<PathBuf>, palette: Palette) {
+        self.record_path = Some(path.into());
+        self.record_palette = palette;
+    }
+
+    // Start recording the display to the record file, replacing whatever it holds
+    #[cfg(feature = "recording")]
+    pub fn start_recording(&mut self) -> Result<(), EmuError> {
+        let Some(path) = &self.record_path else {
+            return Ok(());
+        };
+        let recorder =
+            Recorder::start(path, self.record_palette).map_err(|source| EmuError::Recording {
+                path: path.display().to_string(),
+                source,
+            })?;
+        self.recorder = Some(recorder);
+        // Starts off with what's on display already
+        self.redraw_display();
+        Ok(())
+    }
+
+    // Finish the recording in progress, if any, once the frames recorded so far are written
+    pub fn stop_recording(&mut self) -> Result<(), EmuError> {
+        #[cfg(feature = "recording")]
+        if let Some(recorder) = self.recorder.take() {
+            let path = recorder.path().display().to_string();
+            return recorder
+                .finish()
+                .map_err(|source| EmuError::Recording { path, source });
+        }
+        Ok(())
+    }
+
+    pub fn summary(&self) -> &RunSummary {
+        &self.summary
+    }
+
+    pub fn time(&self) -> &MachineTime {
+        &self.time
+    }
+
+    // Run the emulator (single-threaded) until the program exits or the user quits;
+    // returns early if the guest system halts on a bad ROM (once the user quits, with the
+    // fault screen)
+    pub fn run(&mut self) -> Result<(), EmuError> {
+        self.summary.start();
+        let result = self.run_loop();
+        self.summary.stop();
+        result.and(self.stop_recording())
+    }
+
+    // Run the emulator until it has to wait on the wall clock, or for at most a 60Hz frame's
+    // worth of time, for hosts that own the event loop and mustn't be blocked (e.g. from a
+    // browser's `requestAnimationFrame` callback); call it again on each of the host's
+    // frames. Returns false once the program exits or the user quits.
+    pub fn run_slice(&mut self) -> Result<bool, EmuError> {
+        let mut state = self.take_slice_state();
+
+        let start = Instant::now();
+        let result = loop {
+            match self.run_pass(&mut state) {
+                Ok(Flow::Wait(wait)) if wait.is_zero() && start.elapsed() < MAX_SLICE => {
+                    self.observe_pacing(None)
+                }
+                Ok(Flow::Wait(_)) => break Ok(true),
+                Ok(Flow::Exit) => break Ok(false),
+                Err(e) => break Err(e),
+            }
+        };
+
+        self.end_slice(state, result)
+    }
+
+    // Loop state to carry on from in a slice, starting the run on the first one
+    fn take_slice_state(&mut self) -> LoopState {
+        match self.slice_state.take() {
+            Some(state) => state,
+            None => {
+                self.summary.start();
+                self.loop_state()
+            }
+        }
+    }
+
+    // Keep the loop state for the next slice, or end the run if `result` says it's over
+    fn end_slice(
+        &mut self,
+        state: LoopState,
+        result: Result<bool, EmuError>,
+    ) -> Result<bool, EmuError> {
+        match result {
+            Ok(true) => {
+                self.slice_state = Some(state);
+                result
+            }
+            _ => {
+                self.summary.stop();
+                result.and(self.stop_recording().map(|()| false))
+            }
+        }
+    }
+
+    fn run_loop(&mut self) -> Result<(), EmuError> {
+        let mut state = self.loop_state();
+        let mut sleeper = Sleeper::new();
+        while let Flow::Wait(wait) = self.run_pass(&mut state)? {
+            let mut slept = None;
+            if !wait.is_zero() {
+                let actual =
+                    sleeper.sleep(wait, INPUT_POLL_INTERVAL, || self.poll_between(&mut state));
+                slept = Some((wait, actual));
+            }
+            self.observe_pacing(slept);
+        }
+
+        Ok(())
+    }
+
+    fn loop_state(&self) -> LoopState {
+        let mut pacer = Pacer::new(self.pacing, self.time.elapsed(), self.time.frame());
+        if self.turbo {
+            pacer.set_speed(TURBO_SPEED, self.time.elapsed());
+        }
+        LoopState {
+            frame_pending: false,
+            resumed: false,
+            polled: None,
+            signal: None,
+            pacer,
+            cost: 1,
+            frame_wait: false,
+            watch_hit: false,
+            flags_stored: false,
+            fault: None,
+        }
+    }
+
+    // Whether frames run faster than real time, so not all of them can be presented
+    fn fast_forwarding(&self) -> bool {
+        self.pacing == Pacing::Unthrottled || self.turbo
+    }
+
+    // Multiply the clock speed by `factor`, within the range `--freq` accepts
+    fn scale_clock_speed(&mut self, factor: f32) {
+        let freq = (self.time.clock_rate() * factor).clamp(1.0, MAX_CLOCK_FREQ);
+        self.set_clock_speed(freq);
+        self.notify(&format!("Clock speed: {freq:.0} Hz"));
+    }
+
+    // Poll the input device, unless it was less than `INPUT_POLL_INTERVAL` ago, so how often
+    // that happens is down to the wall clock rather than how fast frames go by
+    fn poll_inputs(&mut self, state: &mut LoopState) -> Signal {
+        let recent = state
+            .polled
+            .is_some_and(|polled| polled.elapsed() < INPUT_POLL_INTERVAL);
+        if recent && !self.frame_locked_input {
+            return Signal::None;
+        }
+        state.polled = Some(Instant::now());
+        self.input.borrow_mut().handle_inputs()
+    }
+
+    // Poll inputs partway through a wait between passes: new keys are latched into the
+    // machine there and then, ready for the next frame, and anything else is left for the
+    // next pass to handle, with no more polls until it has
+    fn poll_between(&mut self, state: &mut LoopState) {
+        if self.frame_locked_input || state.signal.is_some() {
+            return;
+        }
+        match self.poll_inputs(state) {
+            Signal::None => (),
+            Signal::NewInputs => self.latch_inputs(),
+            signal => state.signal = Some(signal),
+        }
+    }
+
+    // Hand the input device's keys to the machine; they only reach it through the peer
+    // exchange during netplay
+    fn latch_inputs(&mut self) {
+        let keys = self.input.borrow().send_inputs();
+        match &mut self.netplay {
+            Some(netplay) => netplay.set_local(keys),
+            None => self.system.receive_input(keys),
+        }
+    }
+
+    // Carry on from a pause, whether it was asked for or a breakpoint's
+    fn resume(&mut self, state: &mut LoopState) {
+        self.paused = false;
+        self.notify("Resumed");
+        self.debugger.resume();
+        state.resumed = true;
+        // Don't try to make up for the time spent paused
+        state.pacer.resync(self.time.elapsed());
+        self.redraw_display();
+    }
+
+    // Step the volume by `steps` of `VOLUME_STEP`, unmuting it
+    fn step_volume(&mut self, steps: f32) {
+        self.muted = false;
+        self.set_volume(((self.volume + steps * VOLUME_STEP) / VOLUME_STEP).round() * VOLUME_STEP);
+        self.notify(&format!("Volume: {:.0}%", self.volume * 100.0));
+    }
+
+    fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.push_volume();
+        match self.muted {
+            true => self.notify("Muted"),
+            false => self.notify(&format!("Volume: {:.0}%", self.volume * 100.0)),
+        }
+    }
+
+    fn set_turbo(&mut self, state: &mut LoopState, turbo: bool) {
+        self.turbo = turbo;
+        let speed = match turbo {
+            true => TURBO_SPEED,
+            false => 1.0,
+        };
+        state.pacer.set_speed(speed, self.time.elapsed());
+    }
+
+    // One pass of the main loop, shared by `run`, `run_async`, `run_slice`, and `run_frame`;
+    // the caller does the waiting
+    //
+    //   Emulated time advances in fixed steps of a frame, one tick of the timers (60Hz unless
+    //   set otherwise): the clock rate's worth of instructions for a tick run back to back
+    //   (with fractions of an instruction carried over to the next frame by `MachineTime`),
+    //   then the timers tick exactly once and whatever was drawn is presented once. The wall
+    //   clock is only consulted between frames, so hiccups on the host delay frames without
+    //   dropping or doubling ticks.
+    //
+    fn run_pass(&mut self, state: &mut LoopState) -> Result<Flow, EmuError> {
+        ////// FRAME START //////
+
+        // --- Handle Inputs
+        // A fault comes in ahead of them, like any other signal
+        let event = match state.fault.take() {
+            Some(fault) => Signal::Fault(fault),
+            None => match state.signal.take() {
+                Some(signal) => signal,
+                None => self.poll_inputs(state),
+            },
+        };
+        if self.fault.is_some() {
+            return self.fault_pass(state, event);
+        }
+        let mut step = false;
+        let mut advance = false;
+
+        match event {
+            Signal::NewInputs => self.latch_inputs(),
+            // Anything that alters the machine state or stops the clock would leave the
+            // peer behind
+            Signal::MarkState
+            | Signal::RestoreMark
+            | Signal::LoadState
+            | Signal::Reset
+            | Signal::Rewind
+            | Signal::Pause
+            | Signal::Resume
+            | Signal::Step
+            | Signal::FrameAdvance
+            | Signal::SpeedUp
+            | Signal::SpeedDown
+            | Signal::TurboOn
+            | Signal::DebugCommand
+                if self.netplay.is_some() =>
+            {
+                self.notify("Not available during netplay")
+            }
+            Signal::ProgramExit => return Ok(Flow::Exit),
+            Signal::MarkState => {
+                self.practice.mark(&self.system, self.time.elapsed());
+                self.notify("Mark set");
+            }
+            Signal::RestoreMark => self.restore_mark(),
+            Signal::SaveState if self.state_path.is_some() => {
+                self.save_state()?;
+                self.notify("State saved");
+            }
+            Signal::LoadState if self.state_path.is_some() => match self.load_state()? {
+                true => self.notify("State loaded"),
+                false => self.notify("No saved state"),
+            },
+            Signal::SaveState | Signal::LoadState => self.notify("No save state file"),
+            Signal::Reset => {
+                self.reset()?;
+                self.notify("Reset");
+            }
+            #[cfg(feature = "recording")]
+            Signal::ToggleRecording if self.recorder.is_some() => {
+                self.stop_recording()?;
+                self.notify("Recording saved");
+            }
+            #[cfg(feature = "recording")]
+            Signal::ToggleRecording if self.record_path.is_some() => {
+                self.start_recording()?;
+                self.notify("Recording");
+            }
+            #[cfg(feature = "recording")]
+            Signal::ToggleRecording => self.notify("No recording file"),
+            #[cfg(not(feature = "recording"))]
+            Signal::ToggleRecording => self.notify("Recording not available in this build"),
+            Signal::SpeedUp => self.scale_clock_speed(SPEED_STEP),
+            Signal::SpeedDown => self.scale_clock_speed(1.0 / SPEED_STEP),
+            Signal::TurboOn => {
+                self.set_turbo(state, true);
+                self.notify(&format!("Turbo ({TURBO_SPEED}x)"));
+            }
+            Signal::TurboOff => self.set_turbo(state, false),
+            Signal::VolumeUp => self.step_volume(1.0),
+            Signal::VolumeDown => self.step_volume(-1.0),
+            Signal::ToggleMute => self.toggle_mute(),
+            // Sent for as long as the rewind key is held; the machine is paused meanwhile
+            Signal::Rewind => return Ok(Flow::Wait(self.rewind_tick())),
+            Signal::Pause if !self.paused => {
+                self.paused = true;
+                self.notify("Paused");
+            }
+            Signal::Pause | Signal::Resume if self.paused => self.resume(state),
+            // Already running
+            Signal::Pause | Signal::Resume => (),
+            // Debugger controls only have an effect while paused
+            Signal::Step => step = self.paused,
+            Signal::FrameAdvance => advance = self.paused,
+            Signal::ToggleBreakpoint if self.paused => {
+                let pc = self.system.pc();
+                match self.debugger.toggle_breakpoint(pc) {
+                    true => self.notify(&format!("Breakpoint set at {pc:#06X}")),
+                    false => self.notify(&format!("Breakpoint cleared at {pc:#06X}")),
+                }
+            }
+            Signal::ScrollMemoryUp if self.paused => self.debugger.scroll_memory(&self.system, -1),
+            Signal::ScrollMemoryDown if self.paused => self.debugger.scroll_memory(&self.system, 1),
+            Signal::ToggleBreakpoint | Signal::ScrollMemoryUp | Signal::ScrollMemoryDown => (),
+            Signal::DebugCommand => {
+                let command = self.input.borrow_mut().take_command();
+                if let Some(command) = command {
+                    match self.paused {
+                        true => {
+                            if self.debugger.execute(&mut self.system, &command) {
+                                self.resume(state);
+                            }
+                        }
+                        false => self
+                            .debugger
+                            .log(format!("error: pause before running '{command}'")),
+                    }
+                }
+            }
+            Signal::RotateDisplay => {
+                self.orientation.rotation = self.orientation.rotation.next();
+                self.notify(&format!("Rotated {} degrees", self.orientation.rotation));
+                self.redraw_display();
+            }
+            Signal::MirrorDisplay => {
+                self.orientation.mirror = !self.orientation.mirror;
+                match self.orientation.mirror {
+                    true => self.notify("Mirrored"),
+                    false => self.notify("Unmirrored"),
+                }
+                self.redraw_display();
+            }
+            // The input device has already switched profiles; redraw with the new settings
+            Signal::CycleProfile => self.redraw_display(),
+            // Frontends are switched between outside the emulator (see `driver::threaded`),
+            // which then has the new display caught up
+            Signal::SwitchFrontend => self.notify("No other frontend to switch to"),
+            Signal::DisplayAttached => self.sync_peripherals(),
+            Signal::Resized => self.redraw_display(),
+            Signal::Fault(fault) => {
+                let err = self.crash(fault.err.clone());
+                self.fault = Some((fault, err));
+                self.notify("ROM crashed");
+                self.redraw_display();
+                return Ok(Flow::Wait(PAUSE_POLL_INTERVAL));
+            }
+            Signal::None => (),
+        }
+
+        // --- Hot reload
+        if self.rom_watcher.as_mut().is_some_and(RomWatcher::changed) {
+            self.reload_rom();
+        }
+
+        if self.paused && !step && !advance {
+            // Keep presenting frames, as some display devices only pick up new inputs then
+            self.redraw_display();
+            return Ok(Flow::Wait(PAUSE_POLL_INTERVAL));
+        }
+
+        // --- CHIP-8 instruction cycles
+        // Run up to the end of the frame (or a single instruction when stepping); a frame
+        // cut short by a breakpoint or pause picks up where it left off
+        let frame_over = loop {
+            // A tick still owed from the last frame (with the clock slower than the timers,
+            // an instruction can take longer than a tick) makes a frame of its own
+            if !step && self.time.tick_overdue() {
+                break true;
+            }
+            if let Some(flow) = self.exec_cycle(state, step || advance)? {
+                self.flush_flags(state);
+                return Ok(flow);
+            }
+            let tick_due = match std::mem::take(&mut state.frame_wait) {
+                true => self.time.step_to_tick(state.cost),
+                false => self.time.step(state.cost),
+            };
+            if tick_due || step || std::mem::take(&mut state.watch_hit) {
+                break tick_due;
+            }
+        };
+        self.flush_flags(state);
+
+        // --- CHIP-8 timers
+        // Ticked once at the end of every frame of emulated time
+        if frame_over {
+            if let Some(netplay) = &mut self.netplay {
+                let keys = netplay.exchange().map_err(EmuError::Netplay)?;
+                self.system.receive_input(Some(keys));
+            }
+            self.system.tick_timers();
+            self.summary.timer_ticks += 1;
+            self.hook_frame();
+            self.rewind.push(&self.system);
+
+            // --- Handle Audio
+            // The sound is sent every frame whether or not it changed, so devices hear of
+            // it stopping as well as starting (e.g. the timer running out, or set to 0)
+            self.push_audio();
+
+            // Pixels fading out have to go on fading with nothing new drawn
+            if self.ghosting.as_ref().is_some_and(Ghosting::fading) {
+                state.frame_pending = true;
+            }
+        }
+
+        // --- Handle Display
+        // Presented once per frame, or at most once per frame's worth of wall-clock time
+        // when frames run faster than real time, so the display device can keep up
+        if state.frame_pending
+            && (step || advance || !self.fast_forwarding() || state.pacer.present_due())
+        {
+            self.refresh_display();
+            state.frame_pending = false;
+        }
+        ////// FRAME END //////
+
+        if step || advance {
+            return Ok(Flow::Wait(PAUSE_POLL_INTERVAL));
+        }
+
+        // --- Emulator clock speed
+        // Wait for the wall clock to catch up with emulated time
+        Ok(Flow::Wait(state.pacer.sync(self.time.elapsed())))
+    }
+
+    // Execute one instruction, unless the run ends or a breakpoint stops it first (returning
+    // what the main loop does then); `forced` runs it regardless of breakpoints, as when
+    // stepping or advancing a frame while paused
+    fn exec_cycle(
+        &mut self,
+        state: &mut LoopState,
+        forced: bool,
+    ) -> Result<Option<Flow>, EmuError> {
+        if self
+            .cycle_limit
+            .is_some_and(|limit| self.time.cycles >= limit)
+            || self
+                .time_limit
+                .is_some_and(|limit| self.time.elapsed() >= limit)
+        {
+            return Ok(Some(Flow::Exit));
+        }
+
+        if self.practice.triggered(&self.system, self.time.elapsed()) {
+            self.restore_mark();
+        }
+
+        // --- Breakpoints
+        // Checked before executing, so a break leaves the instruction at PC up next
+        if !forced && !std::mem::take(&mut state.resumed) && self.debugger.check(&self.system) {
+            self.paused = true;
+            self.redraw_display();
+            return Ok(Some(Flow::Wait(PAUSE_POLL_INTERVAL)));
+        }
+
+        let snapshot = trace::enabled().then(|| trace::Snapshot::take(&self.system));
+        let pc = self.system.pc();
+        let opcode = u16::from_be_bytes([
+            self.system.read_mem(pc),
+            self.system.read_mem(pc.wrapping_add(1)),
+        ]);
+        self.history.push(pc, opcode);
+        self.hook_instruction(opcode);
+        let events = match self.system.step() {
+            Err(chip8::ChipError::UnknownInstruction { pc, opcode }) if self.lenient => {
+                self.summary.log_unknown(pc, opcode);
+                self.system.advance_pc();
+                Events::NONE
+            }
+            // Brought up at the start of the next pass
+            Err(err) if self.fault_screen => {
+                state.fault = Some(self.system.fault(err));
+                return Ok(Some(Flow::Wait(Duration::ZERO)));
+            }
+            Err(err) => return Err(self.crash(err)),
+            Ok(events) => events,
+        };
+        self.summary.cycles += 1;
+        self.profile_instruction(pc, opcode);
+        if let Some(snapshot) = snapshot {
+            snapshot.trace(&self.system);
+        }
+        let skipped = self.system.pc() == pc.wrapping_add(4);
+        state.cost = self.timing.cost(opcode, self.system.v_reg(), skipped);
+
+        // --- Handle core events
+        // Draws are batched up until the end of the frame
+        if events.contains(Events::REFRESH_DISPLAY) {
+            state.frame_pending = true;
+        }
+        // A loop only a timer tick can get the program out of isn't worth spinning through
+        state.frame_wait = events.contains(Events::DISPLAY_WAIT)
+            || idle_loop(&self.system, pc, opcode, self.skip_timer_polls);
+        if events.contains(Events::SKIPPED_INSTRUCTION) {
+            self.summary.skipped += 1;
+            diagnostics::warn("chip8", format!("unsupported instruction {opcode:#06X}"));
+        }
+        // Started right away rather than at the end of the frame
+        if events.contains(Events::SOUND_STARTED) {
+            self.summary.log_sound(self.system.sound_timer());
+            self.push_audio();
+        }
+        if events.contains(Events::FLAGS_SAVED) {
+            state.flags_stored = true;
+        }
+        if events.contains(Events::PROGRAM_EXIT) {
+            return Ok(Some(Flow::Exit));
+        }
+        // The instruction has run by now, so the frame carries on up to this point before
+        // pausing (see `run_pass`)
+        if events.contains(Events::WATCHPOINT) {
+            if let Some(watchpoint) = self.system.watch_hit() {
+                self.debugger.watch_hit(pc, opcode, watchpoint);
+            }
+            self.paused = true;
+            state.watch_hit = true;
+        }
+
+        Ok(None)
+    }
+
+    // Pass of the main loop while the fault screen is up: the machine stays halted until the
+    // user resets it, or quits, ending the run with the fault
+    fn fault_pass(&mut self, state: &mut LoopState, event: Signal) -> Result<Flow, EmuError> {
+        match event {
+            Signal::ProgramExit => {
+                // Only here with a fault
+                let (_, err) = self.fault.take().unwrap();
+                return Err(err);
+            }
+            Signal::Reset => {
+                self.fault = None;
+                self.reset()?;
+                self.notify("Reset");
+                // Don't try to make up for the time spent on the fault screen
+                state.pacer.resync(self.time.elapsed());
+                return Ok(Flow::Wait(Duration::ZERO));
+            }
+            Signal::DisplayAttached => self.sync_peripherals(),
+            _ => (),
+        }
+        // Keep presenting frames, as some display devices only pick up new inputs then
+        self.redraw_display();
+        Ok(Flow::Wait(PAUSE_POLL_INTERVAL))
+    }
+
+    // Error to end the run with after the machine halted on `err`, having dumped its state
+    // if asked to; failing to dump it is only warned about, the crash being the real news
+    fn crash(&self, err: chip8::ChipError) -> EmuError {
+        let Some(path) = &self.crash_path else {
+            return EmuError::Chip(err);
+        };
+        match crash::write_dump(path, &self.system, &err, &self.history) {
+            Ok(()) => EmuError::Crashed {
+                err,
+                dump: path.display().to_string(),
+            },
+            Err(e) => {
+                diagnostics::warn(
+                    "crash",
+                    format!("failed to write crash dump to '{}': {e}", path.display()),
+                );
+                EmuError::Chip(err)
+            }
+        }
+    }
+
+    // Jump back to the marked practice state, keeping the current key states
+    // and showing the restored frame right away
+    fn restore_mark(&mut self) {
+        if self.practice.restore(&mut self.system, self.time.elapsed()) {
+            self.system.receive_input(self.input.borrow().send_inputs());
+            self.notify("Mark restored");
+            self.redraw_display();
+        }
+    }
+
+    // Start over with the watched ROM file as it now is; the program that was running is
+    // kept if the file can't be loaded (e.g. it's only half written)
+    fn reload_rom(&mut self) {
+        let Some(watcher) = &self.rom_watcher else {
+            return;
+        };
+        let path = watcher.path().to_string_lossy().into_owned();
+        match rom::read_rom(&path).and_then(|rom| self.patched(rom)) {
+            Ok(rom) => {
+                let old = std::mem::replace(&mut self.rom, rom);
+                match self.reset() {
+                    Ok(()) => self.notify("ROM reloaded"),
+                    // Too large to fit; back to the program that was running, from the top
+                    Err(e) => {
+                        self.rom = old;
+                        let _ = self.reset();
+                        self.notify(&e.to_string());
+                    }
+                }
+            }
+            Err(e) => self.notify(&e.to_string()),
+        }
+    }
+
+    // Step back one timer tick's worth of machine state; returns how long to wait before
+    // the next step so rewinding runs at normal speed
+    fn rewind_tick(&mut self) -> Duration {
+        if self.rewind.pop(&mut self.system) {
+            self.system.receive_input(self.input.borrow().send_inputs());
+        }
+        // Redraw even with nothing left to rewind, as some display devices only pick
+        // up new inputs while presenting frames
+        self.redraw_display();
+        self.time.frame()
+    }
+
+    // Bring newly attached peripherals up to date: the machine sees the input device's keys,
+    // the display is sent the current frame, and the audio device the current sound
+    fn sync_peripherals(&mut self) {
+        self.latch_inputs();
+        self.redraw_display();
+        self.push_volume();
+        self.push_audio();
+    }
+
+    // Send the audio device the volume to play at
+    fn push_volume(&mut self) {
+        let volume = match self.muted {
+            true => 0.0,
+            false => self.volume,
+        };
+        self.audio.borrow_mut().set_volume(volume);
+    }
+
+    // Send the audio device the sound the machine is making
+    fn push_audio(&mut self) {
+        self.audio
+            .borrow_mut()
+            .receive_signal(self.system.transmit_audio())
+            .play_audio();
+    }
+
+    // Send the current frame to the display along with the region the core has drawn to
+    // since the last one
+    fn refresh_display(&mut self) {
+        let damage = self.system.take_damage();
+        self.present_frame(damage.unwrap_or(Rect::full(self.system.display_dims())));
+    }
+
+    // Send the whole current frame to the display, e.g. after the machine state or the
+    // way it's displayed changed from outside the core
+    fn redraw_display(&mut self) {
+        self.system.take_damage();
+        self.present_frame(Rect::full(self.system.display_dims()));
+    }
+
+    // Send the current frame, transformed to the configured orientation, to the display
+    fn present_frame(&mut self, damage: Rect) {
+        self.summary.frames += 1;
+
+        let frame = match self.system.mode() {
+            chip8::Mode::Chip8X => {
+                color_frame(&self.system, &mut self.color_frame);
+                self.color_frame.iter().map(|plane| &**plane).collect()
+            }
+            _ => self
+                .system
+                .transmit_frame()
+                .into_iter()
+                .collect::<SmallVec<[&BitSlice<usize>; MAX_FRAME_PLANES]>>(),
+        };
+        let dims = self.system.display_dims();
+        #[cfg(feature = "recording")]
+        if let Some(recorder) = &mut self.recorder {
+            recorder.capture(&frame, dims);
+        }
+        for hook in &mut self.hooks {
+            hook.on_draw(&frame, dims);
+        }
+        let mut display = self.display.borrow_mut();
+
+        // The debugger panel is only shown while paused
+        let panel = match &self.fault {
+            Some((fault, _)) => crash::fault_panel(fault, self.system.mode()),
+            None if self.paused && self.debugger.is_enabled() => self.debugger.panel(&self.system),
+            None => Vec::new(),
+        };
+        display.receive_panel(&panel);
+
+        self.stats.update(
+            self.summary.frames,
+            self.time.cycles,
+            self.summary.timer_ticks,
+        );
+        display.receive_status(&StatusMsg {
+            fps: self.stats.fps(),
+            ips: self.stats.ips(),
+            paused: self.paused,
+            clock_rate: self.time.clock_rate(),
+            turbo: self.turbo,
+            pc: self.system.pc(),
+            i: self.system.i_reg(),
+            v: *self.system.v_reg(),
+            delay_timer: self.system.delay_timer(),
+            sound_timer: self.system.sound_timer(),
+            keys: self.system.keys(),
+            key_wait: self.system.key_wait(),
+        });
+        if self.show_stats {
+            display.receive_stats(&self.stats.overlay(self.time.clock_rate()));
+        }
+
+        // Blended after being recorded, so recordings keep the frames as the program drew
+        // them; CHIP-8X frames are left alone, every pixel having a color lit or not
+        let (frame, damage) = match &mut self.ghosting {
+            Some(ghosting) if self.system.mode() != chip8::Mode::Chip8X => (
+                ghosting.apply(&frame, dims, self.summary.timer_ticks),
+                // Pixels fading out lie outside what the program drew to
+                Rect::full(dims),
+            ),
+            _ => (frame, damage),
+        };
+
+        if self.orientation.is_identity() {
+            display
+                .receive_damage(damage)
+                .receive_frame(&frame, dims)
+                .drive_display();
+        } else {
+            let oriented = frame
+                .iter()
+                .zip(self.oriented_frame.iter_mut())
+                .map(|(plane, out)| {
+                    self.orientation.apply(plane, dims, out);
+                    &**out
+                })
+                .collect::<SmallVec<[&BitSlice<usize>; MAX_FRAME_PLANES]>>();
+            display
+                .receive_damage(self.orientation.apply_rect(damage, dims))
+                .receive_frame(&oriented, self.orientation.dims(dims))
+                .drive_display();
+        }
+    }
+}
+
+// Whether the instruction `opcode` at `pc`, just executed, left the program in a loop that
+// nothing but the next timer tick can get it out of: jumping to itself (as programs
+// commonly do once they're over), FX0A waiting on a key (keys only come in between
+// frames), or, with `timer_polls`, polling the delay timer until it runs out, as in
+//
+//   0x300  F007  LD V0, DT
+//   0x302  3000  SE V0, 0x00
+//   0x304  1300  JP 0x300
+//
+//   The rest of such a frame is skipped rather than executed; the machine comes out of it
+//   just as it would have, if having executed fewer instructions.
+//
+fn idle_loop(system: &Chip8, pc: u16, opcode: u16, timer_polls: bool) -> bool {
+    if system.pc() == pc && (opcode & 0xF000 == 0x1000 || system.key_wait().is_some()) {
+        return true;
+    }
+    // The jump back up to the top of a delay timer poll, the timer yet to run out
+    let top = opcode & 0x0FFF;
+    if !timer_polls || opcode & 0xF000 != 0x1000 || system.pc() != top || pc != top.wrapping_add(4)
+    {
+        return false;
+    }
+    let word = |addr: u16| {
+        u16::from_be_bytes([system.read_mem(addr), system.read_mem(addr.wrapping_add(1))])
+    };
+    let (load, check) = (word(top), word(top.wrapping_add(2)));
+    load & 0xF0FF == 0xF007 && check == 0x3000 | (load & 0x0F00)
+}
+
+// Compose the frame of a CHIP-8X machine into `planes`: every pixel gets a color of its own
+// (see `palette::chip8x_color`), lit or not, from the zone it's in or the background
+fn color_frame(system: &Chip8, planes: &mut [BitVec<usize>; MAX_FRAME_PLANES]) {
+    let frame = system.transmit_frame();
+    let (w, h) = system.display_dims();
+    for plane in planes.iter_mut() {
+        plane.resize(w * h, false);
+    }
+    for idx in 0..w * h {
+        let (x, y) = (idx % w, idx / w);
+        let color =
+            palette::chip8x_color(frame[0][idx], system.foreground(x, y), system.background());
+        for (n, plane) in planes.iter_mut().enumerate() {
+            plane.set(idx, color & 1 << n != 0);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    time::Duration,
+};
+
+use bitvec::bitarr;
+
+use crate::{
+    chip8::{fnv1a, NUM_KEYS},
+    driver::InputMsg,
+};
+
+// Sent by both peers on connecting, followed by the host's RNG seed (u64 LE; ignored
+// coming from the joining peer)
+const HELLO: &[u8; 5] = b"C8NP\x01";
+// How long to wait on the peer's inputs before giving up on the session
+const PEER_TIMEOUT: Duration = Duration::from_secs(10);
+// Frames that local inputs are sent ahead of taking effect by default
+pub const DEFAULT_INPUT_DELAY: usize = 2;
+
+// Lockstep netplay between two instances, each running the core locally
+//
+//   Only inputs cross the network: each frame (timer tick), both peers send their keys
+//   and run the frame with the keys of both pressed, so two-player games that split the
+//   keypad between players play out identically on both ends. Local keys are sent
+//   `input_delay` frames before they take effect, which hides that much latency; the
+//   frames in between start out with no keys pressed.
+//
+//   Both ends have to start from the same machine state, including the RNG (the host's
+//   seed is used for both), and run at the same clock rate; this is checked on `start`.
+//
+pub struct Netplay {
+    stream: TcpStream,
+    // RNG seed agreed on for the session
+    seed: u64,
+    // Keys currently held locally
+    local: InputMsg,
+    // Local keys sent to the peer that have yet to take effect, oldest first
+    pending: VecDeque<u16>,
+}
+
+impl Netplay {
+    // Wait for a peer to join at `addr` (e.g. `0.0.0.0:7777`); both ends use `seed`
+    pub fn host(addr: &str, seed: u64, input_delay: usize) -> io::Result<Self> {
+        let (stream, _) = TcpListener::bind(addr)?.accept()?;
+        Self::handshake(stream, Some(seed), input_delay)
+    }
+
+    // Join the session hosted at `addr`
+    pub fn join(addr: &str, input_delay: usize) -> io::Result<Self> {
+        Self::handshake(TcpStream::connect(addr)?, None, input_delay)
+    }
+
+    fn handshake(mut stream: TcpStream, seed: Option<u64>, input_delay: usize) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(PEER_TIMEOUT))?;
+
+        stream.write_all(HELLO)?;
+        stream.write_all(&seed.unwrap_or(0).to_le_bytes())?;
+        let mut hello = [0; HELLO.len() + 8];
+        stream.read_exact(&mut hello)?;
+        if hello[..HELLO.len()] != HELLO[..] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the peer isn't a compatible netplay session",
+            ));
+        }
+        let host_seed = u64::from_le_bytes(hello[HELLO.len()..].try_into().unwrap());
+
+        Ok(Netplay {
+            stream,
+            seed: seed.unwrap_or(host_seed),
+            local: bitarr![0; NUM_KEYS],
+            // Inputs for the frames before local keys take effect, sent on `start`
+            pending: VecDeque::from(vec![0; input_delay]),
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Check that the peer starts from the same machine state (see `Chip8::save_state`) at
+    // the same clock rate, and get the first frames going
+    pub fn start(&mut self, state: &[u8], clock_rate: f32) -> io::Result<()> {
+        let fingerprint = fnv1a(state.iter().copied()) ^ clock_rate.to_bits() as u64;
+        self.stream.write_all(&fingerprint.to_le_bytes())?;
+
+        let mut peer = [0; 8];
+        self.stream.read_exact(&mut peer)?;
+        if u64::from_le_bytes(peer) != fingerprint {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the peer is running a different ROM or settings",
+            ));
+        }
+
+        for keys in &self.pending {
+            self.stream.write_all(&keys.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    // Keys held locally, sent along on the next `exchange`
+    pub fn set_local(&mut self, keys: Option<InputMsg>) {
+        self.local = keys.unwrap_or(bitarr![0; NUM_KEYS]);
+    }
+
+    // Trade inputs with the peer for the next frame, waiting on the peer if need be;
+    // returns the keys to run the frame with
+    pub fn exchange(&mut self) -> io::Result<InputMsg> {
+        let local = self
+            .local
+            .iter_ones()
+            .fold(0_u16, |bits, key| bits | 1 << key);
+        self.stream.write_all(&local.to_le_bytes())?;
+        self.pending.push_back(local);
+
+        let mut remote = [0; 2];
+        self.stream
+            .read_exact(&mut remote)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                    io::Error::new(e.kind(), "the peer stopped responding")
+                }
+                io::ErrorKind::UnexpectedEof => {
+                    io::Error::new(e.kind(), "the peer left the session")
+                }
+                _ => e,
+            })?;
+
+        let bits = self.pending.pop_front().unwrap() | u16::from_le_bytes(remote);
+        let mut keys = bitarr![0; NUM_KEYS];
+        for key in 0..NUM_KEYS {
+            keys.set(key, bits & 1 << key != 0);
+        }
+        Ok(keys)
+    }
+}
+
+// Hang up without cutting the peer off: it may be a few frames behind and still sending,
+// and closing with its inputs unread would reset the connection before it catches up
+impl Drop for Netplay {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Write);
+        let _ = io::copy(&mut self.stream, &mut io::sink());
+    }
+}
@@ -0,0 +1,225 @@
+use std::str::FromStr;
+
+use super::EmuError;
+
+// Header and footer of an IPS file
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Target {
+    // Address in memory
+    Addr(u16),
+    // Offset into the ROM, as IPS patches go
+    Offset(usize),
+}
+
+// Bytes of the ROM to change as it's loaded (see `Emulator::set_patch`), e.g. a fan fix or
+// a cheat
+//
+//   Read either from an IPS file, or as text, one byte per line with `#` starting a
+//   comment:
+//
+//     ADDR:BYTE
+//
+//   in hex, e.g. `3A2:FF` to load 0xFF at 0x3A2. Every byte has to land on the ROM, so a
+//   patch can't quietly scribble over the fonts or past the end of a ROM it wasn't
+//   made for. Later bytes win over earlier ones at the same place.
+//
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Patch {
+    bytes: Vec<(Target, u8)>,
+}
+
+impl Patch {
+    // Patch read from a file's contents, IPS or text
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        match data.strip_prefix(IPS_MAGIC) {
+            Some(records) => Self::from_ips(records),
+            None => std::str::from_utf8(data)
+                .map_err(|_| "not an IPS file, nor text".to_owned())?
+                .parse(),
+        }
+    }
+
+    // Load `byte` at `addr`, after the rest of the patch
+    pub fn poke(&mut self, addr: u16, byte: u8) {
+        self.bytes.push((Target::Addr(addr), byte));
+    }
+
+    // Address and byte of a poke given as `ADDR=BYTE`, in hex (`0x` optional), e.g.
+    // `0x3A2=0xFF`
+    pub fn parse_poke(s: &str) -> Result<(u16, u8), &'static str> {
+        split_poke(s, '=')
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    // Patch `rom`, which is loaded at `start`
+    pub(super) fn apply(&self, rom: &mut [u8], start: u16) -> Result<(), EmuError> {
+        let window = start as usize..start as usize + rom.len();
+        for &(target, byte) in &self.bytes {
+            let addr = match target {
+                Target::Addr(addr) => addr as usize,
+                Target::Offset(offset) => start as usize + offset,
+            };
+            if !window.contains(&addr) {
+                return Err(EmuError::PatchOutsideRom { addr, rom: window });
+            }
+            rom[addr - window.start] = byte;
+        }
+        Ok(())
+    }
+
+    // Records of an IPS file, after its header: each a 3-byte offset and a 2-byte length
+    // (big-endian), then that many bytes, or for a length of 0, a 2-byte count and a byte
+    // to repeat that many times
+    fn from_ips(mut records: &[u8]) -> Result<Self, String> {
+        fn take<'a>(len: usize, records: &mut &'a [u8]) -> Result<&'a [u8], String> {
+            let (field, rest) = records.split_at_checked(len).ok_or("truncated IPS patch")?;
+            *records = rest;
+            Ok(field)
+        }
+        let be = |field: &[u8]| field.iter().fold(0, |n, &byte| n << 8 | byte as usize);
+
+        let mut bytes = Vec::new();
+
+        // Anything after the footer (e.g. a size to truncate to) is of no use here
+        while !records.starts_with(IPS_EOF) {
+            let offset = be(take(3, &mut records)?);
+            match be(take(2, &mut records)?) {
+                0 => {
+                    let count = be(take(2, &mut records)?);
+                    let byte = take(1, &mut records)?[0];
+                    bytes.extend((0..count).map(|n| (Target::Offset(offset + n), byte)));
+                }
+                len => {
+                    let data = take(len, &mut records)?;
+                    bytes.extend(
+                        data.iter()
+                            .enumerate()
+                            .map(|(n, &byte)| (Target::Offset(offset + n), byte)),
+                    );
+                }
+            }
+        }
+        Ok(Patch { bytes })
+    }
+}
+
+impl FromStr for Patch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut patch = Patch::default();
+        for (n, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (addr, byte) = split_poke(line, ':')
+                .map_err(|what| format!("line {}: {what} in patch '{line}'", n + 1))?;
+            patch.poke(addr, byte);
+        }
+        Ok(patch)
+    }
+}
+
+// Address and byte of `ADDR<sep>BYTE`, in hex (`0x` optional)
+fn split_poke(s: &str, sep: char) -> Result<(u16, u8), &'static str> {
+    let (addr, byte) = s.split_once(sep).ok_or("missing byte")?;
+    let (addr, byte) = (addr.trim(), byte.trim());
+    let addr =
+        u16::from_str_radix(addr.trim_start_matches("0x"), 16).map_err(|_| "invalid address")?;
+    let byte = u8::from_str_radix(byte.trim_start_matches("0x"), 16).map_err(|_| "invalid byte")?;
+    Ok((addr, byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patched(patch: &Patch, rom: &[u8]) -> Result<Vec<u8>, EmuError> {
+        let mut rom = rom.to_vec();
+        patch.apply(&mut rom, 0x200)?;
+        Ok(rom)
+    }
+
+    #[test]
+    fn ips_records() {
+        // 2 bytes at offset 1, then 3 of 0xAA at offset 4 (RLE), then the footer
+        let ips = b"PATCH\x00\x00\x01\x00\x02\x11\x22\x00\x00\x04\x00\x00\x00\x03\xAAEOF";
+        let patch = Patch::parse(ips).unwrap();
+        assert_eq!(
+            patched(&patch, &[0; 8]).unwrap(),
+            [0x00, 0x11, 0x22, 0x00, 0xAA, 0xAA, 0xAA, 0x00]
+        );
+    }
+
+    #[test]
+    fn ips_truncated() {
+        for ips in [
+            &b"PATCH\x00\x00"[..],
+            b"PATCH\x00\x00\x01\x00\x04\x11\x22",
+            b"PATCH\x00\x00\x01\x00\x00\x00",
+        ] {
+            assert_eq!(Patch::parse(ips), Err("truncated IPS patch".to_owned()));
+        }
+    }
+
+    #[test]
+    fn text_pokes() {
+        let patch = "# fan fix\n202:FF\n\n0x203 : 0x12  # later wins\n203:34\n"
+            .parse::<Patch>()
+            .unwrap();
+        assert_eq!(patched(&patch, &[0; 4]).unwrap(), [0, 0, 0xFF, 0x34]);
+    }
+
+    #[test]
+    fn text_malformed() {
+        assert_eq!(
+            "200:FF\n201".parse::<Patch>(),
+            Err("line 2: missing byte in patch '201'".to_owned())
+        );
+        assert_eq!(
+            "20G:FF".parse::<Patch>(),
+            Err("line 1: invalid address in patch '20G:FF'".to_owned())
+        );
+        assert_eq!(
+            "200:100".parse::<Patch>(),
+            Err("line 1: invalid byte in patch '200:100'".to_owned())
+        );
+        assert_eq!(
+            Patch::parse(b"\xFF\xFE"),
+            Err("not an IPS file, nor text".to_owned())
+        );
+    }
+
+    #[test]
+    fn outside_rom() {
+        let patch = "1FF:00".parse::<Patch>().unwrap();
+        assert!(matches!(
+            patched(&patch, &[0; 4]),
+            Err(EmuError::PatchOutsideRom { addr: 0x1FF, .. })
+        ));
+        // Past the end, whether by address or by IPS offset
+        let patch = "204:00".parse::<Patch>().unwrap();
+        assert!(matches!(
+            patched(&patch, &[0; 4]),
+            Err(EmuError::PatchOutsideRom { addr: 0x204, .. })
+        ));
+        let patch = Patch::parse(b"PATCH\x00\x00\x03\x00\x02\x11\x22EOF").unwrap();
+        assert!(matches!(
+            patched(&patch, &[0; 4]),
+            Err(EmuError::PatchOutsideRom { addr: 0x204, .. })
+        ));
+    }
+
+    #[test]
+    fn pokes() {
+        assert_eq!(Patch::parse_poke("0x3A2=0xFF"), Ok((0x3A2, 0xFF)));
+        assert_eq!(Patch::parse_poke("3A2"), Err("missing byte"));
+    }
+}
@@ -0,0 +1,287 @@
+use std::{
+    cell::RefCell,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use bitvec::{bitvec, slice::BitSlice};
+
+use crate::{
+    chip8::{KeyEvents, HIRES_DISPLAY_HEIGHT, HIRES_DISPLAY_WIDTH},
+    driver::{DisplayDevice, InputDevice},
+    emulator::Signal,
+};
+
+// How often the input device is polled while the menu is up
+const POLL_INTERVAL: Duration = Duration::from_millis(16);
+// ROM file extensions listed, matched regardless of case
+const ROM_EXTENSIONS: [&str; 2] = ["ch8", "c8"];
+
+// CHIP-8 keys working the menu: W, S, A, D, and E on the default QWERTY keymap
+const KEY_UP: usize = 0x5;
+const KEY_DOWN: usize = 0x8;
+const KEY_PAGE_UP: usize = 0x7;
+const KEY_PAGE_DOWN: usize = 0x9;
+const KEY_RUN: usize = 0x6;
+const HINT: &str = "5/8 (W/S) to move, 6 (E) to run";
+
+// Layout of the menu on a hi-res frame, in 3x5 pixel characters (see `glyph`) spaced 4x6
+//
+//   +--------------------------------+
+//   | ROMS                      2/14 |  header
+//   |--------------------------------|
+//   | BRIX                           |
+//   |[INVADERS                      ]|  selected ROM, inverted
+//   | PONG                           |
+//   | ...                            |
+//   +--------------------------------+
+//
+const WIDTH: usize = HIRES_DISPLAY_WIDTH;
+const HEIGHT: usize = HIRES_DISPLAY_HEIGHT;
+const CHAR_WIDTH: usize = 4;
+const ROW_HEIGHT: usize = 6;
+const MARGIN: usize = 2;
+const LIST_TOP: usize = 8;
+const VISIBLE_ROWS: usize = (HEIGHT - LIST_TOP) / ROW_HEIGHT;
+const MAX_CHARS: usize = (WIDTH - 2 * MARGIN) / CHAR_WIDTH;
+
+// Selection screen listing the ROMs in a directory, shown on the emulator's own devices
+// (so on any frontend) when no ROM is given, and returned to once a ROM picked from it exits
+//
+//   let mut picker = RomPicker::new("roms");
+//   while let Some(rom) = picker.pick(&input, &display) {
+//       /* run `rom` until it exits */
+//   }
+//
+pub struct RomPicker {
+    dir: PathBuf,
+    roms: Vec<PathBuf>,
+    // Index of the ROM selected, and of the one listed at the top
+    selected: usize,
+    top: usize,
+    // Whether the keys working the menu have been pointed out yet
+    hinted: bool,
+}
+
+impl RomPicker {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        RomPicker {
+            dir: dir.into(),
+            roms: Vec::new(),
+            selected: 0,
+            top: 0,
+            hinted: false,
+        }
+    }
+
+    // Show the menu until a ROM is picked, returning its path, or the user exits it
+    pub fn pick<I, D>(&mut self, input: &RefCell<I>, display: &RefCell<D>) -> Option<PathBuf>
+    where
+        I: InputDevice,
+        D: DisplayDevice,
+    {
+        self.scan();
+        {
+            let mut display = display.borrow_mut();
+            display.receive_panel(&[]);
+            if !self.hinted {
+                display.receive_notification(HINT);
+                self.hinted = true;
+            }
+        }
+
+        // Keys still held from the ROM that exited don't count as presses
+        let mut keys = input.borrow().send_inputs().unwrap_or_default();
+        let mut redraw = true;
+        loop {
+            let signal = input.borrow_mut().handle_inputs();
+            match signal {
+                Signal::ProgramExit => return None,
+                Signal::NewInputs => {
+                    let next = input.borrow().send_inputs().unwrap_or(keys);
+                    let pressed = KeyEvents::between(keys, next).pressed;
+                    keys = next;
+
+                    if pressed[KEY_RUN] && !self.roms.is_empty() {
+                        return Some(self.roms[self.selected].clone());
+                    }
+                    let step = match () {
+                        _ if pressed[KEY_UP] => -1,
+                        _ if pressed[KEY_DOWN] => 1,
+                        _ if pressed[KEY_PAGE_UP] => -(VISIBLE_ROWS as isize),
+                        _ if pressed[KEY_PAGE_DOWN] => VISIBLE_ROWS as isize,
+                        _ => 0,
+                    };
+                    redraw |= self.move_by(step);
+                }
+                // A frontend switched to has to be caught up, and a resized one laid out again
+                Signal::DisplayAttached | Signal::Resized => redraw = true,
+                _ => (),
+            }
+
+            if redraw {
+                self.draw(display);
+                redraw = false;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    // List the ROMs in the directory again, keeping the same one selected if it's still there
+    fn scan(&mut self) {
+        let selected = self.roms.get(self.selected).cloned();
+        self.roms = list_roms(&self.dir);
+        self.selected = selected
+            .and_then(|rom| self.roms.iter().position(|other| *other == rom))
+            .unwrap_or(0);
+        self.move_by(0);
+    }
+
+    // Move the selection by `step` ROMs, scrolling it into view; returns whether it moved
+    fn move_by(&mut self, step: isize) -> bool {
+        let last = self.roms.len().saturating_sub(1);
+        let selected = self.selected.saturating_add_signed(step).min(last);
+        let moved = selected != self.selected;
+        self.selected = selected;
+        self.top = self
+            .top
+            .min(selected)
+            .max((selected + 1).saturating_sub(VISIBLE_ROWS));
+        moved
+    }
+
+    fn draw<D: DisplayDevice>(&self, display: &RefCell<D>) {
+        let mut frame = bitvec![0; WIDTH * HEIGHT];
+
+        if self.roms.is_empty() {
+            draw_text(&mut frame, MARGIN, 1, "NO .CH8 OR .C8 ROMS IN", true);
+            let dir = self.dir.display().to_string();
+            // Keep the end of the path, the part most likely to tell directories apart
+            let skip = dir.chars().count().saturating_sub(MAX_CHARS);
+            let dir = dir.chars().skip(skip).collect::<String>();
+            draw_text(&mut frame, MARGIN, 1 + ROW_HEIGHT, &dir, true);
+        } else {
+            let count = format!("{}/{}", self.selected + 1, self.roms.len());
+            let count_x = WIDTH - MARGIN - count.len() * CHAR_WIDTH + 1;
+            draw_text(&mut frame, MARGIN, 1, "ROMS", true);
+            draw_text(&mut frame, count_x, 1, &count, true);
+            frame[(LIST_TOP - 2) * WIDTH..(LIST_TOP - 1) * WIDTH].fill(true);
+
+            let shown = self.roms.iter().enumerate().skip(self.top);
+            for (row, (idx, rom)) in shown.take(VISIBLE_ROWS).enumerate() {
+                let y = LIST_TOP + row * ROW_HEIGHT;
+                let selected = idx == self.selected;
+                if selected {
+                    frame[y * WIDTH..(y + ROW_HEIGHT) * WIDTH].fill(true);
+                }
+                let name = rom.file_stem().unwrap_or_default().to_string_lossy();
+                let name = name.chars().take(MAX_CHARS).collect::<String>();
+                draw_text(&mut frame, MARGIN, y + 1, &name, !selected);
+            }
+        }
+
+        display
+            .borrow_mut()
+            .receive_frame(&[frame.as_bitslice()], (WIDTH, HEIGHT))
+            .drive_display();
+    }
+}
+
+// ROM files directly in `dir`, sorted by name; none if it can't be read
+fn list_roms(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut roms = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path.extension().is_some_and(|ext| {
+                    ROM_EXTENSIONS
+                        .iter()
+                        .any(|rom_ext| ext.eq_ignore_ascii_case(rom_ext))
+                })
+        })
+        .collect::<Vec<_>>();
+    roms.sort_by_key(|path| path.file_name().map(|name| name.to_ascii_lowercase()));
+    roms
+}
+
+// Write `text` with its top-left corner at (x, y), setting its pixels to `lit`; characters
+// running off the right edge are cut off
+fn draw_text(frame: &mut BitSlice<usize>, x: usize, y: usize, text: &str, lit: bool) {
+    for (n, c) in text.chars().enumerate() {
+        let x0 = x + n * CHAR_WIDTH;
+        for (dy, row) in glyph(c).iter().enumerate() {
+            for dx in (0..3).filter(|dx| row & (0b100 >> dx) != 0) {
+                if x0 + dx < WIDTH && y + dy < HEIGHT {
+                    frame.set((y + dy) * WIDTH + x0 + dx, lit);
+                }
+            }
+        }
+    }
+}
+
+// 3x5 pixel rows of a character, top to bottom with the leftmost pixel in bit 2; letters
+// are drawn in uppercase, and characters with no glyph as '?'
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '\\' => [0b100, 0b100, 0b010, 0b001, 0b001],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '[' => [0b011, 0b010, 0b010, 0b010, 0b011],
+        ']' => [0b110, 0b010, 0b010, 0b010, 0b110],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '&' => [0b010, 0b101, 0b010, 0b101, 0b011],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
@@ -0,0 +1,122 @@
+use std::{str::FromStr, time::Duration};
+
+use crate::chip8::Chip8;
+
+// Condition under which a marked machine state is automatically reloaded
+#[derive(Clone, Copy, Debug)]
+pub enum LoopTrigger {
+    // Only reload when the user explicitly asks for it
+    Key,
+    // Reload once a fixed amount of machine time has passed since the state was (re)entered
+    Timer(Duration),
+    // Reload once the byte at `addr` becomes `value` (e.g. a lives counter hitting 0)
+    Watch { addr: u16, value: u8 },
+}
+
+// Parses `key`, `timer:SECS`, or `watch:ADDR=VAL` (ADDR/VAL may be given in hex with a `0x` prefix)
+impl FromStr for LoopTrigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_num = |num: &str| -> Result<u16, String> {
+            match num.strip_prefix("0x") {
+                Some(hex) => u16::from_str_radix(hex, 16),
+                None => num.parse(),
+            }
+            .map_err(|_| format!("invalid number '{num}' in practice trigger"))
+        };
+
+        match s.split_once(':') {
+            None if s == "key" => Ok(LoopTrigger::Key),
+            Some(("timer", secs)) => secs
+                .parse::<f32>()
+                .ok()
+                .filter(|secs| secs.is_finite() && *secs > 0.0)
+                .map(|secs| LoopTrigger::Timer(Duration::from_secs_f32(secs)))
+                .ok_or(format!("invalid duration '{secs}' in practice trigger")),
+            Some(("watch", cond)) => {
+                let (addr, value) = cond
+                    .split_once('=')
+                    .ok_or("expected `watch:ADDR=VAL` practice trigger")?;
+                let value = parse_num(value)?;
+                Ok(LoopTrigger::Watch {
+                    addr: parse_num(addr)?,
+                    value: u8::try_from(value)
+                        .map_err(|_| format!("watch value '{value}' does not fit in a byte"))?,
+                })
+            }
+            _ => Err(format!("unrecognized practice trigger '{s}'")),
+        }
+    }
+}
+
+// Savestate-backed A/B looping: the user marks a machine state (point A) and it
+// is reloaded whenever the trigger fires (point B), which makes drilling a
+// difficult section of a game much less tedious
+//
+//   The mark is kept as a save state (see `Chip8::save_state`) and loaded back like one, so
+//   settings that outlast a load (e.g. trainer rules and watchpoints) aren't undone by
+//   jumping back to it
+//
+pub struct Practice {
+    trigger: LoopTrigger,
+    // Marked machine state and the machine time it was last (re)entered at
+    mark: Option<(Vec<u8>, Duration)>,
+    // Last observed value of a watched address; the watch fires on the transition
+    // into the target value so a mark made while the condition holds doesn't loop forever
+    watched: Option<u8>,
+}
+
+impl Practice {
+    pub fn new(trigger: LoopTrigger) -> Self {
+        Practice {
+            trigger,
+            mark: None,
+            watched: None,
+        }
+    }
+
+    pub fn set_trigger(&mut self, trigger: LoopTrigger) {
+        self.trigger = trigger;
+        self.watched = None;
+    }
+
+    pub fn mark(&mut self, system: &Chip8, now: Duration) {
+        self.mark = Some((system.save_state(), now));
+        self.watched = None;
+    }
+
+    // Forget the marked state, e.g. once the program it was taken of is started over
+    pub fn clear(&mut self) {
+        self.mark = None;
+        self.watched = None;
+    }
+
+    // Reload the marked state into `system`; returns false if no state has been marked
+    pub fn restore(&mut self, system: &mut Chip8, now: Duration) -> bool {
+        match &mut self.mark {
+            Some((state, entered)) if system.load_state(state).is_ok() => {
+                *entered = now;
+                self.watched = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn triggered(&mut self, system: &Chip8, now: Duration) -> bool {
+        let Some((_, entered)) = &self.mark else {
+            return false;
+        };
+
+        match self.trigger {
+            LoopTrigger::Key => false,
+            LoopTrigger::Timer(duration) => now.saturating_sub(*entered) >= duration,
+            LoopTrigger::Watch { addr, value } => {
+                let current = system.read_mem(addr);
+                let prev = self.watched.replace(current);
+                current == value && prev.is_some_and(|prev| prev != value)
+            }
+        }
+    }
+}
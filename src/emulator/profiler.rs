@@ -0,0 +1,191 @@
+use std::{collections::HashMap, fmt, time::Duration};
+
+use super::Emulator;
+use crate::driver::{AudioDevice, DisplayDevice, InputDevice};
+
+// Rows listed of each table in the report
+const TOP_OPCODES: usize = 16;
+const TOP_ADDRESSES: usize = 10;
+
+// Counts of where a run spends its instructions, for ROM authors to find their hot loops and
+// for checking which paths of the core matter most
+//
+//   Instructions are counted by kind (e.g. every 8XY4 together) and by address; draws are
+//   counted apart to give a rate in emulated time, so the figures are the same however fast
+//   the host ran. How late the run loop woke from each sleep is measured too.
+//
+pub struct Profiler {
+    // Instructions executed by `opcode_class`
+    opcodes: HashMap<(u16, u16), u64>,
+    // Instructions executed at each address
+    addresses: HashMap<u16, u64>,
+    draws: u64,
+    // Sleeps taken, and how far they overshot in total
+    sleeps: u64,
+    overshoot: Duration,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler {
+            opcodes: HashMap::new(),
+            addresses: HashMap::new(),
+            draws: 0,
+            sleeps: 0,
+            overshoot: Duration::ZERO,
+        }
+    }
+
+    fn observe_instruction(&mut self, pc: u16, opcode: u16) {
+        *self.opcodes.entry(opcode_class(opcode)).or_default() += 1;
+        *self.addresses.entry(pc).or_default() += 1;
+        if opcode >> 12 == 0xD {
+            self.draws += 1;
+        }
+    }
+
+    fn observe_sleep(&mut self, requested: Duration, actual: Duration) {
+        self.sleeps += 1;
+        self.overshoot += actual.saturating_sub(requested);
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler::new()
+    }
+}
+
+// The kind of instruction `opcode` is, as its fixed digits and a mask of which digits those
+// are; e.g. 8A34 is (0x8004, 0xF00F), shown as 8XY4
+fn opcode_class(opcode: u16) -> (u16, u16) {
+    let mask = match opcode >> 12 {
+        // 00CN/00DN scroll by N, 0NNN calls machine code, the rest are fixed
+        0x0 if matches!(opcode & 0xFFF0, 0x00C0 | 0x00D0) => 0xFFF0,
+        0x0 if opcode & 0xFF00 == 0 => 0xFFFF,
+        0x5 | 0x8 | 0x9 => 0xF00F,
+        0xE | 0xF => 0xF0FF,
+        _ => 0xF000,
+    };
+    (opcode & mask, mask)
+}
+
+// Pattern of an `opcode_class`, in the usual notation: NNN an address, NN a byte, N a
+// nibble, and X and Y registers
+fn class_pattern((opcode, mask): (u16, u16)) -> String {
+    let fixed = |n: u16| format!("{:X}", opcode >> (12 - 4 * n) & 0xF);
+    match (opcode >> 12, mask) {
+        (_, 0xFFFF) => format!("{opcode:04X}"),
+        (_, 0xFFF0) => format!("{:03X}N", opcode >> 4),
+        (_, 0xF00F) => format!("{}XY{}", fixed(0), fixed(3)),
+        (_, 0xF0FF) => format!("{}X{}{}", fixed(0), fixed(2), fixed(3)),
+        (0x0 | 0x1 | 0x2 | 0xA | 0xB, _) => format!("{}NNN", fixed(0)),
+        (0xD, _) => "DXYN".to_owned(),
+        _ => format!("{}XNN", fixed(0)),
+    }
+}
+
+// Results of a `Profiler`
+#[derive(Clone, Debug)]
+pub struct ProfileReport {
+    pub instructions: u64,
+    // Instruction kinds (see `class_pattern`) and addresses, by how many were executed,
+    // most first
+    pub opcodes: Vec<(String, u64)>,
+    pub addresses: Vec<(u16, u64)>,
+    // Sprites drawn per second of emulated time
+    pub draws_per_sec: f64,
+    // Mean sleep overshoot, if the run slept at all
+    pub avg_overshoot: Option<Duration>,
+}
+
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "PROFILE:")?;
+        writeln!(f, "    Instructions        {}", self.instructions)?;
+        writeln!(f, "    Draws/sec           {:.1}", self.draws_per_sec)?;
+        match self.avg_overshoot {
+            Some(overshoot) => writeln!(
+                f,
+                "    Avg sleep overshoot {:.3}ms",
+                overshoot.as_secs_f64() * 1000.0
+            )?,
+            None => writeln!(f, "    Avg sleep overshoot -")?,
+        }
+        let share = |count: u64| count as f64 * 100.0 / self.instructions.max(1) as f64;
+
+        write!(f, "    Opcodes")?;
+        for (pattern, count) in self.opcodes.iter().take(TOP_OPCODES) {
+            write!(
+                f,
+                "\n        {pattern:<8} {count:>12} {:>6.2}%",
+                share(*count)
+            )?;
+        }
+        write!(f, "\n    Hot addresses")?;
+        for (addr, count) in self.addresses.iter().take(TOP_ADDRESSES) {
+            write!(
+                f,
+                "\n        {addr:#06X}   {count:>12} {:>6.2}%",
+                share(*count)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Entries of `counts`, most counted first (ties in key order, so reports are reproducible)
+fn ranked<K: Copy + Ord>(counts: &HashMap<K, u64>) -> Vec<(K, u64)> {
+    let mut ranked = counts
+        .iter()
+        .map(|(&key, &count)| (key, count))
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranked
+}
+
+impl<'a, I, D, A> Emulator<'a, I, D, A>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    // Count the instructions executed from here on; see `profile_report`
+    pub fn enable_profiler(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    pub fn profile_report(&self) -> Option<ProfileReport> {
+        self.profiler.as_ref().map(|profiler| {
+            let secs = self.time.elapsed().as_secs_f64();
+            ProfileReport {
+                instructions: profiler.addresses.values().sum(),
+                opcodes: ranked(&profiler.opcodes)
+                    .into_iter()
+                    .map(|(class, count)| (class_pattern(class), count))
+                    .collect(),
+                addresses: ranked(&profiler.addresses),
+                draws_per_sec: match secs > 0.0 {
+                    true => profiler.draws as f64 / secs,
+                    false => 0.0,
+                },
+                avg_overshoot: (profiler.sleeps > 0)
+                    .then(|| profiler.overshoot.div_f64(profiler.sleeps as f64)),
+            }
+        })
+    }
+
+    // Called by `exec_cycle` for every instruction executed
+    pub(super) fn profile_instruction(&mut self, pc: u16, opcode: u16) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.observe_instruction(pc, opcode);
+        }
+    }
+
+    // Called by the run loop after each sleep (see `observe_pacing`)
+    pub(super) fn profile_sleep(&mut self, requested: Duration, actual: Duration) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.observe_sleep(requested, actual);
+        }
+    }
+}
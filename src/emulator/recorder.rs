@@ -0,0 +1,307 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, SyncSender, TrySendError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use bitvec::slice::BitSlice;
+
+use super::time::Instant;
+use crate::{
+    chip8::{HIRES_DISPLAY_HEIGHT, HIRES_DISPLAY_WIDTH},
+    diagnostics,
+    driver::{
+        palette::{Palette, NUM_COLORS},
+        pixel_color,
+    },
+};
+
+// Frames waiting on the encoder; past this many, new ones are dropped rather than holding
+// up the main loop
+const QUEUE_LEN: usize = 120;
+// Pixels per CHIP-8 hi-res pixel in the recording; lo-res pixels are twice as large
+const SCALE: usize = 4;
+const WIDTH: usize = HIRES_DISPLAY_WIDTH * SCALE;
+const HEIGHT: usize = HIRES_DISPLAY_HEIGHT * SCALE;
+// Shortest time a frame is shown for; browsers slow faster GIF frames down to 10 per
+// second, so frames that don't stay up this long are folded into the next one
+const MIN_DELAY: Duration = Duration::from_millis(20);
+
+// File format of a recording
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RecordFormat {
+    Gif,
+    Apng,
+}
+
+impl RecordFormat {
+    // Format going by the file's extension: `.gif`, or `.png`/`.apng` for APNG
+    pub fn from_path(path: &Path) -> Option<RecordFormat> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "gif" => Some(RecordFormat::Gif),
+            "png" | "apng" => Some(RecordFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+enum Message {
+    // Color indices of the display's pixels (doubled up to hi-res when in lo-res mode), and
+    // when they were presented
+    Frame(Vec<u8>, Instant),
+    // The recording stopped at this time
+    End(Instant),
+}
+
+// Gameplay recording in progress: presented frames are queued up with the time they were
+// presented at, and encoded to an animated GIF or APNG on a background thread so the main
+// loop isn't held up
+pub struct Recorder {
+    path: PathBuf,
+    tx: SyncSender<Message>,
+    encoder: JoinHandle<io::Result<()>>,
+    // Last frame queued; redraws of an unchanged display (e.g. while paused) are skipped
+    last: Vec<u8>,
+    // Frames dropped because the encoder fell behind
+    dropped: usize,
+}
+
+impl Recorder {
+    // Start recording to `path`, in the format its extension names, coloring the display
+    // with `palette`
+    pub fn start(path: impl Into<PathBuf>, palette: Palette) -> io::Result<Recorder> {
+        let path = path.into();
+        let format = RecordFormat::from_path(&path).ok_or(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "unsupported format (expected a .gif, .png, or .apng file)",
+        ))?;
+        let out = BufWriter::new(File::create(&path)?);
+
+        let (tx, rx) = mpsc::sync_channel(QUEUE_LEN);
+        let encoder = thread::Builder::new()
+            .name("recorder".to_owned())
+            .spawn(move || encode(rx, out, format, palette))?;
+
+        Ok(Recorder {
+            path,
+            tx,
+            encoder,
+            last: Vec::new(),
+            dropped: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Queue up a frame of `dims` (w, h) as presented now; frames of any resolution are
+    // stretched over the recording's, lo-res and hi-res ones by whole factors
+    pub fn capture(&mut self, planes: &[&BitSlice<usize>], dims: (usize, usize)) {
+        let pixels = (0..HIRES_DISPLAY_HEIGHT)
+            .flat_map(|y| (0..HIRES_DISPLAY_WIDTH).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (x, y) = (
+                    x * dims.0 / HIRES_DISPLAY_WIDTH,
+                    y * dims.1 / HIRES_DISPLAY_HEIGHT,
+                );
+                pixel_color(planes, y * dims.0 + x) as u8
+            })
+            .collect::<Vec<_>>();
+        if pixels == self.last {
+            return;
+        }
+
+        match self
+            .tx
+            .try_send(Message::Frame(pixels.clone(), Instant::now()))
+        {
+            Ok(()) => self.last = pixels,
+            Err(TrySendError::Full(_)) => self.dropped += 1,
+            // The encoder already gave up; `finish` reports why
+            Err(TrySendError::Disconnected(_)) => (),
+        }
+    }
+
+    // Stop recording, waiting for the encoder to write out the queued frames
+    pub fn finish(self) -> io::Result<()> {
+        // Fails if the encoder already gave up, which joining it reports
+        let _ = self.tx.send(Message::End(Instant::now()));
+        drop(self.tx);
+        if self.dropped > 0 {
+            diagnostics::warn(
+                "recorder",
+                format!(
+                    "dropped {} frame(s) from '{}' the encoder couldn't keep up with",
+                    self.dropped,
+                    self.path.display()
+                ),
+            );
+        }
+
+        self.encoder
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("the encoder panicked")))
+    }
+}
+
+// Encoder thread: write each frame once the next one comes in, and so it's known how long
+// it stayed up for
+fn encode(
+    rx: Receiver<Message>,
+    out: BufWriter<File>,
+    format: RecordFormat,
+    palette: Palette,
+) -> io::Result<()> {
+    let mut sink = Sink::new(out, format, &palette)?;
+    // Frame waiting to be written, and when it went up
+    let mut held: Option<(Vec<u8>, Instant)> = None;
+    // Start of the recording, and the time covered by the frames written since (in 1/100s
+    // of a second), so rounding each frame's delay doesn't add up to drift
+    let mut start = None;
+    let mut written = 0;
+
+    for message in rx {
+        let (next, at) = match message {
+            Message::Frame(pixels, at) => (Some(pixels), at),
+            Message::End(at) => (None, at),
+        };
+        let start = *start.get_or_insert(at);
+
+        if let Some((pixels, since)) = held.take() {
+            match next {
+                Some(next) if at.duration_since(since) < MIN_DELAY => {
+                    held = Some((next, since));
+                    continue;
+                }
+                _ => {
+                    let end = (at.duration_since(start).as_millis() as u64 + 5) / 10;
+                    let delay = u16::try_from(end - written).unwrap_or(u16::MAX);
+                    written = end;
+                    sink.write(pixels, delay)?;
+                }
+            }
+        }
+        match next {
+            Some(next) => held = Some((next, at)),
+            None => break,
+        }
+    }
+
+    sink.finish()
+}
+
+enum Sink {
+    Gif(gif::Encoder<BufWriter<File>>),
+    // APNG files start with the number of frames, so they're held on to (packed 4 pixels to
+    // a byte) until the recording ends
+    Apng {
+        out: BufWriter<File>,
+        palette: Vec<u8>,
+        frames: Vec<(Vec<u8>, u16)>,
+    },
+}
+
+impl Sink {
+    fn new(out: BufWriter<File>, format: RecordFormat, palette: &Palette) -> io::Result<Sink> {
+        let rgb = (0..NUM_COLORS)
+            .flat_map(|n| <[u8; 3]>::from(palette.rgb(n)))
+            .collect::<Vec<_>>();
+
+        match format {
+            RecordFormat::Gif => {
+                let mut encoder = gif::Encoder::new(out, WIDTH as u16, HEIGHT as u16, &rgb)
+                    .map_err(io::Error::other)?;
+                encoder
+                    .set_repeat(gif::Repeat::Infinite)
+                    .map_err(io::Error::other)?;
+                Ok(Sink::Gif(encoder))
+            }
+            RecordFormat::Apng => Ok(Sink::Apng {
+                out,
+                palette: rgb,
+                frames: Vec::new(),
+            }),
+        }
+    }
+
+    // Write a frame of color indices that stays up for `delay` 1/100s of a second
+    fn write(&mut self, pixels: Vec<u8>, delay: u16) -> io::Result<()> {
+        match self {
+            Sink::Gif(encoder) => {
+                let frame = gif::Frame {
+                    width: WIDTH as u16,
+                    height: HEIGHT as u16,
+                    delay,
+                    buffer: Cow::Owned(scale(&pixels)),
+                    ..gif::Frame::default()
+                };
+                encoder.write_frame(&frame).map_err(io::Error::other)
+            }
+            Sink::Apng { frames, .. } => {
+                frames.push((pack(&pixels), delay));
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Sink::Gif(encoder) => encoder.into_inner()?.flush(),
+            Sink::Apng {
+                mut out,
+                palette,
+                frames,
+            } => {
+                let mut encoder = png::Encoder::new(&mut out, WIDTH as u32, HEIGHT as u32);
+                encoder.set_color(png::ColorType::Indexed);
+                encoder.set_depth(png::BitDepth::Two);
+                encoder.set_palette(palette);
+                encoder.set_animated(frames.len() as u32, 0)?;
+
+                let mut writer = encoder.write_header()?;
+                for (packed, delay) in frames {
+                    writer.set_frame_delay(delay, 100)?;
+                    writer.write_image_data(&pack(&scale(&unpack(&packed))))?;
+                }
+                writer.finish()?;
+                out.flush()
+            }
+        }
+    }
+}
+
+// Blow a hi-res frame of color indices up to the size of the recording
+fn scale(pixels: &[u8]) -> Vec<u8> {
+    pixels
+        .chunks(HIRES_DISPLAY_WIDTH)
+        .flat_map(|row| {
+            let row = row
+                .iter()
+                .flat_map(|&color| [color; SCALE])
+                .collect::<Vec<_>>();
+            vec![row; SCALE]
+        })
+        .flatten()
+        .collect()
+}
+
+// Pack color indices into 2 bits each, leftmost pixel in the most significant bits
+fn pack(pixels: &[u8]) -> Vec<u8> {
+    pixels
+        .chunks(4)
+        .map(|px| px.iter().fold(0, |byte, &color| byte << 2 | color))
+        .collect()
+}
+
+fn unpack(packed: &[u8]) -> Vec<u8> {
+    packed
+        .iter()
+        .flat_map(|&byte| [6, 4, 2, 0].map(|shift| byte >> shift & 0b11))
+        .collect()
+}
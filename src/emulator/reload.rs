@@ -0,0 +1,52 @@
+use std::{fs, path::PathBuf, time::Duration, time::SystemTime};
+
+use super::time::Instant;
+
+// How often the ROM file is looked at
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+// ROM file watched for changes (e.g. by an assembler writing it out anew), going by its
+// modification time; a file that can't be looked at (e.g. one being replaced) counts as
+// unchanged until it can be again
+pub struct RomWatcher {
+    path: PathBuf,
+    // Modification time last seen, if it could be read
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl RomWatcher {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        RomWatcher {
+            modified: modified(&path),
+            path,
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    // Whether the file changed since the last time this returned true; it's only looked at
+    // once every `CHECK_INTERVAL`, so this is cheap to call every frame
+    pub fn changed(&mut self) -> bool {
+        if self.last_check.elapsed() < CHECK_INTERVAL {
+            return false;
+        }
+        self.last_check = Instant::now();
+
+        match modified(&self.path) {
+            Some(time) if self.modified != Some(time) => {
+                self.modified = Some(time);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
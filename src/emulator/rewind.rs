@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+
+use crate::chip8::Chip8;
+
+// Snapshots of the last few seconds of machine state to step back through (see the
+// Backspace hotkey), taken every timer tick
+//
+//   Only the newest snapshot is kept in full. Each older one is stored as the XOR of it and
+//   its successor, run-length encoded; consecutive ticks differ in few bytes, so each delta
+//   is tiny compared to a full snapshot (4KB of RAM, or 64KB in XO-CHIP mode)
+//
+pub struct Rewind {
+    // Newest snapshot (see `Chip8::save_state`); empty until the first one is taken
+    head: Vec<u8>,
+    // Encoded deltas leading back from `head`, oldest first
+    deltas: VecDeque<Vec<u8>>,
+    // Maximum number of deltas kept; 0 disables rewinding
+    capacity: usize,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize) -> Self {
+        Rewind {
+            head: Vec::new(),
+            deltas: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.deltas.len() > capacity {
+            self.deltas.pop_front();
+        }
+    }
+
+    // Take a snapshot of `system`, dropping the oldest one if the buffer is full
+    pub fn push(&mut self, system: &Chip8) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let state = system.save_state();
+        if state.len() == self.head.len() {
+            if self.deltas.len() == self.capacity {
+                self.deltas.pop_front();
+            }
+            self.deltas.push_back(encode(&self.head, &state));
+        } else {
+            // Memory was resized (e.g. by switching modes), so older snapshots no longer line up
+            self.deltas.clear();
+        }
+        self.head = state;
+    }
+
+    // Restore `system` to the previous snapshot; returns false if there are none left
+    pub fn pop(&mut self, system: &mut Chip8) -> bool {
+        let Some(delta) = self.deltas.pop_back() else {
+            return false;
+        };
+        decode(&mut self.head, &delta);
+        system.load_state(&self.head).is_ok()
+    }
+}
+
+// Run-length encode `old ^ new` as a sequence of runs, each being the number of unchanged
+// bytes to skip and the number of changed bytes (both u32 LE), followed by the changed
+// bytes XORed
+fn encode(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    let mut pos = 0;
+
+    while pos < new.len() {
+        let skip = (pos..new.len())
+            .find(|&i| old[i] != new[i])
+            .unwrap_or(new.len());
+        let end = (skip..new.len())
+            .find(|&i| old[i] == new[i])
+            .unwrap_or(new.len());
+        if skip == end {
+            break;
+        }
+
+        delta.extend_from_slice(&((skip - pos) as u32).to_le_bytes());
+        delta.extend_from_slice(&((end - skip) as u32).to_le_bytes());
+        delta.extend(
+            old[skip..end]
+                .iter()
+                .zip(&new[skip..end])
+                .map(|(a, b)| a ^ b),
+        );
+        pos = end;
+    }
+
+    delta
+}
+
+// Apply a delta produced by `encode` to `state`, in place; XOR being its own inverse,
+// this goes either way between the two states
+fn decode(state: &mut [u8], delta: &[u8]) {
+    let mut pos = 0;
+    let mut rest = delta;
+
+    while let [s0, s1, s2, s3, l0, l1, l2, l3, tail @ ..] = rest {
+        let skip = u32::from_le_bytes([*s0, *s1, *s2, *s3]) as usize;
+        let len = u32::from_le_bytes([*l0, *l1, *l2, *l3]) as usize;
+        pos += skip;
+        for (byte, diff) in state[pos..pos + len].iter_mut().zip(&tail[..len]) {
+            *byte ^= diff;
+        }
+        pos += len;
+        rest = &tail[len..];
+    }
+}
@@ -0,0 +1,812 @@
+use std::{
+    ffi::OsStr,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::{chip8::fnv1a, emulator::EmuError};
+
+// What a ROM file turned out to hold
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RomFormat {
+    // Program bytes as they're loaded into memory (.ch8, .c8, .sc8, ...)
+    Raw,
+    // Text listing of the bytes in hex, e.g. `00E0 A22A` or `0200: 00 E0 A2 2A`
+    HexDump,
+    // Web page with the program embedded as an array of bytes, e.g. an Octo HTML export
+    Html,
+    // Octo cartridge, a GIF image with the program hidden in its pixels (see `octo_cart`)
+    OctoCart,
+}
+
+// ROMs built into the binary, run with `--builtin=NAME` (or as `builtin:NAME`): the name,
+// what it is, and the program bytes. The test ROMs come from Timendus' CHIP-8 test suite,
+// and the games, all in the public domain, from the CHIP-8 community archive
+// (https://johnearnest.github.io/chip8Archive)
+#[cfg(feature = "bundled-roms")]
+pub const BUILTIN: &[(&str, &str, &[u8])] = &[
+    (
+        "ibm-logo",
+        "Draws the IBM logo, the classic first ROM to get running",
+        include_bytes!("../../roms/test/2-ibm-logo.ch8"),
+    ),
+    (
+        "opcodes",
+        "Checks the common opcodes, marking each with a tick or a cross",
+        include_bytes!("../../roms/test/3-corax+.ch8"),
+    ),
+    (
+        "br8kout",
+        "Breakout clone",
+        include_bytes!("../../roms/br8kout.ch8"),
+    ),
+    (
+        "flightrunner",
+        "Side-scrolling flying game",
+        include_bytes!("../../roms/flightrunner.ch8"),
+    ),
+    (
+        "outlaw",
+        "Western shootout",
+        include_bytes!("../../roms/outlaw.ch8"),
+    ),
+];
+#[cfg(not(feature = "bundled-roms"))]
+pub const BUILTIN: &[(&str, &str, &[u8])] = &[];
+
+// Prefix of the paths naming built-in ROMs
+pub const BUILTIN_PREFIX: &str = "builtin:";
+
+// Most bytes read from stdin or a URL; far more than any ROM, or page embedding one, needs
+const MAX_STREAM_LEN: u64 = 1 << 20;
+
+// Extensions ROMs go by, to pick one out of a ZIP archive along with whatever else it
+// ships with (e.g. a readme)
+const ROM_EXTENSIONS: [&str; 5] = ["ch8", "c8", "sc8", "xo8", "c8x"];
+
+// Signatures of a ZIP archive's records: a file's local header, its central directory
+// entry, and the end of the central directory
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const ZIP_ENTRY_MAGIC: &[u8] = b"PK\x01\x02";
+const ZIP_END_MAGIC: &[u8] = b"PK\x05\x06";
+// Size of the end of central directory record, not counting the comment that may follow
+const ZIP_END_LEN: usize = 22;
+
+// Read the ROM at `path`, converting it to raw program bytes if need be (see `decode`)
+//
+//   `path` is `-` to read the ROM from stdin (e.g. `curl ... | chippity -`), an http:// or
+//   https:// URL to download it (built with the `http` feature), `builtin:NAME` for one of
+//   the `BUILTIN` ROMs (built with the `bundled-roms` feature), or a file path
+//
+pub fn read_rom(path: &str) -> Result<Vec<u8>, EmuError> {
+    let data = match path {
+        "-" => read_stream(io::stdin().lock()),
+        builtin if builtin.starts_with(BUILTIN_PREFIX) => read_builtin(builtin),
+        url if is_url(url) => download(url),
+        path => fs::read(path),
+    }
+    .map_err(|source| EmuError::ReadRom {
+        path: path.to_owned(),
+        source,
+    })?;
+    decode(data)
+        .map(|(rom, _)| rom)
+        .map_err(|reason| EmuError::RomFormat {
+            path: path.to_owned(),
+            reason,
+        })
+}
+
+// Path that files kept alongside a ROM (save states, recordings) are named after: the ROM's
+// own for a file, or else a name for it in the working directory
+pub fn local_path(path: &str) -> &str {
+    match path {
+        "-" => "stdin",
+        builtin if builtin.starts_with(BUILTIN_PREFIX) => &builtin[BUILTIN_PREFIX.len()..],
+        url if is_url(url) => url
+            .split(['?', '#'])
+            .next()
+            .and_then(|url| url.rsplit('/').find(|segment| !segment.is_empty()))
+            .filter(|name| !name.contains(':'))
+            .unwrap_or("download"),
+        path => path,
+    }
+}
+
+// File under `dir` that the flag registers (SCHIP FX75) of `rom` (program bytes) are kept
+// in; named after a hash of the program rather than its path, so they follow the program
+// wherever it's run from
+pub fn flags_path(dir: &Path, rom: &[u8]) -> PathBuf {
+    dir.join(format!("{:016x}.flags", fnv1a(rom.iter().copied())))
+}
+
+fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+fn read_builtin(path: &str) -> io::Result<Vec<u8>> {
+    let name = &path[BUILTIN_PREFIX.len()..];
+    match BUILTIN.iter().find(|(builtin, ..)| *builtin == name) {
+        Some((_, _, rom)) => Ok(rom.to_vec()),
+        None if cfg!(feature = "bundled-roms") => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no such built-in ROM (see --list-builtin)",
+        )),
+        None => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "built-in ROMs need chippity built with the `bundled-roms` feature",
+        )),
+    }
+}
+
+// Read all of `stream`, refusing to go past `MAX_STREAM_LEN`
+fn read_stream(stream: impl Read) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    stream.take(MAX_STREAM_LEN + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_STREAM_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("more than {MAX_STREAM_LEN} bytes, too large to be a ROM"),
+        ));
+    }
+    Ok(data)
+}
+
+#[cfg(feature = "http")]
+fn download(url: &str) -> io::Result<Vec<u8>> {
+    let response = ureq::get(url).call().map_err(io::Error::other)?;
+    read_stream(response.into_reader())
+}
+
+#[cfg(not(feature = "http"))]
+fn download(_url: &str) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "loading ROMs from URLs needs chippity built with the `http` feature",
+    ))
+}
+
+// Work out the format of a ROM file's contents and convert them to raw program bytes, or
+// explain why they can't be run, rather than executing whatever they hold as opcodes
+//
+//   Raw ROMs are rarely valid text (0x00 alone shows up in most programs), so anything
+//   that reads as text is taken to be either a hex dump or a web page
+//
+pub fn decode(data: Vec<u8>) -> Result<(Vec<u8>, RomFormat), String> {
+    if data.is_empty() {
+        return Err("the file is empty".to_owned());
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return octo_cart(&data).map(|rom| (rom, RomFormat::OctoCart));
+    }
+    if data.starts_with(ZIP_MAGIC) {
+        let rom = unzip(&data)?;
+        // Archives nested in it are left to be extracted by hand
+        if rom.starts_with(ZIP_MAGIC) {
+            return Err("this ZIP archive holds another; extract the ROM from it first".to_owned());
+        }
+        return decode(rom);
+    }
+    if data.starts_with(b"HPHP48-") {
+        return Err(
+            "this is an HP48 calculator binary, which wraps the program in a \
+             header that isn't byte-aligned; look for a raw .ch8 or .sc8 copy"
+                .to_owned(),
+        );
+    }
+
+    let is_text = data
+        .iter()
+        .all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace());
+    if !is_text {
+        return Ok((data, RomFormat::Raw));
+    }
+
+    // Checked to be ASCII above
+    let text = String::from_utf8(data).unwrap();
+    let head = text.trim_start().to_ascii_lowercase();
+
+    if head.starts_with("<!doctype html") || head.starts_with("<html") {
+        return match embedded_program(&text) {
+            Some(rom) => Ok((rom, RomFormat::Html)),
+            None => Err(
+                "this is a web page with no program found in it; export the ROM \
+                 as a binary instead"
+                    .to_owned(),
+            ),
+        };
+    }
+    if let Some(rom) = parse_hex_dump(&text) {
+        return Ok((rom, RomFormat::HexDump));
+    }
+    if text
+        .lines()
+        .any(|line| line.trim_start().starts_with(": main"))
+    {
+        return Err("this is Octo source code; assemble it with Octo first".to_owned());
+    }
+
+    Err("this is a text file, but not a hex dump of a ROM".to_owned())
+}
+
+// File in a ZIP archive, as its central directory entry has it
+struct ZipEntry {
+    name: String,
+    // General purpose flags (bit 0 set if encrypted), and how it's compressed
+    flags: usize,
+    method: usize,
+    // Bytes it takes up in the archive, and where its local header starts
+    packed_len: usize,
+    offset: usize,
+}
+
+// Contents of the ROM in a ZIP archive (e.g. as downloaded from an archive site): its one
+// file going by a ROM extension (see `ROM_EXTENSIONS`), or else its only file
+//
+//   Only stored and deflated files can be read, which is all archivers make by default.
+//   Deflated ones are inflated to no more than `MAX_STREAM_LEN` bytes.
+//
+fn unzip(data: &[u8]) -> Result<Vec<u8>, String> {
+    let entries = zip_entries(data).ok_or("this ZIP archive is corrupt")?;
+    // Folders, and the resource forks macOS adds, aren't files to run
+    let files = entries
+        .iter()
+        .filter(|entry| !entry.name.ends_with('/') && !entry.name.starts_with("__MACOSX/"))
+        .collect::<Vec<_>>();
+    let roms = files
+        .iter()
+        .copied()
+        .filter(|entry| {
+            let ext = Path::new(&entry.name).extension().and_then(OsStr::to_str);
+            ext.is_some_and(|ext| ROM_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        })
+        .collect::<Vec<_>>();
+    let entry = match (&roms[..], &files[..]) {
+        ([rom], _) | ([], [rom]) => rom,
+        (_, []) => return Err("this ZIP archive is empty".to_owned()),
+        ([], _) => {
+            return Err(format!(
+                "this ZIP archive holds {} files, none of them a ROM (.{})",
+                files.len(),
+                ROM_EXTENSIONS.join(", .")
+            ))
+        }
+        (roms, _) => {
+            return Err(format!(
+                "this ZIP archive holds {} ROMs; extract the one to run first",
+                roms.len()
+            ))
+        }
+    };
+
+    let name = &entry.name;
+    if entry.flags & 1 != 0 {
+        return Err(format!("'{name}' in this ZIP archive is encrypted"));
+    }
+    let packed = zip_data(data, entry).ok_or("this ZIP archive is corrupt")?;
+    match entry.method {
+        0 => Ok(packed.to_vec()),
+        8 => miniz_oxide::inflate::decompress_to_vec_with_limit(packed, MAX_STREAM_LEN as usize)
+            .map_err(|_| {
+                format!("'{name}' in this ZIP archive is corrupt, or too large to be a ROM")
+            }),
+        method => Err(format!(
+            "'{name}' in this ZIP archive is compressed with an unsupported method ({method})"
+        )),
+    }
+}
+
+// Files listed in a ZIP archive's central directory, or None if it's cut short; that's
+// where their sizes are sure to be, as local headers can leave them to after the data
+fn zip_entries(data: &[u8]) -> Option<Vec<ZipEntry>> {
+    let u16_at =
+        |at: usize| Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?) as usize);
+    let u32_at =
+        |at: usize| Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?) as usize);
+
+    // The end of central directory record is last, but for a comment of up to 64KiB
+    let last = data.len().checked_sub(ZIP_END_LEN)?;
+    let end = (last.saturating_sub(u16::MAX as usize)..=last)
+        .rev()
+        .find(|&at| data[at..].starts_with(ZIP_END_MAGIC))?;
+
+    let mut at = u32_at(end + 16)?;
+    let mut entries = Vec::new();
+    for _ in 0..u16_at(end + 10)? {
+        if !data.get(at..)?.starts_with(ZIP_ENTRY_MAGIC) {
+            return None;
+        }
+        let name_len = u16_at(at + 28)?;
+        let name = data.get(at + 46..at + 46 + name_len)?;
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            flags: u16_at(at + 8)?,
+            method: u16_at(at + 10)?,
+            packed_len: u32_at(at + 20)?,
+            offset: u32_at(at + 42)?,
+        });
+        at += 46 + name_len + u16_at(at + 30)? + u16_at(at + 32)?;
+    }
+    Some(entries)
+}
+
+// Bytes of `entry` as they're stored in the archive, past its local header
+fn zip_data<'a>(data: &'a [u8], entry: &ZipEntry) -> Option<&'a [u8]> {
+    let header = data.get(entry.offset..)?;
+    if !header.starts_with(ZIP_MAGIC) {
+        return None;
+    }
+    let field =
+        |at: usize| Some(u16::from_le_bytes(header.get(at..at + 2)?.try_into().ok()?) as usize);
+    let start = 30 + field(26)? + field(28)?;
+    header.get(start..start + entry.packed_len)
+}
+
+// Program an Octo cartridge (a GIF image of a labelled cartridge) was saved with
+//
+//   The low two bits of each pixel's palette index hold the payload, four pixels to a
+//   byte, frame after frame: its length as a big-endian u32, then that many bytes of JSON
+//   with the program under `"program"`. Octo saves the program's source there, which is
+//   only read if it's nothing but bytes (see `octo_bytes`), there being no assembler here.
+//
+fn octo_cart(data: &[u8]) -> Result<Vec<u8>, String> {
+    let payload = cart_payload(data).ok_or("this GIF image isn't an Octo cartridge")?;
+    // Octo writes the JSON out a byte per character
+    let json = payload.iter().map(|&b| b as char).collect::<String>();
+    match cart_program(&json).ok_or("this Octo cartridge is corrupt")? {
+        CartProgram::Bytes(rom) => Ok(rom),
+        CartProgram::Source(source) => octo_bytes(&source).ok_or_else(|| {
+            "this Octo cartridge holds the program's source code; open it in Octo \
+             and export the ROM as a binary"
+                .to_owned()
+        }),
+    }
+}
+
+// Payload hidden in the pixels of an Octo cartridge, or None if the image can't be decoded
+// or holds less than its length says
+fn cart_payload(data: &[u8]) -> Option<Vec<u8>> {
+    let mut decoder = gif::DecodeOptions::new().read_info(data).ok()?;
+    let mut payload = Vec::new();
+    let mut len = None;
+    while let Some(frame) = decoder.read_next_frame().ok()? {
+        payload.extend(
+            frame
+                .buffer
+                .chunks_exact(4)
+                .map(|pixels| pixels.iter().fold(0, |byte, pixel| byte << 2 | (pixel & 3))),
+        );
+        if len.is_none() && payload.len() >= 4 {
+            let prefix = u32::from_be_bytes(payload[..4].try_into().unwrap()) as u64;
+            if prefix > MAX_STREAM_LEN {
+                return None;
+            }
+            len = Some(4 + prefix as usize);
+        }
+        if let Some(len) = len.filter(|&len| payload.len() >= len) {
+            payload.truncate(len);
+            payload.drain(..4);
+            return Some(payload);
+        }
+    }
+    None
+}
+
+// The program saved in an Octo cartridge's JSON
+enum CartProgram {
+    Source(String),
+    Bytes(Vec<u8>),
+}
+
+// `"program"` member of the JSON object in an Octo cartridge, either source code or an
+// array of bytes, or None if it's missing or the JSON is malformed
+fn cart_program(json: &str) -> Option<CartProgram> {
+    let mut json = Json(json.trim_start());
+    json.eat('{')?;
+    loop {
+        let key = json.string()?;
+        json.eat(':')?;
+        if key == "program" {
+            return match json.peek()? {
+                '"' => json.string().map(CartProgram::Source),
+                _ => json.bytes().map(CartProgram::Bytes),
+            };
+        }
+        json.skip_value()?;
+        json.eat(',')?;
+    }
+}
+
+// What's left of a JSON document, read only as far as Octo cartridges need
+struct Json<'a>(&'a str);
+
+impl Json<'_> {
+    fn peek(&mut self) -> Option<char> {
+        self.0 = self.0.trim_start();
+        self.0.chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> Option<()> {
+        (self.peek()? == c).then(|| self.0 = &self.0[1..])
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.eat('"')?;
+        let mut string = String::new();
+        let mut chars = self.0.char_indices();
+        while let Some((at, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.0 = &self.0[at + 1..];
+                    return Some(string);
+                }
+                '\\' => string.push(match chars.next()?.1 {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'u' => {
+                        let hex = (0..4)
+                            .map(|_| Some(chars.next()?.1))
+                            .collect::<Option<String>>()?;
+                        char::from_u32(u32::from_str_radix(&hex, 16).ok()?).unwrap_or('\u{fffd}')
+                    }
+                    escaped => escaped,
+                }),
+                c => string.push(c),
+            }
+        }
+        None
+    }
+
+    // Array of numbers from 0 to 255
+    fn bytes(&mut self) -> Option<Vec<u8>> {
+        self.eat('[')?;
+        let (array, rest) = self.0.split_once(']')?;
+        self.0 = rest;
+        array
+            .split(',')
+            .map(|value| value.trim().parse().ok())
+            .collect()
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match self.peek()? {
+            '"' => self.string().map(drop),
+            open @ ('{' | '[') => {
+                let close = if open == '{' { '}' } else { ']' };
+                self.eat(open)?;
+                if self.eat(close).is_some() {
+                    return Some(());
+                }
+                loop {
+                    if open == '{' {
+                        self.string()?;
+                        self.eat(':')?;
+                    }
+                    self.skip_value()?;
+                    if self.eat(close).is_some() {
+                        return Some(());
+                    }
+                    self.eat(',')?;
+                }
+            }
+            _ => {
+                // A number, `true`, `false`, or `null`
+                let end = self.0.find([',', '}', ']']).unwrap_or(self.0.len());
+                (end > 0).then(|| self.0 = &self.0[end..])
+            }
+        }
+    }
+}
+
+// Bytes of Octo source that's nothing but numbers (e.g. a ROM imported into Octo as data),
+// decimal, 0x hex, or 0b binary, under an optional `: main` label and with `#` comments
+fn octo_bytes(source: &str) -> Option<Vec<u8>> {
+    let mut tokens = source
+        .lines()
+        .flat_map(|line| {
+            line.split('#')
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+        })
+        .peekable();
+    if tokens.peek() == Some(&":") {
+        tokens.next();
+        tokens.next().filter(|&label| label == "main")?;
+    }
+    let rom = tokens
+        .map(|token| {
+            if let Some(hex) = token.strip_prefix("0x") {
+                u8::from_str_radix(hex, 16).ok()
+            } else if let Some(binary) = token.strip_prefix("0b") {
+                u8::from_str_radix(binary, 2).ok()
+            } else {
+                token.parse().ok()
+            }
+        })
+        .collect::<Option<Vec<u8>>>()?;
+    (!rom.is_empty()).then_some(rom)
+}
+
+// Bytes written out in hex, any number to a word (`00E0`, `00 E0`, or `0x00, 0xE0`), with
+// optional address columns (`0200:`) and comments (`#` or `;` to the end of the line)
+fn parse_hex_dump(text: &str) -> Option<Vec<u8>> {
+    let mut rom = Vec::new();
+
+    for line in text.lines() {
+        let line = line.split(['#', ';']).next().unwrap_or_default();
+        let line = match line.split_once(':') {
+            Some((addr, rest)) if u16::from_str_radix(addr.trim(), 16).is_ok() => rest,
+            _ => line,
+        };
+
+        let words = line.split(|c: char| c.is_ascii_whitespace() || c == ',');
+        for word in words.filter(|word| !word.is_empty()) {
+            let word = word
+                .strip_prefix("0x")
+                .or(word.strip_prefix("0X"))
+                .unwrap_or(word);
+            if word.len() % 2 != 0 {
+                return None;
+            }
+            for pair in word.as_bytes().chunks(2) {
+                rom.push(u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?);
+            }
+        }
+    }
+
+    (!rom.is_empty()).then_some(rom)
+}
+
+// Longest array literal of byte values (decimal or 0x hex) in a page's scripts, which is
+// where exports embed the program
+fn embedded_program(page: &str) -> Option<Vec<u8>> {
+    page.split('[')
+        .skip(1)
+        .filter_map(|rest| {
+            let (array, _) = rest.split_once(']')?;
+            array
+                .split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(|value| match value.strip_prefix("0x") {
+                    Some(hex) => u8::from_str_radix(hex, 16).ok(),
+                    None => value.parse().ok(),
+                })
+                .collect::<Option<Vec<u8>>>()
+        })
+        .filter(|rom| rom.len() >= 2)
+        .max_by_key(Vec::len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw() {
+        let rom = vec![0x00, 0xE0, 0xA2, 0x2A];
+        assert_eq!(decode(rom.clone()), Ok((rom, RomFormat::Raw)));
+        assert_eq!(decode(Vec::new()), Err("the file is empty".to_owned()));
+    }
+
+    #[test]
+    fn hex_dumps() {
+        let rom = vec![0x00, 0xE0, 0xA2, 0x2A];
+        for dump in [
+            "00E0 A22A\n",
+            "00 E0 A2 2A",
+            "0x00, 0xE0,\n0xA2, 0x2A",
+            "0200: 00 E0 # clear\n0202: A2 2A ; load I\n",
+        ] {
+            assert_eq!(
+                decode(dump.into()),
+                Ok((rom.clone(), RomFormat::HexDump)),
+                "{dump:?}"
+            );
+        }
+        // Odd digits, or words that aren't hex
+        assert_eq!(parse_hex_dump("00E A22A"), None);
+        assert_eq!(parse_hex_dump("00E0 G22A"), None);
+        assert_eq!(parse_hex_dump("# nothing but a comment"), None);
+    }
+
+    #[test]
+    fn web_pages() {
+        let page = "<!DOCTYPE html>\n<script>var rom = [0, 224, 0xA2, 0x2A];</script>";
+        assert_eq!(
+            decode(page.into()),
+            Ok((vec![0x00, 0xE0, 0xA2, 0x2A], RomFormat::Html))
+        );
+        assert!(decode("<html><body>hello</body></html>".into()).is_err());
+    }
+
+    #[test]
+    fn not_roms() {
+        assert!(decode(": main\n  clear\n  loop again\n".into())
+            .unwrap_err()
+            .contains("Octo source"));
+        assert!(decode("HPHP48-E\x01\x02".into())
+            .unwrap_err()
+            .contains("HP48"));
+        assert!(decode("just some notes\n".into())
+            .unwrap_err()
+            .contains("not a hex dump"));
+    }
+
+    // ZIP archive of `files`, each a name, its contents, and whether to deflate them
+    fn zip(files: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let u16 = |n: usize| (n as u16).to_le_bytes();
+        let u32 = |n: usize| (n as u32).to_le_bytes();
+        let (mut data, mut directory) = (Vec::new(), Vec::new());
+        for &(name, contents, deflate) in files {
+            let packed = match deflate {
+                true => miniz_oxide::deflate::compress_to_vec(contents, 6),
+                false => contents.to_vec(),
+            };
+            let method = u16(if deflate { 8 } else { 0 });
+            let offset = data.len();
+            data.extend(ZIP_MAGIC);
+            data.extend([20, 0, 0, 0]);
+            data.extend(method);
+            data.extend([0; 8]);
+            data.extend(u32(packed.len()));
+            data.extend(u32(contents.len()));
+            data.extend(u16(name.len()));
+            data.extend(u16(0));
+            data.extend(name.as_bytes());
+            data.extend(&packed);
+
+            directory.extend(ZIP_ENTRY_MAGIC);
+            directory.extend([20, 0, 20, 0, 0, 0]);
+            directory.extend(method);
+            directory.extend([0; 8]);
+            directory.extend(u32(packed.len()));
+            directory.extend(u32(contents.len()));
+            directory.extend(u16(name.len()));
+            directory.extend([0; 12]);
+            directory.extend(u32(offset));
+            directory.extend(name.as_bytes());
+        }
+        let start = data.len();
+        data.extend(&directory);
+        data.extend(ZIP_END_MAGIC);
+        data.extend([0; 4]);
+        data.extend(u16(files.len()));
+        data.extend(u16(files.len()));
+        data.extend(u32(directory.len()));
+        data.extend(u32(start));
+        data.extend(u16(0));
+        data
+    }
+
+    #[test]
+    fn zip_archives() {
+        let rom = [0x00, 0xE0, 0x12, 0x00].repeat(16);
+        for deflate in [false, true] {
+            let archive = zip(&[("readme.txt", b"hi", deflate), ("GAME.CH8", &rom, deflate)]);
+            assert_eq!(decode(archive), Ok((rom.clone(), RomFormat::Raw)));
+        }
+        // Its only file, whatever it's called
+        let archive = zip(&[("game/", b"", false), ("game/rom.bin", &rom, true)]);
+        assert_eq!(decode(archive), Ok((rom.clone(), RomFormat::Raw)));
+        // Converted like any other file
+        let archive = zip(&[("rom.txt", b"00E0 1200", false)]);
+        assert_eq!(
+            decode(archive),
+            Ok((vec![0x00, 0xE0, 0x12, 0x00], RomFormat::HexDump))
+        );
+    }
+
+    #[test]
+    fn zip_archives_without_one_rom() {
+        let error = |files: &[(&str, &[u8], bool)]| decode(zip(files)).unwrap_err();
+        assert_eq!(error(&[("game/", b"", false)]), "this ZIP archive is empty");
+        assert!(error(&[("a.txt", b"a", false), ("b.txt", b"b", false)]).contains("none of them"));
+        assert!(error(&[("a.ch8", b"a", false), ("b.sc8", b"b", false)]).contains("2 ROMs"));
+        assert!(
+            error(&[("inner.zip", &zip(&[("a.ch8", b"a", false)]), false)])
+                .contains("holds another")
+        );
+    }
+
+    #[test]
+    fn corrupt_zip_archives() {
+        let archive = zip(&[("game.ch8", &[0x00, 0xE0], false)]);
+        // Cut short, its directory pointing past the end
+        assert_eq!(
+            decode(archive[..archive.len() - 1].to_vec()),
+            Err("this ZIP archive is corrupt".to_owned())
+        );
+        let mut moved = archive.clone();
+        let end = moved.len() - ZIP_END_LEN;
+        moved[end + 16] += 1;
+        assert_eq!(decode(moved), Err("this ZIP archive is corrupt".to_owned()));
+        // Encrypted, or compressed some other way
+        let mut encrypted = archive.clone();
+        let entry = archive
+            .windows(4)
+            .position(|w| w == ZIP_ENTRY_MAGIC)
+            .unwrap();
+        encrypted[entry + 8] = 1;
+        assert!(decode(encrypted).unwrap_err().contains("encrypted"));
+        let mut imploded = archive;
+        imploded[entry + 10] = 6;
+        assert!(decode(imploded)
+            .unwrap_err()
+            .contains("unsupported method (6)"));
+        // Deflated data that isn't
+        let mut garbled = zip(&[("game.ch8", &[0; 64], true)]);
+        garbled[30 + "game.ch8".len()] = 0xFF;
+        assert!(decode(garbled).unwrap_err().contains("corrupt"));
+    }
+
+    // Octo cartridge holding `json`, two bits to a pixel
+    fn cart(json: &str) -> Vec<u8> {
+        let mut payload = (json.len() as u32).to_be_bytes().to_vec();
+        payload.extend(json.bytes());
+        // Past the low two bits, pixels are the label's colors
+        let pixels = payload
+            .iter()
+            .flat_map(|&b| [b >> 6, b >> 4 & 3, b >> 2 & 3, b & 3])
+            .map(|bits| bits | 4)
+            .collect::<Vec<_>>();
+        let mut gif = Vec::new();
+        let mut encoder = gif::Encoder::new(&mut gif, 16, 16, &[0; 8 * 3]).unwrap();
+        for chunk in pixels.chunks(16 * 16) {
+            let mut buffer = chunk.to_vec();
+            buffer.resize(16 * 16, 0);
+            let frame = gif::Frame {
+                width: 16,
+                height: 16,
+                buffer: buffer.into(),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).unwrap();
+        }
+        drop(encoder);
+        gif
+    }
+
+    #[test]
+    fn octo_carts() {
+        let json = r##"{"options":{"tickrate":20,"keys":{"up":"w"},"fillColor":"#FFCC00"},
+            "program":": main\n  0x00 0xE0 # clear\n  18 0b0\n"}"##;
+        assert_eq!(
+            decode(cart(json)),
+            Ok((vec![0x00, 0xE0, 18, 0], RomFormat::OctoCart))
+        );
+        let json = r#"{"program":[0,224,18,0],"options":{}}"#;
+        assert_eq!(
+            decode(cart(json)),
+            Ok((vec![0x00, 0xE0, 18, 0], RomFormat::OctoCart))
+        );
+    }
+
+    #[test]
+    fn octo_carts_unread() {
+        let source = r#"{"program":": main\n  clear\n  loop again\n","options":{}}"#;
+        assert!(decode(cart(source)).unwrap_err().contains("source code"));
+        assert_eq!(
+            decode(cart(r#"{"options":{}}"#)),
+            Err("this Octo cartridge is corrupt".to_owned())
+        );
+        assert_eq!(
+            decode(cart(r#"{"program": "#)),
+            Err("this Octo cartridge is corrupt".to_owned())
+        );
+        // Its payload cut short, or no GIF at all past the header
+        let mut short = cart(r#"{"program":[0,224]}"#);
+        short.truncate(short.len() / 2);
+        assert!(decode(short)
+            .unwrap_err()
+            .contains("isn't an Octo cartridge"));
+        assert!(decode(b"GIF89a".to_vec())
+            .unwrap_err()
+            .contains("isn't an Octo cartridge"));
+    }
+}
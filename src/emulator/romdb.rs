@@ -0,0 +1,56 @@
+use crate::{
+    chip8::{fnv1a, Platform},
+    driver::palette::Palette,
+};
+
+// Database of known ROMs, compiled into the binary (see the header of the file for its
+// format); more can be added a line at a time
+const DATABASE: &str = include_str!("romdb.csv");
+
+// What's known about a ROM: its title, and the settings it was written to run with
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RomInfo {
+    pub title: &'static str,
+    // Clock rate set to the one it's meant to run at, if it has one
+    pub platform: Platform,
+    pub palette: Option<Palette>,
+}
+
+// Hash the database knows `rom` (program bytes, as loaded into memory) by
+pub fn hash(rom: &[u8]) -> u64 {
+    fnv1a(rom.iter().copied())
+}
+
+// Look up `rom` among the known ROMs
+pub fn lookup(rom: &[u8]) -> Option<RomInfo> {
+    let hash = format!("{:016x}", hash(rom));
+    DATABASE
+        .lines()
+        .filter(|line| line.starts_with(&hash))
+        .find_map(parse_entry)
+}
+
+// Parse a line of the database; the colors come last, so they can keep their commas
+fn parse_entry(line: &'static str) -> Option<RomInfo> {
+    let mut fields = line.splitn(5, ',').map(str::trim);
+    let (_hash, title, platform, tickrate, colors) = (
+        fields.next()?,
+        fields.next()?,
+        fields.next()?,
+        fields.next()?,
+        fields.next()?,
+    );
+    let mut platform = platform.parse::<Platform>().ok()?;
+    if !tickrate.is_empty() {
+        platform.clock_hz = tickrate.parse::<u32>().ok()? as f32 * 60.0;
+    }
+    let palette = match colors {
+        "" => None,
+        colors => Some(colors.parse().ok()?),
+    };
+    Some(RomInfo {
+        title,
+        platform,
+        palette,
+    })
+}
@@ -0,0 +1,175 @@
+use std::{fs, sync::Arc};
+
+use bitvec::slice::BitSlice;
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST, INT};
+
+use super::ExecutionHook;
+use crate::{
+    chip8::{MachineState, NUM_KEYS},
+    diagnostics,
+    driver::events::{EventInput, InputEvent},
+};
+
+// Automation written in Rhai (https://rhai.rs), e.g. a bot playing the game or a cheat
+// holding down a key, run as an `ExecutionHook`: the script's `on_frame(m)` is called at
+// the end of every 60Hz frame with a read-only view of the machine, and presses keys
+// through the `EventInput` handed out alongside it by `load`
+//
+//   fn on_frame(m) {
+//       // Chase the ball with the paddle
+//       if m.peek(0x3F0) > m.v(3) { press(6) } else { release(6) }
+//       if m.frame % 60 == 0 { print(`score ${m.v(0xE)}`) }
+//   }
+//
+//   The view has `pc`, `i`, `dt`, `st`, `frame` (the 60Hz ticks so far), `width`, and
+//   `height`, along with `v(n)`, `peek(addr)`, `key(n)` (whether key n is held), and
+//   `pixel(x, y)` (the bitplanes lit there as a bitmask, 0 for off). `press(key)`,
+//   `release(key)`, and `tap(key)` change keys 0-F, held along with the player's.
+//   Top-level statements run once as the script loads. Whatever it prints is collected
+//   with the diagnostics, and an error stops the script for the rest of the run.
+//
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    // Last frame presented, and the ticks counted so far
+    display: Arc<Display>,
+    frame: INT,
+    failed: bool,
+}
+
+// Pixels of a frame presented, as bitmasks of the planes lit
+#[derive(Default)]
+struct Display {
+    pixels: Vec<u8>,
+    dims: (usize, usize),
+}
+
+// What `on_frame` is handed
+#[derive(Clone)]
+struct Machine {
+    state: Arc<MachineState>,
+    display: Arc<Display>,
+    frame: INT,
+}
+
+impl Script {
+    // Compile the script at `path` and run its top level, along with the input device its
+    // key presses come from
+    pub fn load(path: &str) -> Result<(Self, EventInput), String> {
+        let source =
+            fs::read_to_string(path).map_err(|e| format!("failed to read script '{path}': {e}"))?;
+        let (keys, input) = EventInput::new();
+
+        let mut engine = Engine::new();
+        engine
+            .on_print(|text| diagnostics::warn("script", text))
+            .on_debug(|text, _, _| diagnostics::warn("script", text))
+            .register_type_with_name::<Machine>("Machine")
+            .register_get("pc", |m: &mut Machine| m.state.pc as INT)
+            .register_get("i", |m: &mut Machine| m.state.i_reg as INT)
+            .register_get("dt", |m: &mut Machine| m.state.delay_timer as INT)
+            .register_get("st", |m: &mut Machine| m.state.sound_timer as INT)
+            .register_get("frame", |m: &mut Machine| m.frame)
+            .register_get("width", |m: &mut Machine| m.display.dims.0 as INT)
+            .register_get("height", |m: &mut Machine| m.display.dims.1 as INT)
+            .register_fn("v", |m: &mut Machine, n: INT| {
+                usize::try_from(n)
+                    .ok()
+                    .and_then(|n| m.state.v_reg.get(n))
+                    .map_or(0, |&v| v as INT)
+            })
+            .register_fn("peek", |m: &mut Machine, addr: INT| {
+                usize::try_from(addr)
+                    .ok()
+                    .and_then(|addr| m.state.memory.get(addr))
+                    .map_or(0, |&byte| byte as INT)
+            })
+            .register_fn("key", |m: &mut Machine, n: INT| {
+                (0..NUM_KEYS as INT).contains(&n) && m.state.keys & 1 << n != 0
+            })
+            .register_fn("pixel", |m: &mut Machine, x: INT, y: INT| {
+                let (w, h) = m.display.dims;
+                match (usize::try_from(x), usize::try_from(y)) {
+                    (Ok(x), Ok(y)) if x < w && y < h => m.display.pixels[y * w + x] as INT,
+                    _ => 0,
+                }
+            });
+        // Key presses go down and/or come up; sending fails once the session is over
+        let send = |down: bool, up: bool| {
+            let keys = keys.clone();
+            move |key: INT| {
+                let Ok(key) = u8::try_from(key) else {
+                    return;
+                };
+                if down {
+                    let _ = keys.send(InputEvent::KeyDown(key));
+                }
+                if up {
+                    let _ = keys.send(InputEvent::KeyUp(key));
+                }
+            }
+        };
+        engine
+            .register_fn("press", send(true, false))
+            .register_fn("release", send(false, true))
+            .register_fn("tap", send(true, true));
+
+        let ast = engine
+            .compile(&source)
+            .map_err(|e| format!("script '{path}': {e}"))?;
+        if !ast.iter_functions().any(|f| f.name == "on_frame") {
+            return Err(format!("script '{path}' has no `fn on_frame(m)`"));
+        }
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| format!("script '{path}': {e}"))?;
+
+        let script = Script {
+            engine,
+            ast,
+            scope,
+            display: Arc::default(),
+            frame: 0,
+            failed: false,
+        };
+        Ok((script, input))
+    }
+
+    fn call_on_frame(&mut self, state: &MachineState) -> Result<(), Box<EvalAltResult>> {
+        let machine = Machine {
+            state: Arc::new(state.clone()),
+            display: self.display.clone(),
+            frame: self.frame,
+        };
+        self.engine
+            .call_fn::<Dynamic>(&mut self.scope, &self.ast, "on_frame", (machine,))
+            .map(drop)
+    }
+}
+
+impl ExecutionHook for Script {
+    fn on_frame(&mut self, state: &MachineState) {
+        self.frame += 1;
+        if self.failed {
+            return;
+        }
+        if let Err(e) = self.call_on_frame(state) {
+            diagnostics::warn("script", format!("stopped: {e}"));
+            self.failed = true;
+        }
+    }
+
+    fn on_draw(&mut self, planes: &[&BitSlice<usize>], dims: (usize, usize)) {
+        let pixels = (0..dims.0 * dims.1)
+            .map(|n| {
+                planes
+                    .iter()
+                    .enumerate()
+                    .fold(0, |mask, (plane, bits)| mask | (bits[n] as u8) << plane)
+            })
+            .collect();
+        self.display = Arc::new(Display { pixels, dims });
+    }
+}
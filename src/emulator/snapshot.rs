@@ -0,0 +1,123 @@
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use bitvec::{slice::BitSlice, vec::BitVec};
+
+use super::ExecutionHook;
+#[cfg(feature = "recording")]
+use crate::driver::palette::NUM_COLORS;
+use crate::driver::{
+    palette::{self, Palette},
+    pixel_color,
+};
+
+// File format of a snapshot
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImageFormat {
+    // Binary PBM, lit pixels black
+    Pbm,
+    // Indexed PNG in the palette's colors
+    #[cfg(feature = "recording")]
+    Png,
+}
+
+impl ImageFormat {
+    // PNG where it's built in, the colors being worth keeping
+    #[cfg(feature = "recording")]
+    pub const DEFAULT: ImageFormat = ImageFormat::Png;
+    #[cfg(not(feature = "recording"))]
+    pub const DEFAULT: ImageFormat = ImageFormat::Pbm;
+
+    // Format going by the file's extension: `.pbm` or `.png` (with the `recording` feature)
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "pbm" => Some(ImageFormat::Pbm),
+            #[cfg(feature = "recording")]
+            "png" => Some(ImageFormat::Png),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Pbm => "pbm",
+            #[cfg(feature = "recording")]
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+// Last frame presented, one image pixel per display pixel, to write out as an image, e.g.
+// a screenshot or a golden image for a regression test
+//
+//   Registered with `Emulator::add_hook` by reference, it keeps up with every frame
+//   presented from then on.
+//
+#[derive(Default)]
+pub struct Snapshot {
+    planes: Vec<BitVec<usize>>,
+    dims: (usize, usize),
+}
+
+impl Snapshot {
+    // Whether a frame was presented to take
+    pub fn is_empty(&self) -> bool {
+        self.planes.is_empty()
+    }
+
+    // Write the frame to `path` as `format`, coloring a PNG with `palette`
+    #[cfg_attr(not(feature = "recording"), allow(unused_variables))]
+    pub fn write(&self, path: &Path, format: ImageFormat, palette: &Palette) -> io::Result<()> {
+        let (w, h) = self.dims;
+        let planes = self
+            .planes
+            .iter()
+            .map(BitVec::as_bitslice)
+            .collect::<Vec<_>>();
+        let colors = (0..w * h).map(|n| pixel_color(&planes, n));
+        let mut out = BufWriter::new(File::create(path)?);
+
+        match format {
+            ImageFormat::Pbm => {
+                write!(out, "P4\n{w} {h}\n")?;
+                let lit = colors.map(palette::is_lit).collect::<Vec<_>>();
+                // Rows padded out to whole bytes, leftmost pixel in the most significant bit
+                for row in lit.chunks(w.max(1)) {
+                    let bytes = row.chunks(8).map(|px| {
+                        px.iter()
+                            .enumerate()
+                            .fold(0_u8, |byte, (n, &lit)| byte | (lit as u8) << (7 - n))
+                    });
+                    out.write_all(&bytes.collect::<Vec<_>>())?;
+                }
+            }
+            #[cfg(feature = "recording")]
+            ImageFormat::Png => {
+                let rgb = (0..NUM_COLORS)
+                    .flat_map(|n| <[u8; 3]>::from(palette.rgb(n)))
+                    .collect::<Vec<_>>();
+                let mut encoder = png::Encoder::new(&mut out, w as u32, h as u32);
+                encoder.set_color(png::ColorType::Indexed);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.set_palette(rgb);
+                let mut writer = encoder.write_header()?;
+                writer.write_image_data(&colors.map(|color| color as u8).collect::<Vec<_>>())?;
+                writer.finish()?;
+            }
+        }
+        out.flush()
+    }
+}
+
+impl ExecutionHook for &RefCell<Snapshot> {
+    fn on_draw(&mut self, planes: &[&BitSlice<usize>], dims: (usize, usize)) {
+        let mut snapshot = self.borrow_mut();
+        snapshot.planes = planes.iter().map(|&plane| plane.to_bitvec()).collect();
+        snapshot.dims = dims;
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use super::time::Instant;
+
+// Wall-clock window rates are measured over
+const WINDOW: Duration = Duration::from_secs(1);
+
+// Frames presented, instructions executed, and timer ticks per second of wall-clock time,
+// for frontends to show while the machine runs; rates are measured over one-second windows
+// and hold their value until the next window closes
+pub struct LiveStats {
+    // Start of the current window, and the counts then
+    window_start: Instant,
+    window_frames: u64,
+    window_cycles: u64,
+    window_ticks: u64,
+    // Rates over the last complete window
+    fps: f32,
+    ips: f32,
+    tps: f32,
+}
+
+impl Default for LiveStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiveStats {
+    pub fn new() -> Self {
+        LiveStats {
+            window_start: Instant::now(),
+            window_frames: 0,
+            window_cycles: 0,
+            window_ticks: 0,
+            fps: 0.0,
+            ips: 0.0,
+            tps: 0.0,
+        }
+    }
+
+    // Take in the frames presented, instructions executed, and timer ticks so far, closing
+    // the current window if it has run its course
+    pub fn update(&mut self, frames: u64, cycles: u64, ticks: u64) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < WINDOW {
+            return;
+        }
+
+        let secs = elapsed.as_secs_f32();
+        self.fps = frames.saturating_sub(self.window_frames) as f32 / secs;
+        self.ips = cycles.saturating_sub(self.window_cycles) as f32 / secs;
+        self.tps = ticks.saturating_sub(self.window_ticks) as f32 / secs;
+        self.window_start = Instant::now();
+        self.window_frames = frames;
+        self.window_cycles = cycles;
+        self.window_ticks = ticks;
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    pub fn ips(&self) -> f32 {
+        self.ips
+    }
+
+    // The rates as one line for `--stats`, the instruction rate against the `clock_rate`
+    // it should be keeping to, e.g. `IPS 719/720  FPS 60.0  TPS 60.0`
+    pub fn overlay(&self, clock_rate: f32) -> String {
+        format!(
+            "IPS {:.0}/{clock_rate:.0}  FPS {:.1}  TPS {:.1}",
+            self.ips, self.fps, self.tps
+        )
+    }
+}
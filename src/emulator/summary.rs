@@ -0,0 +1,168 @@
+use std::{fmt, time::Duration};
+
+use super::time::Instant;
+use crate::chip8;
+
+// A sound timer activation (FX18 with a nonzero value), timestamped in emulated time
+#[derive(Clone, Copy, Debug)]
+pub struct SoundEvent {
+    // Instructions executed before the activation
+    pub cycle: u64,
+    // Timer ticks elapsed before the activation
+    pub tick: u64,
+    // Value the sound timer was set to, i.e. how many ticks the beep lasts
+    pub duration: u8,
+}
+
+// Distinct unknown opcodes recorded in the summary; past this many only the count goes up
+const MAX_UNKNOWN_LOG: usize = 8;
+
+// Statistics collected over the course of a run, reported on exit with `--summary`
+#[derive(Clone, Debug)]
+pub struct RunSummary {
+    // Instructions executed by the guest system
+    pub cycles: u64,
+    // Frames sent to the display device
+    pub frames: u64,
+    // Instructions that were skipped over instead of executed (e.g. 0NNN system calls)
+    pub skipped: u64,
+    // Unrecognized instructions skipped over in lenient mode
+    pub unknown: u64,
+    // The first distinct (address, opcode) pairs of those
+    pub unknown_log: Vec<(u16, u16)>,
+    // CHIP-8 timer ticks, and how many of them make a second; they double as the emulated
+    // clock
+    pub timer_ticks: u64,
+    pub timer_freq: f32,
+    // Sound timer activations
+    pub sounds: u64,
+    // Each of them, in order, for checking beeper "music" against the timer model; only kept
+    // when asked for (see `set_sound_log`), as a ROM beeping every frame would pile them up
+    // for as long as it runs
+    pub sound_events: Vec<SoundEvent>,
+    // Wall-clock time spent running
+    pub time_played: Duration,
+    // Start of the current run
+    started: Option<Instant>,
+    // Whether `sound_events` are kept
+    sound_log: bool,
+}
+
+impl Default for RunSummary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunSummary {
+    pub fn new() -> Self {
+        RunSummary {
+            cycles: 0,
+            frames: 0,
+            skipped: 0,
+            unknown: 0,
+            unknown_log: Vec::new(),
+            timer_ticks: 0,
+            timer_freq: chip8::TIMER_FREQ,
+            sounds: 0,
+            sound_events: Vec::new(),
+            time_played: Duration::ZERO,
+            started: None,
+            sound_log: false,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(started) = self.started.take() {
+            self.time_played += started.elapsed();
+        }
+    }
+
+    // Keep each sound timer activation in `sound_events` from now on, for `sound_log_csv`
+    pub fn set_sound_log(&mut self, on: bool) {
+        self.sound_log = on;
+    }
+
+    // Record a sound timer activation at the current emulated time
+    pub fn log_sound(&mut self, duration: u8) {
+        self.sounds += 1;
+        if !self.sound_log {
+            return;
+        }
+        self.sound_events.push(SoundEvent {
+            cycle: self.cycles,
+            tick: self.timer_ticks,
+            duration,
+        });
+    }
+
+    // Record an unrecognized instruction that was skipped over
+    pub fn log_unknown(&mut self, pc: u16, opcode: u16) {
+        self.unknown += 1;
+        if self.unknown_log.len() < MAX_UNKNOWN_LOG && !self.unknown_log.contains(&(pc, opcode)) {
+            self.unknown_log.push((pc, opcode));
+        }
+    }
+
+    // Sound event log as CSV, one activation per row; `time_ms` is emulated time derived
+    // from the timer ticks
+    pub fn sound_log_csv(&self) -> String {
+        let mut csv = String::from("cycle,tick,time_ms,duration_ticks\n");
+        for event in &self.sound_events {
+            csv += &format!(
+                "{},{},{:.1},{}\n",
+                event.cycle,
+                event.tick,
+                event.tick as f64 * 1000.0 / self.timer_freq as f64,
+                event.duration
+            );
+        }
+        csv
+    }
+
+    // Average rate of `count` events per second of play
+    fn per_sec(&self, count: u64) -> f64 {
+        match self.time_played.as_secs_f64() {
+            secs if secs > 0.0 => count as f64 / secs,
+            _ => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.time_played.as_secs_f64();
+
+        writeln!(f, "SESSION SUMMARY:")?;
+        writeln!(
+            f,
+            "    Time played         {}m {:04.1}s",
+            (secs / 60.0) as u64,
+            secs % 60.0
+        )?;
+        writeln!(
+            f,
+            "    Cycles executed     {} (avg. {:.1} IPS)",
+            self.cycles,
+            self.per_sec(self.cycles)
+        )?;
+        writeln!(
+            f,
+            "    Frames drawn        {} (avg. {:.1} FPS)",
+            self.frames,
+            self.per_sec(self.frames)
+        )?;
+        writeln!(f, "    Opcodes skipped     {}", self.skipped)?;
+        if self.unknown > 0 {
+            writeln!(f, "    Unknown opcodes     {}", self.unknown)?;
+            for (pc, opcode) in &self.unknown_log {
+                writeln!(f, "        {opcode:04X} at {pc:#05X}")?;
+            }
+        }
+        write!(f, "    Sound events        {}", self.sounds)
+    }
+}
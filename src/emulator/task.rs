@@ -0,0 +1,64 @@
+use tokio::task;
+
+use super::{
+    time::{Instant, Sleeper},
+    EmuError, Emulator, Flow, INPUT_POLL_INTERVAL,
+};
+use crate::driver::{AudioDevice, DisplayDevice, InputDevice};
+
+impl<'a, I, D, A> Emulator<'a, I, D, A>
+where
+    I: InputDevice,
+    D: DisplayDevice,
+    A: AudioDevice,
+{
+    // Run the emulator as a future on a tokio runtime until the program exits or the user
+    // quits, yielding at frame boundaries instead of blocking the thread; this lets
+    // frontends talking over channels (see `driver::channel`) share the runtime with it
+    //
+    //   The future borrows the peripherals' `RefCell`s, so it is not `Send`; run it with
+    //   `task::spawn_local` on a `LocalSet` or alongside other futures with `select!`/`join!`
+    //
+    pub async fn run_async(&mut self) -> Result<(), EmuError> {
+        self.summary.start();
+        let result = self.run_loop_async().await;
+        self.summary.stop();
+        result.and(self.stop_recording())
+    }
+
+    async fn run_loop_async(&mut self) -> Result<(), EmuError> {
+        let mut state = self.loop_state();
+        let mut sleeper = Sleeper::new();
+
+        while let Flow::Wait(wait) = self.run_pass(&mut state)? {
+            let mut slept = None;
+            if !wait.is_zero() {
+                // As `Sleeper::sleep`, but other tasks get to run through the spin as well
+                let start = Instant::now();
+                loop {
+                    let nap = sleeper
+                        .sleep_part(wait.saturating_sub(start.elapsed()))
+                        .min(INPUT_POLL_INTERVAL);
+                    if nap.is_zero() {
+                        break;
+                    }
+                    let napped = Instant::now();
+                    tokio::time::sleep(nap).await;
+                    sleeper.observe(nap, napped.elapsed());
+                    self.poll_between(&mut state);
+                }
+                while start.elapsed() < wait {
+                    task::yield_now().await;
+                }
+                slept = Some((wait, start.elapsed()));
+            } else {
+                // Hand control back to the runtime between frames run back to back (e.g.
+                // unthrottled, or catching up on lag)
+                task::yield_now().await;
+            }
+            self.observe_pacing(slept);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+use std::{thread, time::Duration};
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web_time::Instant;
+
+use super::timers::TimerClock;
+
+// If the host falls this far behind (e.g. the process was suspended), give up on catching
+// up rather than running flat out until machine time has caught up again
+const MAX_LAG: Duration = Duration::from_millis(250);
+// Sleeps shorter than this are deferred and batched up, as they tend to overshoot anyway
+const MIN_SLEEP: Duration = Duration::from_millis(1);
+// Part of each wait spun out rather than slept through to start with, before the host's
+// actual sleep overshoot is known, and the most it's ever allowed to grow to so a host with
+// a coarse timer doesn't end up spinning through most of every frame
+const INITIAL_SPIN: Duration = Duration::from_millis(1);
+const MAX_SPIN: Duration = Duration::from_millis(4);
+// Factor the spun-out part shrinks by per wait, so a one-off long overshoot wears off
+const SPIN_DECAY: f64 = 0.99;
+// Emulated (machine) time, measured in guest clock cycles and the timer ticks derived from
+// them (see `TimerClock`); an instruction takes one cycle or more (see `chip8::Timing`).
+// Everything the guest can observe (timers, sound, practice timers) runs on machine time,
+// so a run is reproducible no matter how fast the host actually goes; keeping it in step
+// with the wall clock is left to `Pacer`.
+#[derive(Clone, Debug)]
+pub struct MachineTime {
+    // Instructions executed
+    pub cycles: u64,
+    // Clock cycles they took; the same as `cycles` unless instructions cost more than one
+    clock_cycles: u64,
+    // Guest clock rate (Hz) that clock cycles are converted to time with
+    clock_rate: f64,
+    // Machine time and clock cycles as of the last clock rate change, which the time
+    // elapsed since is counted from
+    rebased_at: Duration,
+    rebased_cycles: u64,
+    // When the CHIP-8 timers tick
+    timers: TimerClock,
+}
+
+impl MachineTime {
+    pub fn new(clock_rate: f32) -> Self {
+        MachineTime {
+            cycles: 0,
+            clock_cycles: 0,
+            clock_rate: clock_rate as f64,
+            rebased_at: Duration::ZERO,
+            rebased_cycles: 0,
+            timers: TimerClock::default(),
+        }
+    }
+
+    // Change the clock rate from here on; time already elapsed stays as it was, so this can
+    // be done mid-run
+    pub fn set_clock_rate(&mut self, clock_rate: f32) {
+        self.rebased_at = self.elapsed();
+        self.rebased_cycles = self.clock_cycles;
+        self.clock_rate = clock_rate as f64;
+    }
+
+    pub fn clock_rate(&self) -> f32 {
+        self.clock_rate as f32
+    }
+
+    // Change how often the CHIP-8 timers tick from here on, like the clock rate
+    pub fn set_timer_rate(&mut self, timer_rate: f32) {
+        self.timers.set_rate(timer_rate);
+    }
+
+    pub fn timer_rate(&self) -> f32 {
+        self.timers.rate()
+    }
+
+    // CHIP-8 timer ticks elapsed
+    pub fn ticks(&self) -> u64 {
+        self.timers.ticks()
+    }
+
+    // Machine time a frame lasts: a tick of the timers
+    pub fn frame(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.timers.rate() as f64)
+    }
+
+    // Advance by an instruction taking `cost` clock cycles; returns whether the CHIP-8
+    // timers are due for a tick. One that takes longer than a tick only ticks them once,
+    // the rest being owed until taken (see `tick_overdue`)
+    pub fn step(&mut self, cost: u32) -> bool {
+        self.cycles += 1;
+        self.clock_cycles += cost as u64;
+        self.timers.advance(self.elapsed())
+    }
+
+    // Take a tick of the CHIP-8 timers that fell due before now and is still owed; returns
+    // whether there was one
+    pub fn tick_overdue(&mut self) -> bool {
+        self.timers.advance(self.elapsed())
+    }
+
+    // Advance by an instruction taking `cost` clock cycles, then sit idle through the rest
+    // of the frame, as the core does waiting for the vertical blank; the timers are always
+    // due for a tick after
+    pub fn step_to_tick(&mut self, cost: u32) -> bool {
+        if self.step(cost) {
+            return true;
+        }
+        let idle = self.timers.until_due(self.elapsed()).as_secs_f64() * self.clock_rate;
+        self.clock_cycles += idle.ceil() as u64;
+        self.timers.tick();
+        true
+    }
+
+    // Machine time elapsed since the start of the run
+    pub fn elapsed(&self) -> Duration {
+        let cycles = self.clock_cycles - self.rebased_cycles;
+        self.rebased_at + Duration::from_secs_f64(cycles as f64 / self.clock_rate)
+    }
+}
+
+// How machine time is synchronized with the wall clock
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Pacing {
+    // Sleep so that machine time keeps up with, but doesn't outrun, the wall clock
+    #[default]
+    Realtime,
+    // Never sleep; machine time runs as fast as the host allows (e.g. for benchmarking)
+    Unthrottled,
+}
+
+// Wall-clock synchronization policy layered on top of `MachineTime`
+pub struct Pacer {
+    pacing: Pacing,
+    // Machine time run per unit of wall-clock time; above 1 while fast-forwarding
+    speed: f64,
+    // Wall-clock instant corresponding to `synced_at` in machine time
+    wall_start: Instant,
+    synced_at: Duration,
+    // Last time a frame was presented, for limiting frame rate when draws are coalesced,
+    // and the minimum wall-clock interval between coalesced frames: a frame of machine
+    // time, so frames go by at the pace they do in real time
+    last_present: Instant,
+    present_interval: Duration,
+}
+
+impl Pacer {
+    pub fn new(pacing: Pacing, now: Duration, frame: Duration) -> Self {
+        Pacer {
+            pacing,
+            speed: 1.0,
+            wall_start: Instant::now(),
+            synced_at: now,
+            last_present: Instant::now(),
+            present_interval: frame,
+        }
+    }
+
+    // How long to wait for the wall clock to catch up with machine time `now`; zero if
+    // there's no need to wait (yet)
+    pub fn sync(&mut self, now: Duration) -> Duration {
+        if self.pacing == Pacing::Unthrottled {
+            return Duration::ZERO;
+        }
+
+        // Both in wall-clock time
+        let machine = now.saturating_sub(self.synced_at).div_f64(self.speed);
+        let wall = self.wall_start.elapsed();
+        if wall > machine + MAX_LAG {
+            self.resync(now);
+        } else if machine >= wall + MIN_SLEEP {
+            return machine - wall;
+        }
+        Duration::ZERO
+    }
+
+    // Run machine time `speed` times as fast as the wall clock from machine time `now` on
+    pub fn set_speed(&mut self, speed: f64, now: Duration) {
+        self.speed = speed;
+        self.resync(now);
+    }
+
+    // Start synchronizing afresh from machine time `now`, e.g. after a pause
+    pub fn resync(&mut self, now: Duration) {
+        self.wall_start = Instant::now();
+        self.synced_at = now;
+    }
+
+    // Whether enough wall-clock time has passed to present another coalesced frame
+    pub fn present_due(&mut self) -> bool {
+        if self.last_present.elapsed() >= self.present_interval {
+            self.last_present = Instant::now();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// Waits out what `Pacer` asks for precisely without keeping the CPU busy: the thread sleeps
+// through most of a wait and spins (yielding to other threads) through the rest, which
+// is sized to how far sleeps have been overshooting on this host
+pub struct Sleeper {
+    spin: Duration,
+}
+
+impl Sleeper {
+    pub fn new() -> Self {
+        Sleeper { spin: INITIAL_SPIN }
+    }
+
+    // How much of `wait` to sleep through; the rest is spun out up to its end
+    pub fn sleep_part(&self, wait: Duration) -> Duration {
+        wait.saturating_sub(self.spin)
+    }
+
+    // Adjust to a sleep of `requested` having actually taken `actual`
+    pub fn observe(&mut self, requested: Duration, actual: Duration) {
+        let overshoot = actual.saturating_sub(requested);
+        self.spin = self.spin.mul_f64(SPIN_DECAY).max(overshoot).min(MAX_SPIN);
+    }
+
+    // Block the thread for `wait`, waking up every `interval` at most through the part slept
+    // through to call `poll`; returns how long that actually took
+    pub fn sleep(
+        &mut self,
+        wait: Duration,
+        interval: Duration,
+        mut poll: impl FnMut(),
+    ) -> Duration {
+        let start = Instant::now();
+        loop {
+            let nap = self
+                .sleep_part(wait.saturating_sub(start.elapsed()))
+                .min(interval);
+            if nap.is_zero() {
+                break;
+            }
+            let napped = Instant::now();
+            thread::sleep(nap);
+            self.observe(nap, napped.elapsed());
+            poll();
+        }
+        while start.elapsed() < wait {
+            thread::yield_now();
+        }
+        start.elapsed()
+    }
+}
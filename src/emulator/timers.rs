@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use crate::chip8;
+
+// When the CHIP-8 delay and sound timers tick, in machine time: 60 times a second as on
+// the original hardware, or at another rate (e.g. 50Hz, as on PAL machines)
+//
+//   Tick n falls due n periods of the timer clock in, counted from the last rate change;
+//   each deadline is worked out afresh from the tick count rather than by adding up a
+//   period rounded to the nanosecond, so the schedule never drifts. A tick whose deadline
+//   has passed is owed until it's taken, however far machine time jumped past it (e.g. an
+//   instruction on a clock slower than the timers), so none are ever skipped.
+//
+#[derive(Clone, Debug)]
+pub struct TimerClock {
+    // Ticks taken so far
+    ticks: u64,
+    // Ticks per second of machine time
+    rate: f64,
+    // Machine time and ticks as of the last rate change, which deadlines are counted from
+    rebased_at: Duration,
+    rebased_ticks: u64,
+    // Deadline of the next tick
+    due: Duration,
+}
+
+impl TimerClock {
+    pub fn new(rate: f32) -> Self {
+        let mut clock = TimerClock {
+            ticks: 0,
+            rate: rate as f64,
+            rebased_at: Duration::ZERO,
+            rebased_ticks: 0,
+            due: Duration::ZERO,
+        };
+        clock.due = clock.deadline(1);
+        clock
+    }
+
+    // Change the rate from here on: the next tick falls due a period of the new rate after
+    // the last one did
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rebased_at = self.deadline(0);
+        self.rebased_ticks = self.ticks;
+        self.rate = rate as f64;
+        self.due = self.deadline(1);
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate as f32
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    // Take a tick if one is due by machine time `now`; returns whether one was
+    pub fn advance(&mut self, now: Duration) -> bool {
+        let due = now >= self.due;
+        if due {
+            self.tick();
+        }
+        due
+    }
+
+    // Machine time left from `now` until the next tick is due
+    pub fn until_due(&self, now: Duration) -> Duration {
+        self.due.saturating_sub(now)
+    }
+
+    // Take the next tick, due or not (e.g. once the rest of the frame has been idled away)
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+        self.due = self.deadline(1);
+    }
+
+    // Deadline of the tick `n` after the last one taken
+    fn deadline(&self, n: u64) -> Duration {
+        let periods = (self.ticks + n - self.rebased_ticks) as f64;
+        self.rebased_at + Duration::from_secs_f64(periods / self.rate)
+    }
+}
+
+impl Default for TimerClock {
+    fn default() -> Self {
+        Self::new(chip8::TIMER_FREQ)
+    }
+}
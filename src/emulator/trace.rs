@@ -0,0 +1,108 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::chip8::{disasm, Chip8, NUM_DATA_REGS};
+
+// Target of the per-instruction trace records
+pub const TARGET: &str = "chip8::trace";
+
+// Registers as they were before an instruction, to tell which ones it changed
+pub(crate) struct Snapshot {
+    pc: u16,
+    opcode: u16,
+    i_reg: u16,
+    v_reg: [u8; NUM_DATA_REGS],
+}
+
+impl Snapshot {
+    // Taken right before executing the instruction at PC
+    pub(crate) fn take(system: &Chip8) -> Self {
+        let pc = system.pc();
+        Snapshot {
+            pc,
+            opcode: u16::from_be_bytes([system.read_mem(pc), system.read_mem(pc.wrapping_add(1))]),
+            i_reg: system.i_reg(),
+            v_reg: *system.v_reg(),
+        }
+    }
+
+    // Log the instruction taken, now that `system` has executed it
+    //
+    //   0x204  A22A  LD I, 0x22A           I=0x22A
+    //   0x20A  D01F  DRW V0, V1, 0xF       VF=00
+    //
+    pub(crate) fn trace(&self, system: &Chip8) {
+        let mnemonic = disasm::mnemonic(self.opcode, system.mode());
+        let mut changes = String::new();
+        for (n, (before, after)) in self.v_reg.iter().zip(system.v_reg()).enumerate() {
+            if before != after {
+                write!(changes, " V{n:X}={after:02X}").unwrap();
+            }
+        }
+        if self.i_reg != system.i_reg() {
+            write!(changes, " I={:#05X}", system.i_reg()).unwrap();
+        }
+        log::trace!(
+            target: TARGET,
+            "{:#05X}  {:04X}  {mnemonic:<20}{changes}",
+            self.pc,
+            self.opcode
+        );
+    }
+}
+
+// Writes log records, one per line, to a file or stderr
+//
+//   Installed by `init` for `--trace`; without it, the `log` macros go nowhere and the
+//   emulator doesn't even take snapshots. Records other than traces (e.g. diagnostics) are
+//   prefixed with their level and target
+//
+struct Logger {
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        // Nowhere left to report a failed write to
+        let _ = match record.target() {
+            TARGET => writeln!(out, "{}", record.args()),
+            target => writeln!(out, "{} ({target}): {}", record.level(), record.args()),
+        };
+    }
+
+    fn flush(&self) {
+        let _ = self.out.lock().unwrap_or_else(|e| e.into_inner()).flush();
+    }
+}
+
+// Log everything down to instruction traces to `path`, or stderr if None; the output is
+// buffered, so `log::logger().flush()` before exiting
+pub fn init(path: Option<&Path>) -> io::Result<()> {
+    let out: Box<dyn Write + Send> = match path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stderr())),
+    };
+    log::set_boxed_logger(Box::new(Logger {
+        out: Mutex::new(out),
+    }))
+    .map_err(|e| io::Error::new(io::ErrorKind::AlreadyExists, e))?;
+    log::set_max_level(LevelFilter::Trace);
+    Ok(())
+}
+
+// Whether instructions are being traced
+pub(crate) fn enabled() -> bool {
+    log::log_enabled!(target: TARGET, Level::Trace)
+}
@@ -0,0 +1,23 @@
+// CHIP-8 interpreter library; the `chippity` binary is a thin wrapper around its `cli`
+//
+//   Embedding the interpreter in another frontend comes down to implementing the
+//   `driver::{InputDevice, DisplayDevice, AudioDevice}` traits and handing them to
+//   `emulator::Emulator`, or driving `chip8::Chip8` directly for full control over
+//   the host loop
+//
+//   Built without the default `std` feature, only `chip8` remains, and neither needs the
+//   standard library nor allocates: bring a keypad and something to show the display on
+//
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod chip8;
+#[cfg(feature = "std")]
+pub mod cli;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod diagnostics;
+#[cfg(feature = "std")]
+pub mod driver;
+#[cfg(feature = "std")]
+pub mod emulator;
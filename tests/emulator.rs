@@ -0,0 +1,237 @@
+// The emulator's main loop, run on null devices over tiny ROMs written out opcode by opcode
+//
+//   cargo test --test emulator
+//
+#![cfg(feature = "std")]
+
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use chippity::{
+    chip8::{Trainer, TIMER_FREQ},
+    driver::{InputDevice, InputInfo, InputMsg},
+    emulator::{BoxedEmulator, EmulatorBuilder, Pacing, Signal, DEFAULT_CLOCK_FREQ},
+};
+
+// Instructions in a frame at the default clock rate, when none of them are skipped through
+const FRAME_CYCLES: u64 = (DEFAULT_CLOCK_FREQ / TIMER_FREQ) as u64;
+
+// A delay timer poll after setting the timer to 120 ticks, one that a key can break out of
+// as well, and the jump to self the program ends on
+const TIMER_POLL: [u16; 6] = [0x6078, 0xF015, 0xF007, 0x3000, 0x1204, 0x120A];
+const KEY_TIMER_POLL: [u16; 7] = [0x6078, 0xF015, 0xF007, 0xE19E, 0x3000, 0x1204, 0x120C];
+
+// Draws the digit in V0 every frame, counting up: CLS; LD F, V0; DRW V0, V0, 5; ADD V0, 1;
+// then waits out the delay timer set to 1 tick
+const COUNTER: [u16; 10] = [
+    0x00E0, 0xF029, 0xD005, 0x7001, 0x6101, 0xF115, 0xF107, 0x3100, 0x120C, 0x1200,
+];
+
+// Input device that only counts how often it's polled
+struct PollCounter(Rc<Cell<u32>>);
+
+impl InputDevice for PollCounter {
+    fn device_info(&self) -> InputInfo {
+        InputInfo::None
+    }
+    fn handle_inputs(&mut self) -> Signal {
+        self.0.set(self.0.get() + 1);
+        Signal::None
+    }
+    fn send_inputs(&self) -> Option<InputMsg> {
+        None
+    }
+}
+
+// Input device sending whatever signal is put in it on the next poll
+struct Script(Rc<Cell<Option<Signal>>>);
+
+impl InputDevice for Script {
+    fn device_info(&self) -> InputInfo {
+        InputInfo::None
+    }
+    fn handle_inputs(&mut self) -> Signal {
+        self.0.take().unwrap_or(Signal::None)
+    }
+    fn send_inputs(&self) -> Option<InputMsg> {
+        None
+    }
+}
+
+// Emulator running `rom` off a `Script`, and the signal to send it on the next frame
+fn scripted(rom: &[u16]) -> (BoxedEmulator, Rc<Cell<Option<Signal>>>) {
+    let signal = Rc::new(Cell::new(None));
+    let mut emu = EmulatorBuilder::new()
+        .input(Box::new(Script(signal.clone())))
+        .build();
+    emu.set_frame_locked_input(true);
+    emu.load_rom(&rom_bytes(rom)).unwrap();
+    (emu, signal)
+}
+
+fn rom_bytes(rom: &[u16]) -> Vec<u8> {
+    rom.iter().flat_map(|op| op.to_be_bytes()).collect()
+}
+
+fn emulator(rom: &[u16]) -> BoxedEmulator {
+    let mut emu = EmulatorBuilder::new().build();
+    emu.load_rom(&rom_bytes(rom)).unwrap();
+    emu
+}
+
+// Instructions executed in the frame after the first `settle`, by when the ROM is in its loop
+fn frame_cycles(emu: &mut BoxedEmulator, settle: usize) -> u64 {
+    for _ in 0..settle {
+        emu.run_frame().unwrap();
+    }
+    let cycles = emu.summary().cycles;
+    emu.run_frame().unwrap();
+    emu.summary().cycles - cycles
+}
+
+#[test]
+fn jump_to_self_is_idle() {
+    assert_eq!(frame_cycles(&mut emulator(&[0x1200]), 1), 1);
+}
+
+#[test]
+fn key_wait_is_idle() {
+    assert_eq!(frame_cycles(&mut emulator(&[0xF00A]), 1), 1);
+}
+
+#[test]
+fn timer_poll_is_idle() {
+    // LD V0, DT; SE V0, 0; JP to the top, then on to the next frame
+    assert_eq!(frame_cycles(&mut emulator(&TIMER_POLL), 2), 3);
+}
+
+#[test]
+fn timer_poll_skipping_can_be_turned_off() {
+    let mut emu = emulator(&TIMER_POLL);
+    emu.set_skip_timer_polls(false);
+    assert_eq!(frame_cycles(&mut emu, 2), FRAME_CYCLES);
+}
+
+#[test]
+fn key_checking_timer_poll_is_not_idle() {
+    assert_eq!(
+        frame_cycles(&mut emulator(&KEY_TIMER_POLL), 2),
+        FRAME_CYCLES
+    );
+}
+
+#[test]
+fn cycle_budget_counts_instructions() {
+    for skip in [true, false] {
+        let mut emu = emulator(&TIMER_POLL);
+        emu.set_skip_timer_polls(skip);
+        emu.set_pacing(Pacing::Unthrottled);
+        // Thousands of frames of rewind states would be most of the test's time
+        emu.set_rewind_length(0);
+        emu.set_run_limits(Some(5000), None);
+        emu.run().unwrap();
+        assert_eq!(emu.summary().cycles, 5000, "skipping timer polls: {skip}");
+        assert_eq!(emu.time().cycles, 5000, "skipping timer polls: {skip}");
+    }
+}
+
+#[test]
+fn inputs_are_polled_between_frames() {
+    let polls = Rc::new(Cell::new(0));
+    let mut emu = EmulatorBuilder::new()
+        .input(Box::new(PollCounter(polls.clone())))
+        .build();
+    emu.load_rom(&[0x12, 0x00]).unwrap();
+    // A second of play in real time, so 60 frames and the waits between them
+    emu.set_run_limits(None, Some(Duration::from_secs(1)));
+    emu.run().unwrap();
+
+    let per_sec = polls.get() as f64 / emu.summary().time_played.as_secs_f64();
+    assert!((120.0..=300.0).contains(&per_sec), "{per_sec:.0} polls/s");
+}
+
+#[test]
+fn practice_mark_is_restored() {
+    let (mut emu, signal) = scripted(&COUNTER);
+    for _ in 0..3 {
+        emu.run_frame().unwrap();
+    }
+    signal.set(Some(Signal::MarkState));
+    emu.run_frame().unwrap();
+    let marked = emu.display_hash();
+    for _ in 0..3 {
+        emu.run_frame().unwrap();
+    }
+    assert_ne!(emu.display_hash(), marked);
+
+    // The frame after the mark is drawn from it once more
+    signal.set(Some(Signal::RestoreMark));
+    emu.run_frame().unwrap();
+    let restored = emu.display_hash();
+    let (mut fresh, _) = scripted(&COUNTER);
+    for _ in 0..5 {
+        fresh.run_frame().unwrap();
+    }
+    assert_eq!(restored, fresh.display_hash());
+}
+
+#[test]
+fn practice_restore_keeps_trainer_rules() {
+    let (mut emu, signal) = scripted(&COUNTER);
+    signal.set(Some(Signal::MarkState));
+    for _ in 0..4 {
+        emu.run_frame().unwrap();
+    }
+
+    // Set after the mark, the rule keeps the digit from being drawn after going back to it
+    emu.set_trainer("204 skip".parse::<Trainer>().unwrap());
+    signal.set(Some(Signal::RestoreMark));
+    emu.run_frame().unwrap();
+    emu.run_frame().unwrap();
+    let (mut blank, _) = scripted(&[0x00E0, 0x1202]);
+    blank.run_frame().unwrap();
+    assert_eq!(emu.display_hash(), blank.display_hash());
+}
+
+#[test]
+fn reset_drops_practice_mark() {
+    // Marked a few frames in, then reset, with and without restoring the mark afterwards
+    let hashes = [Signal::RestoreMark, Signal::None].map(|restore| {
+        let (mut emu, signal) = scripted(&COUNTER);
+        for _ in 0..3 {
+            emu.run_frame().unwrap();
+        }
+        signal.set(Some(Signal::MarkState));
+        emu.run_frame().unwrap();
+        emu.reset().unwrap();
+        signal.set(Some(restore));
+        emu.run_frame().unwrap();
+        emu.display_hash()
+    });
+    assert_eq!(hashes[0], hashes[1]);
+}
+
+#[test]
+fn rewind_steps_back_through_frames() {
+    let (mut emu, signal) = scripted(&COUNTER);
+    // Past the first few frames, by when the digit changes every one
+    frame_cycles(&mut emu, 4);
+    let hashes = (0..10)
+        .map(|_| {
+            emu.run_frame().unwrap();
+            emu.display_hash()
+        })
+        .collect::<Vec<_>>();
+    // Each is drawn differently, or going back through them would prove nothing
+    assert!(hashes.windows(2).all(|pair| pair[0] != pair[1]));
+
+    // One tick's worth of state per rewind, each decoded from the deltas back from the last
+    for back in 1..hashes.len() {
+        signal.set(Some(Signal::Rewind));
+        emu.run_frame().unwrap();
+        assert_eq!(
+            emu.display_hash(),
+            hashes[hashes.len() - 1 - back],
+            "{back} back"
+        );
+    }
+}
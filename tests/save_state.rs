@@ -0,0 +1,64 @@
+// Save states taken and loaded on the interpreter core
+//
+//   cargo test --test save_state
+//
+#![cfg(feature = "std")]
+
+use chippity::chip8::{Chip8, Font};
+
+// Where `Chip8::load_fonts` puts the small and large digits
+const FONT_START: u16 = 0x000;
+const BIG_FONT_START: u16 = 0x050;
+
+fn memory(system: &Chip8, start: u16, len: usize) -> Vec<u8> {
+    (start..)
+        .take(len)
+        .map(|addr| system.read_mem(addr))
+        .collect()
+}
+
+#[test]
+fn font_survives_loading_a_state() {
+    // One with its own small digits, one with its own large ones
+    for font in [Font::Vip, Font::Schip] {
+        let mut system = Chip8::new();
+        system.load_rom(&[0x12, 0x00]).unwrap();
+        let state = system.save_state();
+
+        // The font is a setting rather than part of the state, so a reset after loading one
+        // still writes the digits chosen since
+        system.set_font(font);
+        system.load_state(&state).unwrap();
+        system.reset();
+
+        let small = font.sprites().as_flattened();
+        let big = font.big_sprites().as_flattened();
+        assert_eq!(memory(&system, FONT_START, small.len()), small, "{font:?}");
+        assert_eq!(memory(&system, BIG_FONT_START, big.len()), big, "{font:?}");
+    }
+}
+
+#[test]
+fn state_with_too_little_memory_is_rejected() {
+    let mut system = Chip8::new();
+    system.load_rom(&[0x12, 0x00]).unwrap();
+    let state = system.save_state();
+
+    // Cut the memory down to the first 0x100 bytes, short of where programs start. It's
+    // bincode's u64 length followed by the bytes, the first of them the font's
+    let len = (0x1000u64).to_le_bytes();
+    let font = Font::Modern.sprites().as_flattened();
+    let at = state
+        .windows(len.len() + font.len())
+        .position(|w| w[..len.len()] == len && w[len.len()..] == *font)
+        .expect("memory in the state");
+    let mut short = state[..at].to_vec();
+    short.extend_from_slice(&0x100u64.to_le_bytes());
+    short.extend_from_slice(&state[at + len.len()..][..0x100]);
+    short.extend_from_slice(&state[at + len.len() + 0x1000..]);
+
+    assert!(system.load_state(&short).is_err());
+    // Left as it was, so resetting still has room for the fonts
+    assert!(system.save_state() == state);
+    system.reset();
+}
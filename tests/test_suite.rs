@@ -0,0 +1,121 @@
+// Timendus' CHIP-8 test suite (https://github.com/Timendus/chip8-test-suite), run headless
+// on the interpreter core, with the display each ROM ends on compared against the one
+// expected of it
+//
+//   cargo test --test test_suite
+//
+//   The expected displays are in tests/expected, drawn in text: each shows every check of
+//   its ROM passing. After a change that's meant to alter one, regenerate them with
+//
+//   UPDATE_EXPECTED=1 cargo test --test test_suite
+//
+//   and look over the diff before committing it.
+//
+use std::{env, fs, path::PathBuf};
+
+use chippity::chip8::{Chip8, Events, Platform, TIMER_FREQ};
+
+// The quirks test skips its menu when told which platform to check here
+const QUIRKS_PLATFORM_ADDR: u16 = 0x1FF;
+
+fn rom(name: &str) -> Vec<u8> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("roms/test")
+        .join(name);
+    fs::read(&path).unwrap_or_else(|e| panic!("reading {}: {e}", path.display()))
+}
+
+// Run `rom` on `platform` for `cycles` instructions, with the timers ticking as they would at
+// the platform's clock rate, and return the display it leaves
+fn run(platform: &Platform, rom: &[u8], setup: impl FnOnce(&mut Chip8), cycles: u32) -> String {
+    let mut system = Chip8::new();
+    system.set_platform(platform);
+    system.set_rng_seed(0);
+    system.load_rom(rom).unwrap();
+    setup(&mut system);
+
+    let tick_interval = (platform.clock_hz / TIMER_FREQ).round() as u32;
+    let mut until_tick = tick_interval;
+    for _ in 0..cycles {
+        let events = system.step().unwrap();
+        if events.contains(Events::PROGRAM_EXIT) {
+            break;
+        }
+        // Waiting for the vertical blank skips to the next tick
+        until_tick -= 1;
+        if until_tick == 0 || events.contains(Events::DISPLAY_WAIT) {
+            system.tick_timers();
+            until_tick = tick_interval;
+        }
+    }
+    render(&system)
+}
+
+// The display as text, a line per row: `#` for a lit pixel (in any plane), `.` for an unlit one
+fn render(system: &Chip8) -> String {
+    let mut text = String::new();
+    for row in system.framebuffer_rows() {
+        text.extend(row.iter().map(|&color| if color != 0 { '#' } else { '.' }));
+        text.push('\n');
+    }
+    text
+}
+
+// Compare `display` with tests/expected/`name`.txt, or overwrite that with it if
+// UPDATE_EXPECTED is set
+fn check(name: &str, display: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/expected")
+        .join(format!("{name}.txt"));
+    if env::var_os("UPDATE_EXPECTED").is_some() {
+        fs::write(&path, display).unwrap();
+        return;
+    }
+
+    let expected =
+        fs::read_to_string(&path).unwrap_or_else(|e| panic!("reading {}: {e}", path.display()));
+    assert!(
+        display == expected,
+        "{name} ended on a different display\n\nexpected:\n{expected}\nactual:\n{display}"
+    );
+}
+
+#[test]
+fn chip8_logo() {
+    let display = run(&Platform::CHIP8, &rom("1-chip8-logo.ch8"), |_| {}, 1_000);
+    check("chip8-logo", &display);
+}
+
+#[test]
+fn ibm_logo() {
+    let display = run(&Platform::CHIP8, &rom("2-ibm-logo.ch8"), |_| {}, 1_000);
+    check("ibm-logo", &display);
+}
+
+#[test]
+fn corax_opcodes() {
+    let display = run(&Platform::CHIP8, &rom("3-corax+.ch8"), |_| {}, 10_000);
+    check("corax+", &display);
+}
+
+#[test]
+fn flags() {
+    let display = run(&Platform::CHIP8, &rom("4-flags.ch8"), |_| {}, 10_000);
+    check("flags", &display);
+}
+
+// Each platform's quirks, as the test ROM expects them of the interpreter it's named for
+#[test]
+fn quirks() {
+    for (name, platform, choice) in [
+        ("quirks-chip8", Platform::VIP, 1),
+        ("quirks-schip", Platform::SCHIP, 2),
+        ("quirks-xochip", Platform::XOCHIP, 3),
+    ] {
+        // Long enough at each platform's clock rate for its splash screen to go by
+        let cycles = (platform.clock_hz * 5.0) as u32;
+        let preset = |system: &mut Chip8| system.write_mem(QUIRKS_PLATFORM_ADDR, choice).unwrap();
+        let display = run(&platform, &rom("5-quirks.ch8"), preset, cycles);
+        check(name, &display);
+    }
+}